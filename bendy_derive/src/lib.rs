@@ -0,0 +1,392 @@
+//! Derive macros for the `ToBencode` and `FromBencode` traits of [bendy].
+//!
+//! This crate is re-exported by bendy behind its `derive` feature and shouldn't be used
+//! directly. See the documentation of `bendy::encoding::ToBencode` for details.
+//!
+//! [bendy]: https://docs.rs/bendy
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::quote;
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Fields, GenericArgument, Generics, Ident,
+    Lit, LitByteStr, LitInt, LitStr, Meta, NestedMeta, PathArguments, Type,
+};
+
+/// The nesting depth of recursive structs without a `#[bendy(max_depth = ...)]` attribute
+const DEFAULT_RECURSIVE_DEPTH: usize = 64;
+
+/// Derive `ToBencode` for a struct with named fields.
+#[proc_macro_derive(ToBencode, attributes(bendy))]
+pub fn derive_to_bencode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_to_bencode(&input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// Derive `FromBencode` for a struct with named fields.
+#[proc_macro_derive(FromBencode, attributes(bendy))]
+pub fn derive_from_bencode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_bencode(&input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// A field of the struct, in the order of its dict key
+struct Field {
+    ident: Ident,
+    key: LitStr,
+    /// The type of the value in the dict, which for optional fields is the type wrapped
+    /// in `Option`
+    ty: Type,
+    optional: bool,
+    /// Whether the type of the field refers to the struct itself
+    recursive: bool,
+}
+
+impl Field {
+    fn key_bytes(&self) -> LitByteStr {
+        LitByteStr::new(self.key.value().as_bytes(), self.key.span())
+    }
+}
+
+fn expand_to_bencode(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = fields(input)?;
+    let name = &input.ident;
+    let generics = add_bound(&input.generics, quote!(::bendy::encoding::ToBencode));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let depths = fields.iter().filter(|field| !field.recursive).map(|field| {
+        let ty = &field.ty;
+        quote!(<#ty as ::bendy::encoding::ToBencode>::MAX_DEPTH)
+    });
+    let max_depth = max_depth(depths, recursive_depth(input, &fields)?);
+
+    let pairs = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let key = field.key_bytes();
+        if field.optional {
+            quote! {
+                if let ::core::option::Option::Some(value) = &self.#ident {
+                    dict.emit_pair(#key, value)?;
+                }
+            }
+        } else {
+            quote!(dict.emit_pair(#key, &self.#ident)?;)
+        }
+    });
+    let content = if fields.is_empty() {
+        quote!(|_| ::core::result::Result::Ok(()))
+    } else {
+        quote! {
+            |mut dict| {
+                #(#pairs)*
+                ::core::result::Result::Ok(())
+            }
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::bendy::encoding::ToBencode for #name #ty_generics #where_clause {
+            const MAX_DEPTH: usize = #max_depth;
+
+            fn encode(
+                &self,
+                encoder: ::bendy::encoding::SingleItemEncoder,
+            ) -> ::core::result::Result<(), ::bendy::encoding::Error> {
+                encoder.emit_dict(#content)
+            }
+        }
+    })
+}
+
+fn expand_from_bencode(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = fields(input)?;
+    let name = &input.ident;
+    let generics = add_bound(&input.generics, quote!(::bendy::decoding::FromBencode));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let depths = fields.iter().filter(|field| !field.recursive).map(|field| {
+        let ty = &field.ty;
+        quote!(<#ty as ::bendy::decoding::FromBencode>::EXPECTED_RECURSION_DEPTH)
+    });
+    let max_depth = max_depth(depths, recursive_depth(input, &fields)?);
+
+    let idents: Vec<_> = fields.iter().map(|field| &field.ident).collect();
+    let arms = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let ty = &field.ty;
+        let key = field.key_bytes();
+        let context = &field.key;
+        quote! {
+            #key => {
+                #ident = <#ty as ::bendy::decoding::FromBencode>::decode_bencode_object(
+                    __bendy_value,
+                )
+                .map_err(|error| error.context(#context))
+                .map(::core::option::Option::Some)?;
+            },
+        }
+    });
+    let pairs = if fields.is_empty() {
+        quote!(while __bendy_dict.next_pair()?.is_some() {})
+    } else {
+        quote! {
+            while let ::core::option::Option::Some((__bendy_key, __bendy_value)) =
+                __bendy_dict.next_pair()?
+            {
+                match __bendy_key {
+                    #(#arms)*
+                    _ => (), // ignore unknown keys
+                }
+            }
+        }
+    };
+    let values = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let key = &field.key;
+        if field.optional {
+            quote!(#ident)
+        } else {
+            quote! {
+                #ident: #ident.ok_or_else(|| ::bendy::decoding::Error::missing_field(#key))?
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::bendy::decoding::FromBencode for #name #ty_generics #where_clause {
+            const EXPECTED_RECURSION_DEPTH: usize = #max_depth;
+
+            fn decode_bencode_object(
+                object: ::bendy::decoding::Object,
+            ) -> ::core::result::Result<Self, ::bendy::decoding::Error>
+            where
+                Self: Sized,
+            {
+                let mut __bendy_dict = object.try_into_dictionary()?;
+                #(let mut #idents = ::core::option::Option::None;)*
+                #pairs
+
+                ::core::result::Result::Ok(#name {
+                    #(#values,)*
+                })
+            }
+        }
+    })
+}
+
+/// The fields of a struct with named fields, sorted by their dict keys
+fn fields(input: &DeriveInput) -> syn::Result<Vec<Field>> {
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "bendy can only derive for structs with named fields",
+                ))
+            },
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "bendy can only derive for structs with named fields",
+            ))
+        },
+    };
+
+    let mut fields = Vec::with_capacity(named.len());
+    for field in named {
+        let ident = field.ident.clone().expect("named fields have identifiers");
+        let recursive = mentions(&field.ty, &input.ident);
+        let key = match rename(&field.attrs)? {
+            Some(key) => key,
+            None => {
+                let name = ident.to_string();
+                LitStr::new(name.trim_start_matches("r#"), ident.span())
+            },
+        };
+        let (ty, optional) = match option_content(&field.ty) {
+            Some(ty) => (ty.clone(), true),
+            None => (field.ty.clone(), false),
+        };
+
+        fields.push(Field {
+            ident,
+            key,
+            ty,
+            optional,
+            recursive,
+        });
+    }
+
+    fields.sort_by_key(|field| field.key.value().into_bytes());
+    for pair in fields.windows(2) {
+        if pair[0].key.value() == pair[1].key.value() {
+            return Err(syn::Error::new(
+                pair[1].key.span(),
+                format!("duplicate dict key `{}`", pair[1].key.value()),
+            ));
+        }
+    }
+
+    Ok(fields)
+}
+
+/// The key given by a `#[bendy(rename = "...")]` attribute
+fn rename(attrs: &[syn::Attribute]) -> syn::Result<Option<LitStr>> {
+    let mut key = None;
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("bendy")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(syn::Error::new_spanned(meta, "expected `bendy(...)`")),
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(value)) if value.path.is_ident("rename") => {
+                    match value.lit {
+                        Lit::Str(lit) => key = Some(lit),
+                        lit => return Err(syn::Error::new_spanned(lit, "expected a string")),
+                    }
+                },
+                nested => {
+                    return Err(syn::Error::new_spanned(
+                        nested,
+                        "unknown bendy attribute, expected `rename = \"...\"`",
+                    ))
+                },
+            }
+        }
+    }
+    Ok(key)
+}
+
+/// The depth given by a `#[bendy(max_depth = ...)]` attribute of the struct
+fn struct_max_depth(attrs: &[syn::Attribute]) -> syn::Result<Option<LitInt>> {
+    let mut depth = None;
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("bendy")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(syn::Error::new_spanned(meta, "expected `bendy(...)`")),
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(value)) if value.path.is_ident("max_depth") => {
+                    match value.lit {
+                        Lit::Int(lit) => {
+                            lit.base10_parse::<usize>()?;
+                            depth = Some(lit);
+                        },
+                        lit => return Err(syn::Error::new_spanned(lit, "expected an integer")),
+                    }
+                },
+                nested => {
+                    return Err(syn::Error::new_spanned(
+                        nested,
+                        "unknown bendy attribute, expected `max_depth = ...`",
+                    ))
+                },
+            }
+        }
+    }
+    Ok(depth)
+}
+
+/// The nesting depth of a recursive struct, or `None` if the struct isn't recursive.
+///
+/// The depth of a recursive struct can't be derived from its fields, as the constant would
+/// depend on itself. Decoders and encoders with this depth fail with `NestingTooDeep` on
+/// deeper input instead of recursing without bound.
+fn recursive_depth(input: &DeriveInput, fields: &[Field]) -> syn::Result<Option<TokenStream2>> {
+    let depth = struct_max_depth(&input.attrs)?;
+    if !fields.iter().any(|field| field.recursive) {
+        return match depth {
+            Some(depth) => Err(syn::Error::new_spanned(
+                depth,
+                "`max_depth` is only used for recursive structs",
+            )),
+            None => Ok(None),
+        };
+    }
+    Ok(Some(match depth {
+        Some(depth) => quote!(#depth),
+        None => quote!(#DEFAULT_RECURSIVE_DEPTH),
+    }))
+}
+
+/// Whether `ty` refers to the struct `name`, either by its name or as `Self`
+fn mentions(ty: &Type, name: &Ident) -> bool {
+    fn tokens_mention(tokens: TokenStream2, name: &Ident) -> bool {
+        tokens.into_iter().any(|token| match token {
+            TokenTree::Ident(ident) => ident == *name || ident == "Self",
+            TokenTree::Group(group) => tokens_mention(group.stream(), name),
+            _ => false,
+        })
+    }
+    tokens_mention(quote!(#ty), name)
+}
+
+/// The `T` of a field of type `Option<T>`
+fn option_content(ty: &Type) -> Option<&Type> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) if arguments.args.len() == 1 => {
+            match arguments.args.first()? {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+/// The depth of a dict containing values of the given depths, which is at least
+/// `recursive` for recursive structs
+fn max_depth(
+    depths: impl Iterator<Item = TokenStream2>,
+    recursive: Option<TokenStream2>,
+) -> TokenStream2 {
+    let depths: Vec<_> = depths.collect();
+    let recursive = match recursive {
+        Some(recursive) => quote! {
+            if max < #recursive {
+                max = #recursive;
+            }
+        },
+        None => quote!(),
+    };
+    if depths.is_empty() && recursive.is_empty() {
+        return quote!(1);
+    }
+    quote! {{
+        let mut max = 1;
+        #(
+            if #depths + 1 > max {
+                max = #depths + 1;
+            }
+        )*
+        #recursive
+        max
+    }}
+}
+
+/// Require every type parameter to implement `bound`
+fn add_bound(generics: &Generics, bound: TokenStream2) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#bound));
+    }
+    generics
+}
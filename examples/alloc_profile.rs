@@ -0,0 +1,59 @@
+//! Allocation profile of typical workloads
+//!
+//! This example will ...
+//!
+//! - encode the same dict through the sorted and the unsorted dict path,
+//! - decode it into a `Value`,
+//! - and print the number and size of allocations each workload makes.
+//!
+//! Replace `System` with another global allocator to compare allocators.
+//!
+//! # Run the Example
+//!
+//! ```
+//! cargo run --release --features testing --example alloc_profile
+//! ```
+
+use std::{
+    alloc::System,
+    collections::{BTreeMap, HashMap},
+};
+
+use bendy::{
+    decoding::FromBencode,
+    encoding::ToBencode,
+    testing::alloc_audit::{AllocationStats, CountingAllocator},
+    value::Value,
+};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator<System> = CountingAllocator::new(System);
+
+const PAIRS: usize = 1000;
+
+fn print(name: &str, stats: AllocationStats) {
+    println!(
+        "{:<16} {:>6} allocations ({:>6} small, {:>4} reallocations) {:>9} bytes",
+        name,
+        stats.allocations,
+        stats.small_allocations,
+        stats.reallocations,
+        stats.bytes_allocated
+    );
+}
+
+fn main() {
+    let sorted: BTreeMap<String, u64> = (0..PAIRS as u64)
+        .map(|index| (format!("key{:05}", index), index))
+        .collect();
+    let unsorted: HashMap<String, u64> = sorted.clone().into_iter().collect();
+
+    let (encoded, stats) = ALLOCATOR.measure(|| sorted.to_bencode().unwrap());
+    print("sorted dict", stats);
+
+    let (_, stats) = ALLOCATOR.measure(|| unsorted.to_bencode().unwrap());
+    print("unsorted dict", stats);
+
+    let (_, stats) = ALLOCATOR.measure(|| Value::from_bencode(&encoded).unwrap());
+    print("decode to Value", stats);
+}
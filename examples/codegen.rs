@@ -0,0 +1,39 @@
+//! Prints Rust structs inferred from sample bencode files, see `bendy::codegen`.
+//!
+//! # Run the Example
+//!
+//! ```
+//! cargo run --features codegen --example codegen -- <struct name> <sample file>...
+//! ```
+
+use std::{env, fs, process};
+
+use bendy::codegen::Inference;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let name = match args.next() {
+        Some(name) => name,
+        None => {
+            eprintln!("usage: codegen <struct name> <sample file>...");
+            process::exit(2);
+        },
+    };
+
+    let mut inference = Inference::new();
+    for path in args {
+        let sample = match fs::read(&path) {
+            Ok(sample) => sample,
+            Err(error) => {
+                eprintln!("failed to read {}: {}", path, error);
+                process::exit(1);
+            },
+        };
+        if let Err(error) = inference.add_sample(&sample) {
+            eprintln!("failed to decode {}: {}", path, error);
+            process::exit(1);
+        }
+    }
+
+    print!("{}", inference.to_rust(&name));
+}
@@ -0,0 +1,103 @@
+//! A DHT ping round trip without a network
+//!
+//! This example will ...
+//!
+//! - encode a KRPC `ping` query as described in BEP 5,
+//! - answer it the way a remote node would,
+//! - and decode both messages again to print a summary of them.
+//!
+//! # Run the Example
+//!
+//! ```
+//! cargo run --features testing --example dht_ping
+//! ```
+
+use bendy::{
+    decoding::{Cursor, Error as DecodingError},
+    encoding::{AsString, Error as EncodingError, SingleItemEncoder, ToBencode},
+    krpc::replay::{MessageKind, Summary},
+    testing::Pretty,
+};
+
+/// A `ping` query carrying the ID of the querying node.
+struct Ping<'a> {
+    transaction_id: &'a [u8],
+    node_id: &'a [u8; 20],
+}
+
+/// The response to a `ping` query carrying the ID of the queried node.
+struct Pong<'a> {
+    transaction_id: &'a [u8],
+    node_id: &'a [u8; 20],
+}
+
+impl ToBencode for Ping<'_> {
+    const MAX_DEPTH: usize = 2;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), EncodingError> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair_with(b"a", |e| {
+                e.emit_dict(|mut e| e.emit_pair(b"id", AsString(&self.node_id[..])))
+            })?;
+            e.emit_pair(b"q", "ping")?;
+            e.emit_pair(b"t", AsString(self.transaction_id))?;
+            e.emit_pair(b"y", "q")
+        })
+    }
+}
+
+impl ToBencode for Pong<'_> {
+    const MAX_DEPTH: usize = 2;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), EncodingError> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair_with(b"r", |e| {
+                e.emit_dict(|mut e| e.emit_pair(b"id", AsString(&self.node_id[..])))
+            })?;
+            e.emit_pair(b"t", AsString(self.transaction_id))?;
+            e.emit_pair(b"y", "r")
+        })
+    }
+}
+
+/// Play the remote node: answer a ping query with our own node ID.
+fn respond(query: &[u8], node_id: &[u8; 20]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let summary = Summary::new(query)?;
+    if summary.kind != (MessageKind::Query { method: b"ping" }) {
+        return Err("not a ping query".into());
+    }
+
+    let pong = Pong {
+        transaction_id: summary.transaction_id,
+        node_id,
+    };
+    Ok(pong.to_bencode()?)
+}
+
+/// Extract the ID of the remote node from its response.
+fn remote_id(response: &[u8]) -> Result<Option<&[u8]>, DecodingError> {
+    Ok(Cursor::new(response)?
+        .get(b"r")
+        .and_then(|body| body.get(b"id"))
+        .and_then(|id| id.as_bytes()))
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let local_id = [0x11; 20];
+    let remote = [0xab; 20];
+
+    let query = Ping {
+        transaction_id: b"aa",
+        node_id: &local_id,
+    }
+    .to_bencode()?;
+    println!("query:    {}", Summary::new(&query)?);
+    print!("{}", Pretty(&query));
+
+    let response = respond(&query, &remote)?;
+    println!("response: {}", Summary::new(&response)?);
+    print!("{}", Pretty(&response));
+
+    assert_eq!(remote_id(&response)?, Some(&remote[..]));
+    Ok(())
+}
@@ -0,0 +1,40 @@
+//! Prints a summary of every KRPC message in a capture.
+//!
+//! The capture has to consist of records, each holding the length of a UDP payload as
+//! a 32-bit big-endian integer followed by the payload. See `bendy::krpc::replay` for
+//! details.
+//!
+//! # Run the Example
+//!
+//! ```
+//! cargo run --example krpc_replay -- <capture file>
+//! ```
+
+use std::{env, fs, process};
+
+use bendy::krpc::replay;
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: krpc_replay <capture file>");
+            process::exit(2);
+        },
+    };
+
+    let capture = match fs::read(&path) {
+        Ok(capture) => capture,
+        Err(error) => {
+            eprintln!("failed to read {}: {}", path, error);
+            process::exit(1);
+        },
+    };
+
+    for (index, summary) in replay::replay(&capture).enumerate() {
+        match summary {
+            Ok(summary) => println!("{:>6} {}", index, summary),
+            Err(error) => println!("{:>6} malformed: {}", index, error),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! A creator of torrent files using the `torrent` module
+//!
+//! This example will ...
+//!
+//! - split a file into pieces and hash them,
+//! - describe it in a `MetaInfo` and encode it,
+//! - check that the torrent decodes to the same `MetaInfo` again,
+//! - and print the torrent into stdout.
+//!
+//! *Attention*: Please consider to pipe the output into a file of your choice.
+//!
+//! # Run the Example
+//!
+//! ```
+//! cargo run --features torrent --example make_torrent > example.torrent
+//! ```
+
+mod common;
+
+use std::io::{self, Write};
+
+use bendy::{
+    decoding::FromBencode,
+    encoding::ToBencode,
+    torrent::{FileLayout, Info, InfoHash, MetaInfo, Signatures},
+};
+
+static CONTENT: &[u8] = include_bytes!("torrent_files/pieces.iso");
+
+const PIECE_LENGTH: usize = 16 * 1024;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let pieces = CONTENT
+        .chunks(PIECE_LENGTH)
+        .flat_map(|piece| common::sha1(piece).to_vec())
+        .collect();
+
+    let torrent = MetaInfo {
+        announce: Some("http://tracker.example.org:6969/announce".to_owned()),
+        announce_list: None,
+        comment: Some("created by the make_torrent example of bendy".to_owned()),
+        created_by: Some(concat!("bendy/", env!("CARGO_PKG_VERSION")).to_owned()),
+        creation_date: None,
+        encoding: None,
+        info: Info {
+            name: "pieces.iso".to_owned(),
+            piece_length: PIECE_LENGTH as u64,
+            pieces,
+            private: false,
+            layout: FileLayout::Single {
+                length: CONTENT.len() as u64,
+                md5sum: None,
+            },
+        },
+        signatures: Signatures::default(),
+    };
+
+    let encoded = torrent.to_bencode()?;
+    assert_eq!(MetaInfo::from_bencode(&encoded)?, torrent);
+
+    let info_hash = InfoHash::from_torrent(&encoded, common::sha1)?;
+    eprintln!(
+        "{} pieces, info hash {}",
+        torrent.info.piece_hashes().len(),
+        info_hash
+    );
+
+    io::stdout().write_all(&encoded)?;
+    Ok(())
+}
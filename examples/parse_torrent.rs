@@ -0,0 +1,68 @@
+//! A reader for torrent files using the `torrent` module
+//!
+//! This example will ...
+//!
+//! - decode a torrent file into a `MetaInfo`,
+//! - hash its info dict exactly as it appears in the file,
+//! - and print a summary and the magnet link of the torrent.
+//!
+//! # Run the Example
+//!
+//! ```
+//! cargo run --features torrent --example parse_torrent [path/to/file.torrent]
+//! ```
+
+mod common;
+
+use std::{env, fs};
+
+use bendy::{
+    decoding::FromBencode,
+    torrent::{FileLayout, InfoHash, MetaInfo},
+};
+
+static EXAMPLE_TORRENT: &[u8] =
+    include_bytes!("torrent_files/debian-9.4.0-amd64-netinst.iso.torrent");
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let bytes = match env::args_os().nth(1) {
+        Some(path) => fs::read(path)?,
+        None => EXAMPLE_TORRENT.to_vec(),
+    };
+
+    let torrent = MetaInfo::from_bencode(&bytes)?;
+    // The info hash is computed from the original bytes, which may contain keys that
+    // `MetaInfo` doesn't know
+    let info_hash = InfoHash::from_torrent(&bytes, common::sha1)?;
+    let info = &torrent.info;
+
+    println!("name:         {}", info.name);
+    if let Some(announce) = &torrent.announce {
+        println!("announce:     {}", announce);
+    }
+    for (tier, urls) in torrent.announce_list.iter().flatten().enumerate() {
+        println!("tier {}:       {}", tier, urls.join(", "));
+    }
+    if let Some(comment) = &torrent.comment {
+        println!("comment:      {}", comment);
+    }
+    println!("total length: {} bytes", info.total_length());
+    println!(
+        "pieces:       {} of {} bytes",
+        info.piece_hashes().len(),
+        info.piece_length
+    );
+    if let FileLayout::Multiple { files } = &info.layout {
+        for file in files {
+            println!(
+                "file:         {} ({} bytes)",
+                file.path.join("/"),
+                file.length
+            );
+        }
+    }
+    println!("info hash:    {}", info_hash);
+    println!("magnet link:  magnet:?xt=urn:btih:{}", info_hash);
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+//! An announce to an HTTP tracker without a network
+//!
+//! This example will ...
+//!
+//! - build the query string of a `started` announce as described in BEP 3,
+//! - answer it the way a tracker would, with a compact peer list and a warning,
+//! - decode the answer and schedule the next announce from its intervals,
+//! - and handle a tracker rejecting the announce.
+//!
+//! # Run the Example
+//!
+//! ```
+//! cargo run --example tracker_announce
+//! ```
+
+use std::net::SocketAddr;
+
+use bendy::{
+    decoding::FromBencode,
+    encoding::ToBencode,
+    tracker::{AnnounceRequest, AnnounceResponse, AnnounceSchedule, Event, TrackerResult},
+};
+
+/// Play the tracker: hand out two peers and ask for announces every half hour.
+fn announce(request: &AnnounceRequest) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let result = if request.info_hash == [0xab; 20] {
+        let peers: Vec<SocketAddr> =
+            vec!["192.0.2.1:6881".parse()?, "[2001:db8::1]:51413".parse()?];
+        TrackerResult::Success {
+            response: AnnounceResponse {
+                interval: 1800,
+                min_interval: Some(900),
+                complete: Some(1),
+                incomplete: Some(1),
+                peers,
+                ..AnnounceResponse::default()
+            },
+            warning: Some("the tracker moves to a new host soon".to_owned()),
+        }
+    } else {
+        TrackerResult::Failure {
+            reason: "torrent not found".to_owned(),
+        }
+    };
+
+    Ok(result.to_bencode()?)
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let request = AnnounceRequest {
+        event: Event::Started,
+        left: 1 << 20,
+        ..AnnounceRequest::new([0xab; 20], *b"-BY0001-abcdefghijkl", 6881)
+    };
+    println!("request:  /announce?{}", request.to_query());
+
+    let answer = announce(&request)?;
+    let result = TrackerResult::<AnnounceResponse>::from_bencode(&answer)?;
+    if let Some(warning) = result.warning() {
+        println!("warning:  {}", warning);
+    }
+
+    let response = result.into_result()?;
+    for peer in &response.peers {
+        println!("peer:     {}", peer);
+    }
+
+    let schedule = AnnounceSchedule::from_response(&response);
+    // A real client passes a random number to spread its announces
+    if let Some(delay) = schedule.next_announce(request.event, u32::MAX / 2) {
+        println!("next:     in {} seconds", delay.as_secs());
+    }
+    println!("retry:    in {} seconds", schedule.retry_after(1).as_secs());
+
+    let unknown = AnnounceRequest::new([0xcd; 20], *b"-BY0001-abcdefghijkl", 6881);
+    let answer = announce(&unknown)?;
+    match TrackerResult::<AnnounceResponse>::from_bencode(&answer)?.into_result() {
+        Ok(_) => return Err("the tracker should reject unknown torrents".into()),
+        Err(reason) => println!("rejected: {}", reason),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+//! A bencode echo server on top of WebSocket messages, without a network
+//!
+//! This example will ...
+//!
+//! - play a browser sending a few WebSocket messages,
+//! - decode each binary message as a single bencode document,
+//! - answer documents and pings like a server would.
+//!
+//! A real server would map the messages of its WebSocket library, e.g. a
+//! `tungstenite::Message`, to a `Frame` and send the replies back as binary and pong
+//! messages.
+//!
+//! # Run the Example
+//!
+//! ```
+//! cargo run --features testing --example websocket_echo
+//! ```
+
+use bendy::{
+    testing::Pretty,
+    value::Value,
+    websocket::{Frame, Framing, Received},
+};
+
+/// A message the server sends back
+#[derive(Debug)]
+enum Reply {
+    Binary(Vec<u8>),
+    Pong(Vec<u8>),
+}
+
+/// Answer a single message, or `None` if nothing has to be sent back.
+fn serve(framing: &Framing, frame: Frame) -> Result<Option<Reply>, Box<dyn std::error::Error>> {
+    match framing.receive::<Value>(frame)? {
+        Received::Document(document) => Ok(Some(Reply::Binary(framing.encode(&document)?))),
+        Received::Ping(data) => Ok(Some(Reply::Pong(data.to_vec()))),
+        Received::Pong(_) | Received::Close => Ok(None),
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let framing = Framing::new().with_max_message_len(64);
+    let browser = [
+        Frame::Binary(b"d1:q4:ping1:t2:aae"),
+        Frame::Ping(b"keepalive"),
+        Frame::Binary(b"i1ei2e"),
+        Frame::Binary(&[b'l'; 100]),
+        Frame::Text("d1:q4:pinge"),
+        Frame::Close,
+    ];
+
+    for frame in browser.iter() {
+        match serve(&framing, *frame) {
+            Ok(Some(Reply::Binary(message))) => print!("echo:     {}", Pretty(&message)),
+            Ok(Some(Reply::Pong(data))) => {
+                println!("pong:     {:?}", String::from_utf8_lossy(&data))
+            },
+            Ok(None) => println!("no reply: {:?}", frame),
+            Err(error) => println!("rejected: {}", error),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,372 @@
+//! Decoded documents whose nodes share one reusable allocation.
+//!
+//! Decoding into a [`Value`](crate::value::Value) allocates for every list, dict and
+//! string, which thrashes the allocator when parsing millions of small messages, e.g. in a
+//! DHT node. An [`Arena`] instead stores all nodes of a document in a single buffer and
+//! borrows strings from the input. Decoding the next document reuses the buffer, so once
+//! it has grown to fit the largest message, decoding doesn't allocate at all; dropping the
+//! arena frees everything at once.
+//!
+//! ```
+//! use bendy::arena::{Arena, Item};
+//!
+//! let mut arena = Arena::new();
+//! for message in [&b"d1:ti1e1:y1:qe"[..], b"d1:ti2e1:y1:re"].iter() {
+//!     let document = arena.decode(message).unwrap();
+//!     let dict = match document.root() {
+//!         Item::Dict(dict) => dict,
+//!         _ => unreachable!(),
+//!     };
+//!     assert!(matches!(dict.get(b"t"), Some(Item::Integer(1..=2))));
+//! }
+//! assert!(arena.capacity() >= 5);
+//! ```
+//!
+//! A [`Document`] borrows both the arena and the input, so it has to be dropped before
+//! the next document is decoded.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+
+use crate::{
+    decoding::{parse_integer, DecodeOptions, Decoder, Error},
+    encoding::{self, SingleItemEncoder, ToBencode},
+    state_tracker::{StructureError, Token},
+};
+
+/// A single node of a [`Document`] or a `heapless::Value`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Node {
+    Integer(i64),
+    Bytes { start: usize, len: usize },
+    // While decoding, `end` holds the index of the enclosing container plus one
+    List { len: usize, end: usize },
+    Dict { len: usize, end: usize },
+}
+
+/// A reusable buffer for the nodes of decoded documents, see the
+/// [module documentation](self).
+#[derive(Clone, Debug, Default)]
+pub struct Arena {
+    nodes: Vec<Node>,
+    options: DecodeOptions,
+}
+
+impl Arena {
+    /// Create an empty arena, which allocates once the first document is decoded
+    pub fn new() -> Self {
+        <Self as Default>::default()
+    }
+
+    /// Create an arena with room for `nodes` nodes
+    pub fn with_capacity(nodes: usize) -> Self {
+        Arena {
+            nodes: Vec::with_capacity(nodes),
+            options: DecodeOptions::default(),
+        }
+    }
+
+    /// Decode documents with the limits of `options`
+    #[must_use]
+    pub fn with_options(mut self, options: DecodeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The number of nodes the arena can hold without reallocating
+    pub fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    /// Decode the first bencoded value of `input`, replacing the previous document. Any
+    /// data after it is ignored.
+    pub fn decode<'a>(&'a mut self, input: &'a [u8]) -> Result<Document<'a>, Error> {
+        self.nodes.clear();
+        // The innermost unfinished container
+        let mut open = None;
+
+        for token in Decoder::with_options(input, &self.options).tokens() {
+            let node = match token? {
+                Token::End => {
+                    // Matching ends are guaranteed by the decoder
+                    let index: usize = open.expect("end token outside of a container");
+                    let node_count = self.nodes.len();
+                    let parent = match &mut self.nodes[index] {
+                        Node::List { end, .. } | Node::Dict { end, .. } => {
+                            core::mem::replace(end, node_count)
+                        },
+                        _ => unreachable!("only containers are open"),
+                    };
+                    open = parent.checked_sub(1);
+                    if open.is_none() {
+                        break;
+                    }
+                    continue;
+                },
+                Token::Num(text) => Node::Integer(parse_integer(text)?),
+                // Strings are slices of the input, so they are stored as its offsets
+                Token::String(string) => Node::Bytes {
+                    start: string.as_ptr() as usize - input.as_ptr() as usize,
+                    len: string.len(),
+                },
+                Token::List => Node::List {
+                    len: 0,
+                    end: open.map_or(0, |index| index + 1),
+                },
+                Token::Dict => Node::Dict {
+                    len: 0,
+                    end: open.map_or(0, |index| index + 1),
+                },
+            };
+
+            match open {
+                Some(parent) => match &mut self.nodes[parent] {
+                    Node::List { len, .. } | Node::Dict { len, .. } => *len += 1,
+                    _ => unreachable!("only containers are open"),
+                },
+                None if !matches!(node, Node::List { .. } | Node::Dict { .. }) => {
+                    self.nodes.push(node);
+                    break;
+                },
+                None => (),
+            }
+            if let Node::List { .. } | Node::Dict { .. } = node {
+                open = Some(self.nodes.len());
+            }
+            self.nodes.push(node);
+        }
+
+        if self.nodes.is_empty() || open.is_some() {
+            return Err(Error::from(StructureError::UnexpectedEof));
+        }
+        Ok(Document {
+            nodes: &self.nodes,
+            input,
+        })
+    }
+}
+
+/// A document decoded by an [`Arena`], borrowing the arena and the input.
+#[derive(Clone, Copy)]
+pub struct Document<'a> {
+    nodes: &'a [Node],
+    input: &'a [u8],
+}
+
+impl<'a> Document<'a> {
+    /// The top-level item.
+    pub fn root(&self) -> Item<'a> {
+        Items {
+            nodes: self.nodes,
+            bytes: self.input,
+            next: 0,
+            remaining: 1,
+        }
+        .next()
+        .expect("a decoded document has a root")
+    }
+
+    /// The number of nodes of the document.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+impl PartialEq for Document<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.root() == other.root()
+    }
+}
+
+impl Eq for Document<'_> {}
+
+impl Debug for Document<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.root().fmt(f)
+    }
+}
+
+impl ToBencode for Document<'_> {
+    const MAX_DEPTH: usize = <Item as ToBencode>::MAX_DEPTH;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        self.root().encode(encoder)
+    }
+}
+
+/// An item of a [`Document`] or a `heapless::Value`, borrowing its content.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item<'v> {
+    /// A signed integer
+    Integer(i64),
+    /// A byte string
+    Bytes(&'v [u8]),
+    /// A list of items
+    List(Items<'v>),
+    /// A dict mapping byte strings to items
+    Dict(Pairs<'v>),
+}
+
+impl ToBencode for Item<'_> {
+    // Leaves room for external containers, like `value::Value`
+    const MAX_DEPTH: usize = usize::MAX / 4;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        match self {
+            Item::Integer(integer) => encoder.emit_int(*integer),
+            Item::Bytes(bytes) => encoder.emit_bytes(bytes),
+            Item::List(items) => encoder.emit_list(|e| {
+                for item in items.clone() {
+                    e.emit(item)?;
+                }
+                Ok(())
+            }),
+            Item::Dict(pairs) => encoder.emit_dict(|mut e| {
+                for (key, item) in pairs.clone() {
+                    e.emit_pair(key, item)?;
+                }
+                Ok(())
+            }),
+        }
+    }
+}
+
+/// Iterator over the items of a list.
+#[derive(Clone)]
+pub struct Items<'v> {
+    pub(crate) nodes: &'v [Node],
+    pub(crate) bytes: &'v [u8],
+    pub(crate) next: usize,
+    pub(crate) remaining: usize,
+}
+
+impl<'v> Items<'v> {
+    /// The `len` children of the container at `index`
+    fn children(&self, index: usize, len: usize) -> Self {
+        Items {
+            next: index + 1,
+            remaining: len,
+            ..*self
+        }
+    }
+}
+
+impl<'v> Iterator for Items<'v> {
+    type Item = Item<'v>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let index = self.next;
+        let (item, end) = match self.nodes[index] {
+            Node::Integer(integer) => (Item::Integer(integer), index + 1),
+            Node::Bytes { start, len } => (Item::Bytes(&self.bytes[start..start + len]), index + 1),
+            Node::List { len, end } => (Item::List(self.children(index, len)), end),
+            Node::Dict { len, end } => (Item::Dict(Pairs(self.children(index, len))), end),
+        };
+        self.next = end;
+
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for Items<'_> {}
+
+impl PartialEq for Items<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.clone().eq(other.clone())
+    }
+}
+
+impl Eq for Items<'_> {}
+
+impl Debug for Items<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// Iterator over the key-value pairs of a dict.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Pairs<'v>(Items<'v>);
+
+impl<'v> Pairs<'v> {
+    /// The item stored under `key`.
+    pub fn get(&self, key: &[u8]) -> Option<Item<'v>> {
+        self.clone()
+            .find(|(candidate, _)| *candidate == key)
+            .map(|(_, item)| item)
+    }
+}
+
+impl<'v> Iterator for Pairs<'v> {
+    type Item = (&'v [u8], Item<'v>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match (self.0.next()?, self.0.next()?) {
+            (Item::Bytes(key), item) => Some((key, item)),
+            _ => unreachable!("dict keys are byte strings"),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.remaining / 2;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Pairs<'_> {}
+
+impl Debug for Pairs<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(not(feature = "std"))]
+    use alloc::vec::Vec;
+
+    #[test]
+    fn documents_should_reuse_the_arena() {
+        let mut arena = Arena::with_capacity(2);
+        let input = b"d1:ali1eli2ei3ee0:e1:bd1:ci-4eeei5e";
+        let document = arena.decode(input).unwrap();
+        assert_eq!(document.node_count(), 12);
+        assert_eq!(document.to_bencode().unwrap(), &input[..input.len() - 3]);
+        let list = match document.root() {
+            Item::Dict(dict) => dict.get(b"a"),
+            other => panic!("unexpected root {:?}", other),
+        };
+        assert_eq!(list.unwrap().to_bencode().unwrap(), b"li1eli2ei3ee0:e");
+
+        let capacity = arena.capacity();
+        assert_eq!(arena.decode(b"i-7e").unwrap().root(), Item::Integer(-7));
+        assert_eq!(arena.decode(b"3:abc").unwrap().root(), Item::Bytes(b"abc"));
+        assert_eq!(arena.capacity(), capacity);
+    }
+
+    #[test]
+    fn invalid_documents_should_fail() {
+        let mut arena = Arena::new().with_options(DecodeOptions::new().with_max_depth(1));
+        assert!(arena.decode(b"").is_err());
+        assert!(arena.decode(b"li1e").is_err());
+        assert!(arena.decode(b"i01e").is_err());
+        assert!(arena.decode(b"lli1eee").is_err());
+        match arena.decode(b"l1:ae").unwrap().root() {
+            Item::List(items) => assert_eq!(items.collect::<Vec<_>>(), [Item::Bytes(b"a")]),
+            other => panic!("unexpected root {:?}", other),
+        }
+    }
+}
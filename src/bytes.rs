@@ -0,0 +1,192 @@
+//! Integration with the [`bytes`](bytes_) crate, for networking stacks like tokio that pass
+//! buffers around as [`Bytes`] and [`BytesMut`].
+//!
+//! [`encode_into`] appends an encoded value to a [`BytesMut`], e.g. the destination buffer
+//! of a frame codec, and [`to_bytes`] hands the output of the encoder over without copying.
+//!
+//! [`SharedInput`] decodes a received [`Bytes`] buffer and turns the byte strings borrowed
+//! by the decoder into [`Bytes`] handles sharing that buffer, so they can be kept after
+//! decoding without being copied:
+//!
+//! ```
+//! use bendy::bytes::{encode_into, SharedInput};
+//! use bytes_::{Bytes, BytesMut};
+//!
+//! let mut frame = BytesMut::new();
+//! encode_into(
+//!     &vec![Bytes::from_static(b"spam"), Bytes::from_static(b"eggs")],
+//!     &mut frame,
+//! )
+//! .unwrap();
+//!
+//! let input = SharedInput::new(frame.freeze());
+//! let mut decoder = input.decoder();
+//! let mut list = decoder
+//!     .next_object()
+//!     .unwrap()
+//!     .unwrap()
+//!     .try_into_list()
+//!     .unwrap();
+//! let spam = input
+//!     .shared_bytes(list.next_object().unwrap().unwrap())
+//!     .unwrap();
+//!
+//! assert_eq!(spam, "spam");
+//! assert_eq!(spam.as_ptr(), input.as_bytes()[3..].as_ptr());
+//! ```
+//!
+//! [`Bytes`] and [`BytesMut`] also implement [`ToBencode`] and [`FromBencode`], but
+//! [`FromBencode`] has to copy the decoded string.
+//!
+//! This module needs the `bytes` feature.
+
+use bytes_::{Bytes, BytesMut};
+
+use crate::{
+    decoding::{Decoder, Error, FromBencode, Object},
+    encoding::{self, SingleItemEncoder, ToBencode},
+};
+
+/// Append the encoded `value` to `output`
+pub fn encode_into<T>(value: &T, output: &mut BytesMut) -> Result<(), encoding::Error>
+where
+    T: ToBencode + ?Sized,
+{
+    let encoded = value.to_bencode()?;
+    output.extend_from_slice(&encoded);
+    Ok(())
+}
+
+/// Encode `value` into a [`Bytes`], taking over the output buffer of the encoder
+pub fn to_bytes<T>(value: &T) -> Result<Bytes, encoding::Error>
+where
+    T: ToBencode + ?Sized,
+{
+    value.to_bencode().map(Bytes::from)
+}
+
+/// A [`Bytes`] buffer to decode, sharing it with the byte strings taken from its
+/// document. See the [module documentation](self).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SharedInput {
+    bytes: Bytes,
+}
+
+impl SharedInput {
+    /// Wrap the buffer to decode
+    pub fn new(bytes: impl Into<Bytes>) -> Self {
+        SharedInput {
+            bytes: bytes.into(),
+        }
+    }
+
+    /// The buffer to decode
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.bytes
+    }
+
+    /// Create a decoder borrowing from the buffer
+    pub fn decoder(&self) -> Decoder<'_> {
+        Decoder::new(&self.bytes)
+    }
+
+    /// Turn a slice borrowed from the buffer into a handle sharing the buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `borrowed` isn't part of the buffer, i.e. if it wasn't decoded from a
+    /// [`decoder`](Self::decoder) of this input.
+    pub fn share(&self, borrowed: &[u8]) -> Bytes {
+        self.bytes.slice_ref(borrowed)
+    }
+
+    /// Turn a decoded byte string into a handle sharing the buffer, failing if `object`
+    /// isn't a byte string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `object` wasn't decoded from a [`decoder`](Self::decoder) of this input.
+    pub fn shared_bytes(&self, object: Object) -> Result<Bytes, Error> {
+        object.try_into_bytes().map(|bytes| self.share(bytes))
+    }
+}
+
+impl ToBencode for Bytes {
+    const MAX_DEPTH: usize = 0;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_bytes(self)
+    }
+}
+
+impl ToBencode for BytesMut {
+    const MAX_DEPTH: usize = 0;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_bytes(self)
+    }
+}
+
+impl FromBencode for Bytes {
+    const EXPECTED_RECURSION_DEPTH: usize = 0;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        object.try_into_bytes().map(Bytes::copy_from_slice)
+    }
+}
+
+impl FromBencode for BytesMut {
+    const EXPECTED_RECURSION_DEPTH: usize = 0;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        object.try_into_bytes().map(BytesMut::from)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn encoded_values_should_be_appended() {
+        let mut output = BytesMut::from(&b"i1e"[..]);
+        encode_into(&Bytes::from_static(b"abc"), &mut output).unwrap();
+        encode_into(&BytesMut::from("d"), &mut output).unwrap();
+        assert_eq!(output, &b"i1e3:abc1:d"[..]);
+
+        let bytes = to_bytes(&vec![1, 2]).unwrap();
+        assert_eq!(bytes, &b"li1ei2ee"[..]);
+        assert_eq!(Bytes::from_bencode(b"3:abc").unwrap(), "abc");
+        assert!(BytesMut::from_bencode(b"i1e").is_err());
+    }
+
+    #[test]
+    fn decoded_strings_should_share_the_input() {
+        let input = SharedInput::new(&b"d1:a3:xyz1:bi1ee"[..]);
+        let mut decoder = input.decoder();
+        let mut dict = decoder
+            .next_object()
+            .unwrap()
+            .unwrap()
+            .try_into_dictionary()
+            .unwrap();
+
+        let (key, value) = dict.next_pair().unwrap().unwrap();
+        let key = input.share(key);
+        let value = input.shared_bytes(value).unwrap();
+        assert_eq!((&key[..], &value[..]), (&b"a"[..], &b"xyz"[..]));
+        assert_eq!(value.as_ptr(), input.as_bytes()[6..].as_ptr());
+
+        let (_, value) = dict.next_pair().unwrap().unwrap();
+        assert!(input.shared_bytes(value).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Identification of BitTorrent clients.
+//!
+//! DHT messages may carry a four byte client version (`v`): two characters identifying the
+//! client, followed by its major and minor version as binary numbers. Peer IDs commonly
+//! follow the Azureus convention of `-XX1234-` at the start, where `XX` identifies the
+//! client and the following characters its version.
+//!
+//! Both are decoded into a [`ClientVersion`], whose [`name`](ClientVersion::name) is
+//! looked up in a small registry of well-known clients:
+//!
+//! ```
+//! use bendy::client::ClientVersion;
+//!
+//! let version = ClientVersion::from_version_field(b"UT\x03\x05").unwrap();
+//! assert_eq!(version.to_string(), "uTorrent 3.5");
+//!
+//! let version = ClientVersion::from_peer_id(b"-TR4060-0123456789ab").unwrap();
+//! assert_eq!(version.name(), Some("Transmission"));
+//! assert_eq!((version.major, version.minor), (4, 0));
+//! ```
+
+use core::fmt::{self, Display, Formatter};
+
+/// Two character codes of well-known clients and their names.
+///
+/// The codes are shared between the `v` field and Azureus style peer IDs.
+pub const KNOWN_CLIENTS: &[([u8; 2], &str)] = &[
+    (*b"AZ", "Vuze"),
+    (*b"BC", "BitComet"),
+    (*b"BT", "BitTorrent"),
+    (*b"DE", "Deluge"),
+    (*b"KT", "KTorrent"),
+    (*b"LT", "libtorrent (Rasterbar)"),
+    (*b"TR", "Transmission"),
+    (*b"TX", "Tixati"),
+    (*b"UT", "uTorrent"),
+    (*b"UW", "uTorrent Web"),
+    (*b"lt", "libTorrent (rakshasa)"),
+    (*b"qB", "qBittorrent"),
+];
+
+/// A client and its version, see the [module documentation](self).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ClientVersion {
+    /// The two character client code
+    pub client: [u8; 2],
+    /// The major version
+    pub major: u8,
+    /// The minor version
+    pub minor: u8,
+}
+
+impl ClientVersion {
+    /// Decode the four byte `v` field of a KRPC message.
+    ///
+    /// Returns `None` if the field has a different length.
+    pub fn from_version_field(version: &[u8]) -> Option<Self> {
+        match *version {
+            [a, b, major, minor] => Some(ClientVersion {
+                client: [a, b],
+                major,
+                minor,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Decode the client from an Azureus style peer ID (`-XX1234-...`).
+    ///
+    /// Version characters may be digits or letters, where `A` stands for 10, `B` for 11
+    /// and so on. Returns `None` if the peer ID doesn't follow the convention.
+    pub fn from_peer_id(peer_id: &[u8]) -> Option<Self> {
+        match *peer_id {
+            [b'-', a, b, major, minor, _, _, b'-', ..] if a.is_ascii_alphanumeric() => {
+                Some(ClientVersion {
+                    client: [a, b],
+                    major: version_digit(major)?,
+                    minor: version_digit(minor)?,
+                })
+            },
+            _ => None,
+        }
+    }
+
+    /// Encode as a four byte `v` field.
+    pub fn to_version_field(self) -> [u8; 4] {
+        [self.client[0], self.client[1], self.major, self.minor]
+    }
+
+    /// The name of the client, if it is a known one.
+    pub fn name(&self) -> Option<&'static str> {
+        KNOWN_CLIENTS
+            .iter()
+            .find(|(code, _)| *code == self.client)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl Display for ClientVersion {
+    /// Writes the client name, or the escaped client code of unknown clients, followed by
+    /// the version.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name)?,
+            None => {
+                for &byte in &self.client {
+                    write!(f, "{}", core::ascii::escape_default(byte))?;
+                }
+            },
+        }
+        write!(f, " {}.{}", self.major, self.minor)
+    }
+}
+
+/// The value of a single version character of a peer ID
+fn version_digit(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'A'..=b'Z' => Some(digit - b'A' + 10),
+        b'a'..=b'z' => Some(digit - b'a' + 36),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn version_fields_should_be_four_bytes() {
+        let version = ClientVersion::from_version_field(b"LT\x01\x02").unwrap();
+        assert_eq!(version.to_version_field(), *b"LT\x01\x02");
+        assert!(ClientVersion::from_version_field(b"LT\x01").is_none());
+        assert!(ClientVersion::from_version_field(b"LT\x01\x02\x03").is_none());
+    }
+
+    #[test]
+    fn peer_ids_should_follow_the_azureus_convention() {
+        let version = ClientVersion::from_peer_id(b"-qB4A20-abcdefghijkl").unwrap();
+        assert_eq!(version.name(), Some("qBittorrent"));
+        assert_eq!((version.major, version.minor), (4, 10));
+
+        assert!(ClientVersion::from_peer_id(b"M7-4-3--abcdefghijkl").is_none());
+        assert!(ClientVersion::from_peer_id(b"-UT!500-abcdefghijkl").is_none());
+        assert!(ClientVersion::from_peer_id(b"-UT3").is_none());
+    }
+
+    #[test]
+    fn unknown_clients_should_display_their_code() {
+        let version = ClientVersion::from_version_field(b"X\xff\x00\x01").unwrap();
+        assert_eq!(version.name(), None);
+        assert_eq!(version.to_string(), "X\\xff 0.1");
+    }
+}
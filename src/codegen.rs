@@ -0,0 +1,424 @@
+//! Rust struct definitions inferred from sample documents.
+//!
+//! An [`Inference`] collects the shape of one or more samples of an undocumented format
+//! and writes structs deriving `ToBencode` and `FromBencode` for it, as a starting point
+//! for a typed parser:
+//!
+//! ```
+//! use bendy::codegen::Inference;
+//!
+//! let mut inference = Inference::new();
+//! inference
+//!     .add_sample(b"d2:id4:abcd5:nodesld4:porti6881eeee")
+//!     .unwrap();
+//! inference
+//!     .add_sample(b"d2:id4:efgh5:token2:\xff\xfee")
+//!     .unwrap();
+//!
+//! let code = inference.to_rust("Response");
+//! assert!(code.contains("pub struct Response {"));
+//! assert!(code.contains("    pub nodes: Option<Vec<NodesItem>>,"));
+//! assert!(code.contains("    pub token: Option<AsString<Vec<u8>>>,"));
+//! ```
+//!
+//! Dicts become structs named after their key, with the fields that are missing from
+//! some samples wrapped in `Option`. Integers become `i64`, strings become `String` if all
+//! samples are valid UTF-8 and `AsString<Vec<u8>>` otherwise. Values whose samples differ
+//! in type, dicts with keys that aren't valid UTF-8, and lists that were always empty
+//! become a [`Value`]. The result is only as good as the samples; review it before use.
+//! The module needs the `codegen` feature, the generated code the `derive` feature.
+//!
+//! The `codegen` example prints the structs inferred from sample files.
+
+use alloc::{
+    borrow::ToOwned,
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    format,
+    string::String,
+    vec::Vec,
+};
+use core::{fmt::Write, str};
+
+use crate::{decoding::Error, value::Value};
+
+/// The names that can't be used as field names
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// The shape of the samples of a value
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Shape {
+    /// No sample yet, e.g. for the items of lists that were always empty
+    Unknown,
+    Integer,
+    Bytes {
+        utf8: bool,
+    },
+    List(Box<Shape>),
+    Dict {
+        samples: usize,
+        /// The shape of each key and the number of samples holding it
+        fields: BTreeMap<String, (Shape, usize)>,
+    },
+    /// Samples of different types
+    Mixed,
+}
+
+impl Shape {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Integer(_) => Shape::Integer,
+            Value::Bytes(bytes) => Shape::Bytes {
+                utf8: str::from_utf8(bytes).is_ok(),
+            },
+            Value::List(items) => Shape::List(Box::new(
+                items
+                    .iter()
+                    .fold(Shape::Unknown, |shape, item| shape.merge(Shape::of(item))),
+            )),
+            Value::Dict(dict) => {
+                let mut fields = BTreeMap::new();
+                for (key, value) in dict {
+                    let key = match str::from_utf8(key) {
+                        Ok(key) => key.to_owned(),
+                        Err(_) => return Shape::Mixed,
+                    };
+                    fields.insert(key, (Shape::of(value), 1));
+                }
+                Shape::Dict { samples: 1, fields }
+            },
+        }
+    }
+
+    fn merge(self, other: Shape) -> Self {
+        match (self, other) {
+            (Shape::Unknown, shape) | (shape, Shape::Unknown) => shape,
+            (Shape::Integer, Shape::Integer) => Shape::Integer,
+            (Shape::Bytes { utf8 }, Shape::Bytes { utf8: other }) => Shape::Bytes {
+                utf8: utf8 && other,
+            },
+            (Shape::List(items), Shape::List(other)) => Shape::List(Box::new(items.merge(*other))),
+            (
+                Shape::Dict {
+                    samples,
+                    mut fields,
+                },
+                Shape::Dict {
+                    samples: other_samples,
+                    fields: other_fields,
+                },
+            ) => {
+                for (key, (shape, count)) in other_fields {
+                    let merged = match fields.remove(&key) {
+                        Some((known, known_count)) => (known.merge(shape), known_count + count),
+                        None => (shape, count),
+                    };
+                    fields.insert(key, merged);
+                }
+                Shape::Dict {
+                    samples: samples + other_samples,
+                    fields,
+                }
+            },
+            _ => Shape::Mixed,
+        }
+    }
+}
+
+/// Infers structs from sample documents, see the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct Inference {
+    root: Shape,
+    samples: usize,
+}
+
+impl Default for Inference {
+    fn default() -> Self {
+        Inference {
+            root: Shape::Unknown,
+            samples: 0,
+        }
+    }
+}
+
+impl Inference {
+    /// Create an inference without samples
+    pub fn new() -> Self {
+        <Self as Default>::default()
+    }
+
+    /// Add the shape of a bencoded sample document
+    pub fn add_sample(&mut self, bencode: &[u8]) -> Result<(), Error> {
+        let sample = Value::decode(bencode)?;
+        let root = core::mem::replace(&mut self.root, Shape::Unknown);
+        self.root = root.merge(Shape::of(&sample));
+        self.samples += 1;
+        Ok(())
+    }
+
+    /// The number of samples added
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Write the definitions of the inferred structs, with `root_name` as the name of the
+    /// top-level struct. A top-level value that isn't a dict gets a type alias instead.
+    pub fn to_rust(&self, root_name: &str) -> String {
+        let mut generator = Generator {
+            names: BTreeSet::new(),
+            structs: Vec::new(),
+            uses_as_string: false,
+            uses_value: false,
+        };
+        let root = generator.type_of(&self.root, root_name);
+
+        let mut code = String::new();
+        let mut imports = Vec::new();
+        imports.push("decoding::FromBencode");
+        if generator.uses_as_string {
+            imports.push("encoding::{AsString, ToBencode}");
+        } else {
+            imports.push("encoding::ToBencode");
+        }
+        if generator.uses_value {
+            imports.push("value::Value");
+        }
+        let _ = writeln!(code, "use bendy::{{{}}};", imports.join(", "));
+
+        if root != root_name {
+            let _ = write!(code, "\npub type {} = {};\n", root_name, root);
+        }
+        for definition in &generator.structs {
+            code.push('\n');
+            code.push_str(definition);
+        }
+        code
+    }
+}
+
+/// Collects the struct definitions of an [`Inference`]
+struct Generator {
+    /// The struct names in use
+    names: BTreeSet<String>,
+    /// The struct definitions, outermost first
+    structs: Vec<String>,
+    uses_as_string: bool,
+    uses_value: bool,
+}
+
+impl Generator {
+    /// The Rust type of `shape`, defining a struct named after `name` for each dict
+    fn type_of(&mut self, shape: &Shape, name: &str) -> String {
+        match shape {
+            Shape::Integer => "i64".to_owned(),
+            Shape::Bytes { utf8: true } => "String".to_owned(),
+            Shape::Bytes { utf8: false } => {
+                self.uses_as_string = true;
+                "AsString<Vec<u8>>".to_owned()
+            },
+            Shape::List(items) => format!("Vec<{}>", self.type_of(items, &format!("{}Item", name))),
+            Shape::Dict { samples, fields } => {
+                let name = unique(&mut self.names, name.to_owned());
+                self.define_struct(&name, *samples, fields);
+                name
+            },
+            Shape::Unknown | Shape::Mixed => {
+                self.uses_value = true;
+                "Value<'static>".to_owned()
+            },
+        }
+    }
+
+    fn define_struct(
+        &mut self,
+        name: &str,
+        samples: usize,
+        fields: &BTreeMap<String, (Shape, usize)>,
+    ) {
+        // Reserve the place of the struct before its fields define theirs
+        let index = self.structs.len();
+        self.structs.push(String::new());
+
+        let mut definition = String::new();
+        let plural = if samples == 1 { "" } else { "s" };
+        let _ = writeln!(definition, "/// Inferred from {} sample{}", samples, plural);
+        let _ = writeln!(
+            definition,
+            "#[derive(Clone, Debug, PartialEq, ToBencode, FromBencode)]"
+        );
+        let _ = writeln!(definition, "pub struct {} {{", name);
+
+        let mut field_names = BTreeSet::new();
+        for (key, (shape, count)) in fields {
+            let field_name = unique(&mut field_names, field_name(key));
+            let mut ty = self.type_of(shape, &struct_name(key));
+
+            if *count < samples {
+                let _ = writeln!(
+                    definition,
+                    "    /// Missing in {} of {} samples",
+                    samples - count,
+                    samples
+                );
+                ty = format!("Option<{}>", ty);
+            }
+            if *shape == Shape::Mixed {
+                let _ = writeln!(definition, "    /// The samples have different types");
+            }
+            if field_name != *key {
+                let _ = writeln!(definition, "    #[bendy(rename = {:?})]", key);
+            }
+            let _ = writeln!(definition, "    pub {}: {},", field_name, ty);
+        }
+
+        definition.push_str("}\n");
+        self.structs[index] = definition;
+    }
+}
+
+/// A snake case field name for `key`
+fn field_name(key: &str) -> String {
+    let mut name = String::new();
+    let mut separate = false;
+    for c in key.chars() {
+        if !c.is_ascii_alphanumeric() {
+            separate = !name.is_empty();
+            continue;
+        }
+        let after_lowercase = name.ends_with(|c: char| c.is_ascii_lowercase());
+        if separate || (c.is_ascii_uppercase() && after_lowercase) {
+            name.push('_');
+        }
+        separate = false;
+        name.push(c.to_ascii_lowercase());
+    }
+
+    if name.is_empty() {
+        name.push_str("field");
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert_str(0, "field_");
+    }
+    if KEYWORDS.contains(&&name[..]) {
+        name.push('_');
+    }
+    name
+}
+
+/// A pascal case struct name for `key`
+fn struct_name(key: &str) -> String {
+    let name: String = field_name(key)
+        .trim_end_matches('_')
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect();
+
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("Field{}", name)
+    } else {
+        name
+    }
+}
+
+/// Make `name` unique within `names` by appending a number
+fn unique(names: &mut BTreeSet<String>, name: String) -> String {
+    let mut candidate = name.clone();
+    let mut suffix = 2;
+    while names.contains(&candidate) {
+        candidate = format!("{}{}", name, suffix);
+        suffix += 1;
+    }
+    names.insert(candidate.clone());
+    candidate
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn samples_should_be_merged_into_structs() {
+        let mut inference = Inference::new();
+        inference
+            .add_sample(b"d4:infod6:lengthi1e4:name1:ae8:url-listl1:aee")
+            .unwrap();
+        inference
+            .add_sample(b"d4:infod6:lengthi2e4:name1:b6:pieces1:\xffe4:typei1ee")
+            .unwrap();
+        assert_eq!(inference.samples(), 2);
+
+        let expected = "use bendy::{decoding::FromBencode, encoding::{AsString, ToBencode}};
+
+/// Inferred from 2 samples
+#[derive(Clone, Debug, PartialEq, ToBencode, FromBencode)]
+pub struct Torrent {
+    pub info: Info,
+    /// Missing in 1 of 2 samples
+    #[bendy(rename = \"type\")]
+    pub type_: Option<i64>,
+    /// Missing in 1 of 2 samples
+    #[bendy(rename = \"url-list\")]
+    pub url_list: Option<Vec<String>>,
+}
+
+/// Inferred from 2 samples
+#[derive(Clone, Debug, PartialEq, ToBencode, FromBencode)]
+pub struct Info {
+    pub length: i64,
+    pub name: String,
+    /// Missing in 1 of 2 samples
+    pub pieces: Option<AsString<Vec<u8>>>,
+}
+";
+        assert_eq!(inference.to_rust("Torrent"), expected);
+    }
+
+    #[test]
+    fn conflicting_samples_should_be_values() {
+        let mut inference = Inference::new();
+        inference.add_sample(b"d1:ai1e1:bld1:ci1eeee").unwrap();
+        inference.add_sample(b"d1:a1:x1:bld1:ci2eeee").unwrap();
+        let code = inference.to_rust("Root");
+        assert!(
+            code.contains("    /// The samples have different types\n    pub a: Value<'static>,")
+        );
+        assert!(code.contains("    pub b: Vec<BItem>,"));
+        assert!(code.contains("pub struct BItem {\n    pub c: i64,\n}"));
+
+        let mut inference = Inference::new();
+        inference.add_sample(b"le").unwrap();
+        assert!(inference
+            .to_rust("Root")
+            .contains("pub type Root = Vec<Value<'static>>;"));
+        assert!(inference.add_sample(b"i1").is_err());
+    }
+
+    #[test]
+    fn names_should_be_valid_identifiers() {
+        assert_eq!(field_name("piece length"), "piece_length");
+        assert_eq!(field_name("creationDate"), "creation_date");
+        assert_eq!(field_name("1st"), "field_1st");
+        assert_eq!(field_name("--"), "field");
+        assert_eq!(field_name("self"), "self_");
+        assert_eq!(struct_name("url-list"), "UrlList");
+        assert_eq!(struct_name("type"), "Type");
+        assert_eq!(struct_name("1st"), "Field1st");
+
+        let mut names = BTreeSet::new();
+        assert_eq!(unique(&mut names, "a".to_owned()), "a");
+        assert_eq!(unique(&mut names, "a".to_owned()), "a2");
+    }
+}
@@ -0,0 +1,145 @@
+//! Compact peer info as used by trackers ([BEP 23], [BEP 7]) and the DHT ([BEP 5]): an IP
+//! address in network byte order followed by a big endian port.
+//!
+//! [BEP 5]: http://bittorrent.org/beps/bep_0005.html
+//! [BEP 7]: http://bittorrent.org/beps/bep_0007.html
+//! [BEP 23]: http://bittorrent.org/beps/bep_0023.html
+
+use std::{
+    fmt::{self, Display, Formatter},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    slice::ChunksExact,
+};
+
+use crate::decoding::Error;
+
+/// The length of a compact IPv4 peer
+pub(crate) const PEER_LEN: usize = 6;
+
+/// The length of a compact IPv6 peer
+pub(crate) const PEER6_LEN: usize = 18;
+
+/// Append the 6 (IPv4) or 18 (IPv6) byte compact peer info of `peer`
+pub(crate) fn write_peer(peer: &SocketAddr, out: &mut Vec<u8>) {
+    match peer.ip() {
+        IpAddr::V4(ip) => out.extend_from_slice(&ip.octets()),
+        IpAddr::V6(ip) => out.extend_from_slice(&ip.octets()),
+    }
+    out.extend_from_slice(&peer.port().to_be_bytes());
+}
+
+/// Decode a compact IPv4 or IPv6 address, `None` if it's neither 4 nor 16 bytes long
+pub(crate) fn decode_ip(bytes: &[u8]) -> Option<IpAddr> {
+    match bytes.len() {
+        4 => Some(IpAddr::V4(Ipv4Addr::new(
+            bytes[0], bytes[1], bytes[2], bytes[3],
+        ))),
+        16 => {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(bytes);
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        },
+        _ => None,
+    }
+}
+
+/// Decode compact peer info, `None` if it's neither 6 nor 18 bytes long
+fn try_peer(compact: &[u8]) -> Option<SocketAddr> {
+    if compact.len() < 2 {
+        return None;
+    }
+    let (ip, port) = compact.split_at(compact.len() - 2);
+    Some(SocketAddr::new(
+        decode_ip(ip)?,
+        u16::from_be_bytes([port[0], port[1]]),
+    ))
+}
+
+/// Decode an entry of [`PEER_LEN`] or [`PEER6_LEN`] bytes, as split off by [`entries`]
+pub(crate) fn peer(entry: &[u8]) -> SocketAddr {
+    try_peer(entry).expect("compact peers are 6 or 18 bytes long")
+}
+
+/// Decode a single compact peer info of either address family
+pub(crate) fn decode_peer(compact: &[u8]) -> Result<SocketAddr, Error> {
+    try_peer(compact).ok_or_else(|| Error::malformed_content(PeerLength { len: compact.len() }))
+}
+
+/// Split a list of compact `what` into entries of `entry` bytes
+pub(crate) fn entries<'a>(
+    compact: &'a [u8],
+    entry: usize,
+    what: &'static str,
+) -> Result<ChunksExact<'a, u8>, Error> {
+    let entries = compact.chunks_exact(entry);
+    if !entries.remainder().is_empty() {
+        return Err(Error::malformed_content(ListLength {
+            what,
+            len: compact.len(),
+            entry,
+        }));
+    }
+
+    Ok(entries)
+}
+
+/// The length of a compact list isn't a multiple of the size of an entry
+#[derive(Debug)]
+struct ListLength {
+    what: &'static str,
+    len: usize,
+    entry: usize,
+}
+
+impl Display for ListLength {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "compact {} of {} bytes aren't a multiple of {} bytes",
+            self.what, self.len, self.entry
+        )
+    }
+}
+
+impl std::error::Error for ListLength {}
+
+/// Compact peer info that is neither 6 nor 18 bytes long
+#[derive(Debug)]
+struct PeerLength {
+    len: usize,
+}
+
+impl Display for PeerLength {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "compact peer of {} bytes instead of 6 or 18", self.len)
+    }
+}
+
+impl std::error::Error for PeerLength {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn peers_should_round_trip() {
+        let peers: [SocketAddr; 2] = [
+            "127.0.0.1:6881".parse().unwrap(),
+            "[::1]:2".parse().unwrap(),
+        ];
+        for peer in &peers {
+            let mut compact = Vec::new();
+            write_peer(peer, &mut compact);
+            assert_eq!(decode_peer(&compact).unwrap(), *peer);
+        }
+
+        assert!(decode_peer(b"").is_err());
+        assert!(decode_peer(b"aaaaaaa").is_err());
+        assert_eq!(
+            entries(b"aaaaaaa", PEER_LEN, "peers")
+                .unwrap_err()
+                .to_string(),
+            "malformed content discovered: compact peers of 7 bytes aren't a multiple of 6 bytes"
+        );
+    }
+}
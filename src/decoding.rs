@@ -63,14 +63,40 @@
 //! # assert!(syntax_check(b"i18e"));
 //! ```
 
+mod cursor;
 mod decoder;
+mod digits;
 mod error;
 mod from_bencode;
+mod key_report;
+mod known_key;
 mod object;
+mod options;
+#[doc(hidden)]
+pub mod perfect_hash;
+mod push_decoder;
+mod rejection_log;
+mod splice;
+mod validate;
 
+pub(crate) use self::digits::{parse_integer, FromDigits};
 pub use self::{
-    decoder::{Decoder, DictDecoder, ListDecoder, Tokens},
+    cursor::{Children, Cursor},
+    decoder::{Decoder, DictDecoder, EmptyKeyPolicy, ListDecoder, Mode, Tokens},
     error::{Error, ErrorKind, ResultExt},
     from_bencode::FromBencode,
+    key_report::{KeyReport, KeySchema},
+    known_key::KnownKey,
     object::Object,
+    options::DecodeOptions,
+    push_decoder::PushDecoder,
+    rejection_log::{Rejection, RejectionLog},
+    splice::splice,
+    validate::is_valid,
 };
+
+/// Derive [`FromBencode`] for a struct with named fields, see the [README] for details.
+///
+/// [README]: https://github.com/P3KI/bendy#derive-support
+#[cfg(feature = "derive")]
+pub use bendy_derive::FromBencode;
@@ -0,0 +1,291 @@
+use core::str;
+
+use crate::{
+    decoding::{Decoder, Error},
+    state_tracker::{StructureError, Token},
+};
+
+/// A read-only cursor over raw bencode.
+///
+/// A cursor points at a single value within a buffer. It can step to the next sibling,
+/// descend into lists and dicts and return the raw bytes of the value it points at,
+/// all without decoding or copying anything. This makes it a middle ground between the
+/// token stream of a [`Decoder`] and a fully decoded [`Value`].
+///
+/// The whole buffer is validated once when the cursor is created. Navigation afterwards
+/// only has to skip over already validated data and never fails.
+///
+/// ```
+/// use bendy::{decoding::Cursor, state_tracker::Token};
+///
+/// let cursor = Cursor::new(b"d4:infod6:lengthi42ee4:name3:fooe").unwrap();
+///
+/// let info = cursor.get(b"info").unwrap();
+/// assert_eq!(info.raw(), b"d6:lengthi42ee");
+/// assert_eq!(info.get(b"length").unwrap().token(), Token::Num("42"));
+/// assert_eq!(cursor.get(b"name").unwrap().as_bytes(), Some(&b"foo"[..]));
+/// ```
+///
+/// [`Value`]: crate::value::Value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cursor<'a> {
+    source: &'a [u8],
+    start: usize,
+}
+
+impl<'a> Cursor<'a> {
+    /// Validate `buffer` and create a cursor pointing at its first value.
+    ///
+    /// The buffer may contain several values in a row, just like the input of a
+    /// [`Decoder`]; they are reachable through [`Cursor::next_sibling`].
+    pub fn new(buffer: &'a [u8]) -> Result<Self, Error> {
+        Self::validate(buffer, Decoder::new(buffer))
+    }
+
+    /// Like [`Cursor::new`], but with a custom nesting depth limit for the validation.
+    /// See [`Decoder::with_max_depth`] for details.
+    pub fn with_max_depth(buffer: &'a [u8], max_depth: usize) -> Result<Self, Error> {
+        Self::validate(buffer, Decoder::new(buffer).with_max_depth(max_depth))
+    }
+
+    /// Validate a buffer holding a single dict, e.g. a torrent file or a KRPC message,
+    /// and return a cursor pointing at the dict.
+    pub(crate) fn dict_document(buffer: &'a [u8]) -> Result<Self, Error> {
+        let root = Self::new(buffer)?;
+        if root.next_sibling().is_some() {
+            return Err(Error::unexpected_token("end of document", "trailing data"));
+        }
+        if root.token() != Token::Dict {
+            return Err(Error::unexpected_token("Dict", root.token().name()));
+        }
+
+        Ok(root)
+    }
+
+    fn validate(buffer: &'a [u8], decoder: Decoder<'a>) -> Result<Self, Error> {
+        for token in decoder.tokens() {
+            token?;
+        }
+
+        if buffer.is_empty() {
+            return Err(StructureError::UnexpectedEof.into());
+        }
+
+        Ok(Cursor {
+            source: buffer,
+            start: 0,
+        })
+    }
+
+    /// The byte offset of the current value within the buffer.
+    pub fn offset(&self) -> usize {
+        self.start
+    }
+
+    /// The first token of the current value. For atoms, this contains the
+    /// (borrowed) content of the value.
+    pub fn token(&self) -> Token<'a> {
+        match self.source[self.start] {
+            b'l' => Token::List,
+            b'd' => Token::Dict,
+            b'i' => Token::Num(self.integer_at(self.start + 1)),
+            _ => Token::String(self.bytes_at(self.start).0),
+        }
+    }
+
+    /// The raw bytes that make up the current value, including any nested values.
+    pub fn raw(&self) -> &'a [u8] {
+        &self.source[self.start..self.value_end(self.start)]
+    }
+
+    /// The content of the current value if it is a byte string.
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self.token() {
+            Token::String(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// The unparsed content of the current value if it is an integer.
+    pub fn as_integer(&self) -> Option<&'a str> {
+        match self.token() {
+            Token::Num(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    /// Move to the value following the current one within the same container, or the
+    /// next top-level value of the buffer. Returns `None` if there is no such value.
+    ///
+    /// Within a dict, keys and values are siblings of each other.
+    pub fn next_sibling(&self) -> Option<Self> {
+        let next = self.value_end(self.start);
+        match self.source.get(next) {
+            None | Some(b'e') => None,
+            Some(_) => Some(self.at(next)),
+        }
+    }
+
+    /// Descend into the first child of the current list or dict. Returns `None` for
+    /// atoms and empty containers.
+    ///
+    /// The first child of a dict is its first key.
+    pub fn first_child(&self) -> Option<Self> {
+        match self.source[self.start] {
+            b'l' | b'd' if self.source[self.start + 1] != b'e' => Some(self.at(self.start + 1)),
+            _ => None,
+        }
+    }
+
+    /// Iterate over the children of the current list or dict.
+    pub fn children(&self) -> Children<'a> {
+        Children(self.first_child())
+    }
+
+    /// Look up the value stored under `key` if the current value is a dict.
+    pub fn get(&self, key: &[u8]) -> Option<Self> {
+        if self.source[self.start] != b'd' {
+            return None;
+        }
+
+        let mut current_key = self.first_child();
+        while let Some(cursor) = current_key {
+            let value = cursor.next_sibling()?;
+            let current = cursor.as_bytes()?;
+            if current == key {
+                return Some(value);
+            } else if current > key {
+                // Keys are sorted, so the key can't show up any more
+                return None;
+            }
+            current_key = value.next_sibling();
+        }
+
+        None
+    }
+
+    fn at(&self, start: usize) -> Self {
+        Cursor {
+            source: self.source,
+            start,
+        }
+    }
+
+    /// Read the integer starting at `start` (after the `i`)
+    fn integer_at(&self, start: usize) -> &'a str {
+        let end = start
+            + self.source[start..]
+                .iter()
+                .position(|&c| c == b'e')
+                .expect("input has been validated");
+        str::from_utf8(&self.source[start..end]).expect("input has been validated")
+    }
+
+    /// Read the byte string at `start`, returning the content and the end offset
+    fn bytes_at(&self, start: usize) -> (&'a [u8], usize) {
+        let colon = start
+            + self.source[start..]
+                .iter()
+                .position(|&c| c == b':')
+                .expect("input has been validated");
+        let len: usize = str::from_utf8(&self.source[start..colon])
+            .ok()
+            .and_then(|len| len.parse().ok())
+            .expect("input has been validated");
+        let end = colon + 1 + len;
+        (&self.source[colon + 1..end], end)
+    }
+
+    /// Find the end of the value starting at `start`
+    fn value_end(&self, start: usize) -> usize {
+        let mut depth = 0usize;
+        let mut pos = start;
+        loop {
+            match self.source[pos] {
+                b'l' | b'd' => {
+                    depth += 1;
+                    pos += 1;
+                },
+                b'e' => {
+                    depth -= 1;
+                    pos += 1;
+                },
+                b'i' => pos += self.integer_at(pos + 1).len() + 2,
+                _ => pos = self.bytes_at(pos).1,
+            }
+
+            if depth == 0 {
+                return pos;
+            }
+        }
+    }
+}
+
+/// Iterator over the children of a list or dict, see [`Cursor::children`].
+#[derive(Clone, Debug)]
+pub struct Children<'a>(Option<Cursor<'a>>);
+
+impl<'a> Iterator for Children<'a> {
+    type Item = Cursor<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.0.take()?;
+        self.0 = current.next_sibling();
+        Some(current)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    static MSG: &[u8] = b"d3:bari1e3:fooli2e3:quxdeee";
+
+    #[test]
+    fn invalid_input_should_fail() {
+        assert!(Cursor::new(b"").is_err());
+        assert!(Cursor::new(b"d3:fooi1e3:bari2ee").is_err());
+        assert!(Cursor::new(b"li1e").is_err());
+    }
+
+    #[test]
+    fn siblings_and_children_should_be_navigable() {
+        let cursor = Cursor::new(MSG).unwrap();
+        assert_eq!(cursor.raw(), MSG);
+        assert_eq!(cursor.next_sibling(), None);
+
+        let children: Vec<_> = cursor.children().map(|child| child.raw()).collect();
+        assert_eq!(children, [&b"3:bar"[..], b"i1e", b"3:foo", b"li2e3:quxdee"]);
+
+        let list = cursor.get(b"foo").unwrap();
+        let tokens: Vec<_> = list.children().map(|child| child.token()).collect();
+        assert_eq!(
+            tokens,
+            [Token::Num("2"), Token::String(b"qux"), Token::Dict]
+        );
+        assert_eq!(list.children().last().unwrap().first_child(), None);
+    }
+
+    #[test]
+    fn get_should_only_find_existing_keys() {
+        let cursor = Cursor::new(MSG).unwrap();
+        assert_eq!(cursor.get(b"bar").unwrap().as_integer(), Some("1"));
+        assert_eq!(cursor.get(b"baz"), None);
+        assert_eq!(cursor.get(b"zzz"), None);
+        assert_eq!(cursor.get(b"bar").unwrap().get(b"bar"), None);
+    }
+
+    #[test]
+    fn top_level_values_should_be_siblings() {
+        let cursor = Cursor::new(b"i1e3:abcle").unwrap();
+        let second = cursor.next_sibling().unwrap();
+        assert_eq!(second.as_bytes(), Some(&b"abc"[..]));
+        assert_eq!(second.offset(), 3);
+        let third = second.next_sibling().unwrap();
+        assert_eq!(third.raw(), b"le");
+        assert_eq!(third.next_sibling(), None);
+    }
+}
@@ -1,11 +1,101 @@
-use alloc::format;
+use alloc::{format, vec};
 use core::str;
 
 use crate::{
-    decoding::{Error, Object},
+    decoding::{DecodeOptions, Error, FromDigits, Object},
     state_tracker::{StateTracker, StructureError, Token},
 };
 
+/// How the decoder treats empty dict keys.
+///
+/// The empty string is a valid key, but it is rarely intended and some broken encoders
+/// emit it in place of a missing key. Empty keys are accepted by default in either
+/// [`Mode`], like everywhere else in this crate (e.g. by [`is_valid`](crate::is_valid) and
+/// the [`PushDecoder`](crate::decoding::PushDecoder)); rejecting them is opt-in with
+/// [`DecodeOptions::with_empty_key_policy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EmptyKeyPolicy {
+    /// Decode empty keys like any other key
+    Accept,
+    /// Fail with a `structure.empty_key` error when reading an empty key
+    Reject,
+}
+
+// `#[default]` on enum variants requires a newer compiler than the MSRV
+#[allow(clippy::derivable_impls)]
+impl Default for EmptyKeyPolicy {
+    fn default() -> Self {
+        EmptyKeyPolicy::Accept
+    }
+}
+
+/// Which malformed input the decoder tolerates.
+///
+/// Some encoders in the wild produce input that is slightly off, e.g. torrents saved with
+/// a trailing newline. [`Mode::Lenient`] accepts a fixed set of such defects and yields the
+/// tokens of the input as if it had been encoded correctly:
+///
+/// - ASCII whitespace (space, tab, line feed, carriage return) and NUL bytes between
+///   tokens, before the first token and after the last one are skipped.
+/// - A byte string with a negative length prefix, like `-1:`, is read as an empty string.
+///   Nothing after the colon is consumed.
+///
+/// Everything else is still rejected, including unsorted keys, leading zeros and
+/// separators within a token. The rules only apply to the [`Decoder`] and everything
+/// built on it, not to [`is_valid`](crate::decoding::is_valid) or a
+/// [`Cursor`](crate::decoding::Cursor).
+///
+/// ```
+/// use bendy::decoding::{DecodeOptions, Mode};
+///
+/// let options = DecodeOptions::new().with_mode(Mode::Lenient);
+/// let decoded: Vec<String> = options.from_bencode(b"l 3:abc\r\n-1:e").unwrap();
+/// assert_eq!(decoded, ["abc", ""]);
+///
+/// assert!(DecodeOptions::new()
+///     .from_bencode::<Vec<String>>(b"l 3:abce")
+///     .is_err());
+/// assert!(DecodeOptions::new()
+///     .from_bencode::<Vec<String>>(b"l -1:e")
+///     .is_err());
+/// ```
+///
+/// Like [`FromBencode::from_bencode`](crate::decoding::FromBencode::from_bencode),
+/// [`DecodeOptions::from_bencode`](crate::decoding::DecodeOptions::from_bencode) stops
+/// after the first object. Whether anything follows it is only checked when reading on
+/// with the [`Decoder`], which rejects trailing whitespace unless it is lenient:
+///
+/// ```
+/// use bendy::decoding::{DecodeOptions, Decoder, Mode};
+///
+/// let mut decoder = Decoder::with_options(b"i1e\n", &DecodeOptions::new());
+/// assert!(decoder.next_object().unwrap().is_some());
+/// assert!(decoder.next_object().is_err());
+///
+/// let options = DecodeOptions::new().with_mode(Mode::Lenient);
+/// let mut decoder = Decoder::with_options(b"i1e\n", &options);
+/// assert!(decoder.next_object().unwrap().is_some());
+/// assert!(decoder.next_object().unwrap().is_none());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// Only accept canonical bencode
+    Strict,
+    /// Accept the defects listed in the documentation of [`Mode`]
+    Lenient,
+}
+
+// `#[default]` on enum variants requires a newer compiler than the MSRV
+#[allow(clippy::derivable_impls)]
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::Strict
+    }
+}
+
+/// The number of digits of `u64::MAX`, the longest valid length prefix of a byte string
+const MAX_LENGTH_DIGITS: usize = 20;
+
 /// A bencode decoder
 ///
 /// This can be used to either get a stream of tokens (using the [`Decoder::tokens()`] method) or to
@@ -15,6 +105,13 @@ pub struct Decoder<'a> {
     source: &'a [u8],
     offset: usize,
     state: StateTracker<&'a [u8], Error>,
+    max_container_len: usize,
+    max_string_len: usize,
+    max_tokens: usize,
+    max_integer_digits: usize,
+    max_input_len: usize,
+    mode: Mode,
+    tokens_read: usize,
 }
 
 impl<'ser> Decoder<'ser> {
@@ -24,17 +121,124 @@ impl<'ser> Decoder<'ser> {
             source: buffer,
             offset: 0,
             state: StateTracker::new(),
+            max_container_len: usize::MAX,
+            max_string_len: usize::MAX,
+            max_tokens: usize::MAX,
+            max_integer_digits: usize::MAX,
+            max_input_len: usize::MAX,
+            mode: Mode::Strict,
+            tokens_read: 0,
         }
     }
 
+    /// Create a new decoder from the given byte array, configured with all limits and
+    /// policies of `options`
+    pub fn with_options(buffer: &'ser [u8], options: &DecodeOptions) -> Self {
+        Decoder::new(buffer)
+            .with_max_depth(options.max_depth)
+            .with_max_container_len(options.max_container_len)
+            .with_max_string_len(options.max_string_len)
+            .with_max_tokens(options.max_tokens)
+            .with_max_integer_digits(options.max_integer_digits)
+            .with_max_input_len(options.max_input_len)
+            .with_empty_key_policy(options.empty_key_policy)
+            .with_mode(options.mode)
+    }
+
     /// Set the maximum nesting depth of the decoder. An unlimited-depth decoder may be
     /// created using `with_max_depth(<usize>::max_value())`, but be warned that this will likely
-    /// exhaust memory if the nesting depth is too deep (even when reading raw tokens)
+    /// exhaust memory if the nesting depth is too deep (even when reading raw tokens).
+    ///
+    /// Exceeding the depth results in a `structure.nesting_too_deep` error, the same
+    /// as in the [`Encoder`](crate::encoding::Encoder), rather than the
+    /// [`ErrorKind::LimitExceeded`] of the other limits.
+    ///
+    /// [`ErrorKind::LimitExceeded`]: crate::decoding::ErrorKind::LimitExceeded
     pub fn with_max_depth(mut self, new_max_depth: usize) -> Self {
         self.state.set_max_depth(new_max_depth);
         self
     }
 
+    /// Set the maximum number of elements of every list and of pairs of every dict.
+    /// Exceeding it results in an [`ErrorKind::LimitExceeded`] error. The limit may be
+    /// overridden for individual containers using [`ListDecoder::with_max_len`] and
+    /// [`DictDecoder::with_max_len`].
+    ///
+    /// [`ErrorKind::LimitExceeded`]: crate::decoding::ErrorKind::LimitExceeded
+    pub fn with_max_container_len(mut self, new_max_len: usize) -> Self {
+        self.max_container_len = new_max_len;
+        self
+    }
+
+    /// Set the maximum length of byte strings. Exceeding it results in an
+    /// [`ErrorKind::LimitExceeded`] error before the string is read.
+    ///
+    /// [`ErrorKind::LimitExceeded`]: crate::decoding::ErrorKind::LimitExceeded
+    pub fn with_max_string_len(mut self, new_max_len: usize) -> Self {
+        self.max_string_len = new_max_len;
+        self
+    }
+
+    /// Set the maximum number of tokens read from the input, where every integer, string,
+    /// start of a list or dict and end of a list or dict is one token. Exceeding it
+    /// results in an [`ErrorKind::LimitExceeded`] error.
+    ///
+    /// [`ErrorKind::LimitExceeded`]: crate::decoding::ErrorKind::LimitExceeded
+    pub fn with_max_tokens(mut self, new_max_tokens: usize) -> Self {
+        self.max_tokens = new_max_tokens;
+        self
+    }
+
+    /// Set the maximum number of digits of integers and of the length prefixes of byte
+    /// strings. The digits are only scanned up to the limit, so exceeding it results in an
+    /// [`ErrorKind::LimitExceeded`] error without reading the rest of the number.
+    ///
+    /// Integers are unlimited by default, as they may be bignums. Length prefixes are
+    /// always limited to the 20 digits of the largest `u64`.
+    ///
+    /// [`ErrorKind::LimitExceeded`]: crate::decoding::ErrorKind::LimitExceeded
+    pub fn with_max_integer_digits(mut self, new_max_digits: usize) -> Self {
+        self.max_integer_digits = new_max_digits;
+        self
+    }
+
+    /// Set the maximum length of the whole input in bytes. A longer input is rejected with
+    /// an [`ErrorKind::LimitExceeded`] error on the first read, without looking at any of
+    /// its tokens.
+    ///
+    /// [`ErrorKind::LimitExceeded`]: crate::decoding::ErrorKind::LimitExceeded
+    pub fn with_max_input_len(mut self, new_max_len: usize) -> Self {
+        self.max_input_len = new_max_len;
+        self
+    }
+
+    /// Set how empty dict keys are treated, see [`EmptyKeyPolicy`]. They are accepted by
+    /// default.
+    pub fn with_empty_key_policy(mut self, policy: EmptyKeyPolicy) -> Self {
+        self.state
+            .set_reject_empty_keys(policy == EmptyKeyPolicy::Reject);
+        self
+    }
+
+    /// Set which malformed input is tolerated, see [`Mode`]. Only canonical bencode is
+    /// accepted by default.
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Skip the separators tolerated between tokens in [`Mode::Lenient`]
+    fn skip_separators(&mut self) {
+        if self.mode == Mode::Lenient {
+            while let Some(byte) = self.source.get(self.offset) {
+                if !matches!(byte, b' ' | b'\t' | b'\n' | b'\r' | b'\0') {
+                    break;
+                }
+                self.offset += 1;
+            }
+        }
+    }
+
     fn take_byte(&mut self) -> Option<u8> {
         if self.offset < self.source.len() {
             let ret = Some(self.source[self.offset]);
@@ -56,69 +260,32 @@ impl<'ser> Decoder<'ser> {
         }
     }
 
-    fn take_int(&mut self, expected_terminator: char) -> Result<&'ser str, StructureError> {
-        enum State {
-            Start,
-            Sign,
-            Zero,
-            Digits,
-        }
-
-        let mut curpos = self.offset;
-        let mut state = State::Start;
-        let mut success = false;
-        while curpos < self.source.len() {
-            let c = self.source[curpos] as char;
-            state = match state {
-                State::Start => match c {
-                    '-' => State::Sign,
-                    '0' => State::Zero,
-                    '0'..='9' => State::Digits,
-                    _ => return Err(StructureError::unexpected("'-' or '0'..'9'", c, curpos)),
-                },
-                State::Zero => {
-                    if c == expected_terminator {
-                        success = true;
-                        break;
-                    } else {
-                        return Err(StructureError::unexpected(
-                            &format!("{:?}", expected_terminator),
-                            c,
-                            curpos,
-                        ));
-                    }
-                },
-                State::Sign => match c {
-                    '1'..='9' => State::Digits,
-                    _ => return Err(StructureError::unexpected("'1'..'9'", c, curpos)),
-                },
-                State::Digits => match c {
-                    '0'..='9' => state,
-                    x if x == expected_terminator => {
-                        success = true;
-                        break;
-                    },
-                    _ => {
-                        return Err(StructureError::unexpected(
-                            &format!("{:?} or '0'..'9'", expected_terminator),
-                            c,
-                            curpos,
-                        ))
-                    },
-                },
-            };
-            curpos += 1;
-        }
-
-        if !success {
-            return Err(StructureError::UnexpectedEof);
+    fn take_int(
+        &mut self,
+        expected_terminator: char,
+        max_digits: usize,
+    ) -> Result<&'ser str, Error> {
+        // Only scan as far as a number of `max_digits` digits with a sign can reach
+        let end = max_digits
+            .saturating_add(2)
+            .saturating_add(self.offset)
+            .min(self.source.len());
+        let too_long =
+            || Error::limit_exceeded(format!("integer of more than {} digits", max_digits));
+        let curpos = match scan_int(&self.source[..end], self.offset, expected_terminator) {
+            Err(StructureError::UnexpectedEof) if end < self.source.len() => return Err(too_long()),
+            result => result?,
+        };
+        let sign = (self.source[self.offset] == b'-') as usize;
+        if curpos - self.offset - sign > max_digits {
+            return Err(too_long());
         }
 
-        #[cfg(debug)]
-        let ival = istr::from_utf8(&self.source[self.offset..curpos])
+        #[cfg(debug_assertions)]
+        let ival = str::from_utf8(&self.source[self.offset..curpos])
             .expect("We've already examined every byte in the string");
 
-        #[cfg(not(debug))]
+        #[cfg(not(debug_assertions))]
         let ival = // Avoid a second UTF-8 check here
             unsafe { str::from_utf8_unchecked(&self.source[self.offset..curpos]) };
         self.offset = curpos + 1;
@@ -131,16 +298,30 @@ impl<'ser> Decoder<'ser> {
             'e' => Token::End,
             'l' => Token::List,
             'd' => Token::Dict,
-            'i' => Token::Num(self.take_int('e')?),
+            'i' => Token::Num(self.take_int('e', self.max_integer_digits)?),
             '0'..='9' => {
                 self.offset -= 1;
                 let curpos = self.offset;
-                let ival = self.take_int(':')?;
-                let len: usize = str::parse(ival).map_err(|_| StructureError::SyntaxError {
-                    unexpected: format!("Invalid integer at offset {}", curpos),
+                let ival = self.take_int(':', self.max_integer_digits.min(MAX_LENGTH_DIGITS))?;
+                let len = usize::from_digits(ival.as_bytes()).ok_or_else(|| {
+                    StructureError::SyntaxError {
+                        unexpected: format!("Invalid integer at offset {}", curpos),
+                    }
                 })?;
+                if len > self.max_string_len {
+                    return Err(Error::limit_exceeded(format!(
+                        "string of {} bytes, more than {}",
+                        len, self.max_string_len
+                    )));
+                }
                 Token::String(self.take_chunk(len).ok_or(StructureError::UnexpectedEof)?)
             },
+            '-' if self.mode == Mode::Lenient => {
+                self.offset -= 1;
+                self.take_int(':', MAX_LENGTH_DIGITS)?;
+                // An empty slice of the input, so the string still points into it
+                Token::String(&self.source[self.offset..self.offset])
+            },
             tok => {
                 return Err(Error::from(StructureError::SyntaxError {
                     unexpected: format!(
@@ -155,26 +336,233 @@ impl<'ser> Decoder<'ser> {
         Ok(token)
     }
 
+    /// Fail if a container that already holds `len` items isn't about to end
+    fn check_container_len(
+        &mut self,
+        len: usize,
+        max_len: usize,
+        items: &str,
+    ) -> Result<(), Error> {
+        self.state.check_error()?;
+        self.skip_separators();
+
+        if len >= max_len && self.source.get(self.offset) != Some(&b'e') {
+            let error = Error::limit_exceeded(format!("more than {} {}", max_len, items));
+            return self.state.latch_err(Err(error.at_offset(self.offset)));
+        }
+
+        Ok(())
+    }
+
     /// Read the next token. Returns Ok(Some(token)) if a token was successfully read,
     fn next_token(&mut self) -> Result<Option<Token<'ser>>, Error> {
         self.state.check_error()?;
 
-        if self.offset == self.source.len() {
-            self.state.observe_eof()?;
+        if self.source.len() > self.max_input_len {
+            let error = Error::limit_exceeded(format!(
+                "input of {} bytes, more than {}",
+                self.source.len(),
+                self.max_input_len
+            ));
+            return self.state.latch_err(Err(error.at_offset(0)));
+        }
+
+        self.skip_separators();
+        let start = self.offset;
+        if start == self.source.len() {
+            let observed = self.state.observe_eof();
+            self.locate(observed, start)?;
             return Ok(None);
         }
 
-        let tok_result = self.raw_next_token();
+        if self.tokens_read == self.max_tokens {
+            let error = Error::limit_exceeded(format!("more than {} tokens", self.max_tokens));
+            return self.state.latch_err(Err(error.at_offset(start)));
+        }
+
+        let tok_result = self
+            .raw_next_token()
+            .map_err(|error| error.at_offset(start));
         let tok = self.state.latch_err(tok_result)?;
-        self.state.observe_token(&tok)?;
+        let observed = self.state.observe_token(&tok);
+        self.locate(observed, start)?;
+        self.tokens_read += 1;
         Ok(Some(tok))
     }
 
+    /// Add `offset` to the error of a failed observation, which the state tracker latched
+    fn locate(&mut self, observed: Result<(), Error>, offset: usize) -> Result<(), Error> {
+        observed.map_err(|error| {
+            self.state.update_err(|error| error.at_offset(offset));
+            error.at_offset(offset)
+        })
+    }
+
     /// Iterate over the tokens in the input stream. This guarantees that the resulting stream
     /// of tokens constitutes a valid bencoded structure.
     pub fn tokens(self) -> Tokens<'ser> {
         Tokens(self)
     }
+
+    /// Read the rest of the open container described by `level`, including everything
+    /// nested in it, passing each token to `visit`. If `visit` fails, the container is
+    /// still read to its end before returning the error.
+    ///
+    /// Nested containers are tracked on a heap allocated stack instead of by recursion, so
+    /// skipping deeply nested input is only limited by the max depth, not the call stack.
+    fn skip_container(
+        &mut self,
+        level: Level,
+        mut visit: impl FnMut(Token<'ser>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut visited = Ok(());
+        let mut levels = vec![level];
+        while let Some(level) = levels.last_mut() {
+            if !level.value_next {
+                let items = if level.dict {
+                    "pairs in a dict"
+                } else {
+                    "elements in a list"
+                };
+                let (len, max_len) = (level.len, level.max_len);
+                self.check_container_len(len, max_len, items)?;
+            }
+
+            // None would have produced an error, as the container is still open
+            let token = match self.next_token()? {
+                Some(token) => token,
+                None => return visited,
+            };
+
+            let level = levels.last_mut().unwrap();
+            match token {
+                Token::End => {
+                    levels.pop();
+                },
+                _ if level.dict && !level.value_next => level.value_next = true,
+                _ => {
+                    level.len += 1;
+                    level.value_next = false;
+                },
+            }
+            if let Token::List | Token::Dict = token {
+                levels.push(Level {
+                    dict: token == Token::Dict,
+                    len: 0,
+                    max_len: self.max_container_len,
+                    value_next: false,
+                });
+            }
+
+            if visited.is_ok() {
+                visited = visit(token);
+            }
+        }
+
+        visited
+    }
+}
+
+/// Check the syntax of the integer starting at `offset` and return the position of its
+/// terminator
+pub(crate) fn scan_int(
+    source: &[u8],
+    offset: usize,
+    expected_terminator: char,
+) -> Result<usize, StructureError> {
+    // Find the terminator with memchr and check the digits before it in one go. Only
+    // malformed integers are scanned byte by byte, to report where they go wrong.
+    #[cfg(feature = "memchr")]
+    {
+        let rest = source.get(offset..).unwrap_or_default();
+        if let Some(len) = memchr_::memchr(expected_terminator as u8, rest) {
+            if is_canonical_int(&rest[..len]) {
+                return Ok(offset + len);
+            }
+        }
+    }
+
+    scan_int_bytewise(source, offset, expected_terminator)
+}
+
+/// Whether `digits` are the digits of a canonical integer, without leading zeros or a
+/// negative zero
+#[cfg(feature = "memchr")]
+fn is_canonical_int(digits: &[u8]) -> bool {
+    let unsigned = match digits {
+        [b'-', unsigned @ ..] => unsigned,
+        _ => digits,
+    };
+    match unsigned {
+        [b'0'] => unsigned.len() == digits.len(),
+        [b'1'..=b'9', rest @ ..] => rest.iter().all(u8::is_ascii_digit),
+        _ => false,
+    }
+}
+
+fn scan_int_bytewise(
+    source: &[u8],
+    offset: usize,
+    expected_terminator: char,
+) -> Result<usize, StructureError> {
+    enum State {
+        Start,
+        Sign,
+        Zero,
+        Digits,
+    }
+
+    let mut curpos = offset;
+    let mut state = State::Start;
+    let mut success = false;
+    while curpos < source.len() {
+        let c = source[curpos] as char;
+        state = match state {
+            State::Start => match c {
+                '-' => State::Sign,
+                '0' => State::Zero,
+                '0'..='9' => State::Digits,
+                _ => return Err(StructureError::unexpected("'-' or '0'..'9'", c, curpos)),
+            },
+            State::Zero => {
+                if c == expected_terminator {
+                    success = true;
+                    break;
+                } else {
+                    return Err(StructureError::unexpected(
+                        format!("{:?}", expected_terminator),
+                        c,
+                        curpos,
+                    ));
+                }
+            },
+            State::Sign => match c {
+                '1'..='9' => State::Digits,
+                _ => return Err(StructureError::unexpected("'1'..'9'", c, curpos)),
+            },
+            State::Digits => match c {
+                '0'..='9' => state,
+                x if x == expected_terminator => {
+                    success = true;
+                    break;
+                },
+                _ => {
+                    return Err(StructureError::unexpected(
+                        format!("{:?} or '0'..'9'", expected_terminator),
+                        c,
+                        curpos,
+                    ))
+                },
+            },
+        };
+        curpos += 1;
+    }
+
+    if !success {
+        return Err(StructureError::UnexpectedEof);
+    }
+
+    Ok(curpos)
 }
 
 /// Iterator over the tokens in the input stream. This guarantees that the resulting stream
@@ -221,12 +609,24 @@ impl<'ser> Decoder<'ser> {
     }
 }
 
+/// An open container while it is skipped by [`Decoder::skip_container`]
+struct Level {
+    dict: bool,
+    /// The number of elements or pairs read so far
+    len: usize,
+    max_len: usize,
+    /// Whether the key of a pair was read, but not its value
+    value_next: bool,
+}
+
 /// A dictionary read from the input stream
 #[derive(Debug)]
 pub struct DictDecoder<'obj, 'ser: 'obj> {
     decoder: &'obj mut Decoder<'ser>,
     finished: bool,
     start_point: usize,
+    len: usize,
+    max_len: usize,
 }
 
 /// A list read from the input stream
@@ -235,18 +635,45 @@ pub struct ListDecoder<'obj, 'ser: 'obj> {
     decoder: &'obj mut Decoder<'ser>,
     finished: bool,
     start_point: usize,
+    len: usize,
+    max_len: usize,
 }
 
 impl<'obj, 'ser: 'obj> DictDecoder<'obj, 'ser> {
     fn new(decoder: &'obj mut Decoder<'ser>) -> Self {
         let offset = decoder.offset - 1;
+        let max_len = decoder.max_container_len;
         DictDecoder {
             decoder,
             finished: false,
             start_point: offset,
+            len: 0,
+            max_len,
         }
     }
 
+    /// Limit the number of pairs of this dict, overriding the limit set with
+    /// [`Decoder::with_max_container_len`].
+    ///
+    /// ```
+    /// use bendy::decoding::Decoder;
+    ///
+    /// let mut decoder = Decoder::new(b"d1:ai1e1:bi2ee");
+    /// let mut dict = decoder
+    ///     .next_object()
+    ///     .unwrap()
+    ///     .unwrap()
+    ///     .try_into_dictionary()
+    ///     .unwrap()
+    ///     .with_max_len(1);
+    /// assert!(dict.next_pair().unwrap().is_some());
+    /// assert!(dict.next_pair().is_err());
+    /// ```
+    pub fn with_max_len(mut self, new_max_len: usize) -> Self {
+        self.max_len = new_max_len;
+        self
+    }
+
     /// Parse the next key/value pair from the dictionary. Returns `Ok(None)`
     /// at the end of the dictionary
     pub fn next_pair<'item>(
@@ -255,6 +682,8 @@ impl<'obj, 'ser: 'obj> DictDecoder<'obj, 'ser> {
         if self.finished {
             return Ok(None);
         }
+        self.decoder
+            .check_container_len(self.len, self.max_len, "pairs in a dict")?;
 
         // We convert to a token to release the mut ref to decoder
         let key = self.decoder.next_object()?.map(Object::into_token);
@@ -262,6 +691,7 @@ impl<'obj, 'ser: 'obj> DictDecoder<'obj, 'ser> {
         if let Some(Token::String(k)) = key {
             // This unwrap should be safe because None would produce an error here
             let v = self.decoder.next_object()?.unwrap();
+            self.len += 1;
             Ok(Some((k, v)))
         } else {
             // We can't have gotten anything but a string, as anything else would be
@@ -271,14 +701,90 @@ impl<'obj, 'ser: 'obj> DictDecoder<'obj, 'ser> {
         }
     }
 
+    /// Parse the next pair like [`DictDecoder::next_pair`], but return the raw bytes of
+    /// the value exactly as they appear in the input.
+    ///
+    /// This is required to hash values of a document, like the `info` dict of a torrent
+    /// for its info hash. Re-encoding a decoded value only yields the same bytes if the
+    /// decoded type preserves every key and, like `private` below, every representation of
+    /// its fields:
+    ///
+    /// ```
+    /// use bendy::decoding::Decoder;
+    ///
+    /// let torrent = b"d8:announce3:url4:infod4:name1:a7:privatei0e6:sourcei7eee";
+    ///
+    /// let mut decoder = Decoder::new(torrent);
+    /// let mut dict = decoder
+    ///     .next_object()
+    ///     .unwrap()
+    ///     .unwrap()
+    ///     .try_into_dictionary()
+    ///     .unwrap();
+    /// let mut info = None;
+    /// while let Some((key, raw)) = dict.next_pair_raw().unwrap() {
+    ///     if key == b"info" {
+    ///         info = Some(raw);
+    ///     }
+    /// }
+    /// assert_eq!(info, Some(&b"d4:name1:a7:privatei0e6:sourcei7ee"[..]));
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn next_pair_raw(&mut self) -> Result<Option<(&'ser [u8], &'ser [u8])>, Error> {
+        if self.finished {
+            return Ok(None);
+        }
+        self.decoder
+            .check_container_len(self.len, self.max_len, "pairs in a dict")?;
+
+        let key = self.decoder.next_object()?.map(Object::into_token);
+
+        if let Some(Token::String(k)) = key {
+            let start = self.decoder.offset;
+            // As in `next_pair`, None would have produced an error
+            match self.decoder.next_object()?.unwrap() {
+                Object::List(list) => {
+                    list.into_raw()?;
+                },
+                Object::Dict(dict) => {
+                    dict.into_raw()?;
+                },
+                Object::Bytes(_) | Object::Integer(_) => (),
+            }
+            self.len += 1;
+            Ok(Some((k, &self.decoder.source[start..self.decoder.offset])))
+        } else {
+            self.finished = true;
+            Ok(None)
+        }
+    }
+
     /// Consume (and validate the structure of) the rest of the items from the
     /// dictionary. This method should be used to check for encoding errors if
     /// [`DictDecoder::next_pair`] is not called until it returns `Ok(None)`.
     pub fn consume_all(&mut self) -> Result<(), Error> {
-        while self.next_pair()?.is_some() {
-            // just drop the items
+        self.consume_with(|_| Ok(()))
+    }
+
+    /// Consume the rest of the dictionary like [`DictDecoder::consume_all`], passing every
+    /// token to `visit`, up to and including the `End` of the dictionary itself.
+    pub(crate) fn consume_with(
+        &mut self,
+        visit: impl FnMut(Token<'ser>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        if self.finished {
+            return Ok(());
         }
-        Ok(())
+        self.finished = true;
+        self.decoder.skip_container(
+            Level {
+                dict: true,
+                len: self.len,
+                max_len: self.max_len,
+                value_next: false,
+            },
+            visit,
+        )
     }
 
     /// Get the raw bytes that made up this dictionary
@@ -298,22 +804,51 @@ impl<'obj, 'ser: 'obj> Drop for DictDecoder<'obj, 'ser> {
 impl<'obj, 'ser: 'obj> ListDecoder<'obj, 'ser> {
     fn new(decoder: &'obj mut Decoder<'ser>) -> Self {
         let offset = decoder.offset - 1;
+        let max_len = decoder.max_container_len;
         ListDecoder {
             decoder,
             finished: false,
             start_point: offset,
+            len: 0,
+            max_len,
         }
     }
 
+    /// Limit the number of elements of this list, overriding the limit set with
+    /// [`Decoder::with_max_container_len`].
+    ///
+    /// ```
+    /// use bendy::decoding::Decoder;
+    ///
+    /// let mut decoder = Decoder::new(b"li1ei2ee");
+    /// let mut list = decoder
+    ///     .next_object()
+    ///     .unwrap()
+    ///     .unwrap()
+    ///     .try_into_list()
+    ///     .unwrap()
+    ///     .with_max_len(1);
+    /// assert!(list.next_object().unwrap().is_some());
+    /// assert!(list.next_object().is_err());
+    /// ```
+    pub fn with_max_len(mut self, new_max_len: usize) -> Self {
+        self.max_len = new_max_len;
+        self
+    }
+
     /// Get the next item from the list. Returns `Ok(None)` at the end of the list
     pub fn next_object<'item>(&'item mut self) -> Result<Option<Object<'item, 'ser>>, Error> {
         if self.finished {
             return Ok(None);
         }
+        self.decoder
+            .check_container_len(self.len, self.max_len, "elements in a list")?;
 
         let item = self.decoder.next_object()?;
         if item.is_none() {
             self.finished = true;
+        } else {
+            self.len += 1;
         }
 
         Ok(item)
@@ -325,10 +860,28 @@ impl<'obj, 'ser: 'obj> ListDecoder<'obj, 'ser> {
     ///
     /// [`Ok(())`]: https://doc.rust-lang.org/std/result/enum.Result.html#variant.Ok
     pub fn consume_all(&mut self) -> Result<(), Error> {
-        while self.next_object()?.is_some() {
-            // just drop the items
+        self.consume_with(|_| Ok(()))
+    }
+
+    /// Consume the rest of the list like [`ListDecoder::consume_all`], passing every token
+    /// to `visit`, up to and including the `End` of the list itself.
+    pub(crate) fn consume_with(
+        &mut self,
+        visit: impl FnMut(Token<'ser>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        if self.finished {
+            return Ok(());
         }
-        Ok(())
+        self.finished = true;
+        self.decoder.skip_container(
+            Level {
+                dict: false,
+                len: self.len,
+                max_len: self.max_len,
+                value_next: false,
+            },
+            visit,
+        )
     }
 
     /// Get the raw bytes that made up this list
@@ -346,11 +899,18 @@ impl<'obj, 'ser: 'obj> Drop for ListDecoder<'obj, 'ser> {
 }
 
 #[cfg(test)]
+#[allow(
+    clippy::needless_borrow,
+    clippy::redundant_slicing,
+    clippy::redundant_static_lifetimes
+)]
 mod test {
 
     #[cfg(not(feature = "std"))]
-    use alloc::{vec, vec::Vec};
+    use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
     use core::iter;
+    #[cfg(feature = "std")]
+    use std::collections::BTreeMap;
 
     use regex;
 
@@ -358,7 +918,7 @@ mod test {
 
     static SIMPLE_MSG: &'static [u8] = b"d3:bari1e3:fooli2ei3eee";
 
-    fn decode_tokens(msg: &[u8]) -> Vec<Token> {
+    fn decode_tokens(msg: &[u8]) -> Vec<Token<'_>> {
         let tokens: Vec<Result<Token, Error>> = Decoder::new(msg).tokens().collect();
         if tokens.iter().all(Result::is_ok) {
             tokens.into_iter().map(Result::unwrap).collect()
@@ -466,6 +1026,202 @@ mod test {
         decode_err(b"ie", r"Expected.*got 'e'");
     }
 
+    #[test]
+    fn errors_should_record_their_offset() {
+        let error = Decoder::new(b"d1:bi1e1:ai2ee").tokens().last().unwrap();
+        assert_eq!(error.unwrap_err().offset(), Some(7));
+
+        let error = Decoder::new(b"li1e").tokens().last().unwrap();
+        assert_eq!(error.unwrap_err().offset(), Some(4));
+    }
+
+    #[test]
+    fn options_should_limit_strings_and_tokens() {
+        let options = DecodeOptions::new()
+            .with_max_string_len(3)
+            .with_max_tokens(4);
+        let decode = |msg| {
+            Decoder::with_options(msg, &options)
+                .tokens()
+                .last()
+                .unwrap()
+        };
+
+        assert!(decode(b"l3:abce").is_ok());
+        assert!(decode(b"l4:abcde").is_err());
+        assert!(decode(b"li1ei2ee").is_ok());
+        assert!(decode(b"li1ei2ei3ee").is_err());
+        assert!(
+            Decoder::with_options(b"lle", &DecodeOptions::new().with_max_depth(1))
+                .tokens()
+                .last()
+                .unwrap()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn inputs_should_be_limited_in_length() {
+        let options = DecodeOptions::new().with_max_input_len(6);
+        assert_eq!(
+            options.from_bencode::<Vec<i64>>(b"li12ee").unwrap(),
+            vec![12]
+        );
+
+        let mut tokens = Decoder::with_options(b"li123ee", &options).tokens();
+        let error = tokens.next().unwrap().unwrap_err();
+        assert_eq!(error.code(), "decode.limit_exceeded");
+        assert_eq!(error.offset(), Some(0));
+
+        let mut decoder = Decoder::with_options(b"li123ee", &options);
+        assert!(decoder.next_object().is_err());
+        assert!(decoder.next_object().is_err());
+    }
+
+    #[test]
+    fn lenient_mode_should_normalize_broken_torrents() {
+        fn tokens(msg: &[u8], mode: Mode) -> Result<Vec<Token<'_>>, Error> {
+            Decoder::new(msg).with_mode(mode).tokens().collect()
+        }
+        let canonical = b"d7:comment0:4:infod6:lengthi1e4:name1:aee";
+        let expected = tokens(canonical, Mode::Strict).unwrap();
+
+        // Broken variants of the same torrent as produced by real-world encoders and tools
+        let broken: &[&[u8]] = &[
+            b"d7:comment0:4:infod6:lengthi1e4:name1:aee\r\n",
+            b"d7:comment0:4:infod6:lengthi1e4:name1:aee\0\0\0\0",
+            b"d\n  7:comment 0:\n  4:info d\n    6:length i1e\n    4:name 1:a\n  e\ne\n",
+            b"d7:comment-1:4:infod6:lengthi1e4:name1:aee",
+        ];
+        for msg in broken {
+            assert_eq!(tokens(msg, Mode::Lenient).unwrap(), expected);
+            assert!(tokens(msg, Mode::Strict).is_err());
+        }
+
+        // Separators within tokens and other defects are still rejected
+        assert!(tokens(b"i1 e", Mode::Lenient).is_err());
+        assert!(tokens(b"3 :abc", Mode::Lenient).is_err());
+        assert!(tokens(b"-0:", Mode::Lenient).is_err());
+        assert!(tokens(b"d1:bi1e1:ai2ee", Mode::Lenient).is_err());
+
+        let options = DecodeOptions::new()
+            .with_mode(Mode::Lenient)
+            .with_max_container_len(2);
+        assert!(options.from_bencode::<Vec<i64>>(b"li1e i2e \ne").is_ok());
+        assert!(options
+            .from_bencode::<Vec<i64>>(b"li1e i2e i3e\ne")
+            .is_err());
+
+        let string = tokens(b"-5:", Mode::Lenient).unwrap();
+        assert_eq!(string, [Token::String(b"")]);
+        let mut decoder = Decoder::new(b" -5:i1e").with_mode(Mode::Lenient);
+        decoder.next_object().unwrap();
+        assert_eq!(decoder.offset, 4);
+    }
+
+    #[test]
+    fn scanning_should_agree_with_the_bytewise_scan() {
+        let inputs: &[&[u8]] = &[
+            b"0e",
+            b"-0e",
+            b"00e",
+            b"-e",
+            b"e",
+            b"",
+            b"12",
+            b"123e",
+            b"-123e",
+            b"1x3e",
+            b"12:",
+            b"012:",
+            b"1-2e",
+            b"-01e",
+            b"99999999999999999999999e",
+        ];
+        for input in inputs {
+            for terminator in ['e', ':'].iter() {
+                let expected = scan_int_bytewise(input, 0, *terminator);
+                assert_eq!(scan_int(input, 0, *terminator), expected, "{:?}", input);
+            }
+        }
+        assert_eq!(scan_int(b"i42e", 1, 'e'), Ok(3));
+        assert!(scan_int(b"i42", 3, 'e').is_err());
+    }
+
+    #[test]
+    fn integers_should_be_limited_in_digits() {
+        let decode = |msg: &[u8]| {
+            Decoder::new(msg)
+                .with_max_integer_digits(3)
+                .tokens()
+                .last()
+                .unwrap()
+                .map(|_| ())
+        };
+
+        assert!(decode(b"i999e").is_ok());
+        assert!(decode(b"i-999e").is_ok());
+        assert!(decode(b"3:abc").is_ok());
+        assert_eq!(
+            decode(b"i1000e").unwrap_err().code(),
+            "decode.limit_exceeded"
+        );
+        assert_eq!(
+            decode(b"i-1000e").unwrap_err().code(),
+            "decode.limit_exceeded"
+        );
+        assert_eq!(
+            decode(b"1000:").unwrap_err().code(),
+            "decode.limit_exceeded"
+        );
+
+        let mut hostile = vec![b'i'];
+        hostile.extend(iter::repeat(b'9').take(1 << 20));
+        assert_eq!(
+            decode(&hostile).unwrap_err().code(),
+            "decode.limit_exceeded"
+        );
+
+        // Length prefixes can't have more digits than the largest u64 by default
+        let mut hostile = iter::repeat(b'1').take(21).collect::<Vec<_>>();
+        hostile.push(b':');
+        let error = Decoder::new(&hostile).tokens().last().unwrap().unwrap_err();
+        assert_eq!(error.code(), "decode.limit_exceeded");
+    }
+
+    #[test]
+    fn raw_pairs_should_span_their_values() {
+        let mut decoder = Decoder::new(b"d1:ai-1e1:b2:xy1:cl1:ae1:dd1:ei0eee");
+        let mut dict = decoder
+            .next_object()
+            .unwrap()
+            .unwrap()
+            .try_into_dictionary()
+            .unwrap();
+        let mut pairs = Vec::new();
+        while let Some(pair) = dict.next_pair_raw().unwrap() {
+            pairs.push(pair);
+        }
+        assert_eq!(
+            pairs,
+            [
+                (&b"a"[..], &b"i-1e"[..]),
+                (b"b", b"2:xy"),
+                (b"c", b"l1:ae"),
+                (b"d", b"d1:ei0ee"),
+            ]
+        );
+
+        let mut decoder = Decoder::new(b"d1:ali1e");
+        let mut dict = decoder
+            .next_object()
+            .unwrap()
+            .unwrap()
+            .try_into_dictionary()
+            .unwrap();
+        assert!(dict.next_pair_raw().is_err());
+    }
+
     #[test]
     fn recursion_should_be_limited() {
         let mut msg = Vec::new();
@@ -491,6 +1247,49 @@ mod test {
             .is_err());
     }
 
+    #[test]
+    fn deep_nesting_should_be_skipped_without_recursion() {
+        let depth = 1_000_000;
+        let mut msg = Vec::new();
+        for _ in 0..depth / 2 {
+            msg.extend_from_slice(b"ld1:a");
+        }
+        msg.extend_from_slice(b"i1e");
+        msg.extend(iter::repeat(b'e').take(depth));
+        msg.extend_from_slice(b"i2e");
+
+        let mut decoder = Decoder::new(&msg).with_max_depth(depth);
+        drop(decoder.next_object().unwrap());
+        let token = decoder.tokens().next().unwrap().unwrap();
+        assert_eq!(token, Token::Num("2"));
+
+        let mut decoder = Decoder::new(&msg).with_max_depth(depth - 1);
+        let mut list = decoder
+            .next_object()
+            .unwrap()
+            .unwrap()
+            .try_into_list()
+            .unwrap();
+        let error = list.consume_all().unwrap_err();
+        assert_eq!(error.code(), "structure.nesting_too_deep");
+    }
+
+    #[test]
+    fn nested_containers_should_be_limited_when_skipped() {
+        let mut decoder = Decoder::new(b"lld1:ai1e1:bi2eeee").with_max_container_len(1);
+        let mut list = decoder
+            .next_object()
+            .unwrap()
+            .unwrap()
+            .try_into_list()
+            .unwrap();
+        assert!(list.consume_all().is_err());
+
+        let mut decoder = Decoder::new(b"lld1:ai1eeeei1e").with_max_container_len(1);
+        drop(decoder.next_object().unwrap());
+        assert_eq!(decoder.tokens().next().unwrap().unwrap(), Token::Num("1"));
+    }
+
     #[test]
     fn dict_drop_should_consume_struct() {
         let mut decoder = Decoder::new(b"d3:fooi1e3:quxi2eei1000e");
@@ -791,4 +1590,94 @@ mod test {
                 .unwrap_err()
         );
     }
+
+    #[test]
+    fn container_len_limit_should_apply_to_all_containers() {
+        let mut decoder = Decoder::new(b"ld1:ai1eeli1ei2eee").with_max_container_len(1);
+        let mut list = decoder
+            .next_object()
+            .unwrap()
+            .unwrap()
+            .try_into_list()
+            .unwrap();
+
+        let mut dict = list
+            .next_object()
+            .unwrap()
+            .unwrap()
+            .try_into_dictionary()
+            .unwrap();
+        assert!(dict.next_pair().unwrap().is_some());
+        assert!(dict.next_pair().unwrap().is_none());
+        drop(dict);
+
+        let err = list.next_object().map(|_| ()).unwrap_err();
+        assert!(format!("{}", err).contains("more than 1 elements in a list"));
+    }
+
+    #[test]
+    fn container_len_limit_should_be_overridable() {
+        let mut decoder = Decoder::new(b"d1:ali1ei2ei3eee").with_max_container_len(1);
+        let mut dict = decoder
+            .next_object()
+            .unwrap()
+            .unwrap()
+            .try_into_dictionary()
+            .unwrap();
+
+        let (_, values) = dict.next_pair().unwrap().unwrap();
+        let mut values = values.try_into_list().unwrap().with_max_len(3);
+        while values.next_object().unwrap().is_some() {}
+    }
+
+    #[test]
+    fn container_len_errors_should_be_latched() {
+        let mut decoder = Decoder::new(b"d1:ali1ei2eee");
+        let mut dict = decoder
+            .next_object()
+            .unwrap()
+            .unwrap()
+            .try_into_dictionary()
+            .unwrap();
+
+        let (_, values) = dict.next_pair().unwrap().unwrap();
+        let mut values = values.try_into_list().unwrap().with_max_len(1);
+        assert!(values.next_object().unwrap().is_some());
+        assert!(values.next_object().is_err());
+        drop(values);
+
+        assert!(dict.next_pair().is_err());
+    }
+
+    #[test]
+    fn empty_keys_should_follow_the_policy() {
+        let msg = b"d0:i1e1:ai2ee";
+        assert!(Decoder::new(msg).tokens().all(|token| token.is_ok()));
+        assert!(Decoder::new(msg)
+            .with_empty_key_policy(EmptyKeyPolicy::Accept)
+            .tokens()
+            .all(|token| token.is_ok()));
+
+        let tokens: Vec<_> = Decoder::new(msg)
+            .with_empty_key_policy(EmptyKeyPolicy::Reject)
+            .tokens()
+            .collect();
+        assert_eq!(tokens.len(), 2);
+        assert!(format!("{}", tokens[1].as_ref().unwrap_err()).contains("Empty dict key"));
+    }
+
+    #[test]
+    fn options_should_reject_empty_keys_on_request() {
+        let options = DecodeOptions::new().with_mode(Mode::Strict);
+        let map = options
+            .from_bencode::<BTreeMap<String, i64>>(b"d0:i1ee")
+            .unwrap();
+        assert_eq!(map.get(""), Some(&1));
+
+        let options = options.with_empty_key_policy(EmptyKeyPolicy::Reject);
+        let error = options
+            .from_bencode::<BTreeMap<String, i64>>(b"d0:i1ee")
+            .unwrap_err();
+        assert_eq!(error.code(), "structure.empty_key");
+    }
 }
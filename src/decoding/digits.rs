@@ -0,0 +1,113 @@
+use core::{num::ParseIntError, str::FromStr};
+
+use crate::decoding::Error;
+
+/// Integers parsed directly from the digits of a bencoded integer or string length
+pub(crate) trait FromDigits: Sized {
+    /// Parse decimal digits with an optional minus sign, or return `None` if they are
+    /// malformed or out of range.
+    ///
+    /// Unlike [`str::parse`], this doesn't accept a plus sign; the decoder already
+    /// checked the digits, so this only has to detect overflows.
+    fn from_digits(digits: &[u8]) -> Option<Self>;
+}
+
+macro_rules! impl_from_digits {
+    ($($type:ty)*) => {$(
+        impl FromDigits for $type {
+            #[allow(clippy::unnecessary_cast)]
+            fn from_digits(digits: &[u8]) -> Option<Self> {
+                let (negative, digits) = match digits.split_first() {
+                    Some((b'-', digits)) => (true, digits),
+                    _ => (false, digits),
+                };
+                // Unsigned types only parse digits
+                if digits.is_empty() || (negative && <$type>::MIN == 0) {
+                    return None;
+                }
+
+                let mut value: $type = 0;
+                for &digit in digits {
+                    let digit = digit.wrapping_sub(b'0');
+                    if digit > 9 {
+                        return None;
+                    }
+                    value = value.checked_mul(10)?;
+                    // Negative numbers are accumulated downwards, so their minimum fits
+                    value = if negative {
+                        value.checked_sub(digit as $type)?
+                    } else {
+                        value.checked_add(digit as $type)?
+                    };
+                }
+
+                Some(value)
+            }
+        }
+    )*}
+}
+
+impl_from_digits!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize);
+
+/// Parse the text of a bencoded integer, falling back to [`str::parse`] to report why
+/// the fast path failed.
+pub(crate) fn parse_integer<T>(text: &str) -> Result<T, Error>
+where
+    T: FromDigits + FromStr<Err = ParseIntError>,
+{
+    match T::from_digits(text.as_bytes()) {
+        Some(value) => Ok(value),
+        None => text.parse().map_err(Error::from),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn digits_should_parse_like_str_parse() {
+        let texts = [
+            "0",
+            "7",
+            "-7",
+            "255",
+            "256",
+            "-128",
+            "-129",
+            "127",
+            "4294967295",
+            "4294967296",
+            "-9223372036854775808",
+            "-9223372036854775809",
+            "9223372036854775807",
+            "18446744073709551615",
+            "18446744073709551616",
+            "",
+            "-",
+            "-0",
+            "+1",
+            "1a",
+            "0x1",
+        ];
+        for text in texts.iter() {
+            macro_rules! check {
+                ($($type:ty)*) => {$(
+                    let expected = text.parse::<$type>().ok().filter(|_| !text.starts_with('+'));
+                    assert_eq!(<$type>::from_digits(text.as_bytes()), expected, "{}", text);
+                )*}
+            }
+            check!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize);
+        }
+
+        assert_eq!(
+            <i128>::from_digits(i128::MIN.to_string().as_bytes()),
+            Some(i128::MIN)
+        );
+        assert_eq!(parse_integer::<u8>("+1").unwrap(), 1);
+        assert!(parse_integer::<u8>("256").is_err());
+    }
+}
@@ -3,7 +3,10 @@ use alloc::{
     str::Utf8Error,
     string::{FromUtf8Error, String, ToString},
 };
-use core::{fmt::Display, num::ParseIntError};
+use core::{
+    fmt::{self, Display},
+    num::ParseIntError,
+};
 
 #[cfg(feature = "std")]
 use std::sync::Arc;
@@ -12,25 +15,64 @@ use snafu::Snafu;
 
 use crate::state_tracker;
 
+/// A decoding error, with the location where it occurred if known.
+///
+/// The [path](Self::path) of the value that failed to decode, like
+/// `info.files[3].length`, is collected as the error is returned through the
+/// [`FromBencode`] implementations of containers and from [`context`](Self::context). The
+/// byte [offset](Self::offset) is recorded for errors detected by the [`Decoder`] itself,
+/// like syntax errors, unsorted keys and exceeded limits. Both are included in the
+/// error message.
+///
+/// [`FromBencode`]: crate::decoding::FromBencode
+/// [`Decoder`]: crate::decoding::Decoder
 #[derive(Debug, Clone, Snafu)]
+#[snafu(display("{}{}", source, Location { path: context, offset }))]
 pub struct Error {
     context: Option<String>,
+    offset: Option<usize>,
     source: ErrorKind,
 }
 
+/// The suffix of error messages describing where the error occurred
+struct Location<'a> {
+    path: &'a Option<String>,
+    offset: &'a Option<usize>,
+}
+
+impl Display for Location<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = self.path {
+            write!(f, " at `{}`", path)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " (offset {})", offset)?;
+        }
+        Ok(())
+    }
+}
+
 // An enumeration of potential errors that appear during bencode deserialization.
 #[derive(Debug, Clone, Snafu)]
+#[non_exhaustive]
 pub enum ErrorKind {
     /// Error that occurs if the serialized structure contains invalid semantics.
     #[cfg(feature = "std")]
     #[snafu(display("malformed content discovered: {}", source))]
-    MalformedContent { source: Arc<dyn std::error::Error + Send + Sync> },
+    MalformedContent {
+        source: Arc<dyn std::error::Error + Send + Sync>,
+    },
 
     /// Error that occurs if the serialized structure contains invalid semantics.
     #[cfg(not(feature = "std"))]
     #[snafu(display("malformed content discovered"))]
     MalformedContent,
 
+    /// Error that occurs if the input exceeds one of the configured decoding limits.
+    /// Exceeding the nesting depth is reported as a `structure.nesting_too_deep` error instead.
+    #[snafu(display("limit exceeded: {}", limit))]
+    LimitExceeded { limit: String },
+
     /// Error that occurs if the serialized structure is incomplete.
     #[snafu(display("missing field: {}", field))]
     MissingField { field: String },
@@ -53,14 +95,67 @@ pub enum ErrorKind {
     },
 }
 
+impl ErrorKind {
+    /// A stable identifier of the kind of error, see [`Error::code`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "std")]
+            ErrorKind::MalformedContent { .. } => "decode.malformed_content",
+            #[cfg(not(feature = "std"))]
+            ErrorKind::MalformedContent => "decode.malformed_content",
+            ErrorKind::LimitExceeded { .. } => "decode.limit_exceeded",
+            ErrorKind::MissingField { .. } => "decode.missing_field",
+            ErrorKind::StructureError { source } => source.code(),
+            ErrorKind::UnexpectedField { .. } => "decode.unexpected_field",
+            ErrorKind::UnexpectedToken { .. } => "decode.unexpected_token",
+        }
+    }
+}
+
 pub trait ResultExt {
+    /// Add the location of a failed decoding to the error, see [`Error::context`].
+    ///
+    /// The context is only formatted if the result is an error. Pass a computed context
+    /// with `format_args!` instead of `format!`, so that no string is allocated for
+    /// successful results.
     fn context(self, context: impl Display) -> Self;
 }
 
 impl Error {
+    /// A stable identifier of the kind of error, like `decode.missing_field`.
+    ///
+    /// Unlike the messages, codes never change between versions, so they can be used to
+    /// classify failures in logs or map them to protocol errors. Structural errors, which
+    /// are shared with encoding, use the code of the [`StructureError`], like
+    /// `structure.unsorted_keys`.
+    ///
+    /// [`StructureError`]: crate::state_tracker::StructureError
+    pub fn code(&self) -> &'static str {
+        self.source.code()
+    }
+
+    /// The path of the value that failed to decode, like `info.files[3].length`
+    pub fn path(&self) -> Option<&str> {
+        self.context.as_deref()
+    }
+
+    /// The offset into the input at which the decoder detected the error
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// Record the offset at which the error was detected, unless one is already known
+    pub(crate) fn at_offset(mut self, offset: usize) -> Self {
+        self.offset.get_or_insert(offset);
+        self
+    }
+
+    /// Prepend `context` to the path of the error. A context starting with `[`, like a
+    /// list index, is joined without a separating dot.
     pub fn context(mut self, context: impl Display) -> Self {
         if let Some(current) = self.context.as_mut() {
-            *current = format!("{}.{}", context, current);
+            let separator = if current.starts_with('[') { "" } else { "." };
+            *current = format!("{}{}{}", context, separator, current);
         } else {
             self.context = Some(context.to_string());
         }
@@ -84,6 +179,13 @@ impl Error {
         Self::from(ErrorKind::MalformedContent)
     }
 
+    /// Returns a `Error::LimitExceeded` which contains a description of the exceeded limit.
+    pub fn limit_exceeded(limit: impl Display) -> Self {
+        Error::from(ErrorKind::LimitExceeded {
+            limit: limit.to_string(),
+        })
+    }
+
     // Returns a `Error::MissingField` which contains the name of the field.
     pub fn missing_field(field_name: impl Display) -> Self {
         Error::from(ErrorKind::MissingField {
@@ -111,6 +213,7 @@ impl From<ErrorKind> for Error {
     fn from(kind: ErrorKind) -> Self {
         Self {
             context: None,
+            offset: None,
             source: kind,
         }
     }
@@ -146,9 +249,40 @@ impl<T> ResultExt for Result<T, Error> {
     }
 }
 
+#[test]
+fn decoding_error_codes_should_identify_the_kind() {
+    use crate::state_tracker::StructureError;
+
+    assert_eq!(
+        Error::missing_field("info").context("torrent").code(),
+        "decode.missing_field"
+    );
+    assert_eq!(
+        Error::from(StructureError::UnsortedKeys).code(),
+        "structure.unsorted_keys"
+    );
+}
+
+#[test]
+fn decoding_errors_should_describe_their_location() {
+    let error = Error::missing_field("length")
+        .context("[3]")
+        .context("files")
+        .context("info");
+    assert_eq!(error.path(), Some("info.files[3]"));
+    assert_eq!(
+        error.to_string(),
+        "missing field: length at `info.files[3]`"
+    );
+
+    let error = Error::limit_exceeded("too long").at_offset(7).at_offset(9);
+    assert_eq!(error.offset(), Some(7));
+    assert_eq!(error.to_string(), "limit exceeded: too long (offset 7)");
+}
+
 #[test]
 fn decoding_errors_are_sync_send() {
-    use crate::decoding::error::{ ErrorKind, Error };
+    use crate::decoding::error::{Error, ErrorKind};
     fn is_send<T: Send>() {}
     fn is_sync<T: Sync>() {}
     is_send::<Error>();
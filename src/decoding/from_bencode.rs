@@ -1,16 +1,27 @@
 #[cfg(not(feature = "std"))]
-use alloc::{collections::BTreeMap, rc::Rc, string::String, vec::Vec};
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, LinkedList, VecDeque},
+    rc::Rc,
+    string::String,
+    sync::Arc,
+    vec::Vec,
+};
 
 #[cfg(feature = "std")]
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, LinkedList, VecDeque},
     hash::{BuildHasher, Hash},
     rc::Rc,
+    sync::Arc,
 };
 
+use core::fmt::{self, Display, Formatter};
+
 use crate::{
-    decoding::{Decoder, Error, Object},
-    encoding::AsString,
+    decoding::{parse_integer, Decoder, Error, Object, ResultExt},
+    encoding::{max_depth, AsHex, AsString},
+    hex,
     state_tracker::StructureError,
 };
 
@@ -49,9 +60,7 @@ macro_rules! impl_from_bencode_for_integer {
                 Self: Sized,
             {
                 let content = object.try_into_integer()?;
-                let number = content.parse::<$type>()?;
-
-                Ok(number)
+                parse_integer(content)
             }
         }
     )*}
@@ -59,6 +68,30 @@ macro_rules! impl_from_bencode_for_integer {
 
 impl_from_bencode_for_integer!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize);
 
+macro_rules! impl_from_bencode_for_iterable {
+    ($($type:ident)*) => {$(
+        impl<ContentT: FromBencode> FromBencode for $type<ContentT> {
+            const EXPECTED_RECURSION_DEPTH: usize = ContentT::EXPECTED_RECURSION_DEPTH + 1;
+
+            fn decode_bencode_object(object: Object) -> Result<Self, Error>
+            where
+                Self: Sized,
+            {
+                let mut list = object.try_into_list()?;
+                let mut results = $type::new();
+
+                while let Some(object) = list.next_object()? {
+                    let item = ContentT::decode_bencode_object(object)
+                        .context(format_args!("[{}]", results.len()))?;
+                    results.push_back(item);
+                }
+
+                Ok(results)
+            }
+        }
+    )*}
+}
+
 impl<ContentT: FromBencode> FromBencode for Vec<ContentT> {
     const EXPECTED_RECURSION_DEPTH: usize = ContentT::EXPECTED_RECURSION_DEPTH + 1;
 
@@ -70,7 +103,8 @@ impl<ContentT: FromBencode> FromBencode for Vec<ContentT> {
         let mut results = Vec::new();
 
         while let Some(object) = list.next_object()? {
-            let item = ContentT::decode_bencode_object(object)?;
+            let item = ContentT::decode_bencode_object(object)
+                .context(format_args!("[{}]", results.len()))?;
             results.push(item);
         }
 
@@ -78,6 +112,85 @@ impl<ContentT: FromBencode> FromBencode for Vec<ContentT> {
     }
 }
 
+impl_from_bencode_for_iterable!(VecDeque LinkedList);
+
+/// Options are decoded from a list holding at most one value, matching their encoding.
+impl<ContentT: FromBencode> FromBencode for Option<ContentT> {
+    const EXPECTED_RECURSION_DEPTH: usize = ContentT::EXPECTED_RECURSION_DEPTH + 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        let mut list = object.try_into_list()?;
+        let value = match list.next_object()? {
+            Some(object) => Some(ContentT::decode_bencode_object(object).context("[0]")?),
+            None => None,
+        };
+
+        if list.next_object()?.is_some() {
+            return Err(Error::malformed_content(ListLength {
+                expected: 1,
+                at_most: true,
+            }));
+        }
+
+        Ok(value)
+    }
+}
+
+macro_rules! impl_from_bencode_for_tuple {
+    ($(($($name:ident)+))*) => {$(
+        /// Tuples are decoded from lists with exactly one element per field.
+        impl<$($name: FromBencode),+> FromBencode for ($($name,)+) {
+            const EXPECTED_RECURSION_DEPTH: usize =
+                max_depth(&[$($name::EXPECTED_RECURSION_DEPTH),+]) + 1;
+
+            #[allow(non_snake_case, unused_assignments)]
+            fn decode_bencode_object(object: Object) -> Result<Self, Error>
+            where
+                Self: Sized,
+            {
+                let expected = [$(stringify!($name)),+].len();
+                let length_error = || {
+                    Error::malformed_content(ListLength {
+                        expected,
+                        at_most: false,
+                    })
+                };
+
+                let mut list = object.try_into_list()?;
+                let mut index = 0;
+                $(
+                    let $name = match list.next_object()? {
+                        Some(object) => $name::decode_bencode_object(object)
+                            .context(format_args!("[{}]", index))?,
+                        None => return Err(length_error()),
+                    };
+                    index += 1;
+                )+
+
+                if list.next_object()?.is_some() {
+                    return Err(length_error());
+                }
+
+                Ok(($($name,)+))
+            }
+        }
+    )*}
+}
+
+impl_from_bencode_for_tuple!(
+    (A)
+    (A B)
+    (A B C)
+    (A B C D)
+    (A B C D E)
+    (A B C D E F)
+    (A B C D E F G)
+    (A B C D E F G H)
+);
+
 impl FromBencode for String {
     const EXPECTED_RECURSION_DEPTH: usize = 0;
 
@@ -107,8 +220,9 @@ where
         let mut result = BTreeMap::default();
 
         while let Some((key, value)) = dict.next_pair()? {
-            let key = K::decode_bencode_object(Object::Bytes(key))?;
-            let value = V::decode_bencode_object(value)?;
+            let context = String::from_utf8_lossy(key);
+            let key = K::decode_bencode_object(Object::Bytes(key)).context(&context)?;
+            let value = V::decode_bencode_object(value).context(&context)?;
 
             result.insert(key, value);
         }
@@ -134,8 +248,9 @@ where
         let mut result = HashMap::default();
 
         while let Some((key, value)) = dict.next_pair()? {
-            let key = K::decode_bencode_object(Object::Bytes(key))?;
-            let value = V::decode_bencode_object(value)?;
+            let context = String::from_utf8_lossy(key);
+            let key = K::decode_bencode_object(Object::Bytes(key)).context(&context)?;
+            let value = V::decode_bencode_object(value).context(&context)?;
 
             result.insert(key, value);
         }
@@ -144,33 +259,96 @@ where
     }
 }
 
-impl<T: FromBencode> FromBencode for Rc<T> {
-    const EXPECTED_RECURSION_DEPTH: usize = T::EXPECTED_RECURSION_DEPTH;
+macro_rules! impl_from_bencode_for_pointer {
+    ($($type:ident)*) => {$(
+        impl<T: FromBencode> FromBencode for $type<T> {
+            const EXPECTED_RECURSION_DEPTH: usize = T::EXPECTED_RECURSION_DEPTH;
+
+            fn decode_bencode_object(object: Object) -> Result<Self, Error>
+            where
+                Self: Sized,
+            {
+                T::decode_bencode_object(object).map($type::new)
+            }
+        }
+    )*}
+}
+
+impl_from_bencode_for_pointer!(Box Rc Arc);
+
+impl FromBencode for AsString<Vec<u8>> {
+    const EXPECTED_RECURSION_DEPTH: usize = 0;
 
     fn decode_bencode_object(object: Object) -> Result<Self, Error>
     where
         Self: Sized,
     {
-        T::decode_bencode_object(object).map(Rc::new)
+        object.try_into_bytes().map(Vec::from).map(AsString)
     }
 }
 
-impl FromBencode for AsString<Vec<u8>> {
+impl<T> FromBencode for AsHex<T>
+where
+    T: Default + AsMut<[u8]>,
+{
     const EXPECTED_RECURSION_DEPTH: usize = 0;
 
     fn decode_bencode_object(object: Object) -> Result<Self, Error>
     where
         Self: Sized,
     {
-        object.try_into_bytes().map(Vec::from).map(AsString)
+        let digits = object.try_into_bytes()?;
+        let mut buffer = T::default();
+        let bytes = buffer.as_mut();
+
+        match hex::decode_into(digits, bytes) {
+            Some(()) => Ok(AsHex(buffer)),
+            None => Err(Error::malformed_content(InvalidHex { len: bytes.len() })),
+        }
     }
 }
 
+/// A hex string didn't encode the expected number of bytes
+#[derive(Debug)]
+struct InvalidHex {
+    len: usize,
+}
+
+impl Display for InvalidHex {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "expected {} hex digits", self.len * 2)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidHex {}
+
+/// A list decoded into an `Option` or tuple had the wrong number of elements
+#[derive(Debug)]
+struct ListLength {
+    expected: usize,
+    at_most: bool,
+}
+
+impl Display for ListLength {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let at_most = if self.at_most { "at most " } else { "" };
+        write!(
+            f,
+            "expected a list of {}{} elements",
+            at_most, self.expected
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ListLength {}
+
 #[cfg(test)]
 mod test {
 
     #[cfg(not(feature = "std"))]
-    use alloc::{format, vec::Vec};
+    use alloc::{format, string::String, vec::Vec};
 
     use crate::encoding::AsString;
 
@@ -213,4 +391,38 @@ mod test {
     fn from_bencode_to_as_string_should_fail_for_dictionary() {
         AsString::<Vec<u8>>::from_bencode(&b"d1:a1:ae"[..]).unwrap();
     }
+
+    #[test]
+    fn options_and_tuples_should_decode_from_lists() {
+        assert_eq!(Option::<u8>::from_bencode(b"le").unwrap(), None);
+        assert_eq!(Option::<u8>::from_bencode(b"li1ee").unwrap(), Some(1));
+        assert!(Option::<u8>::from_bencode(b"li1ei2ee").is_err());
+
+        let tuple = <(u8, String, Option<u8>)>::from_bencode(b"li1e3:twolee").unwrap();
+        assert_eq!(tuple, (1, String::from("two"), None));
+        assert!(<(u8, u8)>::from_bencode(b"li1ee").is_err());
+        assert!(<(u8, u8)>::from_bencode(b"li1ei2ei3ee").is_err());
+    }
+
+    #[test]
+    fn containers_should_decode_like_vec() {
+        let list = LinkedList::<u8>::from_bencode(b"li1ei2ee").unwrap();
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(VecDeque::<u8>::from_bencode(b"li1ee").unwrap(), [1]);
+        assert_eq!(*Box::<u8>::from_bencode(b"i3e").unwrap(), 3);
+        assert_eq!(*Arc::<u8>::from_bencode(b"i3e").unwrap(), 3);
+    }
+
+    #[test]
+    fn as_hex_should_accept_both_cases() {
+        let decoded = AsHex::<[u8; 2]>::from_bencode(b"4:aB0f").unwrap();
+        assert_eq!(decoded.0, [0xab, 0x0f]);
+    }
+
+    #[test]
+    fn as_hex_should_reject_invalid_digits() {
+        assert!(AsHex::<[u8; 2]>::from_bencode(b"4:abcg").is_err());
+        assert!(AsHex::<[u8; 2]>::from_bencode(b"5:abcde").is_err());
+        assert!(AsHex::<[u8; 2]>::from_bencode(b"i1e").is_err());
+    }
 }
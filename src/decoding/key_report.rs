@@ -0,0 +1,181 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use crate::decoding::{Decoder, Error};
+
+/// A fixed set of dictionary keys, implemented by the enums of
+/// [`bendy_keys!`](crate::bendy_keys).
+pub trait KeySchema: Copy + 'static {
+    /// All keys, the position of each being its [`index`](Self::index)
+    const ALL: &'static [Self];
+
+    /// Classify a raw dictionary key. Returns `None` if the key is not part of the schema.
+    fn from_bytes(key: &[u8]) -> Option<Self>;
+
+    /// The position of the key in [`ALL`](Self::ALL)
+    fn index(self) -> usize;
+}
+
+/// Which keys of a schema a dict contains, and which keys it has in addition.
+///
+/// A report is either filled while decoding a dict, by passing every key to
+/// [`record`](Self::record), or built from a raw dict with [`scan`](Self::scan). Either
+/// way, each key is only looked up once, in the perfect hash table of the schema. The
+/// keys that are present are kept as a bitmap, e.g. to gather statistics about which
+/// optional keys peers send:
+///
+/// ```
+/// use bendy::{bendy_keys, decoding::KeyReport};
+///
+/// bendy_keys! {
+///     pub enum PingKey {
+///         Id = "id",
+///         Token = "token",
+///         Version = "v",
+///     }
+/// }
+///
+/// let report = KeyReport::<PingKey>::scan(b"d2:id3:abc2:ipi1e1:v2:LTe").unwrap();
+///
+/// assert_eq!(report.bitmap(), &[0b101]);
+/// assert!(report.is_present(PingKey::Version));
+/// assert_eq!(
+///     report
+///         .missing(&[PingKey::Id, PingKey::Token])
+///         .collect::<Vec<_>>(),
+///     [PingKey::Token]
+/// );
+/// assert_eq!(report.unexpected(), &[&b"ip"[..]]);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyReport<'ser, K> {
+    present: Vec<u64>,
+    unexpected: Vec<&'ser [u8]>,
+    schema: PhantomData<K>,
+}
+
+impl<'ser, K: KeySchema> KeyReport<'ser, K> {
+    /// Create a report without any keys.
+    pub fn new() -> Self {
+        KeyReport {
+            present: Vec::new(),
+            unexpected: Vec::new(),
+            schema: PhantomData,
+        }
+    }
+
+    /// Record all keys of the bencoded dict in `dict`, skipping over the values.
+    pub fn scan(dict: &'ser [u8]) -> Result<Self, Error> {
+        let mut report = Self::new();
+        let mut decoder = Decoder::new(dict);
+        let mut dict = decoder
+            .next_object()?
+            .ok_or_else(|| Error::unexpected_token("Dict", "end of input"))?
+            .try_into_dictionary()?;
+        while let Some((key, _)) = dict.next_pair()? {
+            report.record(key);
+        }
+
+        Ok(report)
+    }
+
+    /// Record a key of the dict and return it if it is part of the schema.
+    pub fn record(&mut self, key: &'ser [u8]) -> Option<K> {
+        let known = K::from_bytes(key);
+        match known {
+            Some(known) => {
+                let (word, bit) = (known.index() / 64, known.index() % 64);
+                if self.present.len() <= word {
+                    self.present.resize(word + 1, 0);
+                }
+                self.present[word] |= 1 << bit;
+            },
+            None => self.unexpected.push(key),
+        }
+        known
+    }
+
+    /// Whether the dict contains `key`.
+    pub fn is_present(&self, key: K) -> bool {
+        let (word, bit) = (key.index() / 64, key.index() % 64);
+        self.present.get(word).copied().unwrap_or(0) & (1 << bit) != 0
+    }
+
+    /// The keys that are present, where bit `i % 64` of word `i / 64` stands for the key
+    /// with index `i`. Trailing words without any key are left out.
+    pub fn bitmap(&self) -> &[u64] {
+        &self.present
+    }
+
+    /// The keys of `required` that the dict doesn't contain.
+    pub fn missing<'r>(&'r self, required: &'r [K]) -> impl Iterator<Item = K> + 'r {
+        required
+            .iter()
+            .copied()
+            .filter(move |key| !self.is_present(*key))
+    }
+
+    /// The keys that aren't part of the schema, in the order of the dict.
+    pub fn unexpected(&self) -> &[&'ser [u8]] {
+        &self.unexpected
+    }
+}
+
+impl<K: KeySchema> Default for KeyReport<'_, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    crate::bendy_keys! {
+        enum Key {
+            K0 = "a", K1 = "b", K2 = "c", K3 = "d", K4 = "e", K5 = "f", K6 = "g", K7 = "h",
+            K8 = "i", K9 = "j", K10 = "k", K11 = "l", K12 = "m", K13 = "n", K14 = "o",
+            K15 = "p", K16 = "q", K17 = "r", K18 = "s", K19 = "t", K20 = "u", K21 = "v",
+            K22 = "w", K23 = "x", K24 = "y", K25 = "z", K26 = "A", K27 = "B", K28 = "C",
+            K29 = "D", K30 = "E", K31 = "F", K32 = "G", K33 = "H", K34 = "I", K35 = "J",
+            K36 = "K", K37 = "L", K38 = "M", K39 = "N", K40 = "O", K41 = "P", K42 = "Q",
+            K43 = "R", K44 = "S", K45 = "T", K46 = "U", K47 = "V", K48 = "W", K49 = "X",
+            K50 = "Y", K51 = "Z", K52 = "0", K53 = "1", K54 = "2", K55 = "3", K56 = "4",
+            K57 = "5", K58 = "6", K59 = "7", K60 = "8", K61 = "9", K62 = "-", K63 = "_",
+            K64 = "aa", K65 = "ab",
+        }
+    }
+
+    #[test]
+    fn reports_should_cover_more_than_64_keys() {
+        let report = KeyReport::<Key>::scan(b"d1:ai1e2:abi2e2:zzi3ee").unwrap();
+        assert_eq!(report.bitmap(), &[1, 2]);
+        assert!(report.is_present(Key::K65));
+        assert!(!report.is_present(Key::K64));
+        assert_eq!(
+            report
+                .missing(&[Key::K0, Key::K64, Key::K1])
+                .collect::<Vec<_>>(),
+            [Key::K64, Key::K1]
+        );
+        assert_eq!(report.unexpected(), &[&b"zz"[..]]);
+
+        let mut recorded = KeyReport::new();
+        assert_eq!(recorded.record(b"a"), Some(Key::K0));
+        assert_eq!(recorded.record(b"ab"), Some(Key::K65));
+        assert_eq!(recorded.record(b"zz"), None);
+        assert_eq!(recorded, report);
+    }
+
+    #[test]
+    fn only_dicts_should_be_scanned() {
+        assert!(KeyReport::<Key>::scan(b"le").is_err());
+        assert!(KeyReport::<Key>::scan(b"").is_err());
+        assert!(KeyReport::<Key>::scan(b"d1:bi1e1:ai2ee").is_err());
+        assert!(KeyReport::<Key>::scan(b"de").unwrap().bitmap().is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+use core::fmt::{self, Display, Formatter};
+
+macro_rules! known_keys {
+    ($($(#[$doc:meta])* $variant:ident => $key:literal,)*) => {
+        /// A dictionary key defined by one of the common BitTorrent specifications
+        /// (metainfo files, tracker responses and the KRPC protocol of the DHT).
+        ///
+        /// Use [`KnownKey::from_bytes`] to classify the keys returned by
+        /// [`DictDecoder::next_pair`] without allocating, so that decoders can match
+        /// on an enum instead of comparing byte string literals:
+        ///
+        /// ```
+        /// use bendy::decoding::{Decoder, KnownKey};
+        ///
+        /// let mut decoder = Decoder::new(b"d8:intervali1800e5:peers0:e");
+        /// let mut dict = decoder.next_object().unwrap().unwrap().try_into_dictionary().unwrap();
+        ///
+        /// let mut interval = None;
+        /// while let Some((key, value)) = dict.next_pair().unwrap() {
+        ///     match KnownKey::from_bytes(key) {
+        ///         Some(KnownKey::Interval) => interval = Some(value.try_into_integer().unwrap()),
+        ///         Some(KnownKey::Peers) => (),
+        ///         _ => (), // unknown or irrelevant key
+        ///     }
+        /// }
+        ///
+        /// assert_eq!(interval, Some("1800"));
+        /// ```
+        ///
+        /// The variants are declared in the sort order of their byte representation, so
+        /// the derived `Ord` implementation matches the order in which the keys have to
+        /// appear within a bencoded dictionary.
+        ///
+        /// [`DictDecoder::next_pair`]: crate::decoding::DictDecoder::next_pair
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum KnownKey {
+            $( $(#[$doc])* $variant, )*
+        }
+
+        impl KnownKey {
+            /// All known keys in sorted order.
+            pub const ALL: &'static [KnownKey] = &[$( KnownKey::$variant, )*];
+
+            /// Classify a raw dictionary key. Returns `None` if the key is not known.
+            pub fn from_bytes(key: &[u8]) -> Option<Self> {
+                match key {
+                    $( $key => Some(KnownKey::$variant), )*
+                    _ => None,
+                }
+            }
+
+            /// The byte representation of the key as it appears on the wire.
+            pub fn as_bytes(self) -> &'static [u8] {
+                match self {
+                    $( KnownKey::$variant => $key, )*
+                }
+            }
+        }
+    };
+}
+
+known_keys! {
+    /// KRPC query arguments (`a`)
+    Arguments => b"a",
+    /// Tracker URL of a metainfo file (`announce`)
+    Announce => b"announce",
+    /// Tiers of tracker URLs of a metainfo file (`announce-list`, BEP 12)
+    AnnounceList => b"announce-list",
+    /// Free-form comment of a metainfo file (`comment`)
+    Comment => b"comment",
+    /// Number of seeders in a tracker response (`complete`)
+    Complete => b"complete",
+    /// Name and version of the program that created a metainfo file (`created by`)
+    CreatedBy => b"created by",
+    /// Creation time of a metainfo file (`creation date`)
+    CreationDate => b"creation date",
+    /// Number of completed downloads in a scrape response (`downloaded`)
+    Downloaded => b"downloaded",
+    /// KRPC error payload (`e`)
+    Error => b"e",
+    /// String encoding of a metainfo file (`encoding`)
+    Encoding => b"encoding",
+    /// The requester's IP address as seen by a tracker (`external ip`, BEP 24)
+    ExternalIp => b"external ip",
+    /// Error message of a tracker response (`failure reason`)
+    FailureReason => b"failure reason",
+    /// File tree of a v2 metainfo file (`file tree`, BEP 52)
+    FileTree => b"file tree",
+    /// Files of a multi-file torrent or of a scrape response (`files`)
+    Files => b"files",
+    /// HTTP seeds of a metainfo file (`httpseeds`, BEP 17)
+    HttpSeeds => b"httpseeds",
+    /// KRPC node ID (`id`)
+    Id => b"id",
+    /// KRPC `announce_peer` flag to use the source port (`implied_port`)
+    ImpliedPort => b"implied_port",
+    /// Number of leechers in a tracker response (`incomplete`)
+    Incomplete => b"incomplete",
+    /// Info dictionary of a metainfo file (`info`)
+    Info => b"info",
+    /// KRPC info hash argument (`info_hash`)
+    InfoHash => b"info_hash",
+    /// Re-announce interval of a tracker response (`interval`)
+    Interval => b"interval",
+    /// File length (`length`)
+    Length => b"length",
+    /// MD5 checksum of a file (`md5sum`)
+    Md5Sum => b"md5sum",
+    /// Metainfo format version (`meta version`, BEP 52)
+    MetaVersion => b"meta version",
+    /// Minimal re-announce interval of a tracker response (`min interval`)
+    MinInterval => b"min interval",
+    /// Suggested file or directory name (`name`)
+    Name => b"name",
+    /// Compact IPv4 node info (`nodes`)
+    Nodes => b"nodes",
+    /// Compact IPv6 node info (`nodes6`, BEP 32)
+    Nodes6 => b"nodes6",
+    /// Path components of a file in a multi-file torrent (`path`)
+    Path => b"path",
+    /// IPv4 peers of a tracker response (`peers`)
+    Peers => b"peers",
+    /// IPv6 peers of a tracker response (`peers6`, BEP 7)
+    Peers6 => b"peers6",
+    /// Merkle tree layers of a v2 metainfo file (`piece layers`, BEP 52)
+    PieceLayers => b"piece layers",
+    /// Number of bytes per piece (`piece length`)
+    PieceLength => b"piece length",
+    /// Concatenated SHA-1 piece hashes (`pieces`)
+    Pieces => b"pieces",
+    /// Merkle root of a file in a v2 metainfo file (`pieces root`, BEP 52)
+    PiecesRoot => b"pieces root",
+    /// KRPC `announce_peer` port (`port`)
+    Port => b"port",
+    /// Private torrent flag (`private`, BEP 27)
+    Private => b"private",
+    /// KRPC query method name (`q`)
+    Query => b"q",
+    /// KRPC response payload (`r`)
+    Response => b"r",
+    /// KRPC read-only node flag (`ro`, BEP 43)
+    ReadOnly => b"ro",
+    /// Signatures of a metainfo file (`signatures`, BEP 35)
+    Signatures => b"signatures",
+    /// Source tag of a private torrent (`source`)
+    Source => b"source",
+    /// KRPC transaction ID (`t`)
+    TransactionId => b"t",
+    /// KRPC `find_node` target (`target`)
+    Target => b"target",
+    /// KRPC `announce_peer` token (`token`)
+    Token => b"token",
+    /// Tracker session ID (`tracker id`)
+    TrackerId => b"tracker id",
+    /// Web seeds of a metainfo file (`url-list`, BEP 19)
+    UrlList => b"url-list",
+    /// KRPC client version (`v`)
+    Version => b"v",
+    /// KRPC `get_peers` peer values (`values`)
+    Values => b"values",
+    /// KRPC requested address families (`want`, BEP 32)
+    Want => b"want",
+    /// Warning message of a tracker response (`warning message`)
+    WarningMessage => b"warning message",
+    /// KRPC message type (`y`)
+    MessageType => b"y",
+}
+
+impl AsRef<[u8]> for KnownKey {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Display for KnownKey {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        // All known keys are plain ASCII
+        f.write_str(core::str::from_utf8(self.as_bytes()).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn known_keys_round_trip() {
+        for key in KnownKey::ALL {
+            assert_eq!(KnownKey::from_bytes(key.as_bytes()), Some(*key));
+        }
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert_eq!(KnownKey::from_bytes(b"announc"), None);
+        assert_eq!(KnownKey::from_bytes(b"Announce"), None);
+        assert_eq!(KnownKey::from_bytes(b""), None);
+    }
+
+    #[test]
+    fn ord_matches_bencode_key_order() {
+        for pair in KnownKey::ALL.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(pair[0].as_bytes() < pair[1].as_bytes());
+        }
+    }
+}
@@ -112,6 +112,30 @@ impl<'obj, 'ser: 'obj> Object<'obj, 'ser> {
         self.bytes_or_else(|obj| Err(Error::unexpected_token("String", obj.into_token().name())))
     }
 
+    /// The length of the object if it is a byte string, without consuming the object.
+    ///
+    /// The decoder only checks that the declared length fits into the input and never
+    /// copies or inspects the payload, so this allows rejecting oversized values (e.g. the
+    /// `pieces` of a torrent) before doing any work on them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bendy::decoding::Object;
+    ///
+    /// let x = Object::Bytes(b"foo");
+    /// assert_eq!(Some(3), x.bytes_len());
+    ///
+    /// let x = Object::Integer("3");
+    /// assert_eq!(None, x.bytes_len());
+    /// ```
+    pub fn bytes_len(&self) -> Option<usize> {
+        match self {
+            Object::Bytes(content) => Some(content.len()),
+            _ => None,
+        }
+    }
+
     /// Try to treat the object as an integer and return the internal string representation,
     /// mapping [`Object::Integer(v)`] into [`Ok(v)`]. Any other variant returns the given
     /// default value.
@@ -0,0 +1,139 @@
+use crate::{
+    decoding::{Decoder, EmptyKeyPolicy, Error, FromBencode, Mode},
+    state_tracker::StructureError,
+};
+
+/// Limits and policies for decoding untrusted input.
+///
+/// The options are applied with [`Decoder::with_options`] or
+/// [`DecodeOptions::from_bencode`]. Exceeding any of the limits results in an
+/// [`ErrorKind::LimitExceeded`] error, which is reported as soon as the offending token is
+/// read, so a hostile input can't make the decoder do much more work than the limits
+/// allow. The exception is the nesting depth: like in the [`Encoder`] and for
+/// [`FromBencode::EXPECTED_RECURSION_DEPTH`], exceeding it results in a
+/// `structure.nesting_too_deep` error.
+///
+/// ```
+/// use bendy::decoding::{DecodeOptions, ErrorKind};
+///
+/// let options = DecodeOptions::new()
+///     .with_max_input_len(1500)
+///     .with_max_depth(4)
+///     .with_max_string_len(1024)
+///     .with_max_tokens(256);
+///
+/// let packet = b"d1:t2:aa1:y1:qe";
+/// let decoded: std::collections::BTreeMap<String, String> = options.from_bencode(packet).unwrap();
+/// assert_eq!(decoded["y"], "q");
+///
+/// let error = options.from_bencode::<String>(b"2000:").unwrap_err();
+/// assert_eq!(error.code(), "decode.limit_exceeded");
+///
+/// let error = options.from_bencode::<Vec<i64>>(b"llllleeeee").unwrap_err();
+/// assert_eq!(error.code(), "structure.nesting_too_deep");
+/// ```
+///
+/// By default, nothing but the nesting depth is limited, which is limited to 2048 like in
+/// a [`Decoder`].
+///
+/// [`ErrorKind::LimitExceeded`]: crate::decoding::ErrorKind::LimitExceeded
+/// [`Encoder`]: crate::encoding::Encoder
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeOptions {
+    pub(crate) max_depth: usize,
+    pub(crate) max_container_len: usize,
+    pub(crate) max_string_len: usize,
+    pub(crate) max_tokens: usize,
+    pub(crate) max_integer_digits: usize,
+    pub(crate) max_input_len: usize,
+    pub(crate) empty_key_policy: EmptyKeyPolicy,
+    pub(crate) mode: Mode,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        DecodeOptions {
+            max_depth: 2048,
+            max_container_len: usize::MAX,
+            max_string_len: usize::MAX,
+            max_tokens: usize::MAX,
+            max_integer_digits: usize::MAX,
+            max_input_len: usize::MAX,
+            empty_key_policy: EmptyKeyPolicy::default(),
+            mode: Mode::default(),
+        }
+    }
+}
+
+impl DecodeOptions {
+    /// Create options with the defaults of a [`Decoder`]
+    pub fn new() -> Self {
+        <Self as Default>::default()
+    }
+
+    /// Set the maximum nesting depth, see [`Decoder::with_max_depth`]. Unlike the other
+    /// limits, exceeding it results in a `structure.nesting_too_deep` error.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Set the maximum number of elements of lists and pairs of dicts, see
+    /// [`Decoder::with_max_container_len`]
+    pub fn with_max_container_len(mut self, max_len: usize) -> Self {
+        self.max_container_len = max_len;
+        self
+    }
+
+    /// Set the maximum length of byte strings, see [`Decoder::with_max_string_len`]
+    pub fn with_max_string_len(mut self, max_len: usize) -> Self {
+        self.max_string_len = max_len;
+        self
+    }
+
+    /// Set the maximum number of tokens of the input, see [`Decoder::with_max_tokens`]
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Set the maximum number of digits of integers and length prefixes, see
+    /// [`Decoder::with_max_integer_digits`]
+    pub fn with_max_integer_digits(mut self, max_digits: usize) -> Self {
+        self.max_integer_digits = max_digits;
+        self
+    }
+
+    /// Set the maximum length of the input in bytes, see [`Decoder::with_max_input_len`]
+    pub fn with_max_input_len(mut self, max_len: usize) -> Self {
+        self.max_input_len = max_len;
+        self
+    }
+
+    /// Set how empty dict keys are treated, see [`EmptyKeyPolicy`]. They are accepted by
+    /// default.
+    pub fn with_empty_key_policy(mut self, policy: EmptyKeyPolicy) -> Self {
+        self.empty_key_policy = policy;
+        self
+    }
+
+    /// Set which malformed input is tolerated, see [`Mode`]
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Decode an object like [`FromBencode::from_bencode`], but with these options. The
+    /// nesting depth is limited to the smaller of the configured max depth and the
+    /// [`EXPECTED_RECURSION_DEPTH`](FromBencode::EXPECTED_RECURSION_DEPTH) of `T`.
+    pub fn from_bencode<T: FromBencode>(&self, bytes: &[u8]) -> Result<T, Error> {
+        let mut decoder = Decoder::with_options(bytes, self)
+            .with_max_depth(self.max_depth.min(T::EXPECTED_RECURSION_DEPTH));
+        let object = decoder.next_object()?;
+
+        object.map_or(
+            Err(Error::from(StructureError::UnexpectedEof)),
+            T::decode_bencode_object,
+        )
+    }
+}
@@ -0,0 +1,319 @@
+//! Support for [`bendy_keys!`](crate::bendy_keys), which isn't part of the public API.
+//!
+//! The lookup tables of a key enum are built at compile time with the
+//! "hash, displace and compress" scheme: every key is assigned to one of
+//! [`bucket_count`] buckets, and each bucket gets a displacement (a seed of the second
+//! hash) that moves all its keys to free slots of the table. A lookup therefore only has
+//! to hash the key twice and compare it with the single candidate in its slot.
+
+/// Generate an enum of the dictionary keys used by an application.
+///
+/// Decoders of application-specific dicts can match on the enum instead of comparing
+/// byte string literals. For every variant, the macro generates:
+///
+/// - `from_bytes`, a lookup of a raw dictionary key using a perfect hash table that is
+///   built at compile time,
+/// - `as_bytes` and `as_str`, both `const fn`s so they can define byte constants,
+/// - `ALL`, all variants in declaration order,
+/// - `AsRef<[u8]>` and `Display` implementations,
+/// - a [`KeySchema`](crate::decoding::KeySchema) implementation, to check which keys a
+///   dict contains with a [`KeyReport`](crate::decoding::KeyReport).
+///
+/// ```
+/// use bendy::{bendy_keys, decoding::Decoder};
+///
+/// bendy_keys! {
+///     /// The keys of a resume file
+///     pub enum ResumeKey {
+///         /// Bytes downloaded so far
+///         Downloaded = "downloaded",
+///         InfoHash = "info-hash",
+///         Paused = "paused",
+///     }
+/// }
+///
+/// const PAUSED: &[u8] = ResumeKey::Paused.as_bytes();
+///
+/// let mut decoder = Decoder::new(b"d10:downloadedi42e6:pausedi1e7:versioni2ee");
+/// let mut dict = decoder
+///     .next_object()
+///     .unwrap()
+///     .unwrap()
+///     .try_into_dictionary()
+///     .unwrap();
+///
+/// let mut downloaded = None;
+/// while let Some((key, value)) = dict.next_pair().unwrap() {
+///     match ResumeKey::from_bytes(key) {
+///         Some(ResumeKey::Downloaded) => downloaded = Some(value.try_into_integer().unwrap()),
+///         Some(ResumeKey::InfoHash) | Some(ResumeKey::Paused) => (),
+///         None => (), // unknown key
+///     }
+/// }
+///
+/// assert_eq!(downloaded, Some("42"));
+/// assert_eq!(ResumeKey::from_bytes(PAUSED), Some(ResumeKey::Paused));
+/// assert_eq!(ResumeKey::InfoHash.to_string(), "info-hash");
+/// ```
+///
+/// Declaring the same key twice fails to compile. The enum derives `Clone`, `Copy`,
+/// `Debug`, `PartialEq`, `Eq` and `Hash`; further attributes are passed through.
+#[macro_export]
+macro_rules! bendy_keys {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $( $(#[$variant_meta:meta])* $variant:ident = $key:literal ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $( $(#[$variant_meta])* $variant, )*
+        }
+
+        impl $name {
+            /// All keys in declaration order.
+            pub const ALL: &'static [$name] = &[$( $name::$variant, )*];
+
+            /// Classify a raw dictionary key. Returns `None` if the key is not known.
+            pub fn from_bytes(key: &[u8]) -> ::core::option::Option<Self> {
+                use $crate::decoding::perfect_hash::{bucket, bucket_count, slot, table_len};
+
+                const KEYS: &[&[u8]] = &[$( $key.as_bytes(), )*];
+                const LEN: usize = table_len(KEYS.len());
+                const BUCKETS: usize = bucket_count(KEYS.len());
+                const EMPTY: usize = usize::MAX;
+                // The displacements of the buckets and the index of the key in each slot
+                const TABLES: ([u32; BUCKETS], [usize; LEN]) = {
+                    let mut displacements = [0; BUCKETS];
+                    let mut slots = [EMPTY; LEN];
+                    let count = KEYS.len();
+
+                    let mut i = 0;
+                    while i < count {
+                        let mut j = 0;
+                        while j < i {
+                            if $crate::decoding::perfect_hash::eq(KEYS[i], KEYS[j]) {
+                                #[allow(unconditional_panic)]
+                                let _duplicate_key = [(); 0][i];
+                            }
+                            j += 1;
+                        }
+                        i += 1;
+                    }
+
+                    // Place the largest buckets first, while most slots are still free
+                    let mut size = count;
+                    while size > 0 {
+                        let mut b = 0;
+                        while b < BUCKETS {
+                            let mut len = 0;
+                            let mut i = 0;
+                            while i < count {
+                                if bucket(KEYS[i], BUCKETS) == b {
+                                    len += 1;
+                                }
+                                i += 1;
+                            }
+
+                            if len == size {
+                                let mut displacement = 0;
+                                loop {
+                                    let mut fits = true;
+                                    let mut i = 0;
+                                    while fits && i < count {
+                                        if bucket(KEYS[i], BUCKETS) == b {
+                                            let s = slot(KEYS[i], displacement, LEN);
+                                            if slots[s] != EMPTY {
+                                                fits = false;
+                                            }
+                                            let mut j = 0;
+                                            while fits && j < i {
+                                                if bucket(KEYS[j], BUCKETS) == b
+                                                    && slot(KEYS[j], displacement, LEN) == s
+                                                {
+                                                    fits = false;
+                                                }
+                                                j += 1;
+                                            }
+                                        }
+                                        i += 1;
+                                    }
+                                    if fits {
+                                        break;
+                                    }
+                                    displacement += 1;
+                                }
+
+                                displacements[b] = displacement;
+                                let mut i = 0;
+                                while i < count {
+                                    if bucket(KEYS[i], BUCKETS) == b {
+                                        slots[slot(KEYS[i], displacement, LEN)] = i;
+                                    }
+                                    i += 1;
+                                }
+                            }
+                            b += 1;
+                        }
+                        size -= 1;
+                    }
+
+                    (displacements, slots)
+                };
+
+                let displacement = TABLES.0[bucket(key, BUCKETS)];
+                let index = TABLES.1[slot(key, displacement, LEN)];
+                if index != EMPTY && KEYS[index] == key {
+                    ::core::option::Option::Some(Self::ALL[index])
+                } else {
+                    ::core::option::Option::None
+                }
+            }
+
+            /// The byte representation of the key as it appears on the wire.
+            pub const fn as_bytes(self) -> &'static [u8] {
+                self.as_str().as_bytes()
+            }
+
+            /// The key as a string.
+            pub const fn as_str(self) -> &'static str {
+                match self {
+                    $( $name::$variant => $key, )*
+                }
+            }
+        }
+
+        impl $crate::decoding::KeySchema for $name {
+            const ALL: &'static [Self] = &[$( $name::$variant, )*];
+
+            fn from_bytes(key: &[u8]) -> ::core::option::Option<Self> {
+                $name::from_bytes(key)
+            }
+
+            fn index(self) -> usize {
+                match self {
+                    $( $name::$variant => $name::$variant as usize, )*
+                }
+            }
+        }
+
+        impl ::core::convert::AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                self.as_bytes()
+            }
+        }
+
+        impl ::core::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+/// A seeded 32-bit FNV-1a hash with a final avalanche step
+pub const fn hash(key: &[u8], seed: u32) -> u32 {
+    let mut hash = 0x811c_9dc5 ^ seed.wrapping_mul(0x9e37_79b9);
+    let mut i = 0;
+    while i < key.len() {
+        hash ^= key[i] as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+        i += 1;
+    }
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^ (hash >> 13)
+}
+
+/// The number of slots of the table for `keys` keys, which keeps it at most half full
+pub const fn table_len(keys: usize) -> usize {
+    keys * 2 + 1
+}
+
+/// The number of buckets for `keys` keys
+pub const fn bucket_count(keys: usize) -> usize {
+    keys / 2 + 1
+}
+
+/// The bucket of `key`
+pub const fn bucket(key: &[u8], buckets: usize) -> usize {
+    hash(key, 0) as usize % buckets
+}
+
+/// The slot of `key` in a bucket with the given displacement
+pub const fn slot(key: &[u8], displacement: u32, len: usize) -> usize {
+    hash(key, displacement.wrapping_add(1)) as usize % len
+}
+
+/// Byte slice equality, which isn't available in constants
+pub const fn eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::{format, vec::Vec};
+
+    use crate::decoding::KnownKey;
+
+    bendy_keys! {
+        enum Key {
+            Empty = "",
+            A = "a",
+            Announce = "announce",
+            AnnounceList = "announce-list",
+            Info = "info",
+            PieceLength = "piece length",
+        }
+    }
+
+    bendy_keys! {
+        enum NoKeys {}
+    }
+
+    #[test]
+    fn keys_should_round_trip() {
+        for key in Key::ALL {
+            assert_eq!(Key::from_bytes(key.as_bytes()), Some(*key));
+        }
+        assert_eq!(format!("{}", Key::PieceLength), "piece length");
+        assert_eq!(Key::from_bytes(b"announc"), None);
+        assert_eq!(Key::from_bytes(b"b"), None);
+        assert_eq!(NoKeys::from_bytes(b""), None);
+    }
+
+    #[test]
+    fn large_key_sets_should_be_supported() {
+        let keys: Vec<&[u8]> = KnownKey::ALL.iter().map(|key| key.as_bytes()).collect();
+
+        bendy_keys! {
+            enum Many {
+                K0 = "a", K1 = "announce", K2 = "announce-list", K3 = "comment",
+                K4 = "complete", K5 = "created by", K6 = "creation date", K7 = "downloaded",
+                K8 = "e", K9 = "encoding", K10 = "external ip", K11 = "failure reason",
+                K12 = "file tree", K13 = "files", K14 = "httpseeds", K15 = "id",
+                K16 = "implied_port", K17 = "incomplete", K18 = "info", K19 = "info_hash",
+            }
+        }
+        for (key, bytes) in Many::ALL.iter().zip(&keys) {
+            assert_eq!(key.as_bytes(), *bytes);
+            assert_eq!(Many::from_bytes(bytes), Some(*key));
+        }
+        for key in &keys[Many::ALL.len()..] {
+            assert_eq!(Many::from_bytes(key), None);
+        }
+    }
+}
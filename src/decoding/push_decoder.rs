@@ -0,0 +1,294 @@
+#[cfg(not(feature = "std"))]
+use alloc::{format, vec::Vec};
+
+use crate::{decoding::Error, state_tracker::StructureError};
+
+/// A sans-IO decoder that is fed chunks of input and yields complete values.
+///
+/// Input can be split at arbitrary points, e.g. as it arrives from a socket. Each call
+/// to [`next_value`](Self::next_value) returns the next complete top-level value, if
+/// enough input has been fed. Values are returned as their raw bencode, validated with
+/// the same rules as [`Decoder`](crate::decoding::Decoder), so they can be decoded with
+/// [`FromBencode::from_bencode`](crate::decoding::FromBencode::from_bencode) or parsed
+/// further without copying:
+///
+/// ```
+/// use bendy::decoding::PushDecoder;
+///
+/// let mut decoder = PushDecoder::new();
+/// decoder.feed(b"d1:ai1e1:");
+/// assert_eq!(decoder.next_value().unwrap(), None);
+///
+/// decoder.feed(b"b2:xyei2");
+/// assert_eq!(decoder.next_value().unwrap().unwrap(), b"d1:ai1e1:b2:xye");
+/// assert_eq!(decoder.next_value().unwrap(), None);
+///
+/// decoder.feed(b"e");
+/// assert_eq!(decoder.next_value().unwrap().unwrap(), b"i2e");
+/// ```
+///
+/// The framing of values is tracked incrementally, so every byte is only scanned once
+/// until its value is complete. Errors are reported as soon as the framing is broken and
+/// nesting or value size limits are exceeded, before the rest of the value arrives. Once
+/// an error was returned, the decoder returns it for every further call.
+#[derive(Clone, Debug)]
+pub struct PushDecoder {
+    buffer: Vec<u8>,
+    /// Offset of the first byte of the current value in `buffer`
+    start: usize,
+    /// Number of bytes of the stream already removed from `buffer`
+    discarded: usize,
+    /// Offset of the first byte in `buffer` that wasn't scanned yet
+    scanned: usize,
+    scan: Scan,
+    depth: usize,
+    max_depth: usize,
+    max_value_len: Option<usize>,
+    error: Option<Error>,
+}
+
+/// What the scanner expects next
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Scan {
+    /// The start of a value or the end of a container
+    Value,
+    /// The rest of an integer, up to its `e`
+    Integer,
+    /// The rest of the length of a string, up to its `:`
+    Length(usize),
+    /// The given number of bytes of string content
+    Content(usize),
+}
+
+impl Default for PushDecoder {
+    fn default() -> Self {
+        PushDecoder {
+            buffer: Vec::new(),
+            start: 0,
+            discarded: 0,
+            scanned: 0,
+            scan: Scan::Value,
+            depth: 0,
+            max_depth: 2048,
+            max_value_len: None,
+            error: None,
+        }
+    }
+}
+
+impl PushDecoder {
+    /// Create a decoder with the default max depth of a [`Decoder`](crate::decoding::Decoder)
+    pub fn new() -> Self {
+        <Self as Default>::default()
+    }
+
+    /// Set the maximum nesting depth of values
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Reject values longer than `max_value_len` bytes, so that a peer can't make the
+    /// decoder buffer an unbounded amount of input
+    #[must_use]
+    pub fn with_max_value_len(mut self, max_value_len: usize) -> Self {
+        self.max_value_len = Some(max_value_len);
+        self
+    }
+
+    /// Append a chunk of input
+    pub fn feed(&mut self, chunk: &[u8]) {
+        // Drop returned values before the buffer has to grow
+        if self.start > 0 && self.buffer.len() + chunk.len() > self.buffer.capacity() {
+            self.buffer.drain(..self.start);
+            self.discarded += self.start;
+            self.scanned -= self.start;
+            self.start = 0;
+        }
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// The number of bytes fed but not yet returned as part of a value
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len() - self.start
+    }
+
+    /// Return the next complete value, or `None` if more input is needed
+    pub fn next_value(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+
+        match self.scan_value() {
+            Ok(Some(end)) => {
+                let value = self.buffer[self.start..end].to_vec();
+                self.start = end;
+                Ok(Some(value))
+            },
+            Ok(None) => Ok(None),
+            Err(error) => {
+                self.error = Some(error.clone());
+                Err(error)
+            },
+        }
+    }
+
+    /// Scan the fed input and return the end of the current value, if it is complete
+    fn scan_value(&mut self) -> Result<Option<usize>, Error> {
+        while self.scanned < self.buffer.len() {
+            let byte = self.buffer[self.scanned];
+            let complete = match self.scan {
+                Scan::Content(len) => {
+                    let available = len.min(self.buffer.len() - self.scanned);
+                    self.scanned += available;
+                    if available == len {
+                        self.scan = Scan::Value;
+                        true
+                    } else {
+                        self.scan = Scan::Content(len - available);
+                        false
+                    }
+                },
+                Scan::Integer => {
+                    match self.buffer[self.scanned..].iter().position(|&b| b == b'e') {
+                        Some(end) => {
+                            self.scanned += end + 1;
+                            self.scan = Scan::Value;
+                            true
+                        },
+                        None => {
+                            self.scanned = self.buffer.len();
+                            false
+                        },
+                    }
+                },
+                Scan::Length(len) => {
+                    self.scanned += 1;
+                    match byte {
+                        b'0'..=b'9' => {
+                            let len = len
+                                .checked_mul(10)
+                                .and_then(|len| len.checked_add(usize::from(byte - b'0')))
+                                .ok_or_else(|| Error::limit_exceeded("string length overflow"))?;
+                            self.scan = Scan::Length(len);
+                            false
+                        },
+                        b':' => {
+                            self.scan = Scan::Content(len);
+                            self.check_len(self.scanned + len)?;
+                            len == 0 && {
+                                self.scan = Scan::Value;
+                                true
+                            }
+                        },
+                        _ => return Err(self.unexpected("a digit or `:`", byte)),
+                    }
+                },
+                Scan::Value => {
+                    self.scanned += 1;
+                    match byte {
+                        b'i' => {
+                            self.scan = Scan::Integer;
+                            false
+                        },
+                        b'0'..=b'9' => {
+                            self.scan = Scan::Length(usize::from(byte - b'0'));
+                            false
+                        },
+                        b'l' | b'd' => {
+                            if self.depth == self.max_depth {
+                                return Err(StructureError::NestingTooDeep.into());
+                            }
+                            self.depth += 1;
+                            false
+                        },
+                        b'e' if self.depth > 0 => {
+                            self.depth -= 1;
+                            true
+                        },
+                        _ => return Err(self.unexpected("the start of a value", byte)),
+                    }
+                },
+            };
+
+            self.check_len(self.scanned)?;
+            if complete && self.depth == 0 {
+                let value = &self.buffer[self.start..self.scanned];
+                crate::decoding::is_valid(value)?;
+                return Ok(Some(self.scanned));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Fail if the current value would extend up to `end`, beyond its maximum length
+    fn check_len(&self, end: usize) -> Result<(), Error> {
+        match self.max_value_len {
+            Some(max) if end - self.start > max => Err(Error::limit_exceeded(format!(
+                "value longer than {} bytes",
+                max
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn unexpected(&self, expected: &str, byte: u8) -> Error {
+        let offset = self.discarded + self.scanned - 1;
+        StructureError::unexpected(expected, char::from(byte), offset).into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn values_should_be_split_at_any_point() {
+        let input: &[u8] = b"d3:bar4:spam3:fooli42ei-1eee0:i7el1:x1:yei";
+        let expected: [&[u8]; 4] = [b"d3:bar4:spam3:fooli42ei-1eee", b"0:", b"i7e", b"l1:x1:ye"];
+
+        for chunk_len in 1..input.len() {
+            let mut decoder = PushDecoder::new();
+            let mut values = Vec::new();
+            for chunk in input.chunks(chunk_len) {
+                decoder.feed(chunk);
+                while let Some(value) = decoder.next_value().unwrap() {
+                    values.push(value);
+                }
+            }
+
+            assert_eq!(values, expected, "chunks of {} bytes", chunk_len);
+            assert_eq!(decoder.buffered_len(), 1);
+        }
+    }
+
+    #[test]
+    fn errors_should_be_reported_early_and_latched() {
+        let mut decoder = PushDecoder::new();
+        decoder.feed(b"i1ex");
+        assert_eq!(decoder.next_value().unwrap().unwrap(), b"i1e");
+        assert!(decoder.next_value().is_err());
+        decoder.feed(b"i2e");
+        assert!(decoder.next_value().is_err());
+
+        let mut decoder = PushDecoder::new().with_max_depth(2);
+        decoder.feed(b"lll");
+        assert!(decoder.next_value().is_err());
+
+        let mut decoder = PushDecoder::new().with_max_value_len(10);
+        decoder.feed(b"100:");
+        assert!(decoder.next_value().is_err());
+    }
+
+    #[test]
+    fn complete_values_should_be_validated() {
+        let mut decoder = PushDecoder::new();
+        decoder.feed(b"d1:bi1e1:ai2ee");
+        assert!(decoder.next_value().is_err());
+
+        let mut decoder = PushDecoder::new();
+        decoder.feed(b"i01e");
+        assert!(decoder.next_value().is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use alloc::{collections::VecDeque, vec::Vec};
+
+use crate::decoding::Error;
+
+/// A bounded record of inputs that failed to decode, for debugging.
+///
+/// The log holds the most recent rejections in a ring buffer of fixed capacity, each with
+/// up to a fixed number of leading bytes of the input and the error. Nothing is copied or
+/// allocated for inputs that decode successfully, so the log can stay enabled on busy
+/// servers:
+///
+/// ```
+/// use bendy::decoding::{FromBencode, RejectionLog};
+///
+/// let mut log = RejectionLog::new(16).with_prefix_len(8);
+///
+/// for packet in [&b"i1e"[..], b"i2", b"d1:ai1e1:ai2ee"].iter() {
+///     if let Ok(value) = log.capture(packet, u64::from_bencode(packet)) {
+///         assert_eq!(value, 1);
+///     }
+/// }
+///
+/// let prefixes: Vec<_> = log
+///     .rejections()
+///     .map(|rejection| &rejection.prefix[..])
+///     .collect();
+/// assert_eq!(prefixes, [&b"i2"[..], &b"d1:ai1e1"[..]]);
+/// assert_eq!(log.total(), 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RejectionLog {
+    rejections: VecDeque<Rejection>,
+    capacity: usize,
+    prefix_len: usize,
+    total: u64,
+}
+
+/// An input that failed to decode, as recorded by a [`RejectionLog`].
+#[derive(Clone, Debug)]
+pub struct Rejection {
+    /// The leading bytes of the input
+    pub prefix: Vec<u8>,
+    /// The length of the complete input
+    pub input_len: usize,
+    /// The error the input was rejected with, including its path and offset if known
+    pub error: Error,
+}
+
+impl RejectionLog {
+    /// Create a log that keeps the last `capacity` rejections with up to 64 bytes of
+    /// their input
+    pub fn new(capacity: usize) -> Self {
+        RejectionLog {
+            rejections: VecDeque::with_capacity(capacity),
+            capacity,
+            prefix_len: 64,
+            total: 0,
+        }
+    }
+
+    /// Set the maximum number of leading input bytes kept per rejection
+    pub fn with_prefix_len(mut self, prefix_len: usize) -> Self {
+        self.prefix_len = prefix_len;
+        self
+    }
+
+    /// Record `error` as the reason `input` was rejected, evicting the oldest rejection
+    /// if the log is full
+    pub fn record(&mut self, input: &[u8], error: &Error) {
+        self.total += 1;
+        if self.capacity == 0 {
+            return;
+        }
+        if self.rejections.len() == self.capacity {
+            self.rejections.pop_front();
+        }
+
+        let prefix_len = input.len().min(self.prefix_len);
+        self.rejections.push_back(Rejection {
+            prefix: input[..prefix_len].to_vec(),
+            input_len: input.len(),
+            error: error.clone(),
+        });
+    }
+
+    /// Pass through the result of decoding `input`, recording it if it is an error
+    pub fn capture<T>(&mut self, input: &[u8], result: Result<T, Error>) -> Result<T, Error> {
+        if let Err(error) = &result {
+            self.record(input, error);
+        }
+        result
+    }
+
+    /// The recorded rejections, from the oldest to the most recent
+    pub fn rejections(&self) -> impl Iterator<Item = &Rejection> + '_ {
+        self.rejections.iter()
+    }
+
+    /// Remove and return all recorded rejections, from the oldest to the most recent
+    pub fn take(&mut self) -> Vec<Rejection> {
+        self.rejections.drain(..).collect()
+    }
+
+    /// The number of rejections recorded since the log was created, including those that
+    /// were evicted or taken
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::string::String;
+
+    use super::*;
+    use crate::decoding::FromBencode;
+
+    #[test]
+    fn oldest_rejections_should_be_evicted() {
+        let mut log = RejectionLog::new(2);
+        for input in [&b"i1"[..], b"i2", b"i3", b"i4e"].iter() {
+            log.capture(input, i64::from_bencode(input)).ok();
+        }
+
+        let prefixes: Vec<_> = log.rejections().map(|r| r.prefix.clone()).collect();
+        assert_eq!(prefixes, [b"i2".to_vec(), b"i3".to_vec()]);
+        assert_eq!(log.total(), 3);
+
+        assert_eq!(log.take().len(), 2);
+        assert_eq!(log.rejections().count(), 0);
+        assert_eq!(log.total(), 3);
+    }
+
+    #[test]
+    fn rejections_should_keep_the_error_and_input_length() {
+        let mut log = RejectionLog::new(1).with_prefix_len(2);
+        let input = b"l1:ai1e";
+        let error = Vec::<String>::from_bencode(input).unwrap_err();
+        log.record(input, &error);
+
+        let rejection = log.rejections().next().unwrap();
+        assert_eq!(rejection.prefix, b"l1");
+        assert_eq!(rejection.input_len, 7);
+        assert_eq!(rejection.error.path(), Some("[1]"));
+
+        let mut disabled = RejectionLog::new(0);
+        disabled.record(input, &error);
+        assert_eq!(disabled.rejections().count(), 0);
+        assert_eq!(disabled.total(), 1);
+    }
+}
@@ -0,0 +1,111 @@
+use alloc::string::String;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::{
+    decoding::{is_valid, Cursor, Error},
+    state_tracker::Token,
+};
+
+/// Replace the value at `path`, a sequence of dictionary keys, with the raw bencode in
+/// `fragment` and return the resulting buffer.
+///
+/// Everything but the replaced value is copied verbatim, so nothing is decoded or
+/// re-encoded. `fragment` has to be a single canonical bencode value, and the result is
+/// validated again, e.g. as the fragment could nest too deep at its new position. An empty
+/// path replaces the first value of `input`.
+///
+/// ```
+/// use bendy::decoding::splice;
+///
+/// let torrent = b"d8:announce12:http://a/ann4:infod6:lengthi42eee";
+///
+/// let rewritten = splice(torrent, &["announce"], b"12:http://b/ann").unwrap();
+/// assert_eq!(
+///     rewritten,
+///     &b"d8:announce12:http://b/ann4:infod6:lengthi42eee"[..]
+/// );
+///
+/// assert!(splice(torrent, &["announce"], b"i42").is_err());
+/// assert!(splice(torrent, &["comment"], b"0:").is_err());
+/// ```
+///
+/// Only existing values can be replaced; to add a key, replace the dict that will hold it.
+pub fn splice<K: AsRef<[u8]>>(input: &[u8], path: &[K], fragment: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut target = Cursor::new(input)?;
+    for (depth, key) in path.iter().enumerate() {
+        let key = key.as_ref();
+        let found = if target.token() == Token::Dict {
+            target
+                .get(key)
+                .ok_or_else(|| Error::missing_field(String::from_utf8_lossy(key)))
+        } else {
+            Err(Error::unexpected_token("Dict", target.token().name()))
+        };
+        target = found.map_err(|error| {
+            path[..depth].iter().rev().fold(error, |error, parent| {
+                error.context(String::from_utf8_lossy(parent.as_ref()))
+            })
+        })?;
+    }
+
+    if Cursor::new(fragment)?.next_sibling().is_some() {
+        return Err(Error::unexpected_token("end of fragment", "trailing data"));
+    }
+
+    let start = target.offset();
+    let end = start + target.raw().len();
+    let mut output = Vec::with_capacity(input.len() - (end - start) + fragment.len());
+    output.extend_from_slice(&input[..start]);
+    output.extend_from_slice(fragment);
+    output.extend_from_slice(&input[end..]);
+
+    is_valid(&output)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn nested_values_should_be_replaced() {
+        let input = b"d1:ad1:bi1e1:cli2eee1:d0:e";
+        assert_eq!(
+            splice(input, &["a", "c"], b"de").unwrap(),
+            &b"d1:ad1:bi1e1:cdee1:d0:e"[..]
+        );
+        assert_eq!(
+            splice(input, &["d"], b"i3e").unwrap(),
+            &b"d1:ad1:bi1e1:cli2eee1:di3ee"[..]
+        );
+        assert_eq!(
+            splice(b"i1ei2e", &[] as &[&str], b"le").unwrap(),
+            &b"lei2e"[..]
+        );
+    }
+
+    #[test]
+    fn invalid_splices_should_fail() {
+        let input = b"d1:ad1:bi1eee";
+
+        let error = splice(input, &["a", "c"], b"0:").unwrap_err();
+        assert_eq!(error.code(), "decode.missing_field");
+        assert_eq!(error.path(), Some("a"));
+        let error = splice(input, &["a", "b", "c"], b"0:").unwrap_err();
+        assert_eq!(error.path(), Some("a.b"));
+
+        assert!(splice(input, &["a"], b"i1ei2e").is_err());
+        assert!(splice(input, &["a"], b"d1:bi1e1:ai2ee").is_err());
+        assert!(splice(b"d1:ai1ee", &["a"], b"").is_err());
+
+        // Valid on its own, but too deep at its new position
+        let mut deep = vec![b'l'; 2048];
+        deep.resize(4096, b'e');
+        assert!(splice(&deep, &[] as &[&str], &deep).is_ok());
+        assert!(splice(input, &["a"], &deep).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use alloc::format;
+use core::str;
+
+use crate::{
+    decoding::{decoder::scan_int, Error},
+    state_tracker::StructureError,
+};
+
+/// Maximum nesting depth accepted by [`is_valid`], the same as the default of a
+/// [`Decoder`](crate::decoding::Decoder)
+const MAX_DEPTH: usize = 2048;
+
+/// Stack marker of an open list
+const LIST: usize = usize::MAX;
+
+/// Stack marker of an open dict that has not seen any key yet
+const EMPTY_DICT: usize = usize::MAX - 1;
+
+/// Check that `buffer` starts with a single well-formed bencode value and return the
+/// number of bytes it occupies.
+///
+/// This applies the same rules as the [`Decoder`](crate::decoding::Decoder), including
+/// canonical integers and sorted dictionary keys, but only makes a single pass over the
+/// input and doesn't allocate unless it has to report an error. Any data following the
+/// first value is ignored.
+///
+/// ```
+/// assert_eq!(bendy::is_valid(b"d3:bari1e3:foolee").unwrap(), 17);
+/// assert_eq!(bendy::is_valid(b"i42etrailing").unwrap(), 4);
+///
+/// assert!(bendy::is_valid(b"d3:fooi1e3:bari2ee").is_err());
+/// assert!(bendy::is_valid(b"i042e").is_err());
+/// ```
+pub fn is_valid(buffer: &[u8]) -> Result<usize, Error> {
+    // Open containers: `LIST` for lists, the offset of the last key for dicts
+    let mut stack = [LIST; MAX_DEPTH];
+    let mut depth = 0;
+    // Whether the innermost dict is waiting for the value of its last key
+    let mut expect_value = false;
+    let mut pos = 0;
+
+    loop {
+        let byte = *buffer.get(pos).ok_or(StructureError::UnexpectedEof)?;
+        let expect_key = depth > 0 && stack[depth - 1] != LIST && !expect_value;
+
+        match byte {
+            b'e' => {
+                if depth == 0 {
+                    return Err(
+                        StructureError::invalid_state("End not allowed at top level").into(),
+                    );
+                } else if expect_value {
+                    return Err(StructureError::invalid_state("Missing map value").into());
+                }
+                depth -= 1;
+                pos += 1;
+            },
+            b'0'..=b'9' => {
+                let (key, end) = string_at(buffer, pos)?;
+                if expect_key {
+                    let last = stack[depth - 1];
+                    if last != EMPTY_DICT && string_at(buffer, last)?.0 >= key {
+                        return Err(StructureError::UnsortedKeys.into());
+                    }
+                    stack[depth - 1] = pos;
+                }
+                expect_value = expect_key;
+                pos = end;
+            },
+            _ if expect_key => {
+                return Err(StructureError::invalid_state("Map keys must be strings").into());
+            },
+            b'l' | b'd' => {
+                if depth == MAX_DEPTH {
+                    return Err(StructureError::NestingTooDeep.into());
+                }
+                stack[depth] = if byte == b'l' { LIST } else { EMPTY_DICT };
+                depth += 1;
+                expect_value = false;
+                pos += 1;
+            },
+            b'i' => {
+                pos = scan_int(buffer, pos + 1, 'e')? + 1;
+                expect_value = false;
+            },
+            _ => {
+                return Err(StructureError::SyntaxError {
+                    unexpected: format!(
+                        "Invalid token starting with {:?} at offset {}",
+                        byte as char, pos
+                    ),
+                }
+                .into());
+            },
+        }
+
+        if depth == 0 {
+            return Ok(pos);
+        }
+    }
+}
+
+/// Read the byte string starting at `pos`, returning its content and end offset
+fn string_at(buffer: &[u8], pos: usize) -> Result<(&[u8], usize), Error> {
+    let colon = scan_int(buffer, pos, ':')?;
+    let len: usize = str::from_utf8(&buffer[pos..colon])
+        .ok()
+        .and_then(|len| len.parse().ok())
+        .ok_or_else(|| StructureError::SyntaxError {
+            unexpected: format!("Invalid integer at offset {}", pos),
+        })?;
+
+    match (colon + 1).checked_add(len) {
+        Some(end) if end <= buffer.len() => Ok((&buffer[colon + 1..end], end)),
+        _ => Err(StructureError::UnexpectedEof.into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec::Vec;
+    use core::iter;
+
+    use super::*;
+    use crate::decoding::Decoder;
+
+    fn decoder_accepts(buffer: &[u8]) -> bool {
+        !buffer.is_empty() && Decoder::new(buffer).tokens().all(|token| token.is_ok())
+    }
+
+    #[test]
+    fn valid_values_should_report_their_length() {
+        assert_eq!(is_valid(b"i-12e").unwrap(), 5);
+        assert_eq!(is_valid(b"0:").unwrap(), 2);
+        assert_eq!(is_valid(b"3:abcxyz").unwrap(), 5);
+        assert_eq!(is_valid(b"lli1eedee").unwrap(), 9);
+        assert_eq!(is_valid(b"d1:ad1:bleee1:c").unwrap(), 12);
+    }
+
+    #[test]
+    fn should_agree_with_decoder() {
+        let samples: &[&[u8]] = &[
+            b"",
+            b"e",
+            b"i-0e",
+            b"i01e",
+            b"ie",
+            b"01:a",
+            b"-1:a",
+            b"5:abc",
+            b"l",
+            b"li1e",
+            b"d1:ae",
+            b"di1ei2ee",
+            b"d1:ai1e1:ai2ee",
+            b"d1:bi1e1:ai2ee",
+            b"d1:ai1e1:bi2ee",
+            b"x",
+            b"d1:ad1:b1:ce1:dle1:ei3ee",
+        ];
+
+        for sample in samples {
+            assert_eq!(
+                is_valid(sample).is_ok(),
+                decoder_accepts(sample),
+                "{:?}",
+                sample
+            );
+        }
+    }
+
+    #[test]
+    fn nesting_depth_should_be_limited() {
+        let nested = |depth| {
+            iter::repeat(b'l')
+                .take(depth)
+                .chain(iter::repeat(b'e').take(depth))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(is_valid(&nested(MAX_DEPTH)).is_ok());
+        assert!(is_valid(&nested(MAX_DEPTH + 1)).is_err());
+    }
+}
@@ -76,6 +76,18 @@
 //!
 //! Most primitive types already implement [`ToBencode`].
 //!
+//! # Maps
+//!
+//! Bencode requires the keys of a dict to be sorted. A `BTreeMap` already iterates in
+//! this order, so it is written straight to the output. A `HashMap` has to collect and
+//! sort references to its pairs first, which costs an extra allocation and an
+//! `O(n log n)` sort for every map encoded. Prefer `BTreeMap` for frequently encoded maps;
+//! pick `HashMap` if lookup performance matters more than encoding performance.
+//!
+//! Custom types can choose the same way: [`SingleItemEncoder::emit_dict`] and
+//! [`SingleItemEncoder::emit_sorted_pairs`] expect sorted keys and don't buffer anything,
+//! while [`SingleItemEncoder::emit_unsorted_dict`] buffers and sorts all pairs.
+//!
 //! # Nesting depth limits
 //!
 //! To allow this to be used on limited platforms, all implementations of [`ToBencode`] include a
@@ -109,6 +121,10 @@
 //! [`ToBencode::encode`] is called before returning an error; such callbacks should
 //! respond to failure by bailing out as quickly as possible.
 //!
+//! Whether an [`Encoder`] failed can be checked with [`Encoder::is_poisoned`] and
+//! [`Encoder::error`]. Instead of dropping a failed encoder, it can be reset to a
+//! [`Checkpoint`] taken before the failure with [`Encoder::try_recover_at_checkpoint`].
+//!
 //! Not all values in [`Error`] can be caused by an encoding operation. Specifically, you only need
 //! to worry about [`UnsortedKeys`] and [`NestingTooDeep`].
 //!
@@ -116,14 +132,36 @@
 //! [`UnsortedKeys`]: self::Error#UnsortedKeys
 //! [`NestingTooDeep`]: self::Error#NestingTooDeep
 
+mod emit_macro;
 mod encoder;
 mod error;
 mod printable_integer;
+#[cfg(feature = "heapless")]
+mod slice_encoder;
+mod stream_encoder;
 mod to_bencode;
+#[cfg(feature = "std")]
+mod vectored_encoder;
+mod write;
+
+pub(crate) use self::to_bencode::max_depth;
 
 pub use self::{
-    encoder::{Encoder, SingleItemEncoder, SortedDictEncoder, UnsortedDictEncoder},
+    emit_macro::keys_are_sorted,
+    encoder::{Checkpoint, Encoder, SingleItemEncoder, SortedDictEncoder, UnsortedDictEncoder},
     error::Error,
     printable_integer::PrintableInteger,
-    to_bencode::{AsString, ToBencode},
+    stream_encoder::StreamEncoder,
+    to_bencode::{AsHex, AsString, EncodeIter, ToBencode},
+    write::Write,
 };
+
+#[cfg(feature = "heapless")]
+pub use self::slice_encoder::SliceEncoder;
+#[cfg(feature = "std")]
+pub use self::vectored_encoder::VectoredEncoder;
+/// Derive [`ToBencode`] for a struct with named fields, see the [README] for details.
+///
+/// [README]: https://github.com/P3KI/bendy#derive-support
+#[cfg(feature = "derive")]
+pub use bendy_derive::ToBencode;
@@ -0,0 +1,197 @@
+/// Emit a value described with a JSON-like syntax.
+///
+/// Dicts are written as `{ "key": value, ... }` with string literal keys and lists as
+/// `[value, ...]`. Every other value has to implement [`ToBencode`] and must be a single
+/// token tree, so expressions other than literals and variables need to be wrapped in
+/// parentheses. The first argument can be an [`Encoder`] or a [`SingleItemEncoder`].
+///
+/// Whether the keys of a dict are sorted is decided at compile time: sorted dicts are
+/// written straight to the output like with [`emit_dict`], the others are sorted first
+/// like with [`emit_unsorted_dict`].
+///
+/// ```
+/// use bendy::{
+///     emit,
+///     encoding::{AsString, EncodeIter, Encoder, Error},
+/// };
+///
+/// # fn main() -> Result<(), Error> {
+/// let peers = vec![[127, 0, 0, 1, 0x1a, 0xe1]];
+///
+/// let mut encoder = Encoder::new();
+/// emit!(encoder, {
+///     "interval": 1800,
+///     "peers": (EncodeIter::new(peers.iter().map(AsString))),
+///     "complete": [1, (1 + 1)],
+/// })?;
+///
+/// assert_eq!(
+///     encoder.get_output()?,
+///     &b"d8:completeli1ei2ee8:intervali1800e5:peersl6:\x7f\x00\x00\x01\x1a\xe1ee"[..]
+/// );
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`ToBencode`]: crate::encoding::ToBencode
+/// [`Encoder`]: crate::encoding::Encoder
+/// [`SingleItemEncoder`]: crate::encoding::SingleItemEncoder
+/// [`emit_dict`]: crate::encoding::SingleItemEncoder::emit_dict
+/// [`emit_unsorted_dict`]: crate::encoding::SingleItemEncoder::emit_unsorted_dict
+#[macro_export]
+macro_rules! emit {
+    (@value $e:ident, {}) => {
+        $e.emit_dict(|_| Ok(()))
+    };
+    (@value $e:ident, { $($key:literal : $value:tt),+ $(,)? }) => {{
+        const SORTED: bool = $crate::encoding::keys_are_sorted(&[$($key.as_bytes()),+]);
+        if SORTED {
+            $e.emit_dict(|mut dict| {
+                $(dict.emit_pair_with($key.as_bytes(), |e| $crate::emit!(@value e, $value))?;)+
+                Ok(())
+            })
+        } else {
+            $e.emit_unsorted_dict(|dict| {
+                $(dict.emit_pair_with($key.as_bytes(), |e| $crate::emit!(@value e, $value))?;)+
+                Ok(())
+            })
+        }
+    }};
+    (@value $e:ident, []) => {
+        $e.emit_list(|_| Ok(()))
+    };
+    (@value $e:ident, [ $($value:tt),+ $(,)? ]) => {
+        $e.emit_list(|list| {
+            $(list.emit_with(|e| $crate::emit!(@value e, $value))?;)+
+            Ok(())
+        })
+    };
+    (@value $e:ident, $value:expr) => {
+        $e.emit(&$value)
+    };
+    ($encoder:expr, $($value:tt)+) => {
+        $encoder.emit_with(|e| $crate::emit!(@value e, $($value)+))
+    };
+}
+
+/// Assert at compile time that byte string literals are sorted dict keys.
+///
+/// This turns the `UnsortedKeys` error that [`emit_dict`] would return at runtime into a
+/// compile error for call sites with literal keys:
+///
+/// ```
+/// use bendy::{
+///     assert_sorted_keys,
+///     encoding::{Encoder, Error},
+/// };
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut encoder = Encoder::new();
+/// assert_sorted_keys!(b"interval", b"peers");
+/// encoder.emit_dict(|mut e| {
+///     e.emit_pair(b"interval", 1800)?;
+///     e.emit_pair(b"peers", "")
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Unsorted or duplicate keys fail to compile with an error about a mismatched array size:
+///
+/// ```compile_fail
+/// bendy::assert_sorted_keys!(b"peers", b"interval");
+/// ```
+///
+/// [`emit_dict`]: crate::encoding::SingleItemEncoder::emit_dict
+#[macro_export]
+macro_rules! assert_sorted_keys {
+    ($($key:expr),* $(,)?) => {
+        const _: [(); 0] = [(); !$crate::encoding::keys_are_sorted(&[$($key),*]) as usize];
+    };
+}
+
+/// Check whether dict keys are strictly increasing, i.e. sorted and without duplicates.
+///
+/// Being a `const fn`, this can be evaluated at compile time for literal keys.
+///
+/// ```
+/// use bendy::encoding::keys_are_sorted;
+///
+/// const SORTED: bool = keys_are_sorted(&[b"interval", b"peers"]);
+/// assert!(SORTED);
+/// assert!(!keys_are_sorted(&[b"peers", b"interval"]));
+/// assert!(!keys_are_sorted(&[b"peers", b"peers"]));
+/// ```
+pub const fn keys_are_sorted(keys: &[&[u8]]) -> bool {
+    let mut index = 1;
+    while index < keys.len() {
+        if !is_less(keys[index - 1], keys[index]) {
+            return false;
+        }
+        index += 1;
+    }
+
+    true
+}
+
+/// Compare byte strings the same way `Ord` does for slices
+const fn is_less(left: &[u8], right: &[u8]) -> bool {
+    let mut index = 0;
+    while index < left.len() && index < right.len() {
+        if left[index] != right[index] {
+            return left[index] < right[index];
+        }
+        index += 1;
+    }
+
+    left.len() < right.len()
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::{vec, vec::Vec};
+
+    use crate::encoding::{AsString, Encoder, Error, SingleItemEncoder, ToBencode};
+
+    struct Announce {
+        interval: u32,
+        peers: Vec<Vec<u8>>,
+    }
+
+    impl ToBencode for Announce {
+        const MAX_DEPTH: usize = 2;
+
+        fn encode(&self, encoder: SingleItemEncoder) -> Result<(), Error> {
+            emit!(encoder, {
+                "peers": (self.peers.iter().map(AsString).collect::<Vec<_>>()),
+                "interval": (self.interval),
+                "extra": {},
+                "empty": [],
+            })
+        }
+    }
+
+    #[test]
+    fn unsorted_keys_should_be_sorted() {
+        let announce = Announce {
+            interval: 30,
+            peers: vec![b"abc".to_vec()],
+        };
+        assert_eq!(
+            announce.to_bencode().unwrap(),
+            &b"d5:emptyle5:extrade8:intervali30e5:peersl3:abcee"[..]
+        );
+    }
+
+    #[test]
+    fn plain_values_should_be_emitted() {
+        let mut encoder = Encoder::new();
+        emit!(encoder, (2 + 3)).unwrap();
+        assert_eq!(encoder.get_output().unwrap(), b"i5e");
+
+        let mut encoder = Encoder::new();
+        emit!(encoder, ["a", { "b": [[]] }]).unwrap();
+        assert_eq!(encoder.get_output().unwrap(), b"l1:ad1:blleeee");
+    }
+}
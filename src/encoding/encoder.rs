@@ -1,25 +1,57 @@
 #[cfg(not(feature = "std"))]
-use alloc::{
-    borrow::ToOwned,
-    collections::BTreeMap,
-    format,
-    string::{String, ToString},
-    vec::Vec,
-};
+use alloc::{borrow::ToOwned, collections::BTreeMap, format, string::String, vec::Vec};
 #[cfg(feature = "std")]
 use std::{collections::BTreeMap, vec::Vec};
 
+use core::{
+    fmt::{self, Display, Formatter, Write},
+    mem,
+};
+
 use crate::{
     encoding::{Error, PrintableInteger, ToBencode},
-    state_tracker::{StateTracker, StructureError, Token},
+    state_tracker::{OwnedKey, StateTracker, StructureError, Token},
 };
 
+/// Check that a byte string of `len` bytes, including its length prefix, fits into the
+/// address space of the target after `written` bytes of output.
+///
+/// Slices are limited to `isize::MAX` bytes, so a longer document couldn't be decoded
+/// (or, for the `Encoder`, even be allocated) on this platform.
+pub(crate) fn check_string_len(written: usize, len: u64) -> Result<(), Error> {
+    // The decimal digits of the length and the colon
+    let mut prefix = 2;
+    let mut rest = len / 10;
+    while rest > 0 {
+        prefix += 1;
+        rest /= 10;
+    }
+
+    let total = (written as u64)
+        .checked_add(prefix)
+        .and_then(|total| total.checked_add(len));
+    match total {
+        Some(total) if total <= isize::MAX as u64 => Ok(()),
+        _ => Err(Error::LengthOverflow { len }),
+    }
+}
+
 /// The actual encoder. Unlike the decoder, this is not zero-copy, as that would
 /// result in a horrible interface
-#[derive(Default, Debug)]
+#[derive(Debug)]
 pub struct Encoder {
-    state: StateTracker<Vec<u8>, Error>,
+    state: StateTracker<OwnedKey, Error>,
     output: Vec<u8>,
+    /// The number of resets, which invalidate the checkpoints taken before
+    epoch: usize,
+    /// The size hint of the innermost hinted container whose first element is being written
+    len_hint: Option<LenHint>,
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Encoder::with_capacity(0)
+    }
 }
 
 impl Encoder {
@@ -28,6 +60,17 @@ impl Encoder {
         <Self as Default>::default()
     }
 
+    /// Create a new encoder with room for `capacity` bytes of output, so that the output
+    /// buffer doesn't have to grow while encoding values of a known size.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Encoder {
+            state: StateTracker::new(),
+            output: Vec::with_capacity(capacity),
+            epoch: 0,
+            len_hint: None,
+        }
+    }
+
     /// Set the max depth of the encoded object
     #[must_use]
     pub fn with_max_depth(mut self, max_depth: usize) -> Self {
@@ -35,6 +78,35 @@ impl Encoder {
         self
     }
 
+    /// Reserve room for at least `additional` more bytes of output.
+    ///
+    /// Inside of [`emit_list`](Self::emit_list), this sizes the output once for a large
+    /// list of elements with a known encoded size:
+    ///
+    /// ```
+    /// # use bendy::encoding::{Encoder, Error};
+    /// #
+    /// # fn main() -> Result<(), Error> {
+    /// let pieces = vec![[0u8; 20]; 1000];
+    ///
+    /// let mut encoder = Encoder::new();
+    /// encoder.emit_list(|e| {
+    ///     // Every piece is encoded as `20:` and its 20 bytes
+    ///     e.reserve(pieces.len() * 23 + 1);
+    ///     for piece in &pieces {
+    ///         e.emit_bytes(piece)?;
+    ///     }
+    ///     Ok(())
+    /// })?;
+    ///
+    /// assert_eq!(encoder.get_output()?.len(), 1000 * 23 + 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reserve(&mut self, additional: usize) {
+        self.output.reserve(additional);
+    }
+
     /// Emit a single token to the encoder
     pub(crate) fn emit_token(&mut self, token: Token) -> Result<(), Error> {
         self.state.check_error()?;
@@ -43,9 +115,9 @@ impl Encoder {
             Token::List => self.output.push(b'l'),
             Token::Dict => self.output.push(b'd'),
             Token::String(s) => {
-                // Writing to a vec can't fail
-                let length = s.len().to_string();
-                self.output.extend_from_slice(length.as_bytes());
+                self.state
+                    .latch_err(check_string_len(self.output.len(), s.len() as u64))?;
+                s.len().write_digits(&mut self.output);
                 self.output.push(b':');
                 self.output.extend_from_slice(s);
             },
@@ -58,9 +130,46 @@ impl Encoder {
             Token::End => self.output.push(b'e'),
         }
 
+        self.apply_len_hint();
         Ok(())
     }
 
+    /// Reserve the output for the rest of a hinted container once its first element was
+    /// written, assuming that the other elements are about the same size.
+    fn apply_len_hint(&mut self) {
+        if let Some(hint) = &mut self.len_hint {
+            if self.state.remaining_depth() != hint.depth {
+                return;
+            }
+
+            hint.values_left -= 1;
+            if hint.values_left == 0 {
+                let size = self.output.len().saturating_sub(hint.start);
+                let additional = size.saturating_mul(hint.len - 1).saturating_add(1);
+                self.output.reserve(additional);
+                self.len_hint = None;
+            }
+        }
+    }
+
+    /// Write a container holding `len` elements of `values` values each with `content_cb`
+    fn emit_hinted<F>(&mut self, len: usize, values: usize, content_cb: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Self) -> Result<(), Error>,
+    {
+        let hint = LenHint {
+            depth: self.state.remaining_depth(),
+            start: self.output.len(),
+            len,
+            values_left: values,
+        };
+        let outer = mem::replace(&mut self.len_hint, Some(hint).filter(|_| len > 1));
+        let result = content_cb(self);
+        // Restored before the end of the container, which may complete an outer element
+        self.len_hint = outer;
+        result
+    }
+
     /// Emit an arbitrary encodable object
     pub fn emit<E: ToBencode>(&mut self, value: E) -> Result<(), Error> {
         self.emit_with(|e| value.encode(e))
@@ -101,8 +210,9 @@ impl Encoder {
         // possible (for performance)
         self.state.observe_token(&Token::Num(""))?;
         self.output.push(b'i');
-        self.output.extend_from_slice(value.to_string().as_bytes());
+        value.write_digits(&mut self.output);
         self.output.push(b'e');
+        self.apply_len_hint();
         Ok(())
     }
 
@@ -116,6 +226,117 @@ impl Encoder {
         self.emit_token(Token::String(value))
     }
 
+    /// Emit the `Display` representation of a value as a string, without formatting it
+    /// into a temporary `String` first.
+    ///
+    /// The value is formatted twice: once to determine the length of the string and once
+    /// to write it to the output. Fails if the value formats differently the second time,
+    /// or if the string isn't valid at this point, e.g. as an unsorted dict key. A string
+    /// that fails is removed from the output again.
+    ///
+    /// ```
+    /// # use bendy::encoding::{Encoder, Error};
+    /// #
+    /// # fn main() -> Result<(), Error>{
+    /// let mut encoder = Encoder::new();
+    /// encoder.emit_display(&format_args!("udp://tracker.example:{}", 6969))?;
+    ///
+    /// assert_eq!(encoder.get_output()?, b"26:udp://tracker.example:6969");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn emit_display(&mut self, value: &impl Display) -> Result<(), Error> {
+        self.state.check_error()?;
+
+        let mut counter = LengthCounter(0);
+        if write!(counter, "{}", value).is_err() {
+            return self
+                .state
+                .latch_err(Err(Error::malformed_content(fmt::Error)));
+        }
+        let len = counter.0;
+
+        self.emit_string_with(len as u64, |output| {
+            let start = output.len();
+            let written = write!(OutputWriter(output), "{}", value);
+            if written.is_err() || output.len() - start != len {
+                return Err(Error::malformed_content(InconsistentDisplay));
+            }
+            Ok(())
+        })
+    }
+
+    /// Emit a byte string of `len` bytes given as a sequence of chunks, e.g. read from a
+    /// file piece by piece, without concatenating them first.
+    ///
+    /// Fails if the chunks don't add up to exactly `len` bytes, or if the string isn't
+    /// valid at this point, e.g. as an unsorted dict key. A string that fails is removed
+    /// from the output again.
+    ///
+    /// ```
+    /// # use bendy::encoding::{Encoder, Error};
+    /// #
+    /// # fn main() -> Result<(), Error>{
+    /// let mut encoder = Encoder::new();
+    /// encoder.emit_bytes_chunked(6, vec![&b"spam"[..], b"", b"!!"])?;
+    ///
+    /// assert_eq!(encoder.get_output()?, b"6:spam!!");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn emit_bytes_chunked<'a>(
+        &mut self,
+        len: u64,
+        chunks: impl IntoIterator<Item = &'a [u8]>,
+    ) -> Result<(), Error> {
+        self.emit_string_with(len, |output| {
+            let start = output.len();
+            for chunk in chunks {
+                let written = (output.len() - start + chunk.len()) as u64;
+                if written > len {
+                    return Err(Error::malformed_content(ChunkLength::new(len, written)));
+                }
+                output.extend_from_slice(chunk);
+            }
+
+            let written = (output.len() - start) as u64;
+            if written != len {
+                return Err(Error::malformed_content(ChunkLength::new(len, written)));
+            }
+            Ok(())
+        })
+    }
+
+    /// Emit a string of `len` bytes, whose content is appended to the output by
+    /// `write_content`. The string is only kept once it was validated as the next token,
+    /// so a string that fails leaves the output as it was.
+    fn emit_string_with<F>(&mut self, len: u64, write_content: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Vec<u8>) -> Result<(), Error>,
+    {
+        self.state.check_error()?;
+        self.state
+            .latch_err(check_string_len(self.output.len(), len))?;
+
+        let token_start = self.output.len();
+        len.write_digits(&mut self.output);
+        self.output.push(b':');
+        let start = self.output.len();
+        let result = match write_content(&mut self.output) {
+            Ok(()) => self
+                .state
+                .observe_token(&Token::String(&self.output[start..])),
+            Err(error) => self.state.latch_err(Err(error)),
+        };
+        if result.is_err() {
+            self.output.truncate(token_start);
+            return result;
+        }
+
+        self.apply_len_hint();
+        Ok(())
+    }
+
     /// Emit a dictionary where you know that the keys are already
     /// sorted.  The callback must emit key/value pairs to the given
     /// encoder in sorted order.  If the key/value pairs may not be
@@ -145,6 +366,19 @@ impl Encoder {
         self.emit_token(Token::End)
     }
 
+    /// Like [`emit_dict`](Self::emit_dict), for a dict of about `len` pairs. Like in
+    /// [`emit_list_with_len_hint`](Self::emit_list_with_len_hint), the output is reserved
+    /// for the rest of the dict once the first pair is written.
+    pub fn emit_dict_with_len_hint<F>(&mut self, len: usize, content_cb: F) -> Result<(), Error>
+    where
+        F: FnOnce(SortedDictEncoder) -> Result<(), Error>,
+    {
+        self.emit_token(Token::Dict)?;
+        // Every pair is a key and a value
+        self.emit_hinted(len, 2, |encoder| content_cb(SortedDictEncoder { encoder }))?;
+        self.emit_token(Token::End)
+    }
+
     /// Emit an arbitrary list. The callback should emit the contents
     /// of the list to the given encoder.
     ///
@@ -170,6 +404,39 @@ impl Encoder {
         self.emit_token(Token::End)
     }
 
+    /// Like [`emit_list`](Self::emit_list), for a list of about `len` elements.
+    ///
+    /// Once the first element is written, the output is reserved for the rest of the list,
+    /// assuming that all elements are about the same size, so that it doesn't have to grow
+    /// element by element. The hint doesn't limit the number of elements.
+    ///
+    /// ```
+    /// # use bendy::encoding::{Encoder, Error};
+    /// #
+    /// # fn main() -> Result<(), Error> {
+    /// let pieces = vec![[0u8; 20]; 1000];
+    ///
+    /// let mut encoder = Encoder::new();
+    /// encoder.emit_list_with_len_hint(pieces.len(), |e| {
+    ///     for piece in &pieces {
+    ///         e.emit_bytes(piece)?;
+    ///     }
+    ///     Ok(())
+    /// })?;
+    ///
+    /// assert_eq!(encoder.get_output()?.len(), 1000 * 23 + 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn emit_list_with_len_hint<F>(&mut self, len: usize, list_cb: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Encoder) -> Result<(), Error>,
+    {
+        self.emit_token(Token::List)?;
+        self.emit_hinted(len, 1, list_cb)?;
+        self.emit_token(Token::End)
+    }
+
     /// Emit a dictionary that may have keys out of order. This will write the dict
     /// values to temporary memory, then sort them before adding them to the serialized
     /// stream
@@ -199,12 +466,221 @@ impl Encoder {
         self.end_unsorted_dict(encoder)
     }
 
+    /// Emit a dictionary with its keys in the given `order` instead of sorted, for peers
+    /// that require a specific key order.
+    ///
+    /// # Non-canonical output
+    ///
+    /// Bencode requires dict keys to be sorted, so unless `order` happens to be sorted,
+    /// the output is not canonical bencode and is rejected by this crate's own
+    /// [`Decoder`](crate::decoding::Decoder). Only use this to talk to peers that insist
+    /// on a different order.
+    ///
+    /// Like in [`emit_and_sort_dict`](Self::emit_and_sort_dict), the pairs are buffered
+    /// and can be emitted in any order. Keys that are missing from `order` fail the
+    /// encoding; keys of `order` without a pair are left out.
+    ///
+    /// ```
+    /// # use bendy::encoding::{Encoder, Error};
+    /// #
+    /// # fn main() -> Result<(), Error> {
+    /// let mut encoder = Encoder::new();
+    /// encoder.emit_dict_with_order(&[b"y", b"t", b"q"], |e| {
+    ///     e.emit_pair(b"q", "ping")?;
+    ///     e.emit_pair(b"y", "q")
+    /// })?;
+    ///
+    /// assert_eq!(encoder.get_output()?, b"d1:y1:q1:q4:pinge");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn emit_dict_with_order<F>(&mut self, order: &[&[u8]], content_cb: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut UnsortedDictEncoder) -> Result<(), Error>,
+    {
+        let mut encoder = self.begin_unsorted_dict()?;
+        content_cb(&mut encoder)?;
+        let mut content = encoder.done()?;
+
+        if let Some(key) = content.keys().find(|key| !order.contains(&&key[..])) {
+            let error = StructureError::invalid_state(format!(
+                "Key {} is not part of the order",
+                String::from_utf8_lossy(key)
+            ));
+            return self.state.latch_err(Err(Error::from(error)));
+        }
+
+        // The keys are written without showing them to the state tracker, which would
+        // reject them as unsorted, so to it the dict stays empty
+        for key in order {
+            if let Some(value) = content.remove(*key) {
+                self.state
+                    .latch_err(check_string_len(self.output.len(), key.len() as u64))?;
+                key.len().write_digits(&mut self.output);
+                self.output.push(b':');
+                self.output.extend_from_slice(key);
+                self.output.extend_from_slice(&value);
+            }
+        }
+
+        self.emit_token(Token::End)
+    }
+
+    /// The error the encoder failed with, if any. Once an error occurred, every further
+    /// call fails with the same error, see [Error handling](crate::encoding#error-handling).
+    pub fn error(&self) -> Option<Error> {
+        self.state.check_error().err()
+    }
+
+    /// Whether the encoder failed and rejects any further output.
+    pub fn is_poisoned(&self) -> bool {
+        self.state.check_error().is_err()
+    }
+
+    /// Remember the current position in the output, so that the encoder can be reset to it
+    /// using [`try_recover_at_checkpoint`](Encoder::try_recover_at_checkpoint).
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            encoder: self.address(),
+            epoch: self.epoch,
+            output_len: self.output.len(),
+            state: self.state.clone(),
+            len_hint: self.len_hint,
+        }
+    }
+
+    /// Reset the encoder to a checkpoint, discarding everything written since and clearing
+    /// any error that occurred in the meantime. This allows skipping values that fail to
+    /// encode:
+    ///
+    /// ```
+    /// # use bendy::encoding::{Encoder, Error};
+    /// #
+    /// # fn main() -> Result<(), Error> {
+    /// let mut encoder = Encoder::new();
+    /// encoder.emit_list(|e| {
+    ///     for key in &[&b"a"[..], b"b", b"a"] {
+    ///         let checkpoint = e.checkpoint();
+    ///         // Fails on the duplicate key
+    ///         let result = e.emit_dict(|mut e| {
+    ///             e.emit_pair(b"a", 1)?;
+    ///             e.emit_pair(key, 2)
+    ///         });
+    ///         if result.is_err() {
+    ///             e.try_recover_at_checkpoint(checkpoint)?;
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// })?;
+    ///
+    /// assert_eq!(encoder.get_output()?, b"ld1:ai1e1:bi2eee");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Fails if the checkpoint was taken from a different encoder, before the encoder was
+    /// reset, or after the output was already shorter. Checkpoints are matched to the
+    /// encoder by its address, so they are also rejected after the encoder was moved.
+    /// Recovering at a checkpoint taken while the encoder was poisoned leaves it poisoned.
+    pub fn try_recover_at_checkpoint(&mut self, checkpoint: Checkpoint) -> Result<(), Error> {
+        if checkpoint.encoder != self.address() || checkpoint.epoch != self.epoch {
+            return Err(Error::from(StructureError::invalid_state(
+                "Checkpoint was taken from a different encoder or before a reset",
+            )));
+        }
+
+        if checkpoint.output_len > self.output.len() {
+            return Err(Error::from(StructureError::invalid_state(
+                "Checkpoint is ahead of the encoder output",
+            )));
+        }
+
+        self.output.truncate(checkpoint.output_len);
+        self.state = checkpoint.state;
+        self.len_hint = checkpoint.len_hint;
+        Ok(())
+    }
+
+    /// Identifies the encoder in its checkpoints
+    fn address(&self) -> usize {
+        self as *const Self as usize
+    }
+
     /// Return the encoded string, if all objects written are complete
     pub fn get_output(mut self) -> Result<Vec<u8>, Error> {
         self.state.observe_eof()?;
         Ok(self.output)
     }
 
+    /// Return a view of the encoded string, if all objects written are complete.
+    ///
+    /// Unlike [`get_output`](Self::get_output), this keeps the encoder, so that together
+    /// with [`reset`](Self::reset) the same output buffer can be used for many messages:
+    ///
+    /// ```
+    /// # use bendy::encoding::{Encoder, Error};
+    /// #
+    /// # fn main() -> Result<(), Error> {
+    /// let mut encoder = Encoder::new();
+    /// for interval in &[1800, 900] {
+    ///     encoder.reset();
+    ///     encoder.emit_dict(|mut e| e.emit_pair(b"interval", interval))?;
+    ///     let response = encoder.get_output_ref()?;
+    ///     assert!(response.starts_with(b"d8:intervali"));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_output_ref(&mut self) -> Result<&[u8], Error> {
+        self.state.observe_eof()?;
+        Ok(&self.output)
+    }
+
+    /// Move the encoded string out of the encoder, if all objects written are complete,
+    /// and reset the encoder. The next output starts in a new buffer; to reuse the buffer,
+    /// use [`get_output_ref`](Self::get_output_ref) instead.
+    pub fn take_output(&mut self) -> Result<Vec<u8>, Error> {
+        self.state.observe_eof()?;
+        self.state.reset();
+        self.len_hint = None;
+        self.epoch = self.epoch.wrapping_add(1);
+        Ok(mem::take(&mut self.output))
+    }
+
+    /// Discard the output and any error, so that the encoder can be used for a new
+    /// message. The max depth and the capacity of the output buffer are kept.
+    pub fn reset(&mut self) {
+        self.state.reset();
+        self.output.clear();
+        self.len_hint = None;
+        self.epoch = self.epoch.wrapping_add(1);
+    }
+
+    /// Write the encoded string to an output sink, if all objects written are complete.
+    /// Failures of the sink are returned as [`Error::Io`].
+    ///
+    /// ```
+    /// # use bendy::encoding::{Encoder, Error};
+    /// #
+    /// # fn main() -> Result<(), Error> {
+    /// let mut encoder = Encoder::new();
+    /// encoder.emit_str("spam")?;
+    ///
+    /// let mut sink = [0; 3];
+    /// assert!(matches!(
+    ///     encoder.write_output(&mut &mut sink[..]),
+    ///     Err(Error::Io { .. })
+    /// ));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_output(self, sink: &mut impl std::io::Write) -> Result<(), Error> {
+        let output = self.get_output()?;
+        sink.write_all(&output)?;
+        Ok(())
+    }
+
     pub(crate) fn begin_unsorted_dict(&mut self) -> Result<UnsortedDictEncoder, Error> {
         // emit the dict token so that a pre-existing state error is reported early
         self.emit_token(Token::Dict)?;
@@ -220,6 +696,7 @@ impl Encoder {
             // We know that the output is a single object by construction
             self.state.observe_token(&Token::Num(""))?;
             self.output.extend_from_slice(&v);
+            self.apply_len_hint();
         }
 
         self.emit_token(Token::End)?;
@@ -271,6 +748,24 @@ impl<'a> SingleItemEncoder<'a> {
         self.encoder.emit_bytes(value)
     }
 
+    /// Emit a byte string given as a sequence of chunks, see
+    /// [`Encoder::emit_bytes_chunked`].
+    pub fn emit_bytes_chunked<'chunk>(
+        self,
+        len: u64,
+        chunks: impl IntoIterator<Item = &'chunk [u8]>,
+    ) -> Result<(), Error> {
+        *self.value_written = true;
+        self.encoder.emit_bytes_chunked(len, chunks)
+    }
+
+    /// Emit the `Display` representation of a value as a string, see
+    /// [`Encoder::emit_display`].
+    pub fn emit_display(self, value: &impl Display) -> Result<(), Error> {
+        *self.value_written = true;
+        self.encoder.emit_display(value)
+    }
+
     /// Emit an arbitrary list
     pub fn emit_list<F>(self, list_cb: F) -> Result<(), Error>
     where
@@ -280,6 +775,31 @@ impl<'a> SingleItemEncoder<'a> {
         self.encoder.emit_list(list_cb)
     }
 
+    /// Emit a list of about `len` elements, see [`Encoder::emit_list_with_len_hint`]
+    pub fn emit_list_with_len_hint<F>(self, len: usize, list_cb: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Encoder) -> Result<(), Error>,
+    {
+        *self.value_written = true;
+        self.encoder.emit_list_with_len_hint(len, list_cb)
+    }
+
+    /// Emit the object as a sequence of tokens written by `tokens_cb`, which allows
+    /// encoding recursive structures with a loop instead of nested callbacks.
+    pub(crate) fn emit_tokens<F>(self, tokens_cb: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Encoder) -> Result<(), Error>,
+    {
+        *self.value_written = true;
+        tokens_cb(self.encoder)
+    }
+
+    /// Reserve room for at least `additional` more bytes of output, see
+    /// [`Encoder::reserve`].
+    pub fn reserve(&mut self, additional: usize) {
+        self.encoder.reserve(additional);
+    }
+
     /// Emit a sorted dictionary. If the input dictionary is unsorted, this will return an error.
     pub fn emit_dict<F>(self, content_cb: F) -> Result<(), Error>
     where
@@ -289,6 +809,45 @@ impl<'a> SingleItemEncoder<'a> {
         self.encoder.emit_dict(content_cb)
     }
 
+    /// Emit a sorted dictionary of about `len` pairs, see
+    /// [`Encoder::emit_dict_with_len_hint`]
+    pub fn emit_dict_with_len_hint<F>(self, len: usize, content_cb: F) -> Result<(), Error>
+    where
+        F: FnOnce(SortedDictEncoder) -> Result<(), Error>,
+    {
+        *self.value_written = true;
+        self.encoder.emit_dict_with_len_hint(len, content_cb)
+    }
+
+    /// Emit a dictionary from key/value pairs that are already sorted by key, writing
+    /// them straight to the output. If the keys are not sorted, this will return an error.
+    ///
+    /// ```
+    /// # use bendy::encoding::{Encoder, Error};
+    /// #
+    /// # fn main() -> Result<(), Error> {
+    /// let mut encoder = Encoder::new();
+    /// encoder.emit_with(|e| e.emit_sorted_pairs(vec![("a", 1), ("b", 2)]))?;
+    /// assert_eq!(encoder.get_output()?, b"d1:ai1e1:bi2ee");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn emit_sorted_pairs<K, V>(
+        self,
+        pairs: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        V: ToBencode,
+    {
+        self.emit_dict(|mut e| {
+            for (key, value) in pairs {
+                e.emit_pair(key.as_ref(), value)?;
+            }
+            Ok(())
+        })
+    }
+
     /// Emit a dictionary that may have keys out of order. This will write the dict
     /// values to temporary memory, then sort them before adding them to the serialized
     /// stream
@@ -300,6 +859,16 @@ impl<'a> SingleItemEncoder<'a> {
         self.encoder.emit_and_sort_dict(content_cb)
     }
 
+    /// Emit a dictionary with its keys in the given `order` instead of sorted, see
+    /// [`Encoder::emit_dict_with_order`]. The output is not canonical bencode.
+    pub fn emit_dict_with_order<F>(self, order: &[&[u8]], content_cb: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut UnsortedDictEncoder) -> Result<(), Error>,
+    {
+        *self.value_written = true;
+        self.encoder.emit_dict_with_order(order, content_cb)
+    }
+
     /// Emit an arbitrary list.
     ///
     /// Attention: If this method is used while canonical output is required
@@ -323,6 +892,12 @@ pub struct SortedDictEncoder<'a> {
 }
 
 impl<'a> SortedDictEncoder<'a> {
+    /// Reserve room for at least `additional` more bytes of output, see
+    /// [`Encoder::reserve`].
+    pub fn reserve(&mut self, additional: usize) {
+        self.encoder.reserve(additional);
+    }
+
     /// Emit a key/value pair
     pub fn emit_pair<E>(&mut self, key: &[u8], value: E) -> Result<(), Error>
     where
@@ -393,16 +968,20 @@ impl UnsortedDictEncoder {
                 state: "No value was emitted".to_owned(),
             }));
         } else {
-            self.error = encoder.state.observe_eof().map_err(Error::from);
+            self.error = encoder.state.observe_eof();
         }
 
         if self.error.is_err() {
             return self.error.clone();
         }
 
-        let encoded_object = encoder
-            .get_output()
-            .expect("Any errors should have been caught by observe_eof");
+        let encoded_object = match encoder.get_output() {
+            Ok(encoded_object) => encoded_object,
+            Err(error) => {
+                self.error = Err(error.clone());
+                return Err(error);
+            },
+        };
 
         self.save_pair(key, encoded_object)
     }
@@ -450,6 +1029,97 @@ impl UnsortedDictEncoder {
     }
 }
 
+/// The size hint of a container, see [`Encoder::emit_list_with_len_hint`]
+#[derive(Clone, Copy, Debug)]
+struct LenHint {
+    /// The remaining depth of the encoder inside of the container
+    depth: usize,
+    /// The output length before the first element
+    start: usize,
+    /// The expected number of elements
+    len: usize,
+    /// The number of values left to write of the first element
+    values_left: usize,
+}
+
+/// A saved position of an [`Encoder`], see [`Encoder::checkpoint`].
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    encoder: usize,
+    epoch: usize,
+    output_len: usize,
+    state: StateTracker<OwnedKey, Error>,
+    len_hint: Option<LenHint>,
+}
+
+/// Counts the bytes written to it
+struct LengthCounter(usize);
+
+impl Write for LengthCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/// Appends everything written to it to a byte buffer
+struct OutputWriter<'a>(&'a mut Vec<u8>);
+
+impl Write for OutputWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// A value formatted to a different length on the second pass of `emit_display`
+#[derive(Debug)]
+struct InconsistentDisplay;
+
+impl Display for InconsistentDisplay {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("the value changed while being formatted")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InconsistentDisplay {}
+
+/// Chunks of a byte string that don't add up to its declared length
+#[derive(Debug)]
+pub(crate) struct ChunkLength {
+    declared: u64,
+    written: u64,
+}
+
+impl ChunkLength {
+    /// The chunks of a string of `declared` bytes added up to `written` bytes so far
+    pub(crate) fn new(declared: u64, written: u64) -> Self {
+        ChunkLength { declared, written }
+    }
+}
+
+impl Display for ChunkLength {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.written > self.declared {
+            write!(
+                f,
+                "chunks of more than the declared {} bytes",
+                self.declared
+            )
+        } else {
+            write!(
+                f,
+                "chunks of {} bytes instead of the declared {}",
+                self.written, self.declared
+            )
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ChunkLength {}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -476,9 +1146,324 @@ mod test {
         );
     }
 
+    #[test]
+    fn string_lengths_should_fit_the_target() {
+        assert!(check_string_len(0, 0).is_ok());
+        assert!(check_string_len(isize::MAX as usize - 3, 1).is_ok());
+        assert!(check_string_len(isize::MAX as usize - 2, 1).is_err());
+
+        let error = check_string_len(0, u64::MAX).unwrap_err();
+        assert_eq!(error.code(), "encode.length_overflow");
+        assert!(matches!(error, Error::LengthOverflow { len: u64::MAX }));
+
+        // Strings of 4 GB and more are only valid on 64-bit targets
+        let len = 5 * 1024 * 1024 * 1024;
+        assert_eq!(
+            check_string_len(0, len).is_ok(),
+            cfg!(target_pointer_width = "64")
+        );
+    }
+
+    #[test]
+    fn chunks_should_add_up_to_the_declared_length() {
+        let mut encoder = Encoder::new();
+        encoder
+            .emit_dict(|mut e| {
+                e.emit_pair_with(b"a", |e| {
+                    e.emit_bytes_chunked(3, [&b"x"[..], b"yz"].iter().copied())
+                })?;
+                e.emit_pair_with(b"b", |e| e.emit_bytes_chunked(0, None))
+            })
+            .unwrap();
+        assert_eq!(encoder.get_output().unwrap(), b"d1:a3:xyz1:b0:e");
+
+        let mut encoder = Encoder::new();
+        let error = encoder
+            .emit_bytes_chunked(3, [&b"xy"[..], b"zz"].iter().copied())
+            .unwrap_err();
+        assert_eq!(error.code(), "encode.malformed_content");
+        assert!(encoder.output.is_empty());
+        assert!(encoder.get_output().is_err());
+
+        let mut encoder = Encoder::new();
+        assert!(encoder.emit_bytes_chunked(3, Some(&b"xy"[..])).is_err());
+        assert!(encoder.output.is_empty());
+
+        // Chunked keys are validated before they are kept
+        let mut encoder = Encoder::new();
+        encoder.emit_token(Token::Dict).unwrap();
+        encoder.emit_bytes_chunked(1, Some(&b"b"[..])).unwrap();
+        encoder.emit_int(1).unwrap();
+        let error = encoder.emit_bytes_chunked(1, Some(&b"a"[..])).unwrap_err();
+        assert_eq!(error.code(), "structure.unsorted_keys");
+        assert_eq!(encoder.output, b"d1:bi1e");
+    }
+
     #[test]
     fn emit_cb_must_emit() {
         let mut encoder = Encoder::new();
         assert!(encoder.emit_with(|_| Ok(())).is_err());
     }
+
+    #[test]
+    fn emit_display_should_write_a_string() {
+        let mut encoder = Encoder::new();
+        encoder
+            .emit_list(|e| {
+                e.emit_display(&12345)?;
+                e.emit_display(&"")
+            })
+            .unwrap();
+        assert_eq!(encoder.get_output().unwrap(), b"l5:123450:e");
+    }
+
+    #[test]
+    fn emit_display_should_fail_on_inconsistent_values() {
+        use core::cell::Cell;
+
+        struct Growing(Cell<usize>);
+
+        impl Display for Growing {
+            fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+                self.0.set(self.0.get() + 1);
+                write!(f, "{}", "x".repeat(self.0.get()))
+            }
+        }
+
+        let mut encoder = Encoder::new();
+        assert!(encoder.emit_display(&Growing(Cell::new(0))).is_err());
+        assert!(encoder.output.is_empty());
+        assert!(encoder.get_output().is_err());
+    }
+
+    #[test]
+    fn emit_display_should_not_write_invalid_keys() {
+        let mut encoder = Encoder::new();
+        encoder.emit_token(Token::Dict).unwrap();
+        encoder.emit_display(&"b").unwrap();
+        encoder.emit_int(1).unwrap();
+
+        let error = encoder.emit_display(&"a").unwrap_err();
+        assert_eq!(error.code(), "structure.unsorted_keys");
+        assert_eq!(encoder.output, b"d1:bi1e");
+    }
+
+    #[test]
+    fn errors_should_poison_the_encoder_until_recovery() {
+        let mut encoder = Encoder::new();
+        encoder.emit_list(|e| e.emit_int(1)).unwrap();
+        let checkpoint = encoder.checkpoint();
+        assert!(!encoder.is_poisoned());
+
+        assert!(encoder
+            .emit_dict(|mut e| {
+                e.emit_pair(b"b", 1)?;
+                e.emit_pair(b"a", 2)
+            })
+            .is_err());
+        assert!(encoder.is_poisoned());
+        assert!(encoder.error().is_some());
+        assert!(encoder.emit_int(3).is_err());
+
+        encoder.try_recover_at_checkpoint(checkpoint).unwrap();
+        assert!(encoder.error().is_none());
+        encoder.emit_int(3).unwrap();
+        assert_eq!(encoder.get_output().unwrap(), b"li1eei3e");
+    }
+
+    #[test]
+    fn long_keys_should_be_sorted_too() {
+        let short = [b'k'; 32];
+        let long = [b'k'; 33];
+
+        let mut encoder = Encoder::new();
+        encoder
+            .emit_dict(|mut e| {
+                e.emit_pair(&short, 1)?;
+                e.emit_pair(&long, 2)
+            })
+            .unwrap();
+
+        let mut encoder = Encoder::new();
+        assert!(encoder
+            .emit_dict(|mut e| {
+                e.emit_pair(&long, 1)?;
+                e.emit_pair(&short, 2)
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn reset_should_keep_the_output_buffer() {
+        let mut encoder = Encoder::new().with_max_depth(1);
+        encoder.emit_list(|e| e.emit_str("spam")).unwrap();
+        assert_eq!(encoder.get_output_ref().unwrap(), b"l4:spame");
+        let capacity = encoder.output.capacity();
+
+        encoder.reset();
+        assert!(encoder.emit_list(|e| e.emit_list(|_| Ok(()))).is_err());
+        encoder.reset();
+        encoder.emit_int(1).unwrap();
+        assert_eq!(encoder.get_output_ref().unwrap(), b"i1e");
+        assert_eq!(encoder.output.capacity(), capacity);
+
+        encoder.emit_token(Token::List).unwrap();
+        assert!(encoder.get_output_ref().is_err());
+        assert!(encoder.take_output().is_err());
+
+        encoder.reset();
+        encoder.emit_str("a").unwrap();
+        assert_eq!(encoder.take_output().unwrap(), b"1:a");
+        assert_eq!(encoder.get_output_ref().unwrap(), b"");
+    }
+
+    #[test]
+    fn reserved_capacity_should_be_kept() {
+        let mut encoder = Encoder::with_capacity(64);
+        assert!(encoder.output.capacity() >= 64);
+        encoder
+            .emit_dict(|mut e| {
+                e.reserve(1024);
+                e.emit_pair_with(b"a", |mut e| {
+                    e.reserve(2048);
+                    e.emit_int(1)
+                })
+            })
+            .unwrap();
+        assert!(encoder.output.capacity() >= 2048);
+        assert_eq!(encoder.get_output().unwrap(), b"d1:ai1ee");
+    }
+
+    #[test]
+    fn len_hints_should_size_the_output_after_the_first_element() {
+        let mut encoder = Encoder::new();
+        encoder
+            .emit_list_with_len_hint(100, |e| {
+                e.emit_bytes(&[0; 20])?;
+                // 99 more pieces of 23 bytes and the end of the list
+                let capacity = e.output.capacity();
+                assert!(capacity >= 100 * 23 + 2);
+                for _ in 1..100 {
+                    e.emit_bytes(&[0; 20])?;
+                }
+                assert_eq!(e.output.capacity(), capacity);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(encoder.get_output().unwrap().len(), 100 * 23 + 2);
+
+        let mut encoder = Encoder::new();
+        encoder
+            .emit_dict_with_len_hint(10, |mut e| {
+                e.emit_pair(b"a", "first")?;
+                // Every pair is `1:a5:first`
+                assert!(e.encoder.output.capacity() >= 10 * 10 + 2);
+                e.emit_pair(b"b", "other")
+            })
+            .unwrap();
+        assert_eq!(encoder.get_output().unwrap(), b"d1:a5:first1:b5:othere");
+    }
+
+    #[test]
+    fn checkpoints_should_restore_the_len_hint() {
+        let mut encoder = Encoder::new();
+        encoder
+            .emit_list_with_len_hint(1000, |e| {
+                let checkpoint = e.checkpoint();
+                e.emit_int(1)?;
+                e.try_recover_at_checkpoint(checkpoint)?;
+                // The recovered first element sizes the output again
+                e.emit_bytes(&[0; 20])?;
+                assert!(e.output.capacity() >= 1000 * 23 + 2);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(encoder.get_output().unwrap().len(), 23 + 2);
+    }
+
+    #[test]
+    fn nested_len_hints_should_size_their_own_container() {
+        let mut encoder = Encoder::new();
+        encoder
+            .emit_list_with_len_hint(1000, |e| {
+                e.emit_list_with_len_hint(2, |e| {
+                    e.emit_int(1)?;
+                    // The inner list only needs room for one more `i2e` and its end
+                    assert!(e.output.capacity() >= 9);
+                    assert!(e.output.capacity() < 1000);
+                    e.emit_int(2)
+                })?;
+                // The outer one is sized from the whole first inner list
+                assert_eq!(e.output.len(), 1 + 8);
+                assert!(e.output.capacity() >= 1000 * 8 + 2);
+                e.emit_list(|e| e.emit_int(3))
+            })
+            .unwrap();
+        assert_eq!(encoder.get_output().unwrap(), b"lli1ei2eeli3eee");
+    }
+
+    #[test]
+    fn dicts_should_follow_the_given_order() {
+        let order: &[&[u8]] = &[b"z", b"a", b"m"];
+        let mut encoder = Encoder::new();
+        encoder
+            .emit_list(|e| {
+                e.emit_dict_with_order(order, |e| {
+                    e.emit_pair(b"a", 1)?;
+                    e.emit_pair_with(b"z", |e| e.emit_dict_with_order(&[], |_| Ok(())))
+                })?;
+                e.emit_int(2)
+            })
+            .unwrap();
+        assert_eq!(encoder.get_output().unwrap(), b"ld1:zde1:ai1eei2ee");
+
+        let mut encoder = Encoder::new();
+        let error = encoder
+            .emit_dict_with_order(order, |e| e.emit_pair(b"b", 1))
+            .unwrap_err();
+        assert_eq!(error.code(), "structure.invalid_state");
+        assert!(encoder.is_poisoned());
+
+        let mut encoder = Encoder::new().with_max_depth(1);
+        assert!(encoder
+            .emit_dict_with_order(order, |e| e
+                .emit_pair_with(b"a", |e| e.emit_list(|e| e.emit_int(1))))
+            .is_err());
+    }
+
+    #[test]
+    fn foreign_checkpoints_should_be_rejected() {
+        let mut other = Encoder::new();
+        other.emit_str("long string").unwrap();
+
+        let mut encoder = Encoder::new();
+        assert!(encoder
+            .try_recover_at_checkpoint(other.checkpoint())
+            .is_err());
+
+        // The output of the other encoder is shorter than this one
+        let mut encoder = Encoder::new();
+        encoder.emit_str("even longer string").unwrap();
+        assert!(encoder
+            .try_recover_at_checkpoint(other.checkpoint())
+            .is_err());
+        assert_eq!(encoder.get_output().unwrap(), b"18:even longer string");
+    }
+
+    #[test]
+    fn checkpoints_should_not_survive_a_reset() {
+        let mut encoder = Encoder::new();
+        encoder.emit_str("before").unwrap();
+        let checkpoint = encoder.checkpoint();
+
+        encoder.reset();
+        encoder.emit_str("after the reset").unwrap();
+        assert!(encoder.try_recover_at_checkpoint(checkpoint).is_err());
+        assert_eq!(encoder.get_output_ref().unwrap(), b"15:after the reset");
+
+        let checkpoint = encoder.checkpoint();
+        encoder.take_output().unwrap();
+        encoder.emit_str("after taking the output").unwrap();
+        assert!(encoder.try_recover_at_checkpoint(checkpoint).is_err());
+    }
 }
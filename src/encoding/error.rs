@@ -26,9 +26,47 @@ pub enum Error {
     StructureError {
         source: state_tracker::StructureError,
     },
+
+    /// Error that occurs if a byte string and its length prefix are too long to be
+    /// addressed on the target, so that a decoder on the same platform couldn't read it.
+    #[snafu(display("byte string of {} bytes is too long for the target", len))]
+    LengthOverflow { len: u64 },
+
+    /// Error that occurs if the output doesn't fit into the buffer of a
+    /// [`SliceEncoder`](crate::encoding::SliceEncoder).
+    #[snafu(display("the output doesn't fit into the buffer of {} bytes", capacity))]
+    BufferFull { capacity: usize },
+
+    /// Error that occurs if the output sink fails.
+    #[cfg(feature = "std")]
+    #[snafu(display("failed to write the output: {}", source))]
+    Io { source: Arc<std::io::Error> },
+
+    /// Error that occurs if the output sink fails.
+    #[cfg(not(feature = "std"))]
+    #[snafu(display("failed to write the output"))]
+    Io,
 }
 
 impl Error {
+    /// A stable identifier of the kind of error, like `encode.io`, see
+    /// [`decoding::Error::code`](crate::decoding::Error::code).
+    pub fn code(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "std")]
+            Error::MalformedContent { .. } => "encode.malformed_content",
+            #[cfg(not(feature = "std"))]
+            Error::MalformedContent => "encode.malformed_content",
+            Error::StructureError { source } => source.code(),
+            Error::LengthOverflow { .. } => "encode.length_overflow",
+            Error::BufferFull { .. } => "encode.buffer_full",
+            #[cfg(feature = "std")]
+            Error::Io { .. } => "encode.io",
+            #[cfg(not(feature = "std"))]
+            Error::Io => "encode.io",
+        }
+    }
+
     /// Raised when there is a general error while deserializing a type.
     /// The message should not be capitalized and should not end with a period.
     ///
@@ -49,6 +87,15 @@ impl Error {
     }
 }
 
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io {
+            source: Arc::new(error),
+        }
+    }
+}
+
 impl From<state_tracker::StructureError> for Error {
     fn from(error: state_tracker::StructureError) -> Self {
         Error::StructureError { source: error }
@@ -1,10 +1,30 @@
 #[cfg(not(feature = "std"))]
-use core::fmt::Display;
+use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
+use core::fmt::{self, Display, Write};
 #[cfg(feature = "std")]
-use std::fmt::Display;
+use std::fmt::{self, Display, Write};
 
 /// A value that can be formatted as a decimal integer
-pub trait PrintableInteger: Display {}
+pub trait PrintableInteger: Display {
+    /// Append the decimal digits of the value to `output`
+    #[doc(hidden)]
+    fn write_digits(&self, output: &mut Vec<u8>) {
+        // Formatting straight into the output avoids a temporary string; appending to a
+        // vector can't fail
+        let _ = write!(DigitWriter(output), "{}", self);
+    }
+}
+
+/// Appends the formatted digits to a byte buffer
+struct DigitWriter<'a>(&'a mut Vec<u8>);
+
+impl Write for DigitWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
 
 macro_rules! impl_integer {
     ($($type:ty)*) => {$(
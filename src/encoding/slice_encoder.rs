@@ -0,0 +1,350 @@
+use core::fmt::{self, Write as _};
+
+use crate::{
+    encoding::{Error, PrintableInteger},
+    state_tracker::StructureError,
+};
+
+/// An open list or dict of a [`SliceEncoder`]
+#[derive(Clone, Copy, Debug)]
+struct Level {
+    dict: bool,
+    value_next: bool,
+    /// The position and length of the last key in the output
+    last_key: Option<(usize, usize)>,
+}
+
+/// An encoder writing to a fixed buffer, e.g. for a UDP packet, without allocating.
+///
+/// The interface is that of a [`StreamEncoder`](super::StreamEncoder), with up to
+/// `MAX_DEPTH` nested lists and dicts. Dict keys have to be emitted in sorted order, which
+/// is validated against the keys already written to the buffer. Output that doesn't fit
+/// into the buffer fails with [`Error::BufferFull`]; like any other error, this poisons the
+/// encoder. Nothing is allocated unless encoding fails.
+///
+/// ```
+/// # use bendy::encoding::{Error, SliceEncoder};
+/// #
+/// # fn main() -> Result<(), Error> {
+/// let mut packet = [0; 64];
+/// let mut encoder = SliceEncoder::<4>::new(&mut packet);
+/// encoder.begin_dict()?;
+/// encoder.emit_str("t")?;
+/// encoder.emit_str("aa")?;
+/// encoder.emit_str("y")?;
+/// encoder.emit_int(1)?;
+/// encoder.end()?;
+///
+/// let len = encoder.finish()?;
+/// assert_eq!(&packet[..len], b"d1:t2:aa1:yi1ee");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// This needs the `heapless` feature.
+#[derive(Debug)]
+pub struct SliceEncoder<'buf, const MAX_DEPTH: usize> {
+    output: &'buf mut [u8],
+    len: usize,
+    levels: [Level; MAX_DEPTH],
+    depth: usize,
+    error: Option<Error>,
+}
+
+impl<'buf, const MAX_DEPTH: usize> SliceEncoder<'buf, MAX_DEPTH> {
+    /// Create an encoder writing to the start of `output`
+    pub fn new(output: &'buf mut [u8]) -> Self {
+        SliceEncoder {
+            output,
+            len: 0,
+            levels: [Level {
+                dict: false,
+                value_next: false,
+                last_key: None,
+            }; MAX_DEPTH],
+            depth: 0,
+            error: None,
+        }
+    }
+
+    /// Emit an integer
+    pub fn emit_int<T: PrintableInteger>(&mut self, value: T) -> Result<(), Error> {
+        self.observe_value()?;
+        let start = self.len;
+        let mut writer = SliceWriter {
+            output: self.output,
+            len: start,
+        };
+        let written = write!(writer, "i{}e", value);
+        let end = writer.len;
+        if written.is_err() {
+            let error = self.full();
+            return self.latch(Err(error));
+        }
+        self.len = end;
+        Ok(())
+    }
+
+    /// Emit a string
+    pub fn emit_str(&mut self, value: &str) -> Result<(), Error> {
+        self.emit_bytes(value.as_bytes())
+    }
+
+    /// Emit a byte array, which may be a dict key
+    pub fn emit_bytes(&mut self, value: &[u8]) -> Result<(), Error> {
+        self.check_error()?;
+        let key = match self.current() {
+            Some(Level {
+                dict: true,
+                value_next: false,
+                last_key,
+            }) => Some(*last_key),
+            _ => None,
+        };
+        if let Some(Some((start, len))) = key {
+            if &self.output[start..start + len] >= value {
+                return self.latch(Err(Error::from(StructureError::UnsortedKeys)));
+            }
+        }
+        if key.is_none() {
+            self.observe_value()?;
+        }
+
+        let mut writer = SliceWriter {
+            output: self.output,
+            len: self.len,
+        };
+        let prefix = write!(writer, "{}:", value.len());
+        let start = writer.len;
+        if prefix.is_err() || self.output.len() - start < value.len() {
+            let error = self.full();
+            return self.latch(Err(error));
+        }
+        self.output[start..start + value.len()].copy_from_slice(value);
+        self.len = start + value.len();
+
+        if key.is_some() {
+            let level = &mut self.levels[self.depth - 1];
+            level.last_key = Some((start, value.len()));
+            level.value_next = true;
+        }
+        Ok(())
+    }
+
+    /// Start a list, which is closed with [`end`](Self::end)
+    pub fn begin_list(&mut self) -> Result<(), Error> {
+        self.begin(false)
+    }
+
+    /// Start a dict, which is closed with [`end`](Self::end)
+    pub fn begin_dict(&mut self) -> Result<(), Error> {
+        self.begin(true)
+    }
+
+    /// Close the innermost list or dict
+    pub fn end(&mut self) -> Result<(), Error> {
+        self.check_error()?;
+        match self.current() {
+            None => {
+                return self.latch(Err(Error::from(StructureError::invalid_state(
+                    "End not allowed at top level",
+                ))));
+            },
+            Some(Level {
+                value_next: true, ..
+            }) => {
+                return self.latch(Err(Error::from(StructureError::invalid_state(
+                    "Missing map value",
+                ))));
+            },
+            Some(_) => {},
+        }
+
+        self.push_byte(b'e')?;
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// The length of the output written so far
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether nothing was written yet
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return the length of the output, if all lists and dicts were closed.
+    pub fn finish(mut self) -> Result<usize, Error> {
+        self.check_error()?;
+        if self.depth > 0 {
+            return self.latch(Err(Error::from(StructureError::UnexpectedEof)));
+        }
+        Ok(self.len)
+    }
+
+    fn begin(&mut self, dict: bool) -> Result<(), Error> {
+        self.observe_value()?;
+        if self.depth == MAX_DEPTH {
+            return self.latch(Err(Error::from(StructureError::NestingTooDeep)));
+        }
+
+        self.push_byte(if dict { b'd' } else { b'l' })?;
+        self.levels[self.depth] = Level {
+            dict,
+            value_next: false,
+            last_key: None,
+        };
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn current(&self) -> Option<&Level> {
+        self.depth.checked_sub(1).map(|depth| &self.levels[depth])
+    }
+
+    /// Check that a value other than a dict key may be written next
+    fn observe_value(&mut self) -> Result<(), Error> {
+        self.check_error()?;
+        let level = match self.depth.checked_sub(1) {
+            Some(depth) if self.levels[depth].dict => &mut self.levels[depth],
+            _ => return Ok(()),
+        };
+        if !level.value_next {
+            return self.latch(Err(Error::from(StructureError::invalid_state(
+                "Map keys must be strings",
+            ))));
+        }
+        level.value_next = false;
+        Ok(())
+    }
+
+    fn push_byte(&mut self, byte: u8) -> Result<(), Error> {
+        match self.output.get_mut(self.len) {
+            Some(slot) => {
+                *slot = byte;
+                self.len += 1;
+                Ok(())
+            },
+            None => {
+                let error = self.full();
+                self.latch(Err(error))
+            },
+        }
+    }
+
+    fn full(&self) -> Error {
+        Error::BufferFull {
+            capacity: self.output.len(),
+        }
+    }
+
+    fn check_error(&self) -> Result<(), Error> {
+        match &self.error {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+
+    fn latch<T>(&mut self, result: Result<T, Error>) -> Result<T, Error> {
+        if let Err(error) = &result {
+            self.error = Some(error.clone());
+        }
+        result
+    }
+}
+
+/// Formats into a byte slice, failing if it is full
+struct SliceWriter<'a> {
+    output: &'a mut [u8],
+    len: usize,
+}
+
+impl fmt::Write for SliceWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.output.len() {
+            return Err(fmt::Error);
+        }
+        self.output[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nested_values_should_be_written_to_the_slice() {
+        let mut buffer = [0; 32];
+        let mut encoder = SliceEncoder::<2>::new(&mut buffer);
+        encoder.begin_list().unwrap();
+        encoder.emit_int(-12).unwrap();
+        encoder.begin_dict().unwrap();
+        encoder.emit_str("a").unwrap();
+        encoder.emit_bytes(b"xyz").unwrap();
+        encoder.emit_str("b").unwrap();
+        encoder.emit_int(0u8).unwrap();
+        encoder.end().unwrap();
+        encoder.end().unwrap();
+        encoder.emit_str("").unwrap();
+        assert_eq!(encoder.len(), 25);
+
+        let len = encoder.finish().unwrap();
+        assert_eq!(&buffer[..len], b"li-12ed1:a3:xyz1:bi0eee0:");
+    }
+
+    #[test]
+    fn overflowing_the_slice_should_poison_the_encoder() {
+        let mut buffer = [0; 8];
+        let mut encoder = SliceEncoder::<1>::new(&mut buffer);
+        encoder.begin_list().unwrap();
+        encoder.emit_str("spam").unwrap();
+        let error = encoder.emit_str("x").unwrap_err();
+        assert_eq!(error.code(), "encode.buffer_full");
+        assert!(matches!(error, Error::BufferFull { capacity: 8 }));
+        assert!(encoder.end().is_err());
+
+        let mut buffer = [0; 4];
+        let mut encoder = SliceEncoder::<1>::new(&mut buffer);
+        assert!(encoder.emit_int(12345).is_err());
+        assert!(encoder.finish().is_err());
+    }
+
+    #[test]
+    fn structure_should_be_validated() {
+        let mut buffer = [0; 32];
+
+        let mut encoder = SliceEncoder::<1>::new(&mut buffer);
+        encoder.begin_dict().unwrap();
+        encoder.emit_str("b").unwrap();
+        encoder.emit_int(1).unwrap();
+        let error = encoder.emit_str("a").unwrap_err();
+        assert_eq!(error.code(), "structure.unsorted_keys");
+
+        let mut encoder = SliceEncoder::<1>::new(&mut buffer);
+        encoder.begin_dict().unwrap();
+        assert!(encoder.emit_int(1).is_err());
+
+        let mut encoder = SliceEncoder::<1>::new(&mut buffer);
+        encoder.begin_dict().unwrap();
+        encoder.emit_str("a").unwrap();
+        assert!(encoder.end().is_err());
+
+        let mut encoder = SliceEncoder::<1>::new(&mut buffer);
+        encoder.begin_list().unwrap();
+        let error = encoder.begin_list().unwrap_err();
+        assert_eq!(error.code(), "structure.nesting_too_deep");
+
+        let mut encoder = SliceEncoder::<1>::new(&mut buffer);
+        assert!(encoder.end().is_err());
+
+        let mut encoder = SliceEncoder::<1>::new(&mut buffer);
+        encoder.begin_list().unwrap();
+        let error = encoder.finish().unwrap_err();
+        assert_eq!(error.code(), "structure.unexpected_eof");
+    }
+}
@@ -0,0 +1,390 @@
+#[cfg(not(feature = "std"))]
+use alloc::format;
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use crate::{
+    encoding::{
+        encoder::{check_string_len, ChunkLength},
+        Encoder, Error, PrintableInteger, ToBencode, Write,
+    },
+    state_tracker::{OwnedKey, StateTracker, Token},
+};
+
+/// An encoder writing tokens directly to a [`Write`] instead of buffering the whole
+/// output like [`Encoder`].
+///
+/// Lists and dicts are opened with [`begin_list`](Self::begin_list) and
+/// [`begin_dict`](Self::begin_dict) and closed with [`end`](Self::end). As nothing is
+/// buffered, dict keys can't be sorted and have to be emitted in sorted order; this is
+/// validated like in the [`Encoder`]. Large strings can be copied from a reader with
+/// [`emit_bytes_from`](Self::emit_bytes_from), so e.g. the `pieces` of a torrent never
+/// have to be held in memory:
+///
+/// ```
+/// # use bendy::encoding::{Error, StreamEncoder};
+/// #
+/// # fn main() -> Result<(), Error> {
+/// # let pieces_file = &[0u8; 40][..];
+/// let mut encoder = StreamEncoder::new(Vec::new());
+/// encoder.begin_dict()?;
+/// encoder.emit_str("name")?;
+/// encoder.emit_str("example")?;
+/// encoder.emit_str("pieces")?;
+/// # #[cfg(feature = "std")]
+/// encoder.emit_bytes_from(40, pieces_file)?;
+/// # #[cfg(not(feature = "std"))]
+/// # encoder.emit_bytes_chunked(40, Some(pieces_file))?;
+/// encoder.end()?;
+///
+/// let output = encoder.finish()?;
+/// assert!(output.starts_with(b"d4:name7:example6:pieces40:"));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Writes aren't buffered either, so a [`BufWriter`](std::io::BufWriter) should be used
+/// for writers like files or sockets. Without the `std` feature, any [`Write`]
+/// implementation can be used, except for [`emit_bytes_from`](Self::emit_bytes_from).
+#[derive(Debug)]
+pub struct StreamEncoder<W> {
+    state: StateTracker<OwnedKey, Error>,
+    writer: W,
+}
+
+impl<W: Write> StreamEncoder<W> {
+    /// Create an encoder writing to `writer`
+    pub fn new(writer: W) -> Self {
+        StreamEncoder {
+            state: StateTracker::new(),
+            writer,
+        }
+    }
+
+    /// Set the max depth of the encoded object
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.state.set_max_depth(max_depth);
+        self
+    }
+
+    /// Emit an arbitrary encodable object.
+    ///
+    /// The object is encoded into a temporary buffer first, so large objects should be
+    /// emitted token by token instead.
+    pub fn emit<E: ToBencode>(&mut self, value: E) -> Result<(), Error> {
+        self.state.check_error()?;
+        let mut encoder = Encoder::new().with_max_depth(self.state.remaining_depth());
+        let encoded = encoder.emit(value).and_then(|()| encoder.get_output());
+        let encoded = self.state.latch_err(encoded)?;
+
+        match encoded.iter().position(|&byte| byte == b':') {
+            Some(colon) if encoded[0].is_ascii_digit() => {
+                self.state
+                    .observe_token(&Token::String(&encoded[colon + 1..]))?;
+            },
+            // Lists, dicts and integers are all observed as a single value that can't be a
+            // key, the nesting inside of lists and dicts was already validated
+            _ => self.state.observe_token(&Token::Num(""))?,
+        }
+        self.write(&encoded)
+    }
+
+    /// Emit an integer
+    pub fn emit_int<T: PrintableInteger>(&mut self, value: T) -> Result<(), Error> {
+        self.state.check_error()?;
+        self.state.observe_token(&Token::Num(""))?;
+        self.write(format!("i{}e", value).as_bytes())
+    }
+
+    /// Emit a string
+    pub fn emit_str(&mut self, value: &str) -> Result<(), Error> {
+        self.emit_bytes(value.as_bytes())
+    }
+
+    /// Emit a byte array
+    pub fn emit_bytes(&mut self, value: &[u8]) -> Result<(), Error> {
+        self.state.check_error()?;
+        self.state.observe_token(&Token::String(value))?;
+        self.write(format!("{}:", value.len()).as_bytes())?;
+        self.write(value)
+    }
+
+    /// Emit a byte string of `len` bytes given as a sequence of chunks, without holding
+    /// all of it in memory.
+    ///
+    /// Like with [`emit_bytes_from`](Self::emit_bytes_from), the string can't be used as
+    /// a dict key. Fails if the chunks don't add up to exactly `len` bytes; no chunk that
+    /// would exceed `len` is written.
+    pub fn emit_bytes_chunked<'a>(
+        &mut self,
+        len: u64,
+        chunks: impl IntoIterator<Item = &'a [u8]>,
+    ) -> Result<(), Error> {
+        self.state.check_error()?;
+        // Observed as an integer, which the state tracker rejects in place of a key
+        self.state.observe_token(&Token::Num(""))?;
+        self.state.latch_err(check_string_len(0, len))?;
+        self.write(format!("{}:", len).as_bytes())?;
+
+        let mut written = 0;
+        for chunk in chunks {
+            written += chunk.len() as u64;
+            if written > len {
+                return self
+                    .state
+                    .latch_err(Err(Error::malformed_content(ChunkLength::new(
+                        len, written,
+                    ))));
+            }
+            self.write(chunk)?;
+        }
+
+        if written != len {
+            return self
+                .state
+                .latch_err(Err(Error::malformed_content(ChunkLength::new(
+                    len, written,
+                ))));
+        }
+        Ok(())
+    }
+
+    /// Start a list, which has to be closed with [`end`](Self::end)
+    pub fn begin_list(&mut self) -> Result<(), Error> {
+        self.emit_token(Token::List, b"l")
+    }
+
+    /// Start a dict, which has to be closed with [`end`](Self::end). Keys have to be
+    /// emitted in sorted order.
+    pub fn begin_dict(&mut self) -> Result<(), Error> {
+        self.emit_token(Token::Dict, b"d")
+    }
+
+    /// End the innermost list or dict
+    pub fn end(&mut self) -> Result<(), Error> {
+        self.emit_token(Token::End, b"e")
+    }
+
+    /// Flush and return the writer, if all objects written are complete
+    pub fn finish(mut self) -> Result<W, Error> {
+        self.state.observe_eof()?;
+        let flushed = self.writer.flush();
+        self.state.latch_err(flushed)?;
+        Ok(self.writer)
+    }
+
+    fn emit_token(&mut self, token: Token, encoded: &[u8]) -> Result<(), Error> {
+        self.state.check_error()?;
+        self.state.observe_token(&token)?;
+        self.write(encoded)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let written = self.writer.write_all(bytes);
+        self.state.latch_err(written)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> StreamEncoder<W> {
+    /// Emit a byte string of `len` bytes read from `reader`, without holding it in memory.
+    ///
+    /// As its content isn't known in advance, the string can't be used as a dict key.
+    /// Fails if `reader` ends before `len` bytes were read, or with
+    /// [`Error::LengthOverflow`] if `len` is larger than a byte string a decoder on this
+    /// target can address.
+    pub fn emit_bytes_from(&mut self, len: u64, reader: impl Read) -> Result<(), Error> {
+        self.state.check_error()?;
+        // Observed as an integer, which the state tracker rejects in place of a key
+        self.state.observe_token(&Token::Num(""))?;
+        self.state.latch_err(check_string_len(0, len))?;
+        self.write(format!("{}:", len).as_bytes())?;
+
+        let copied = io::copy(&mut reader.take(len), &mut self.writer);
+        match copied {
+            Ok(copied) if copied == len => Ok(()),
+            Ok(_) => self.state.latch_err(Err(Error::from(io::Error::from(
+                io::ErrorKind::UnexpectedEof,
+            )))),
+            Err(error) => self.state.latch_err(Err(Error::from(error))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(not(feature = "std"))]
+    use alloc::{vec, vec::Vec};
+
+    /// Keeps the first bytes written and counts the rest
+    #[cfg(all(feature = "std", target_pointer_width = "64"))]
+    #[derive(Default)]
+    struct Head(Vec<u8>, u64);
+
+    #[cfg(all(feature = "std", target_pointer_width = "64"))]
+    impl io::Write for Head {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            let keep = bytes.len().min(16 - self.0.len().min(16));
+            self.0.extend_from_slice(&bytes[..keep]);
+            self.1 += bytes.len() as u64;
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn stream_encoder_should_match_encoder() {
+        let mut encoder = StreamEncoder::new(Vec::new());
+        encoder.begin_dict().unwrap();
+        encoder.emit_str("a").unwrap();
+        encoder.emit(vec![1, 2]).unwrap();
+        encoder.emit("b").unwrap();
+        encoder.begin_list().unwrap();
+        encoder.emit_int(-3).unwrap();
+        encoder.emit_bytes_from(3, &b"xyz."[..]).unwrap();
+        encoder.end().unwrap();
+        encoder.end().unwrap();
+
+        assert_eq!(encoder.finish().unwrap(), b"d1:ali1ei2ee1:bli-3e3:xyzee");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn stream_encoder_should_validate_the_structure() {
+        let mut encoder = StreamEncoder::new(Vec::new());
+        encoder.begin_dict().unwrap();
+        encoder.emit_str("b").unwrap();
+        encoder.emit_int(1).unwrap();
+        assert!(encoder.emit_str("a").is_err());
+        assert!(encoder.end().is_err());
+
+        let mut encoder = StreamEncoder::new(Vec::new());
+        encoder.begin_dict().unwrap();
+        assert!(encoder.emit_bytes_from(1, &b"a"[..]).is_err());
+
+        let mut encoder = StreamEncoder::new(Vec::new());
+        encoder.begin_list().unwrap();
+        assert!(encoder.finish().is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", target_pointer_width = "64"))]
+    fn strings_of_more_than_4_gb_should_be_streamed() {
+        let len = (1 << 32) + 1;
+        let mut encoder = StreamEncoder::new(Head::default());
+        encoder.emit_bytes_from(len, io::repeat(b'x')).unwrap();
+
+        let head = encoder.finish().unwrap();
+        assert_eq!(head.0, b"4294967297:xxxxx");
+        assert_eq!(head.1, len + 11);
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", target_pointer_width = "64"))]
+    fn chunks_of_more_than_4_gb_should_be_streamed() {
+        let chunk = vec![b'x'; 1 << 20];
+        let len: u64 = 4097 << 20;
+
+        let mut encoder = StreamEncoder::new(Head::default());
+        encoder
+            .emit_bytes_chunked(len, (0..4097).map(|_| &chunk[..]))
+            .unwrap();
+
+        let head = encoder.finish().unwrap();
+        assert_eq!(head.0, b"4296015872:xxxxx");
+        assert_eq!(head.1, len + 11);
+    }
+
+    #[test]
+    fn chunks_should_add_up_to_the_declared_length() {
+        let mut encoder = StreamEncoder::new(Vec::new());
+        encoder.begin_list().unwrap();
+        encoder
+            .emit_bytes_chunked(4, vec![&b"ab"[..], b"cd"])
+            .unwrap();
+        encoder.end().unwrap();
+        assert_eq!(encoder.finish().unwrap(), b"l4:abcde");
+
+        let mut encoder = StreamEncoder::new(Vec::new());
+        assert!(encoder
+            .emit_bytes_chunked(3, vec![&b"ab"[..], b"cd"])
+            .is_err());
+        assert!(encoder.finish().is_err());
+
+        let mut encoder = StreamEncoder::new(Vec::new());
+        assert!(encoder.emit_bytes_chunked(5, vec![&b"ab"[..]]).is_err());
+
+        let mut encoder = StreamEncoder::new(Vec::new());
+        encoder.begin_dict().unwrap();
+        assert!(encoder.emit_bytes_chunked(1, vec![&b"a"[..]]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn overlong_strings_should_fail() {
+        let mut encoder = StreamEncoder::new(Vec::new());
+        let error = encoder.emit_bytes_from(u64::MAX, io::empty()).unwrap_err();
+        assert!(matches!(error, Error::LengthOverflow { len: u64::MAX }));
+        assert!(encoder.finish().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn short_readers_should_fail() {
+        let mut encoder = StreamEncoder::new(Vec::new());
+        assert!(encoder.emit_bytes_from(5, &b"abc"[..]).is_err());
+        assert!(encoder.emit_int(1).is_err());
+    }
+
+    /// A fixed buffer, like the packet buffer of a network stack without `std`
+    struct Packet {
+        buffer: [u8; 16],
+        len: usize,
+    }
+
+    impl Write for Packet {
+        fn write_all(&mut self, bytes: &[u8]) -> Result<(), Error> {
+            let end = self.len + bytes.len();
+            if end > self.buffer.len() {
+                #[cfg(feature = "std")]
+                return Err(io::Error::from(io::ErrorKind::WriteZero).into());
+                #[cfg(not(feature = "std"))]
+                return Err(Error::Io);
+            }
+            self.buffer[self.len..end].copy_from_slice(bytes);
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn custom_writers_should_be_supported() {
+        let packet = Packet {
+            buffer: [0; 16],
+            len: 0,
+        };
+        let mut encoder = StreamEncoder::new(packet);
+        encoder.begin_dict().unwrap();
+        encoder.emit_str("q").unwrap();
+        encoder.emit_str("ping").unwrap();
+        encoder.end().unwrap();
+        let packet = encoder.finish().unwrap();
+        assert_eq!(&packet.buffer[..packet.len], b"d1:q4:pinge");
+
+        let mut encoder = StreamEncoder::new(packet);
+        let error = encoder.emit_bytes(&[0; 16]).unwrap_err();
+        assert_eq!(error.code(), "encode.io");
+        assert!(encoder.emit_int(1).is_err());
+
+        let mut encoder = StreamEncoder::new(vec![0]);
+        encoder.emit_int(1).unwrap();
+        assert_eq!(encoder.finish().unwrap(), [0, b'i', b'1', b'e']);
+    }
+}
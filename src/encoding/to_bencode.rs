@@ -15,7 +15,15 @@ use std::{
     sync::Arc,
 };
 
-use crate::encoding::{Encoder, Error, SingleItemEncoder};
+use core::{
+    cell::RefCell,
+    fmt::{self, Display, Formatter},
+};
+
+use crate::{
+    encoding::{Encoder, Error, SingleItemEncoder},
+    hex::Hex,
+};
 
 /// An object that can be encoded into a single bencode object
 pub trait ToBencode {
@@ -29,7 +37,7 @@ pub trait ToBencode {
     /// Encode this object to a byte string
     fn to_bencode(&self) -> Result<Vec<u8>, Error> {
         let mut encoder = Encoder::new().with_max_depth(Self::MAX_DEPTH);
-        encoder.emit_with(|e| self.encode(e).map_err(Error::into))?;
+        encoder.emit_with(|e| self.encode(e))?;
 
         let bytes = encoder.get_output()?;
         Ok(bytes)
@@ -40,6 +48,29 @@ pub trait ToBencode {
 #[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub struct AsString<I>(pub I);
 
+/// Wrapper to encode binary data as a string of lowercase hex digits, as some trackers
+/// do for the `info_hash` they echo back.
+///
+/// Decoding accepts both upper and lower case digits into any fixed-size buffer like
+/// `[u8; 20]`, failing unless the string holds exactly as many bytes as the buffer.
+///
+/// ```
+/// use bendy::{
+///     decoding::FromBencode,
+///     encoding::{AsHex, ToBencode},
+/// };
+///
+/// let info_hash = [0xab; 20];
+/// let encoded = AsHex(info_hash).to_bencode().unwrap();
+/// assert_eq!(encoded, b"40:abababababababababababababababababababab");
+///
+/// let decoded = AsHex::<[u8; 20]>::from_bencode(&encoded).unwrap();
+/// assert_eq!(decoded.0, info_hash);
+/// assert!(AsHex::<[u8; 20]>::from_bencode(b"2:ab").is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct AsHex<I>(pub I);
+
 // Forwarding impls
 impl<'a, E: 'a + ToBencode + Sized> ToBencode for &'a E {
     const MAX_DEPTH: usize = E::MAX_DEPTH;
@@ -54,7 +85,7 @@ impl<E: ToBencode> ToBencode for Box<E> {
     const MAX_DEPTH: usize = E::MAX_DEPTH;
 
     fn encode(&self, encoder: SingleItemEncoder) -> Result<(), Error> {
-        E::encode(&*self, encoder)
+        E::encode(self, encoder)
     }
 }
 
@@ -62,7 +93,7 @@ impl<E: ToBencode> ToBencode for Rc<E> {
     const MAX_DEPTH: usize = E::MAX_DEPTH;
 
     fn encode(&self, encoder: SingleItemEncoder) -> Result<(), Error> {
-        E::encode(&*self, encoder)
+        E::encode(self, encoder)
     }
 }
 
@@ -70,16 +101,16 @@ impl<E: ToBencode> ToBencode for Arc<E> {
     const MAX_DEPTH: usize = E::MAX_DEPTH;
 
     fn encode(&self, encoder: SingleItemEncoder) -> Result<(), Error> {
-        E::encode(&*self, encoder)
+        E::encode(self, encoder)
     }
 }
 
 // Base type impls
-impl<'a> ToBencode for &'a str {
+impl ToBencode for &str {
     const MAX_DEPTH: usize = 0;
 
     fn encode(&self, encoder: SingleItemEncoder) -> Result<(), Error> {
-        encoder.emit_str(self).map_err(Error::from)
+        encoder.emit_str(self)
     }
 }
 
@@ -87,7 +118,7 @@ impl ToBencode for String {
     const MAX_DEPTH: usize = 0;
 
     fn encode(&self, encoder: SingleItemEncoder) -> Result<(), Error> {
-        encoder.emit_str(self).map_err(Error::from)
+        encoder.emit_str(self)
     }
 }
 
@@ -114,7 +145,7 @@ macro_rules! impl_encodable_iterable {
             const MAX_DEPTH: usize = ContentT::MAX_DEPTH + 1;
 
             fn encode(&self, encoder: SingleItemEncoder) -> Result<(), Error> {
-                encoder.emit_list(|e| {
+                encoder.emit_list_with_len_hint(self.len(), |e| {
                     for item in self {
                         e.emit(item)?;
                     }
@@ -129,14 +160,14 @@ macro_rules! impl_encodable_iterable {
 
 impl_encodable_iterable!(Vec VecDeque LinkedList);
 
-impl<'a, ContentT> ToBencode for &'a [ContentT]
+impl<ContentT> ToBencode for &[ContentT]
 where
     ContentT: ToBencode,
 {
     const MAX_DEPTH: usize = ContentT::MAX_DEPTH + 1;
 
     fn encode(&self, encoder: SingleItemEncoder) -> Result<(), Error> {
-        encoder.emit_list(|e| {
+        encoder.emit_list_with_len_hint(self.len(), |e| {
             for item in *self {
                 e.emit(item)?;
             }
@@ -147,21 +178,73 @@ where
     }
 }
 
-impl<K: AsRef<[u8]>, V: ToBencode> ToBencode for BTreeMap<K, V> {
-    const MAX_DEPTH: usize = V::MAX_DEPTH + 1;
+/// Options are encoded like serde does: `None` as an empty list and `Some(value)` as a
+/// list holding just the value.
+impl<ContentT: ToBencode> ToBencode for Option<ContentT> {
+    const MAX_DEPTH: usize = ContentT::MAX_DEPTH + 1;
 
     fn encode(&self, encoder: SingleItemEncoder) -> Result<(), Error> {
-        encoder.emit_dict(|mut e| {
-            for (k, v) in self {
-                e.emit_pair(k.as_ref(), v)?;
+        encoder.emit_list(|e| match self {
+            Some(value) => e.emit(value),
+            None => Ok(()),
+        })
+    }
+}
+
+/// The largest of the given depths
+pub(crate) const fn max_depth(depths: &[usize]) -> usize {
+    let mut max = 0;
+    let mut i = 0;
+    while i < depths.len() {
+        if depths[i] > max {
+            max = depths[i];
+        }
+        i += 1;
+    }
+    max
+}
+
+macro_rules! impl_encodable_tuple {
+    ($(($($name:ident)+))*) => {$(
+        /// Tuples are encoded as lists of their elements.
+        impl<$($name: ToBencode),+> ToBencode for ($($name,)+) {
+            const MAX_DEPTH: usize = max_depth(&[$($name::MAX_DEPTH),+]) + 1;
+
+            #[allow(non_snake_case)]
+            fn encode(&self, encoder: SingleItemEncoder) -> Result<(), Error> {
+                let ($($name,)+) = self;
+                encoder.emit_list(|e| {
+                    $(e.emit($name)?;)+
+                    Ok(())
+                })
             }
-            Ok(())
-        })?;
+        }
+    )*}
+}
 
-        Ok(())
+impl_encodable_tuple!(
+    (A)
+    (A B)
+    (A B C)
+    (A B C D)
+    (A B C D E)
+    (A B C D E F)
+    (A B C D E F G)
+    (A B C D E F G H)
+);
+
+/// Maps are emitted in their own order, which already is the order required by
+/// bencode, so no buffering or sorting is needed.
+impl<K: AsRef<[u8]>, V: ToBencode> ToBencode for BTreeMap<K, V> {
+    const MAX_DEPTH: usize = V::MAX_DEPTH + 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), Error> {
+        encoder.emit_sorted_pairs(self)
     }
 }
 
+/// Maps have to be sorted by key first, which requires a temporary buffer holding a
+/// reference to each pair. Use a `BTreeMap` to avoid this.
 #[cfg(feature = "std")]
 impl<K, V, S> ToBencode for HashMap<K, V, S>
 where
@@ -172,19 +255,13 @@ where
     const MAX_DEPTH: usize = V::MAX_DEPTH + 1;
 
     fn encode(&self, encoder: SingleItemEncoder) -> Result<(), Error> {
-        encoder.emit_dict(|mut e| {
-            let mut pairs = self
-                .iter()
-                .map(|(k, v)| (k.as_ref(), v))
-                .collect::<Vec<_>>();
-            pairs.sort_by_key(|&(k, _)| k);
-            for (k, v) in pairs {
-                e.emit_pair(k, v)?;
-            }
-            Ok(())
-        })?;
+        let mut pairs = self
+            .iter()
+            .map(|(k, v)| (k.as_ref(), v))
+            .collect::<Vec<_>>();
+        pairs.sort_by_key(|&(k, _)| k);
 
-        Ok(())
+        encoder.emit_sorted_pairs(pairs)
     }
 }
 
@@ -200,6 +277,17 @@ where
     }
 }
 
+impl<I> ToBencode for AsHex<I>
+where
+    I: AsRef<[u8]>,
+{
+    const MAX_DEPTH: usize = 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), Error> {
+        encoder.emit_display(&Hex(self.0.as_ref()))
+    }
+}
+
 impl<I> AsRef<[u8]> for AsString<I>
 where
     I: AsRef<[u8]>,
@@ -218,7 +306,67 @@ where
     }
 }
 
+/// Wrapper to encode the items of an iterator as a list, without collecting them into an
+/// intermediate container first.
+///
+/// As iterators can only be traversed once, the wrapper can only be encoded once as well.
+/// Any further attempt fails with an error.
+///
+/// ```
+/// use bendy::encoding::{EncodeIter, ToBencode};
+///
+/// let squares = EncodeIter::new((1..4).map(|i| i * i));
+/// assert_eq!(squares.to_bencode().unwrap(), b"li1ei4ei9ee");
+/// assert!(squares.to_bencode().is_err());
+/// ```
+#[derive(Debug)]
+pub struct EncodeIter<I>(RefCell<Option<I>>);
+
+impl<I: Iterator> EncodeIter<I> {
+    /// Wrap the given iterator.
+    pub fn new(items: impl IntoIterator<IntoIter = I>) -> Self {
+        EncodeIter(RefCell::new(Some(items.into_iter())))
+    }
+}
+
+impl<I> ToBencode for EncodeIter<I>
+where
+    I: Iterator,
+    I::Item: ToBencode,
+{
+    const MAX_DEPTH: usize = I::Item::MAX_DEPTH + 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), Error> {
+        let items = self
+            .0
+            .borrow_mut()
+            .take()
+            .ok_or_else(|| Error::malformed_content(IteratorConsumed))?;
+
+        encoder.emit_list(|e| {
+            for item in items {
+                e.emit(item)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// The iterator of an [`EncodeIter`] has already been consumed
+#[derive(Debug)]
+struct IteratorConsumed;
+
+impl Display for IteratorConsumed {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("the iterator has already been encoded")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for IteratorConsumed {}
+
 #[cfg(test)]
+#[allow(clippy::needless_borrows_for_generic_args)]
 mod test {
 
     #[cfg(not(feature = "std"))]
@@ -262,4 +410,39 @@ mod test {
             &b"d3:bari5e3:bazl3:foo3:bare3:qux3:quxe"[..]
         );
     }
+
+    #[test]
+    fn encode_iter_should_encode_nested_iterators() {
+        let rows = EncodeIter::new((0..2).map(|row| EncodeIter::new(row..row + 2)));
+        assert_eq!(rows.to_bencode().unwrap(), b"lli0ei1eeli1ei2eee");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn maps_should_encode_identically() {
+        let btree: BTreeMap<_, _> = vec![("b", 2), ("a", 1), ("c", 3)].into_iter().collect();
+        let hash: HashMap<_, _> = btree.clone().into_iter().collect();
+
+        assert_eq!(btree.to_bencode().unwrap(), b"d1:ai1e1:bi2e1:ci3ee");
+        assert_eq!(hash.to_bencode().unwrap(), btree.to_bencode().unwrap());
+    }
+
+    #[test]
+    fn options_and_tuples_should_encode_as_lists() {
+        assert_eq!(None::<u8>.to_bencode().unwrap(), b"le");
+        assert_eq!(Some("a").to_bencode().unwrap(), b"l1:ae");
+        assert_eq!(
+            (1, "two", vec![(3,)]).to_bencode().unwrap(),
+            b"li1e3:twolli3eeee"
+        );
+        assert_eq!(<(&str, Vec<(&str,)>)>::MAX_DEPTH, 3);
+    }
+
+    #[test]
+    fn unsorted_pairs_should_fail() {
+        let mut encoder = Encoder::new();
+        assert!(encoder
+            .emit_with(|e| e.emit_sorted_pairs(vec![("b", 1), ("a", 2)]))
+            .is_err());
+    }
 }
@@ -0,0 +1,244 @@
+use std::io::{self, IoSlice};
+
+use crate::{
+    encoding::{encoder::check_string_len, Error, PrintableInteger},
+    state_tracker::{OwnedKey, StateTracker, Token},
+};
+
+/// An encoder producing its output as a sequence of [`IoSlice`]s, so that large byte
+/// strings can be written with [`write_vectored`](io::Write::write_vectored) without
+/// copying them.
+///
+/// Everything but the strings emitted with
+/// [`emit_bytes_borrowed`](Self::emit_bytes_borrowed) is buffered, so the output
+/// alternates between the buffered tokens and the borrowed strings. The interface is that
+/// of a [`StreamEncoder`](super::StreamEncoder), so dict keys have to be emitted in sorted
+/// order:
+///
+/// ```
+/// # use bendy::encoding::{Error, VectoredEncoder};
+/// #
+/// # fn main() -> Result<(), Error> {
+/// let pieces = vec![0; 20 * 1000];
+///
+/// let mut encoder = VectoredEncoder::new();
+/// encoder.begin_dict()?;
+/// encoder.emit_str("name")?;
+/// encoder.emit_str("example")?;
+/// encoder.emit_str("pieces")?;
+/// encoder.emit_bytes_borrowed(&pieces)?;
+/// encoder.end()?;
+///
+/// let slices = encoder.io_slices()?;
+/// assert_eq!(slices.len(), 3);
+/// assert_eq!(&*slices[0], b"d4:name7:example6:pieces20000:");
+///
+/// let mut output = Vec::new();
+/// encoder.write_to(&mut output)?;
+/// assert_eq!(output.len(), encoder.len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct VectoredEncoder<'data> {
+    state: StateTracker<OwnedKey, Error>,
+    buffer: Vec<u8>,
+    /// The borrowed strings, each with the length of the buffer when it was emitted
+    borrowed: Vec<(usize, &'data [u8])>,
+}
+
+impl<'data> VectoredEncoder<'data> {
+    /// Create a new encoder
+    pub fn new() -> Self {
+        <Self as Default>::default()
+    }
+
+    /// Set the max depth of the encoded object
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.state.set_max_depth(max_depth);
+        self
+    }
+
+    /// Emit an integer
+    pub fn emit_int<T: PrintableInteger>(&mut self, value: T) -> Result<(), Error> {
+        self.state.check_error()?;
+        self.state.observe_token(&Token::Num(""))?;
+        self.buffer.push(b'i');
+        value.write_digits(&mut self.buffer);
+        self.buffer.push(b'e');
+        Ok(())
+    }
+
+    /// Emit a string
+    pub fn emit_str(&mut self, value: &str) -> Result<(), Error> {
+        self.emit_bytes(value.as_bytes())
+    }
+
+    /// Emit a byte array, copying it into the buffer
+    pub fn emit_bytes(&mut self, value: &[u8]) -> Result<(), Error> {
+        self.emit_prefix(value)?;
+        self.buffer.extend_from_slice(value);
+        Ok(())
+    }
+
+    /// Emit a byte array without copying it, as a slice of its own in the output
+    pub fn emit_bytes_borrowed(&mut self, value: &'data [u8]) -> Result<(), Error> {
+        self.emit_prefix(value)?;
+        self.borrowed.push((self.buffer.len(), value));
+        Ok(())
+    }
+
+    /// Start a list, which has to be closed with [`end`](Self::end)
+    pub fn begin_list(&mut self) -> Result<(), Error> {
+        self.emit_token(Token::List, b'l')
+    }
+
+    /// Start a dict, which has to be closed with [`end`](Self::end). Keys have to be
+    /// emitted in sorted order.
+    pub fn begin_dict(&mut self) -> Result<(), Error> {
+        self.emit_token(Token::Dict, b'd')
+    }
+
+    /// End the innermost list or dict
+    pub fn end(&mut self) -> Result<(), Error> {
+        self.emit_token(Token::End, b'e')
+    }
+
+    /// The total length of the output
+    pub fn len(&self) -> usize {
+        let borrowed: usize = self.borrowed.iter().map(|(_, value)| value.len()).sum();
+        self.buffer.len() + borrowed
+    }
+
+    /// Whether nothing was emitted yet
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the output as a sequence of slices, if all objects written are complete.
+    /// Empty slices are left out.
+    pub fn io_slices(&mut self) -> Result<Vec<IoSlice<'_>>, Error> {
+        self.state.observe_eof()?;
+        Ok(self.segments().into_iter().map(IoSlice::new).collect())
+    }
+
+    /// Write the output to `writer` with vectored writes, if all objects written are
+    /// complete. Failures of the writer are returned as [`Error::Io`].
+    pub fn write_to(&mut self, writer: &mut impl io::Write) -> Result<(), Error> {
+        self.state.observe_eof()?;
+        let mut segments = self.segments();
+        let mut first = 0;
+        while first < segments.len() {
+            let slices = segments[first..]
+                .iter()
+                .map(|segment| IoSlice::new(segment))
+                .collect::<Vec<_>>();
+            let mut written = match writer.write_vectored(&slices) {
+                Ok(0) => return Err(Error::from(io::Error::from(io::ErrorKind::WriteZero))),
+                Ok(written) => written,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(Error::from(error)),
+            };
+
+            // Skip the segments that were written completely
+            while first < segments.len() && written >= segments[first].len() {
+                written -= segments[first].len();
+                first += 1;
+            }
+            if written > 0 {
+                segments[first] = &segments[first][written..];
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The buffered and borrowed parts of the output in order, without empty parts
+    fn segments(&self) -> Vec<&[u8]> {
+        let mut segments = Vec::with_capacity(2 * self.borrowed.len() + 1);
+        let mut start = 0;
+        for &(end, value) in &self.borrowed {
+            segments.push(&self.buffer[start..end]);
+            segments.push(value);
+            start = end;
+        }
+        segments.push(&self.buffer[start..]);
+        segments.retain(|segment| !segment.is_empty());
+        segments
+    }
+
+    fn emit_prefix(&mut self, value: &[u8]) -> Result<(), Error> {
+        self.state.check_error()?;
+        self.state.observe_token(&Token::String(value))?;
+        self.state
+            .latch_err(check_string_len(self.len(), value.len() as u64))?;
+        value.len().write_digits(&mut self.buffer);
+        self.buffer.push(b':');
+        Ok(())
+    }
+
+    fn emit_token(&mut self, token: Token, encoded: u8) -> Result<(), Error> {
+        self.state.check_error()?;
+        self.state.observe_token(&token)?;
+        self.buffer.push(encoded);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Accepts at most 3 bytes per write
+    struct Trickle(Vec<u8>);
+
+    impl io::Write for Trickle {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            let len = bytes.len().min(3);
+            self.0.extend_from_slice(&bytes[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn borrowed_strings_should_be_separate_slices() {
+        let large = vec![b'x'; 10];
+        let mut encoder = VectoredEncoder::new();
+        encoder.begin_list().unwrap();
+        encoder.emit_bytes_borrowed(&large).unwrap();
+        encoder.emit_bytes_borrowed(&large[..2]).unwrap();
+        encoder.emit_bytes_borrowed(b"").unwrap();
+        encoder.emit_int(7).unwrap();
+        encoder.end().unwrap();
+
+        let slices = encoder.io_slices().unwrap();
+        let slices = slices.iter().map(|slice| &**slice).collect::<Vec<_>>();
+        let expected = [&b"l10:"[..], &large, b"2:", b"xx", b"0:", b"i7ee"];
+        assert_eq!(slices, expected);
+
+        let mut output = Trickle(Vec::new());
+        encoder.write_to(&mut output).unwrap();
+        assert_eq!(output.0, b"l10:xxxxxxxxxx2:xx0:i7ee");
+        assert_eq!(encoder.len(), output.0.len());
+    }
+
+    #[test]
+    fn incomplete_output_should_be_rejected() {
+        let mut encoder = VectoredEncoder::new();
+        encoder.begin_dict().unwrap();
+        encoder.emit_bytes_borrowed(b"b").unwrap();
+        encoder.emit_int(1).unwrap();
+        assert!(encoder.emit_bytes_borrowed(b"a").is_err());
+        assert!(encoder.io_slices().is_err());
+
+        let mut encoder = VectoredEncoder::new();
+        encoder.begin_list().unwrap();
+        assert!(encoder.write_to(&mut Vec::new()).is_err());
+        assert!(VectoredEncoder::new().io_slices().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,50 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::encoding::Error;
+
+/// The output of a [`StreamEncoder`](super::StreamEncoder).
+///
+/// With the `std` feature, this is implemented for every [`std::io::Write`]. Without it,
+/// implementations for `Vec<u8>` and mutable references are provided, and other outputs
+/// like a serial port or a fixed packet buffer only need [`write_all`](Self::write_all).
+/// Failures should be reported as [`Error::Io`].
+pub trait Write {
+    /// Write all of `bytes`, or fail.
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Error>;
+
+    /// Flush any buffered output. Does nothing by default.
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write + ?Sized> Write for W {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        std::io::Write::write_all(self, bytes).map_err(Error::from)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        std::io::Write::flush(self).map_err(Error::from)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Write for Vec<u8> {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        (**self).write_all(bytes)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        (**self).flush()
+    }
+}
@@ -0,0 +1,288 @@
+//! Synthetic bencode documents for benchmarks and fuzzing seeds.
+//!
+//! A [`Generator`] produces random but reproducible documents: the same seed and
+//! configuration always yield the same output, so measurements of different versions or
+//! machines can be compared on identical workloads. This module is only available with the
+//! `gen` feature.
+//!
+//! ```
+//! use bendy::gen::{Generator, KeyDistribution};
+//!
+//! let generator = || {
+//!     Generator::new(42)
+//!         .with_depth(3)
+//!         .with_fanout(2..=4)
+//!         .with_string_len(0..=64)
+//!         .with_keys(KeyDistribution::Pool(8))
+//! };
+//!
+//! let document = generator().document();
+//! assert_eq!(document, generator().document());
+//! assert_eq!(
+//!     bendy::decoding::is_valid(&document).unwrap(),
+//!     document.len()
+//! );
+//! ```
+
+use alloc::{borrow::Cow, collections::BTreeMap, format, vec::Vec};
+use core::ops::RangeInclusive;
+
+use crate::{encoding::ToBencode, value::Value};
+
+/// How the keys of generated dicts are chosen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyDistribution {
+    /// Random lowercase ASCII keys with a length in the configured key length range.
+    Random,
+    /// `k0`, `k1`, `k2` and so on, unique within the document.
+    Sequential,
+    /// Keys drawn uniformly from `k0` to `k{n - 1}`, so the same keys repeat across
+    /// dicts like the field names of real documents.
+    Pool(usize),
+}
+
+/// A reproducible generator of bencode documents, see the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct Generator {
+    rng: SplitMix64,
+    depth: usize,
+    fanout: RangeInclusive<usize>,
+    string_len: RangeInclusive<usize>,
+    key_len: RangeInclusive<usize>,
+    keys: KeyDistribution,
+    next_key: usize,
+}
+
+impl Generator {
+    /// Create a generator from a seed. By default, documents are nested up to 4 levels
+    /// deep, containers hold 0 to 8 entries, strings are up to 32 bytes long and keys are
+    /// random with 1 to 16 characters.
+    pub fn new(seed: u64) -> Self {
+        Generator {
+            rng: SplitMix64(seed),
+            depth: 4,
+            fanout: 0..=8,
+            string_len: 0..=32,
+            key_len: 1..=16,
+            keys: KeyDistribution::Random,
+            next_key: 0,
+        }
+    }
+
+    /// Set the maximum nesting depth of containers. The outermost value is a dict unless
+    /// the depth is 0, in which case documents are single integers or strings.
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Set the range of the number of entries of lists and dicts. Dicts may end up with
+    /// fewer entries if the same key is chosen twice.
+    ///
+    /// # Panics
+    ///
+    /// If the range is empty.
+    pub fn with_fanout(mut self, fanout: RangeInclusive<usize>) -> Self {
+        assert!(fanout.start() <= fanout.end(), "empty fanout range");
+        self.fanout = fanout;
+        self
+    }
+
+    /// Set the range of the length of byte strings.
+    ///
+    /// # Panics
+    ///
+    /// If the range is empty.
+    pub fn with_string_len(mut self, string_len: RangeInclusive<usize>) -> Self {
+        assert!(
+            string_len.start() <= string_len.end(),
+            "empty string length range"
+        );
+        self.string_len = string_len;
+        self
+    }
+
+    /// Set the range of the length of [`KeyDistribution::Random`] keys.
+    ///
+    /// # Panics
+    ///
+    /// If the range is empty.
+    pub fn with_key_len(mut self, key_len: RangeInclusive<usize>) -> Self {
+        assert!(key_len.start() <= key_len.end(), "empty key length range");
+        self.key_len = key_len;
+        self
+    }
+
+    /// Set how dict keys are chosen.
+    ///
+    /// # Panics
+    ///
+    /// If the distribution is a pool without keys.
+    pub fn with_keys(mut self, keys: KeyDistribution) -> Self {
+        assert!(keys != KeyDistribution::Pool(0), "empty key pool");
+        self.keys = keys;
+        self
+    }
+
+    /// Generate the next document as a [`Value`].
+    pub fn value(&mut self) -> Value<'static> {
+        if self.depth == 0 {
+            self.atom()
+        } else {
+            self.dict(1)
+        }
+    }
+
+    /// Generate the next document as bencode.
+    pub fn document(&mut self) -> Vec<u8> {
+        self.value()
+            .to_bencode()
+            .expect("generated values are valid bencode")
+    }
+
+    fn any(&mut self, level: usize) -> Value<'static> {
+        if level >= self.depth {
+            return self.atom();
+        }
+        match self.rng.below(4) {
+            0 => self.list(level + 1),
+            1 => self.dict(level + 1),
+            _ => self.atom(),
+        }
+    }
+
+    fn atom(&mut self) -> Value<'static> {
+        if self.rng.below(2) == 0 {
+            // Shift by a random amount to vary the number of digits
+            let shift = self.rng.below(64) as u32;
+            Value::Integer((self.rng.next() as i64) >> shift)
+        } else {
+            let len = self.rng.in_range(&self.string_len);
+            let bytes = (0..len).map(|_| self.rng.next() as u8).collect();
+            Value::Bytes(Cow::Owned(bytes))
+        }
+    }
+
+    fn list(&mut self, level: usize) -> Value<'static> {
+        let len = self.rng.in_range(&self.fanout);
+        Value::List((0..len).map(|_| self.any(level)).collect())
+    }
+
+    fn dict(&mut self, level: usize) -> Value<'static> {
+        let len = self.rng.in_range(&self.fanout);
+        let mut dict = BTreeMap::new();
+        for _ in 0..len {
+            let key = self.key();
+            let value = self.any(level);
+            dict.insert(Cow::Owned(key), value);
+        }
+        Value::Dict(dict)
+    }
+
+    fn key(&mut self) -> Vec<u8> {
+        match self.keys {
+            KeyDistribution::Random => {
+                let len = self.rng.in_range(&self.key_len);
+                (0..len).map(|_| b'a' + self.rng.below(26) as u8).collect()
+            },
+            KeyDistribution::Sequential => {
+                self.next_key += 1;
+                numbered_key(self.next_key - 1)
+            },
+            KeyDistribution::Pool(len) => numbered_key(self.rng.below(len as u64) as usize),
+        }
+    }
+}
+
+/// The key `k{n}`
+fn numbered_key(n: usize) -> Vec<u8> {
+    format!("k{}", n).into_bytes()
+}
+
+/// The SplitMix64 generator, which is tiny and good enough for synthetic data
+#[derive(Clone, Debug)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..bound`, with a negligible bias for small bounds
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    fn in_range(&mut self, range: &RangeInclusive<usize>) -> usize {
+        let span = (range.end() - range.start()) as u64 + 1;
+        range.start() + self.below(span) as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoding::Decoder;
+
+    #[test]
+    fn documents_should_be_reproducible() {
+        let mut first = Generator::new(7);
+        let mut second = Generator::new(7);
+        for _ in 0..10 {
+            assert_eq!(first.document(), second.document());
+        }
+        assert_ne!(Generator::new(7).document(), Generator::new(8).document());
+    }
+
+    #[test]
+    fn documents_should_respect_the_shape() {
+        let mut generator = Generator::new(1)
+            .with_depth(3)
+            .with_fanout(3..=3)
+            .with_string_len(5..=5)
+            .with_keys(KeyDistribution::Sequential);
+
+        for _ in 0..10 {
+            let document = generator.document();
+            let mut decoder = Decoder::new(&document).with_max_depth(3);
+            while decoder.next_object().unwrap().is_some() {}
+
+            fn check(value: &Value) {
+                match value {
+                    Value::Bytes(bytes) => assert_eq!(bytes.len(), 5),
+                    Value::Integer(_) => {},
+                    Value::List(list) => {
+                        assert_eq!(list.len(), 3);
+                        list.iter().for_each(check);
+                    },
+                    Value::Dict(dict) => {
+                        assert_eq!(dict.len(), 3);
+                        dict.values().for_each(check);
+                    },
+                }
+            }
+            check(&Value::decode(&document).unwrap());
+        }
+    }
+
+    #[test]
+    fn pooled_keys_should_repeat() {
+        let value = Generator::new(3)
+            .with_depth(1)
+            .with_fanout(20..=20)
+            .with_keys(KeyDistribution::Pool(2))
+            .value();
+
+        match &value {
+            Value::Dict(dict) => {
+                let keys: Vec<_> = dict.keys().map(|key| key.as_ref()).collect();
+                assert_eq!(keys, [&b"k0"[..], &b"k1"[..]]);
+            },
+            other => panic!("expected a dict, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Fixed-capacity values for parsing without allocating per node.
+//!
+//! [`Value`] stores a decoded document in two inline arrays: up to `MAX_NODES` integers,
+//! strings, lists and dicts, and up to `MAX_BYTES` bytes of string content. Inputs that
+//! don't fit are rejected with [`ErrorKind::LimitExceeded`] instead of growing a buffer,
+//! which makes the memory used for a document known at compile time. This is meant for
+//! microcontrollers and other targets where the heap is small or absent.
+//!
+//! Strings are copied out of the input, so the input buffer can be reused once a value is
+//! decoded.
+//!
+//! ```
+//! use bendy::heapless::{Item, Value};
+//!
+//! let value = Value::<8, 32>::decode(b"d2:id4:abcd1:qi5ee").unwrap();
+//! let dict = match value.root() {
+//!     Item::Dict(dict) => dict,
+//!     _ => unreachable!(),
+//! };
+//! assert_eq!(dict.get(b"id"), Some(Item::Bytes(b"abcd")));
+//!
+//! assert!(Value::<2, 32>::decode(b"d2:id4:abcd1:qi5ee").is_err());
+//! ```
+//!
+//! This module needs the `heapless` feature, which requires Rust 1.51 for const generics.
+//!
+//! [`ErrorKind::LimitExceeded`]: crate::decoding::ErrorKind::LimitExceeded
+
+use core::fmt::{self, Debug, Formatter};
+
+pub use crate::arena::{Item, Items, Pairs};
+
+use crate::{
+    arena::Node,
+    decoding::{parse_integer, Decoder, Error},
+    encoding::{self, SingleItemEncoder, ToBencode},
+    state_tracker::{StructureError, Token},
+};
+
+/// A bencoded value of at most `MAX_NODES` nodes and `MAX_BYTES` bytes of string content,
+/// see the [module documentation](self).
+///
+/// Nodes are stored in document order; containers know the index after their last
+/// descendant, so the tree can be traversed without further bookkeeping.
+#[derive(Clone)]
+pub struct Value<const MAX_NODES: usize, const MAX_BYTES: usize> {
+    nodes: [Node; MAX_NODES],
+    node_count: usize,
+    bytes: [u8; MAX_BYTES],
+    byte_count: usize,
+}
+
+impl<const MAX_NODES: usize, const MAX_BYTES: usize> Value<MAX_NODES, MAX_BYTES> {
+    /// Decode the first bencoded value of `input`. Any data after it is ignored.
+    pub fn decode(input: &[u8]) -> Result<Self, Error> {
+        let mut value = Value {
+            nodes: [Node::Integer(0); MAX_NODES],
+            node_count: 0,
+            bytes: [0; MAX_BYTES],
+            byte_count: 0,
+        };
+        // The innermost unfinished container
+        let mut open = None;
+
+        for token in Decoder::new(input).tokens() {
+            let node = match token? {
+                Token::End => {
+                    // Matching ends are guaranteed by the decoder
+                    let index: usize = open.expect("end token outside of a container");
+                    let parent = match &mut value.nodes[index] {
+                        Node::List { end, .. } | Node::Dict { end, .. } => {
+                            core::mem::replace(end, value.node_count)
+                        },
+                        _ => unreachable!("only containers are open"),
+                    };
+                    open = parent.checked_sub(1);
+                    if open.is_none() {
+                        break;
+                    }
+                    continue;
+                },
+                Token::Num(text) => Node::Integer(parse_integer(text)?),
+                Token::String(string) => {
+                    let start = value.byte_count;
+                    let content = value
+                        .bytes
+                        .get_mut(start..start + string.len())
+                        .ok_or_else(|| {
+                            Error::limit_exceeded(format_args!(
+                                "{} bytes of string content",
+                                MAX_BYTES
+                            ))
+                        })?;
+                    content.copy_from_slice(string);
+                    value.byte_count += string.len();
+                    Node::Bytes {
+                        start,
+                        len: string.len(),
+                    }
+                },
+                Token::List => Node::List {
+                    len: 0,
+                    end: open.map_or(0, |index| index + 1),
+                },
+                Token::Dict => Node::Dict {
+                    len: 0,
+                    end: open.map_or(0, |index| index + 1),
+                },
+            };
+
+            let slot = value
+                .nodes
+                .get_mut(value.node_count)
+                .ok_or_else(|| Error::limit_exceeded(format_args!("{} nodes", MAX_NODES)))?;
+            *slot = node;
+
+            match open {
+                Some(parent) => match &mut value.nodes[parent] {
+                    Node::List { len, .. } | Node::Dict { len, .. } => *len += 1,
+                    _ => unreachable!("only containers are open"),
+                },
+                None if !matches!(node, Node::List { .. } | Node::Dict { .. }) => {
+                    value.node_count += 1;
+                    return Ok(value);
+                },
+                None => (),
+            }
+            if let Node::List { .. } | Node::Dict { .. } = node {
+                open = Some(value.node_count);
+            }
+            value.node_count += 1;
+        }
+
+        if value.node_count == 0 || open.is_some() {
+            return Err(Error::from(StructureError::UnexpectedEof));
+        }
+        Ok(value)
+    }
+
+    /// The top-level item.
+    pub fn root(&self) -> Item<'_> {
+        self.item(0)
+    }
+
+    /// The number of nodes in use, at most `MAX_NODES`.
+    pub fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    /// The number of bytes of string content in use, at most `MAX_BYTES`.
+    pub fn byte_count(&self) -> usize {
+        self.byte_count
+    }
+
+    fn item(&self, index: usize) -> Item<'_> {
+        Items {
+            nodes: &self.nodes[..self.node_count],
+            bytes: &self.bytes[..self.byte_count],
+            next: index,
+            remaining: 1,
+        }
+        .next()
+        .expect("a decoded value has a root")
+    }
+}
+
+impl<const MAX_NODES: usize, const MAX_BYTES: usize> PartialEq for Value<MAX_NODES, MAX_BYTES> {
+    fn eq(&self, other: &Self) -> bool {
+        self.root() == other.root()
+    }
+}
+
+impl<const MAX_NODES: usize, const MAX_BYTES: usize> Eq for Value<MAX_NODES, MAX_BYTES> {}
+
+impl<const MAX_NODES: usize, const MAX_BYTES: usize> Debug for Value<MAX_NODES, MAX_BYTES> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.root().fmt(f)
+    }
+}
+
+impl<const MAX_NODES: usize, const MAX_BYTES: usize> ToBencode for Value<MAX_NODES, MAX_BYTES> {
+    // A value can't be nested deeper than it has nodes
+    const MAX_DEPTH: usize = MAX_NODES;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        self.root().encode(encoder)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(not(feature = "std"))]
+    use alloc::{format, vec::Vec};
+
+    type Small = Value<16, 64>;
+
+    #[test]
+    fn values_should_round_trip() {
+        let input = b"d1:ali1eli2ei3ee0:e1:bd1:ci-4eee";
+        let value = Small::decode(input).unwrap();
+        assert_eq!(value.node_count(), 12);
+        assert_eq!(value.byte_count(), 3);
+        assert_eq!(value.to_bencode().unwrap(), &input[..]);
+
+        let dict = match value.root() {
+            Item::Dict(dict) => dict,
+            other => panic!("unexpected root {:?}", other),
+        };
+        assert_eq!(dict.len(), 2);
+        let list: Vec<_> = match dict.get(b"a") {
+            Some(Item::List(list)) => list.collect(),
+            other => panic!("unexpected item {:?}", other),
+        };
+        assert_eq!(list.len(), 3);
+        assert_eq!(list[0], Item::Integer(1));
+        assert_eq!(list[2], Item::Bytes(b""));
+        assert!(dict.get(b"c").is_none());
+    }
+
+    #[test]
+    fn scalars_should_be_values() {
+        assert_eq!(Small::decode(b"i-7e").unwrap().root(), Item::Integer(-7));
+        assert_eq!(Small::decode(b"3:abc").unwrap().root(), Item::Bytes(b"abc"));
+        assert!(Small::decode(b"").is_err());
+        assert!(Small::decode(b"li1e").is_err());
+    }
+
+    #[test]
+    fn limits_should_be_enforced() {
+        assert!(Value::<2, 0>::decode(b"li1ee").is_ok());
+        let error = Value::<2, 0>::decode(b"li1ei2ee").unwrap_err();
+        assert!(format!("{}", error).contains("limit exceeded: 2 nodes"));
+
+        assert!(Value::<1, 3>::decode(b"3:abc").is_ok());
+        let error = Value::<1, 3>::decode(b"4:abcd").unwrap_err();
+        assert!(format!("{}", error).contains("limit exceeded: 3 bytes of string content"));
+    }
+}
@@ -0,0 +1,63 @@
+//! Hex digits of binary data such as hashes and node IDs.
+
+use core::fmt::{self, Display, Formatter};
+
+/// Formats binary data as lowercase hex digits
+pub(crate) struct Hex<'a>(pub(crate) &'a [u8]);
+
+impl Display for Hex<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+/// The value of a hex digit of either case
+fn digit(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'a'..=b'f' => Some(digit - b'a' + 10),
+        b'A'..=b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Decode hex digits of either case into `bytes`, which have to be exactly twice as many.
+pub(crate) fn decode_into(hex: &[u8], bytes: &mut [u8]) -> Option<()> {
+    if hex.len() != bytes.len() * 2 {
+        return None;
+    }
+    for (byte, digits) in bytes.iter_mut().zip(hex.chunks(2)) {
+        *byte = digit(digits[0])? << 4 | digit(digits[1])?;
+    }
+    Some(())
+}
+
+/// Decode hex digits of either case
+#[cfg(feature = "std")]
+pub(crate) fn decode(hex: &[u8]) -> Option<Vec<u8>> {
+    let mut bytes = vec![0; hex.len() / 2];
+    decode_into(hex, &mut bytes)?;
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn hex_should_round_trip() {
+        let bytes = [0x00, 0x01, 0x7f, 0x80, 0xfe, 0xff];
+        assert_eq!(Hex(&bytes).to_string(), "00017f80feff");
+
+        let mut decoded = [0; 6];
+        assert_eq!(decode_into(b"00017F80feFF", &mut decoded), Some(()));
+        assert_eq!(decoded, bytes);
+
+        assert_eq!(decode_into(b"0", &mut []), None);
+        assert_eq!(decode_into(b"0g", &mut [0; 1]), None);
+        assert_eq!(decode_into(b"0000", &mut [0; 1]), None);
+    }
+}
@@ -0,0 +1,279 @@
+//! Integer types with an explicit numeric domain.
+//!
+//! Bencode integers have no size limit, but most implementations store them as signed
+//! 64-bit values. The wrappers in this module make the supported range part of an API
+//! signature, and conversions between them fail instead of silently wrapping around:
+//!
+//! - [`I64`] holds any integer an `i64` can represent; this is the safest choice for
+//!   interoperability with other implementations.
+//! - [`U64`] holds non-negative integers up to `u64::MAX`. Values above `i64::MAX` may
+//!   be rejected by other implementations.
+//! - [`BigUintStr`] holds non-negative integers of arbitrary size as decimal text.
+//!
+//! ```
+//! use std::convert::TryFrom;
+//!
+//! use bendy::{
+//!     decoding::FromBencode,
+//!     encoding::ToBencode,
+//!     integer::{BigUintStr, I64, U64},
+//! };
+//!
+//! let large = U64::from_bencode(b"i18446744073709551615e").unwrap();
+//! assert!(I64::try_from(large).is_err());
+//! assert!(I64::from_bencode(b"i18446744073709551615e").is_err());
+//!
+//! let huge = BigUintStr::from_bencode(b"i340282366920938463463374607431768211456e").unwrap();
+//! assert!(U64::try_from(&huge).is_err());
+//! assert_eq!(
+//!     huge.to_bencode().unwrap(),
+//!     b"i340282366920938463463374607431768211456e"
+//! );
+//! ```
+
+#[cfg(not(feature = "std"))]
+use alloc::string::{String, ToString};
+use core::{
+    convert::TryFrom,
+    fmt::{self, Display, Formatter},
+    num::TryFromIntError,
+    str::FromStr,
+};
+
+use crate::{
+    decoding::{self, FromBencode, Object},
+    encoding::{self, PrintableInteger, SingleItemEncoder, ToBencode},
+};
+
+macro_rules! fixed_width_integer {
+    ($(#[$doc:meta])* $name:ident($type:ty)) => {
+        $(#[$doc])*
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(pub $type);
+
+        impl $name {
+            /// The integer value.
+            pub fn get(self) -> $type {
+                self.0
+            }
+        }
+
+        impl From<$type> for $name {
+            fn from(value: $type) -> Self {
+                $name(value)
+            }
+        }
+
+        impl From<$name> for $type {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl PrintableInteger for $name {}
+
+        impl ToBencode for $name {
+            const MAX_DEPTH: usize = 1;
+
+            fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+                encoder.emit_int(self.0)
+            }
+        }
+
+        impl FromBencode for $name {
+            const EXPECTED_RECURSION_DEPTH: usize = 0;
+
+            fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+            where
+                Self: Sized,
+            {
+                let content = object.try_into_integer()?;
+                Ok($name(content.parse()?))
+            }
+        }
+    };
+}
+
+fixed_width_integer! {
+    /// A bencode integer restricted to the range of an `i64`.
+    I64(i64)
+}
+
+fixed_width_integer! {
+    /// A bencode integer restricted to the range of a `u64`.
+    U64(u64)
+}
+
+impl TryFrom<U64> for I64 {
+    type Error = TryFromIntError;
+
+    fn try_from(value: U64) -> Result<Self, Self::Error> {
+        i64::try_from(value.0).map(I64)
+    }
+}
+
+impl TryFrom<I64> for U64 {
+    type Error = TryFromIntError;
+
+    fn try_from(value: I64) -> Result<Self, Self::Error> {
+        u64::try_from(value.0).map(U64)
+    }
+}
+
+/// A non-negative bencode integer of arbitrary size, stored as its canonical decimal
+/// representation.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BigUintStr(String);
+
+impl BigUintStr {
+    /// The decimal digits of the integer.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Ord for BigUintStr {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        // Canonical numbers have no leading zeros, so longer numbers are larger
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.cmp(&other.0))
+    }
+}
+
+impl PartialOrd for BigUintStr {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The error returned when parsing an invalid [`BigUintStr`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseBigUintError;
+
+impl Display for ParseBigUintError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("not a canonical non-negative integer")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseBigUintError {}
+
+impl FromStr for BigUintStr {
+    type Err = ParseBigUintError;
+
+    /// Parse a canonical decimal number: only digits, without leading zeros.
+    fn from_str(digits: &str) -> Result<Self, Self::Err> {
+        let canonical = match digits.as_bytes() {
+            [] => false,
+            [b'0'] => true,
+            [b'0', ..] => false,
+            digits => digits.iter().all(u8::is_ascii_digit),
+        };
+
+        if canonical {
+            Ok(BigUintStr(digits.to_string()))
+        } else {
+            Err(ParseBigUintError)
+        }
+    }
+}
+
+impl From<U64> for BigUintStr {
+    fn from(value: U64) -> Self {
+        BigUintStr(value.to_string())
+    }
+}
+
+impl From<u64> for BigUintStr {
+    fn from(value: u64) -> Self {
+        BigUintStr(value.to_string())
+    }
+}
+
+impl TryFrom<&BigUintStr> for U64 {
+    type Error = core::num::ParseIntError;
+
+    fn try_from(value: &BigUintStr) -> Result<Self, Self::Error> {
+        value.0.parse().map(U64)
+    }
+}
+
+impl Display for BigUintStr {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl PrintableInteger for BigUintStr {}
+impl PrintableInteger for &BigUintStr {}
+
+impl ToBencode for BigUintStr {
+    const MAX_DEPTH: usize = 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_int(self)
+    }
+}
+
+impl FromBencode for BigUintStr {
+    const EXPECTED_RECURSION_DEPTH: usize = 0;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let content = object.try_into_integer()?;
+        content.parse().map_err(decoding::Error::malformed_content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fixed_width_integers_should_reject_out_of_range_values() {
+        assert_eq!(U64::from_bencode(b"i0e").unwrap(), U64(0));
+        assert!(U64::from_bencode(b"i-1e").is_err());
+        assert!(U64::from_bencode(b"i18446744073709551616e").is_err());
+        assert_eq!(
+            I64::from_bencode(b"i-9223372036854775808e").unwrap(),
+            I64(i64::MIN)
+        );
+        assert!(I64::from_bencode(b"i9223372036854775808e").is_err());
+    }
+
+    #[test]
+    fn conversions_should_check_the_range() {
+        assert_eq!(I64::try_from(U64(5)), Ok(I64(5)));
+        assert!(I64::try_from(U64(u64::MAX)).is_err());
+        assert!(U64::try_from(I64(-1)).is_err());
+    }
+
+    #[test]
+    fn big_integers_should_be_canonical() {
+        assert!("0".parse::<BigUintStr>().is_ok());
+        assert!("123".parse::<BigUintStr>().is_ok());
+        assert!("".parse::<BigUintStr>().is_err());
+        assert!("012".parse::<BigUintStr>().is_err());
+        assert!("-1".parse::<BigUintStr>().is_err());
+        assert!(BigUintStr::from_bencode(b"i-5e").is_err());
+    }
+
+    #[test]
+    fn big_integers_should_be_ordered_numerically() {
+        let small: BigUintStr = "99".parse().unwrap();
+        let large: BigUintStr = "100".parse().unwrap();
+        assert!(small < large);
+        assert_eq!(U64::try_from(&large), Ok(U64(100)));
+    }
+}
@@ -0,0 +1,852 @@
+//! Conversion between bencode and JSON text.
+//!
+//! [`to_json`] and [`JsonConverter::write`] walk the tokens of a bencoded value and write
+//! the corresponding JSON directly, without building an intermediate [`Value`] or JSON
+//! document:
+//!
+//! ```
+//! let json =
+//!     bendy::json::to_json(b"d4:infod6:lengthi42e6:pieces2:\x00\xffe4:tagsl3:fooee").unwrap();
+//! assert_eq!(
+//!     json,
+//!     r#"{"info":{"length":42,"pieces":{"$hex":"00ff"}},"tags":["foo"]}"#
+//! );
+//! ```
+//!
+//! Integers become JSON numbers with the same digits, lists become arrays and dicts
+//! become objects with their keys in bencode order. Byte strings that are valid UTF-8
+//! become JSON strings, all others become an object with a single `$hex` or `$base64` key,
+//! depending on the [`BinaryEncoding`], holding the encoded bytes.
+//!
+//! Dict keys have to be valid UTF-8. To keep the conversion unambiguous, a dict that
+//! would be mistaken for an encoded byte string, i.e. that only has a `$hex` or `$base64`
+//! key with a string value, is rejected.
+//!
+//! [`from_json`] converts in the opposite direction and emits canonical bencode. JSON
+//! strings become UTF-8 byte strings, and objects with a single `$hex` or `$base64` key
+//! and a string value are decoded to the byte string they hold, so both binary encodings
+//! are accepted regardless of the configured one. Keys of other objects are sorted, and
+//! numbers have to be integers, as bencode has neither floats nor `null` or booleans:
+//!
+//! ```
+//! let bencode = bendy::json::from_json(r#"{"tags":["foo"],"pieces":{"$hex":"00ff"}}"#).unwrap();
+//! assert_eq!(bencode, b"d6:pieces2:\x00\xff4:tagsl3:fooee");
+//! ```
+//!
+//! [`Value`]: crate::value::Value
+
+use std::{
+    collections::{btree_map::Entry, BTreeMap, VecDeque},
+    io::{self, Write},
+    str,
+    sync::Arc,
+};
+
+use snafu::Snafu;
+
+use crate::{
+    decoding::{self, Decoder, Tokens},
+    hex::{self, Hex},
+    state_tracker::{StructureError, Token},
+};
+
+/// The keys marking a JSON object as an encoded byte string
+const MARKERS: [&[u8]; 2] = [b"$hex", b"$base64"];
+
+/// How byte strings which aren't valid UTF-8 are represented in JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryEncoding {
+    /// `{"$hex": "00ff"}` with lowercase hex digits
+    Hex,
+    /// `{"$base64": "AP8="}` with the standard alphabet and padding
+    Base64,
+}
+
+// `#[default]` on enum variants requires a newer compiler than the MSRV
+#[allow(clippy::derivable_impls)]
+impl Default for BinaryEncoding {
+    fn default() -> Self {
+        BinaryEncoding::Hex
+    }
+}
+
+/// An enumeration of potential errors that appear while converting to JSON.
+#[derive(Debug, Clone, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    /// Error that occurs if the input isn't valid bencode.
+    #[snafu(display("invalid bencode: {}", source))]
+    Decode { source: decoding::Error },
+
+    /// Error that occurs if the input contains more than a single value.
+    #[snafu(display("trailing bytes after the bencoded value"))]
+    TrailingBytes,
+
+    /// Error that occurs if a dict key isn't valid UTF-8 and can't be a JSON key.
+    #[snafu(display("dict key is not valid UTF-8: {}", String::from_utf8_lossy(key)))]
+    NonUtf8Key { key: Vec<u8> },
+
+    /// Error that occurs if a dict would be mistaken for an encoded byte string.
+    #[snafu(display(
+        "dict with the single key `{}` would be mistaken for a byte string",
+        String::from_utf8_lossy(key)
+    ))]
+    AmbiguousDict { key: Vec<u8> },
+
+    /// Error that occurs if the input isn't valid JSON.
+    #[snafu(display("invalid JSON at offset {}: expected {}", offset, expected))]
+    Syntax {
+        offset: usize,
+        expected: &'static str,
+    },
+
+    /// Error that occurs if a JSON value has no bencode equivalent, like `null` or a
+    /// number with a fraction.
+    #[snafu(display("{} at offset {} can't be converted to bencode", kind, offset))]
+    Unrepresentable { offset: usize, kind: &'static str },
+
+    /// Error that occurs if a JSON object contains a key twice.
+    #[snafu(display("duplicate key `{}`", key))]
+    DuplicateKey { key: String },
+
+    /// Error that occurs if the string of a `$hex` or `$base64` object isn't valid.
+    #[snafu(display("invalid {} string at offset {}", encoding, offset))]
+    InvalidBinary {
+        offset: usize,
+        encoding: &'static str,
+    },
+
+    /// Error that occurs if the JSON input is nested deeper than the max depth.
+    #[snafu(display("JSON input nested deeper than {} levels", max_depth))]
+    NestingTooDeep { max_depth: usize },
+
+    /// Error that occurs if the output sink fails.
+    #[snafu(display("failed to write the output: {}", source))]
+    Io { source: Arc<io::Error> },
+}
+
+impl Error {
+    /// A stable identifier of the kind of error, like `json.syntax`, see
+    /// [`decoding::Error::code`]. Errors of the bencode decoder keep their own code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Decode { source } => source.code(),
+            Error::TrailingBytes => "json.trailing_bytes",
+            Error::NonUtf8Key { .. } => "json.non_utf8_key",
+            Error::AmbiguousDict { .. } => "json.ambiguous_dict",
+            Error::Syntax { .. } => "json.syntax",
+            Error::Unrepresentable { .. } => "json.unrepresentable",
+            Error::DuplicateKey { .. } => "json.duplicate_key",
+            Error::InvalidBinary { .. } => "json.invalid_binary",
+            Error::NestingTooDeep { .. } => "json.nesting_too_deep",
+            Error::Io { .. } => "json.io",
+        }
+    }
+}
+
+impl From<decoding::Error> for Error {
+    fn from(error: decoding::Error) -> Self {
+        Error::Decode { source: error }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io {
+            source: Arc::new(error),
+        }
+    }
+}
+
+/// Convert a bencoded value to JSON text, using [`BinaryEncoding::Hex`].
+pub fn to_json(bencode: &[u8]) -> Result<String, Error> {
+    JsonConverter::new().to_string(bencode)
+}
+
+/// Convert JSON text to canonical bencode.
+pub fn from_json(json: &str) -> Result<Vec<u8>, Error> {
+    JsonConverter::new().to_bencode(json)
+}
+
+/// A configurable converter between bencode and JSON, see the
+/// [module documentation](self).
+#[derive(Clone, Copy, Debug)]
+pub struct JsonConverter {
+    binary: BinaryEncoding,
+    max_depth: usize,
+}
+
+impl Default for JsonConverter {
+    fn default() -> Self {
+        JsonConverter {
+            binary: BinaryEncoding::default(),
+            max_depth: 2048,
+        }
+    }
+}
+
+impl JsonConverter {
+    /// Create a converter using [`BinaryEncoding::Hex`] and accepting JSON input nested
+    /// up to 2048 levels deep
+    pub fn new() -> Self {
+        <Self as Default>::default()
+    }
+
+    /// Set the maximum nesting depth of JSON input converted by
+    /// [`to_bencode`](Self::to_bencode)
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Convert JSON text to canonical bencode
+    pub fn to_bencode(&self, json: &str) -> Result<Vec<u8>, Error> {
+        let mut parser = Parser {
+            json: json.as_bytes(),
+            offset: 0,
+            depth: 0,
+            max_depth: self.max_depth,
+        };
+        let mut bencode = Vec::new();
+        parser.value(&mut bencode)?;
+        parser.skip_whitespace();
+        if parser.offset < parser.json.len() {
+            return Err(parser.syntax("the end of the input"));
+        }
+        Ok(bencode)
+    }
+
+    /// Represent byte strings which aren't valid UTF-8 using `binary`
+    pub fn with_binary(mut self, binary: BinaryEncoding) -> Self {
+        self.binary = binary;
+        self
+    }
+
+    /// Convert a bencoded value to JSON text
+    pub fn to_string(&self, bencode: &[u8]) -> Result<String, Error> {
+        let mut json = Vec::new();
+        self.write(bencode, &mut json)?;
+        Ok(String::from_utf8(json).expect("JSON output is valid UTF-8"))
+    }
+
+    /// Convert a bencoded value and write the JSON text to `writer`.
+    ///
+    /// The output is written token by token, so a buffered writer should be used.
+    pub fn write(&self, bencode: &[u8], mut writer: impl Write) -> Result<(), Error> {
+        let mut tokens = Lookahead {
+            tokens: Decoder::new(bencode).tokens(),
+            buffer: VecDeque::new(),
+        };
+        let mut open = Vec::new();
+
+        loop {
+            let token = match tokens.next()? {
+                Some(token) => token,
+                None => return Err(decoding::Error::from(StructureError::UnexpectedEof).into()),
+            };
+
+            if token == Token::End {
+                match open.pop() {
+                    Some(Container::List { .. }) => writer.write_all(b"]")?,
+                    Some(_) => writer.write_all(b"}")?,
+                    None => unreachable!("the decoder rejects unbalanced ends"),
+                }
+            } else {
+                let is_key = match open.last_mut() {
+                    Some(Container::List { first }) => {
+                        if !*first {
+                            writer.write_all(b",")?;
+                        }
+                        *first = false;
+                        false
+                    },
+                    Some(container @ Container::Key { .. }) => {
+                        if let Container::Key { first: false } = container {
+                            writer.write_all(b",")?;
+                        }
+                        *container = Container::Value;
+                        true
+                    },
+                    Some(container @ Container::Value) => {
+                        *container = Container::Key { first: false };
+                        false
+                    },
+                    None => false,
+                };
+
+                match token {
+                    Token::List => {
+                        writer.write_all(b"[")?;
+                        open.push(Container::List { first: true });
+                    },
+                    Token::Dict => {
+                        tokens.reject_marker_dict()?;
+                        writer.write_all(b"{")?;
+                        open.push(Container::Key { first: true });
+                    },
+                    Token::Num(num) => writer.write_all(num.as_bytes())?,
+                    Token::String(key) if is_key => {
+                        match str::from_utf8(key) {
+                            Ok(key) => write_string(&mut writer, key)?,
+                            Err(_) => return Err(Error::NonUtf8Key { key: key.to_vec() }),
+                        }
+                        writer.write_all(b":")?;
+                    },
+                    Token::String(bytes) => self.write_bytes(&mut writer, bytes)?,
+                    Token::End => unreachable!("handled above"),
+                }
+            }
+
+            if open.is_empty() {
+                break;
+            }
+        }
+
+        if tokens.next()?.is_some() {
+            return Err(Error::TrailingBytes);
+        }
+        Ok(())
+    }
+
+    fn write_bytes(&self, writer: &mut impl Write, bytes: &[u8]) -> Result<(), Error> {
+        if let Ok(string) = str::from_utf8(bytes) {
+            return write_string(writer, string);
+        }
+
+        match self.binary {
+            BinaryEncoding::Hex => {
+                writer.write_all(br#"{"$hex":""#)?;
+                write!(writer, "{}", Hex(bytes))?;
+            },
+            BinaryEncoding::Base64 => {
+                writer.write_all(br#"{"$base64":""#)?;
+                writer.write_all(&base64(bytes))?;
+            },
+        }
+        writer.write_all(br#""}"#)?;
+        Ok(())
+    }
+}
+
+/// Write a JSON string literal
+fn write_string(writer: &mut impl Write, string: &str) -> Result<(), Error> {
+    writer.write_all(b"\"")?;
+    let mut start = 0;
+    for (index, byte) in string.bytes().enumerate() {
+        let escaped: &[u8] = match byte {
+            b'"' => b"\\\"",
+            b'\\' => b"\\\\",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\t' => b"\\t",
+            0..=0x1f => b"",
+            _ => continue,
+        };
+        writer.write_all(&string.as_bytes()[start..index])?;
+        if escaped.is_empty() {
+            write!(writer, "\\u{:04x}", byte)?;
+        } else {
+            writer.write_all(escaped)?;
+        }
+        start = index + 1;
+    }
+    writer.write_all(&string.as_bytes()[start..])?;
+    writer.write_all(b"\"")?;
+    Ok(())
+}
+
+/// Standard base64 with padding
+fn base64(bytes: &[u8]) -> Vec<u8> {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = Vec::with_capacity(bytes.chunks(3).len() * 4);
+    for chunk in bytes.chunks(3) {
+        let group = match *chunk {
+            [a] => u32::from(a) << 16,
+            [a, b] => u32::from(a) << 16 | u32::from(b) << 8,
+            [a, b, c] => u32::from(a) << 16 | u32::from(b) << 8 | u32::from(c),
+            _ => unreachable!("chunks have one to three bytes"),
+        };
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize]);
+            } else {
+                encoded.push(b'=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decode standard base64, with or without padding
+fn decode_base64(encoded: &[u8]) -> Option<Vec<u8>> {
+    let encoded = match encoded {
+        [rest @ .., b'=', b'='] | [rest @ .., b'='] if encoded.len() & 3 == 0 => rest,
+        _ => encoded,
+    };
+    if encoded.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(encoded.len() / 4 * 3 + 2);
+    for chunk in encoded.chunks(4) {
+        let mut group = 0;
+        for (i, &digit) in chunk.iter().enumerate() {
+            let value = match digit {
+                b'A'..=b'Z' => digit - b'A',
+                b'a'..=b'z' => digit - b'a' + 26,
+                b'0'..=b'9' => digit - b'0' + 52,
+                b'+' => 62,
+                b'/' => 63,
+                _ => return None,
+            };
+            group |= u32::from(value) << (18 - 6 * i);
+        }
+        let group = group.to_be_bytes();
+        bytes.extend_from_slice(&group[1..chunk.len()]);
+    }
+    Some(bytes)
+}
+
+/// Write a bencoded byte string
+fn write_bencode_bytes(bencode: &mut Vec<u8>, bytes: &[u8]) {
+    bencode.extend_from_slice(bytes.len().to_string().as_bytes());
+    bencode.push(b':');
+    bencode.extend_from_slice(bytes);
+}
+
+/// A recursive descent JSON parser writing bencode
+struct Parser<'a> {
+    json: &'a [u8],
+    offset: usize,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn value(&mut self, bencode: &mut Vec<u8>) -> Result<(), Error> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.nested(|parser| parser.object(bencode)),
+            Some(b'[') => self.nested(|parser| parser.array(bencode)),
+            Some(b'"') => {
+                let string = self.string()?;
+                write_bencode_bytes(bencode, string.as_bytes());
+                Ok(())
+            },
+            Some(b'-') | Some(b'0'..=b'9') => self.number(bencode),
+            Some(b't') | Some(b'f') => Err(self.unrepresentable("a boolean")),
+            Some(b'n') => Err(self.unrepresentable("null")),
+            _ => Err(self.syntax("a value")),
+        }
+    }
+
+    fn nested(&mut self, parse: impl FnOnce(&mut Self) -> Result<(), Error>) -> Result<(), Error> {
+        if self.depth == self.max_depth {
+            return Err(Error::NestingTooDeep {
+                max_depth: self.max_depth,
+            });
+        }
+        self.depth += 1;
+        parse(self)?;
+        self.depth -= 1;
+        Ok(())
+    }
+
+    fn object(&mut self, bencode: &mut Vec<u8>) -> Result<(), Error> {
+        self.offset += 1;
+        let start = self.offset;
+        let mut pairs = BTreeMap::new();
+
+        self.skip_whitespace();
+        if !self.eat(b'}') {
+            loop {
+                self.skip_whitespace();
+                if self.peek() != Some(b'"') {
+                    return Err(self.syntax("a key"));
+                }
+                let key = self.string()?;
+                self.skip_whitespace();
+                if !self.eat(b':') {
+                    return Err(self.syntax("`:`"));
+                }
+
+                let mut value = Vec::new();
+                self.value(&mut value)?;
+                match pairs.entry(key) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(value);
+                    },
+                    Entry::Occupied(entry) => {
+                        return Err(Error::DuplicateKey {
+                            key: entry.key().clone(),
+                        })
+                    },
+                }
+
+                self.skip_whitespace();
+                if self.eat(b'}') {
+                    break;
+                }
+                if !self.eat(b',') {
+                    return Err(self.syntax("`,` or `}`"));
+                }
+            }
+        }
+
+        if pairs.len() == 1 {
+            let (key, value) = pairs.iter().next().expect("one pair");
+            if let Some(bytes) = self.marked_bytes(key, value, start)? {
+                write_bencode_bytes(bencode, &bytes);
+                return Ok(());
+            }
+        }
+
+        bencode.push(b'd');
+        for (key, value) in pairs {
+            write_bencode_bytes(bencode, key.as_bytes());
+            bencode.extend_from_slice(&value);
+        }
+        bencode.push(b'e');
+        Ok(())
+    }
+
+    /// The bytes encoded by an object with the single pair of `key` and the bencoded
+    /// `value`, if it represents a byte string
+    fn marked_bytes(
+        &self,
+        key: &str,
+        value: &[u8],
+        start: usize,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let encoded = match value.iter().position(|&byte| byte == b':') {
+            Some(colon) if value[0].is_ascii_digit() => &value[colon + 1..],
+            _ => return Ok(None),
+        };
+        let (bytes, encoding) = match key {
+            "$hex" => (hex::decode(encoded), "hex"),
+            "$base64" => (decode_base64(encoded), "base64"),
+            _ => return Ok(None),
+        };
+
+        match bytes {
+            Some(bytes) => Ok(Some(bytes)),
+            None => Err(Error::InvalidBinary {
+                offset: start - 1,
+                encoding,
+            }),
+        }
+    }
+
+    fn array(&mut self, bencode: &mut Vec<u8>) -> Result<(), Error> {
+        self.offset += 1;
+        bencode.push(b'l');
+
+        self.skip_whitespace();
+        if !self.eat(b']') {
+            loop {
+                self.value(bencode)?;
+                self.skip_whitespace();
+                if self.eat(b']') {
+                    break;
+                }
+                if !self.eat(b',') {
+                    return Err(self.syntax("`,` or `]`"));
+                }
+            }
+        }
+
+        bencode.push(b'e');
+        Ok(())
+    }
+
+    fn number(&mut self, bencode: &mut Vec<u8>) -> Result<(), Error> {
+        let start = self.offset;
+        let negative = self.eat(b'-');
+        let digits = self.offset;
+        match self.peek() {
+            Some(b'0') => self.offset += 1,
+            Some(b'1'..=b'9') => {
+                while let Some(b'0'..=b'9') = self.peek() {
+                    self.offset += 1;
+                }
+            },
+            _ => return Err(self.syntax("a digit")),
+        }
+        if let Some(b'.') | Some(b'e') | Some(b'E') = self.peek() {
+            self.offset = start;
+            return Err(self.unrepresentable("a number with a fraction or exponent"));
+        }
+
+        let digits = &self.json[digits..self.offset];
+        bencode.push(b'i');
+        // Bencode has no negative zero
+        if negative && digits != b"0" {
+            bencode.push(b'-');
+        }
+        bencode.extend_from_slice(digits);
+        bencode.push(b'e');
+        Ok(())
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.offset += 1;
+        let mut string = String::new();
+        loop {
+            let start = self.offset;
+            while let Some(byte) = self.peek() {
+                if byte == b'"' || byte == b'\\' || byte < 0x20 {
+                    break;
+                }
+                self.offset += 1;
+            }
+            string
+                .push_str(str::from_utf8(&self.json[start..self.offset]).expect("input is a str"));
+
+            match self.peek() {
+                Some(b'"') => {
+                    self.offset += 1;
+                    return Ok(string);
+                },
+                Some(b'\\') => {
+                    self.offset += 1;
+                    let escaped = match self.peek() {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            string.push(self.unicode_escape()?);
+                            continue;
+                        },
+                        _ => return Err(self.syntax("an escape sequence")),
+                    };
+                    self.offset += 1;
+                    string.push(escaped);
+                },
+                _ => return Err(self.syntax("`\"`")),
+            }
+        }
+    }
+
+    /// Parse the rest of a `\u` escape, including the second half of surrogate pairs
+    fn unicode_escape(&mut self) -> Result<char, Error> {
+        let high = self.hex_quad()?;
+        let code = match high {
+            0xd800..=0xdbff => {
+                if !(self.eat(b'\\') && self.peek() == Some(b'u')) {
+                    return Err(self.syntax("the second half of a surrogate pair"));
+                }
+                let low = self.hex_quad()?;
+                if !(0xdc00..=0xdfff).contains(&low) {
+                    return Err(self.syntax("the second half of a surrogate pair"));
+                }
+                0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+            },
+            _ => high,
+        };
+        core::char::from_u32(code).ok_or_else(|| self.syntax("a valid code point"))
+    }
+
+    /// Parse the `u` and four hex digits of a `\u` escape
+    fn hex_quad(&mut self) -> Result<u32, Error> {
+        self.offset += 1;
+        let digits = self
+            .json
+            .get(self.offset..self.offset + 4)
+            .and_then(|digits| str::from_utf8(digits).ok())
+            .filter(|digits| digits.bytes().all(|digit| digit.is_ascii_hexdigit()))
+            .ok_or_else(|| self.syntax("four hex digits"))?;
+        self.offset += 4;
+        Ok(u32::from_str_radix(digits, 16).expect("hex digits"))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.offset += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.json.get(self.offset).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let matches = self.peek() == Some(byte);
+        if matches {
+            self.offset += 1;
+        }
+        matches
+    }
+
+    fn syntax(&self, expected: &'static str) -> Error {
+        Error::Syntax {
+            offset: self.offset,
+            expected,
+        }
+    }
+
+    fn unrepresentable(&self, kind: &'static str) -> Error {
+        Error::Unrepresentable {
+            offset: self.offset,
+            kind,
+        }
+    }
+}
+
+/// The state of a list or dict being converted
+enum Container {
+    List {
+        first: bool,
+    },
+    /// A dict expecting a key
+    Key {
+        first: bool,
+    },
+    /// A dict expecting the value of the last key
+    Value,
+}
+
+/// The token stream with a small buffer to look ahead into dicts
+struct Lookahead<'a> {
+    tokens: Tokens<'a>,
+    buffer: VecDeque<Token<'a>>,
+}
+
+impl<'a> Lookahead<'a> {
+    fn next(&mut self) -> Result<Option<Token<'a>>, Error> {
+        match self.buffer.pop_front() {
+            Some(token) => Ok(Some(token)),
+            None => Ok(self.tokens.next().transpose()?),
+        }
+    }
+
+    /// Fail if the dict just started only consists of a marker key and a string
+    fn reject_marker_dict(&mut self) -> Result<(), Error> {
+        while self.buffer.len() < 3 {
+            match self.tokens.next().transpose()? {
+                Some(token) => self.buffer.push_back(token),
+                None => break,
+            }
+        }
+
+        let buffer = &self.buffer;
+        match (buffer.front(), buffer.get(1), buffer.get(2)) {
+            (Some(Token::String(key)), Some(Token::String(_)), Some(Token::End))
+                if MARKERS.contains(key) =>
+            {
+                Err(Error::AmbiguousDict { key: key.to_vec() })
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn empty_keys_should_round_trip() {
+        let bencode = from_json(r#"{"":1}"#).unwrap();
+        assert_eq!(bencode, b"d0:i1ee");
+        assert_eq!(to_json(&bencode).unwrap(), r#"{"":1}"#);
+    }
+
+    #[test]
+    fn values_should_convert_to_json() {
+        assert_eq!(to_json(b"i-12e").unwrap(), "-12");
+        assert_eq!(to_json(b"le").unwrap(), "[]");
+        assert_eq!(to_json(b"de").unwrap(), "{}");
+        assert_eq!(
+            to_json(b"d1:ali1eli2ee0:e1:bd1:c1:\"ee").unwrap(),
+            r#"{"a":[1,[2],""],"b":{"c":"\""}}"#
+        );
+        assert_eq!(to_json(b"3:\x01\n\\").unwrap(), r#""\u0001\n\\""#);
+    }
+
+    #[test]
+    fn binary_strings_should_be_marked() {
+        let converter = JsonConverter::new().with_binary(BinaryEncoding::Base64);
+        assert_eq!(to_json(b"2:\xff\x00").unwrap(), r#"{"$hex":"ff00"}"#);
+        assert_eq!(
+            converter
+                .to_string(b"l1:\xff2:\xff\x003:\xff\x00\x01e")
+                .unwrap(),
+            r#"[{"$base64":"/w=="},{"$base64":"/wA="},{"$base64":"/wAB"}]"#
+        );
+    }
+
+    #[test]
+    fn unrepresentable_input_should_fail() {
+        assert!(matches!(
+            to_json(b"d4:$hex2:00e"),
+            Err(Error::AmbiguousDict { .. })
+        ));
+        assert_eq!(
+            to_json(b"d4:$hex2:005:other0:e").unwrap(),
+            r#"{"$hex":"00","other":""}"#
+        );
+        assert!(matches!(
+            to_json(b"d1:\xffi1ee"),
+            Err(Error::NonUtf8Key { .. })
+        ));
+        assert!(matches!(to_json(b"i1ei2e"), Err(Error::TrailingBytes)));
+        assert!(matches!(to_json(b"li1e"), Err(Error::Decode { .. })));
+        assert!(matches!(
+            to_json(b"d1:bi1e1:ai2ee"),
+            Err(Error::Decode { .. })
+        ));
+    }
+
+    #[test]
+    fn json_should_convert_to_canonical_bencode() {
+        let json = r#" { "b" : [ -0 , 12, "é😀\n" ], "a": {}, "": [] } "#;
+        assert_eq!(
+            from_json(json).unwrap(),
+            "d0:le1:ade1:bli0ei12e7:\u{e9}\u{1f600}\nee".as_bytes()
+        );
+        assert_eq!(
+            from_json(r#"[{"$hex":"00FF"},{"$base64":"/wA"},{"$hex":1}]"#).unwrap(),
+            b"l2:\x00\xff2:\xff\x00d4:$hexi1eee"
+        );
+
+        let bencode = b"d1:ad1:b2:\xff\x00ee";
+        let converter = JsonConverter::new().with_binary(BinaryEncoding::Base64);
+        let json = converter.to_string(bencode).unwrap();
+        assert_eq!(converter.to_bencode(&json).unwrap(), bencode);
+    }
+
+    #[test]
+    fn unconvertible_json_should_fail() {
+        let error = |json| from_json(json).unwrap_err();
+        assert!(matches!(
+            error("1.5"),
+            Error::Unrepresentable { offset: 0, .. }
+        ));
+        assert!(matches!(
+            error("[true]"),
+            Error::Unrepresentable { offset: 1, .. }
+        ));
+        assert!(matches!(error("[null]"), Error::Unrepresentable { .. }));
+        assert!(matches!(
+            error(r#"{"a":1,"a":2}"#),
+            Error::DuplicateKey { .. }
+        ));
+        assert!(matches!(
+            error(r#"{"$hex":"0"}"#),
+            Error::InvalidBinary { .. }
+        ));
+        assert!(matches!(error("[1,]"), Error::Syntax { offset: 3, .. }));
+        assert!(matches!(error("01"), Error::Syntax { offset: 1, .. }));
+        assert!(matches!(error(r#""\ud83d""#), Error::Syntax { .. }));
+        assert!(matches!(
+            JsonConverter::new().with_max_depth(2).to_bencode("[[[]]]"),
+            Err(Error::NestingTooDeep { max_depth: 2 })
+        ));
+    }
+}
@@ -0,0 +1,54 @@
+//! Types for the KRPC protocol used by the BitTorrent DHT ([BEP 5]).
+//!
+//! KRPC messages are bencoded dictionaries sent over UDP. Every message carries a
+//! transaction ID (`t`) and a message type (`y`), which is either a query (`q`), a
+//! response (`r`) or an error (`e`).
+//!
+//! Queries may carry an `ro` flag marking the sender as a read-only node ([BEP 43]), see
+//! [`NodeMode`].
+//!
+//! `find_node` and `get_peers` queries may ask for IPv4 and IPv6 nodes with `want`
+//! ([BEP 32]); these types need the `std` feature for their socket addresses.
+//!
+//! [`Query`], [`Response`] and [`ErrorResponse`] wrap arguments and return values of
+//! any type. The [`Message`] enum decodes any message of the methods defined by BEP 5
+//! (`ping`, `find_node`, `get_peers` and `announce_peer`) into typed arguments and
+//! return values, and needs the `std` feature as well.
+//!
+//! The signatures of mutable items stored in the DHT ([BEP 44]) cover a canonical payload
+//! built by [`mutable_item_payload`].
+//!
+//! [BEP 5]: http://bittorrent.org/beps/bep_0005.html
+//! [BEP 32]: http://bittorrent.org/beps/bep_0032.html
+//! [BEP 43]: http://bittorrent.org/beps/bep_0043.html
+//! [BEP 44]: http://bittorrent.org/beps/bep_0044.html
+
+mod error;
+mod item;
+#[cfg(feature = "std")]
+mod message;
+mod node_id;
+#[cfg(feature = "std")]
+mod nodes;
+mod query;
+pub mod replay;
+mod response;
+#[cfg(feature = "std")]
+mod token;
+
+#[cfg(feature = "std")]
+pub use self::{
+    message::{AnnouncePeer, Message, Ping, QueryArguments, ResponseValues},
+    nodes::{
+        FindNode, GetPeers, NodeInfo, NodesResponse, NodesResponseBuilder, PeersResponse, Want,
+    },
+    token::{TokenIssuer, TokenMac},
+};
+
+pub use self::{
+    error::{ErrorCode, ErrorResponse, KrpcError},
+    item::{mutable_item_payload, MAX_ITEM_SALT_LEN, MAX_ITEM_VALUE_LEN},
+    node_id::NodeId,
+    query::{NodeMode, Query},
+    response::Response,
+};
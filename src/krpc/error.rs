@@ -0,0 +1,364 @@
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{self, Display, Formatter};
+
+use crate::{
+    decoding::{self, FromBencode, Object, ResultExt},
+    encoding::{self, AsString, SingleItemEncoder, ToBencode},
+};
+
+/// The error codes defined by BEP 5, plus any custom code a node may send.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// `201`: A generic error
+    Generic,
+    /// `202`: An error on the side of the responding node
+    Server,
+    /// `203`: A malformed packet, invalid arguments or a bad token
+    Protocol,
+    /// `204`: The queried method is unknown
+    MethodUnknown,
+    /// Any code not covered by the registry
+    Other(i64),
+}
+
+impl ErrorCode {
+    /// The numeric value of the code as it appears on the wire.
+    pub fn code(self) -> i64 {
+        match self {
+            ErrorCode::Generic => 201,
+            ErrorCode::Server => 202,
+            ErrorCode::Protocol => 203,
+            ErrorCode::MethodUnknown => 204,
+            ErrorCode::Other(code) => code,
+        }
+    }
+}
+
+impl From<i64> for ErrorCode {
+    fn from(code: i64) -> Self {
+        match code {
+            201 => ErrorCode::Generic,
+            202 => ErrorCode::Server,
+            203 => ErrorCode::Protocol,
+            204 => ErrorCode::MethodUnknown,
+            other => ErrorCode::Other(other),
+        }
+    }
+}
+
+/// Every decoding error of a received packet is the fault of the sender, so all of them
+/// map to a `203 Protocol Error`.
+impl From<&decoding::Error> for ErrorCode {
+    fn from(_: &decoding::Error) -> Self {
+        ErrorCode::Protocol
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            ErrorCode::Generic => "generic error",
+            ErrorCode::Server => "server error",
+            ErrorCode::Protocol => "protocol error",
+            ErrorCode::MethodUnknown => "method unknown",
+            ErrorCode::Other(_) => "error",
+        };
+        write!(f, "{} {}", self.code(), name)
+    }
+}
+
+/// The payload of a KRPC error message, encoded as the list `[code, message]`.
+///
+/// ```
+/// use bendy::{encoding::ToBencode, krpc::KrpcError};
+///
+/// let error = KrpcError::protocol("bad token");
+/// assert_eq!(error.to_bencode().unwrap(), b"li203e9:bad tokene");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KrpcError {
+    /// The error code
+    pub code: ErrorCode,
+    /// A human readable description of the error
+    pub message: String,
+}
+
+impl KrpcError {
+    /// Create an error with an arbitrary code.
+    pub fn new(code: impl Into<ErrorCode>, message: impl Display) -> Self {
+        KrpcError {
+            code: code.into(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Create a `201 Generic Error`.
+    pub fn generic(message: impl Display) -> Self {
+        Self::new(ErrorCode::Generic, message)
+    }
+
+    /// Create a `202 Server Error`.
+    pub fn server(message: impl Display) -> Self {
+        Self::new(ErrorCode::Server, message)
+    }
+
+    /// Create a `203 Protocol Error`.
+    pub fn protocol(message: impl Display) -> Self {
+        Self::new(ErrorCode::Protocol, message)
+    }
+
+    /// Create a `204 Method Unknown` error.
+    pub fn method_unknown(message: impl Display) -> Self {
+        Self::new(ErrorCode::MethodUnknown, message)
+    }
+}
+
+/// The `203 Protocol Error` to answer a packet with that failed to decode.
+///
+/// The message only describes the kind of error, like `malformed packet` or
+/// `packet too large`, so that no details of the input are echoed back to the sender:
+///
+/// ```
+/// use bendy::{
+///     decoding::FromBencode,
+///     krpc::{ErrorCode, KrpcError},
+/// };
+///
+/// let error = KrpcError::from_bencode(b"li203e9:bad").unwrap_err();
+/// let response = KrpcError::from(&error);
+/// assert_eq!(response.code, ErrorCode::Protocol);
+/// assert_eq!(response.message, "malformed packet");
+/// ```
+impl From<&decoding::Error> for KrpcError {
+    fn from(error: &decoding::Error) -> Self {
+        let message = match error.code() {
+            "decode.limit_exceeded" => "packet too large",
+            "structure.nesting_too_deep" => "packet nested too deeply",
+            code if code.starts_with("structure.") => "malformed packet",
+            _ => "invalid arguments",
+        };
+        KrpcError::new(ErrorCode::from(error), message)
+    }
+}
+
+impl Display for KrpcError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for KrpcError {}
+
+impl ToBencode for KrpcError {
+    const MAX_DEPTH: usize = 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_list(|e| {
+            e.emit_int(self.code.code())?;
+            e.emit_str(&self.message)
+        })
+    }
+}
+
+impl FromBencode for KrpcError {
+    const EXPECTED_RECURSION_DEPTH: usize = 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut list = object.try_into_list()?;
+
+        let code = list
+            .next_object()?
+            .ok_or_else(|| decoding::Error::missing_field("code"))?;
+        let code = i64::decode_bencode_object(code).context("code")?;
+
+        let message = list
+            .next_object()?
+            .ok_or_else(|| decoding::Error::missing_field("message"))?;
+        let message = String::decode_bencode_object(message).context("message")?;
+
+        if list.next_object()?.is_some() {
+            return Err(decoding::Error::unexpected_field(
+                "list element after message",
+            ));
+        }
+
+        Ok(KrpcError::new(code, message))
+    }
+}
+
+/// A complete KRPC error message (`"y": "e"`).
+///
+/// ```
+/// use bendy::{
+///     decoding::FromBencode,
+///     encoding::ToBencode,
+///     krpc::{ErrorResponse, KrpcError},
+/// };
+///
+/// let response = ErrorResponse::new(
+///     b"aa".to_vec(),
+///     KrpcError::generic("A Generic Error Ocurred"),
+/// );
+/// let encoded = response.to_bencode().unwrap();
+/// assert_eq!(
+///     encoded,
+///     &b"d1:eli201e23:A Generic Error Ocurrede1:t2:aa1:y1:ee"[..]
+/// );
+///
+/// assert_eq!(ErrorResponse::from_bencode(&encoded).unwrap(), response);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorResponse {
+    /// The transaction ID of the query this error answers
+    pub transaction_id: Vec<u8>,
+    /// The error payload
+    pub error: KrpcError,
+}
+
+impl ErrorResponse {
+    /// Create an error message answering the query with the given transaction ID.
+    pub fn new(transaction_id: impl Into<Vec<u8>>, error: KrpcError) -> Self {
+        ErrorResponse {
+            transaction_id: transaction_id.into(),
+            error,
+        }
+    }
+}
+
+impl ToBencode for ErrorResponse {
+    const MAX_DEPTH: usize = KrpcError::MAX_DEPTH + 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair(b"e", &self.error)?;
+            e.emit_pair(b"t", AsString(&self.transaction_id))?;
+            e.emit_pair(b"y", "e")
+        })
+    }
+}
+
+impl FromBencode for ErrorResponse {
+    const EXPECTED_RECURSION_DEPTH: usize = KrpcError::EXPECTED_RECURSION_DEPTH + 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut error = None;
+        let mut transaction_id = None;
+        let mut message_type = None;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"e", value) => {
+                    error = KrpcError::decode_bencode_object(value)
+                        .context("e")
+                        .map(Some)?;
+                },
+                (b"t", value) => {
+                    transaction_id = AsString::decode_bencode_object(value)
+                        .context("t")
+                        .map(|bytes| Some(bytes.0))?;
+                },
+                (b"y", value) => {
+                    message_type = value.try_into_bytes().context("y").map(Some)?;
+                },
+                _ => (), // ignore unknown keys, e.g. the client version
+            }
+        }
+
+        match message_type {
+            Some(b"e") => (),
+            Some(other) => {
+                return Err(decoding::Error::unexpected_token(
+                    "message type \"e\"",
+                    String::from_utf8_lossy(other),
+                ))
+            },
+            None => return Err(decoding::Error::missing_field("y")),
+        }
+
+        Ok(ErrorResponse {
+            transaction_id: transaction_id.ok_or_else(|| decoding::Error::missing_field("t"))?,
+            error: error.ok_or_else(|| decoding::Error::missing_field("e"))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn registry_codes_round_trip() {
+        for code in 201..=204 {
+            assert_eq!(ErrorCode::from(code).code(), code);
+            assert!(!matches!(ErrorCode::from(code), ErrorCode::Other(_)));
+        }
+        assert_eq!(ErrorCode::from(301), ErrorCode::Other(301));
+    }
+
+    #[test]
+    fn decoding_errors_should_map_to_protocol_errors() {
+        use crate::decoding::DecodeOptions;
+
+        let options = DecodeOptions::new().with_max_string_len(4);
+        let response = |packet: &[u8]| {
+            let error = options.from_bencode::<KrpcError>(packet).unwrap_err();
+            KrpcError::from(&error)
+        };
+
+        assert_eq!(
+            response(b"li203e5:helloe"),
+            KrpcError::protocol("packet too large")
+        );
+        assert_eq!(
+            response(b"li203ee"),
+            KrpcError::protocol("invalid arguments")
+        );
+        assert_eq!(
+            response(b"lli1eee"),
+            KrpcError::protocol("packet nested too deeply")
+        );
+        assert_eq!(
+            response(b"li203ex"),
+            KrpcError::protocol("malformed packet")
+        );
+    }
+
+    #[test]
+    fn error_payload_round_trips() {
+        let error = KrpcError::method_unknown("no such method");
+        let encoded = error.to_bencode().unwrap();
+        assert_eq!(encoded, b"li204e14:no such methode");
+        assert_eq!(KrpcError::from_bencode(&encoded).unwrap(), error);
+    }
+
+    #[test]
+    fn custom_codes_are_preserved() {
+        let error = KrpcError::from_bencode(b"li405e4:nopee").unwrap();
+        assert_eq!(error.code, ErrorCode::Other(405));
+        assert_eq!(error.message, "nope");
+    }
+
+    #[test]
+    fn error_payload_must_be_complete() {
+        assert!(KrpcError::from_bencode(b"li201ee").is_err());
+        assert!(KrpcError::from_bencode(b"li201e1:a1:be").is_err());
+    }
+
+    #[test]
+    fn error_response_requires_error_type() {
+        let result = ErrorResponse::from_bencode(b"d1:eli201e1:ae1:t2:aa1:y1:re");
+        assert!(result.is_err());
+    }
+}
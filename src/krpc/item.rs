@@ -0,0 +1,150 @@
+use alloc::format;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+
+use crate::decoding::{self, ResultExt};
+
+/// The maximum length of the bencoded value of a DHT item ([BEP 44]).
+///
+/// [BEP 44]: http://bittorrent.org/beps/bep_0044.html
+pub const MAX_ITEM_VALUE_LEN: usize = 1000;
+
+/// The maximum length of the salt of a mutable DHT item ([BEP 44]).
+///
+/// [BEP 44]: http://bittorrent.org/beps/bep_0044.html
+pub const MAX_ITEM_SALT_LEN: usize = 64;
+
+/// The bytes that are signed for a mutable DHT item ([BEP 44]).
+///
+/// The signature covers the `salt`, `seq` and `v` pairs of a dict, concatenated in key order
+/// but without the surrounding `d` and `e`. `value` is the bencoded value of the item, which
+/// is copied verbatim, so it has to be a single canonical bencode value: anything else
+/// fails, as other clients would compute a different payload. An empty salt is treated like
+/// no salt at all.
+///
+/// ```
+/// use bendy::{encoding::ToBencode, krpc::mutable_item_payload};
+///
+/// let value = "Hello World!".to_bencode().unwrap();
+///
+/// assert_eq!(
+///     mutable_item_payload(None, 1, &value).unwrap(),
+///     &b"3:seqi1e1:v12:Hello World!"[..]
+/// );
+/// assert_eq!(
+///     mutable_item_payload(Some(b"foobar"), 1, &value).unwrap(),
+///     &b"4:salt6:foobar3:seqi1e1:v12:Hello World!"[..]
+/// );
+///
+/// assert!(mutable_item_payload(None, 1, b"i01e").is_err());
+/// ```
+///
+/// The payload is meant to be signed with ed25519, e.g. by a
+/// [`Signer`](crate::signing::Signer).
+///
+/// [BEP 44]: http://bittorrent.org/beps/bep_0044.html
+pub fn mutable_item_payload(
+    salt: Option<&[u8]>,
+    seq: i64,
+    value: &[u8],
+) -> Result<Vec<u8>, decoding::Error> {
+    if value.len() > MAX_ITEM_VALUE_LEN {
+        return Err(decoding::Error::malformed_content(
+            InvalidItem::ValueLength(value.len()),
+        ))
+        .context("v");
+    }
+    if decoding::is_valid(value).context("v")? != value.len() {
+        return Err(decoding::Error::malformed_content(
+            InvalidItem::TrailingBytes,
+        ))
+        .context("v");
+    }
+
+    let mut payload = Vec::new();
+    if let Some(salt) = salt.filter(|salt| !salt.is_empty()) {
+        if salt.len() > MAX_ITEM_SALT_LEN {
+            return Err(decoding::Error::malformed_content(InvalidItem::SaltLength(
+                salt.len(),
+            )))
+            .context("salt");
+        }
+        payload.extend_from_slice(format!("4:salt{}:", salt.len()).as_bytes());
+        payload.extend_from_slice(salt);
+    }
+    payload.extend_from_slice(format!("3:seqi{}e1:v", seq).as_bytes());
+    payload.extend_from_slice(value);
+    Ok(payload)
+}
+
+/// The parts of a mutable item that can't be signed
+#[derive(Debug)]
+enum InvalidItem {
+    ValueLength(usize),
+    SaltLength(usize),
+    TrailingBytes,
+}
+
+impl Display for InvalidItem {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            InvalidItem::ValueLength(len) => write!(
+                f,
+                "value of {} bytes is longer than {} bytes",
+                len, MAX_ITEM_VALUE_LEN
+            ),
+            InvalidItem::SaltLength(len) => write!(
+                f,
+                "salt of {} bytes is longer than {} bytes",
+                len, MAX_ITEM_SALT_LEN
+            ),
+            InvalidItem::TrailingBytes => write!(f, "value isn't a single bencode value"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidItem {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn payloads_should_follow_the_test_vectors() {
+        let value = b"12:Hello World!";
+        assert_eq!(
+            mutable_item_payload(Some(b""), -1, value).unwrap(),
+            &b"3:seqi-1e1:v12:Hello World!"[..]
+        );
+        assert_eq!(
+            mutable_item_payload(None, 0, b"d1:ai1e1:bli2eee").unwrap(),
+            &b"3:seqi0e1:vd1:ai1e1:bli2eee"[..]
+        );
+    }
+
+    #[test]
+    fn invalid_items_should_fail() {
+        let error = mutable_item_payload(None, 1, b"d1:bi1e1:ai2ee").unwrap_err();
+        assert_eq!(error.path(), Some("v"));
+        assert_eq!(error.code(), "structure.unsorted_keys");
+
+        let error = mutable_item_payload(None, 1, b"i1ei2e").unwrap_err();
+        assert_eq!(error.code(), "decode.malformed_content");
+
+        let string = |len: usize| {
+            let mut value = format!("{}:", len).into_bytes();
+            value.resize(value.len() + len, b'x');
+            value
+        };
+        assert_eq!(string(996).len(), MAX_ITEM_VALUE_LEN);
+        assert!(mutable_item_payload(None, 1, &string(996)).is_ok());
+        let error = mutable_item_payload(None, 1, &string(997)).unwrap_err();
+        assert_eq!(error.path(), Some("v"));
+
+        let error = mutable_item_payload(Some(&[0; 65]), 1, b"0:").unwrap_err();
+        assert_eq!(error.path(), Some("salt"));
+        assert!(mutable_item_payload(Some(&[0; 64]), 1, b"0:").is_ok());
+    }
+}
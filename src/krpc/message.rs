@@ -0,0 +1,524 @@
+use crate::{
+    decoding::{self, Decoder, FromBencode, Object, ResultExt},
+    encoding::{self, AsString, SingleItemEncoder, ToBencode},
+    krpc::{
+        ErrorResponse, FindNode, GetPeers, NodeId, NodesResponse, PeersResponse, Query, Response,
+    },
+    value::Value,
+};
+
+/// The arguments of a `ping` query, which are also the return values of `ping` and
+/// `announce_peer` queries: the ID of the sending node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Ping {
+    /// The ID of the sending node (`id`)
+    pub id: NodeId,
+}
+
+impl Ping {
+    /// Create the arguments or return values of the node with the given ID.
+    pub fn new(id: NodeId) -> Self {
+        Ping { id }
+    }
+}
+
+impl ToBencode for Ping {
+    const MAX_DEPTH: usize = 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| e.emit_pair(b"id", self.id))
+    }
+}
+
+impl FromBencode for Ping {
+    const EXPECTED_RECURSION_DEPTH: usize = 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut id = None;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            if let (b"id", value) = pair {
+                id = NodeId::decode_bencode_object(value)
+                    .context("id")
+                    .map(Some)?;
+            }
+        }
+
+        Ok(Ping {
+            id: id.ok_or_else(|| decoding::Error::missing_field("id"))?,
+        })
+    }
+}
+
+/// The arguments of an `announce_peer` query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnnouncePeer {
+    /// The ID of the querying node (`id`)
+    pub id: NodeId,
+    /// Whether the source port of the query should be used instead of `port`
+    /// (`implied_port`)
+    pub implied_port: bool,
+    /// The info hash of the torrent the querying node is downloading (`info_hash`)
+    pub info_hash: [u8; 20],
+    /// The port the querying node is downloading on (`port`)
+    pub port: u16,
+    /// The token of an earlier `get_peers` response (`token`)
+    pub token: Vec<u8>,
+}
+
+impl AnnouncePeer {
+    /// The port of the announced peer, given the source port of the query.
+    pub fn peer_port(&self, source_port: u16) -> u16 {
+        if self.implied_port {
+            source_port
+        } else {
+            self.port
+        }
+    }
+}
+
+impl ToBencode for AnnouncePeer {
+    const MAX_DEPTH: usize = 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair(b"id", self.id)?;
+            if self.implied_port {
+                e.emit_pair(b"implied_port", 1)?;
+            }
+            e.emit_pair(b"info_hash", AsString(&self.info_hash))?;
+            e.emit_pair(b"port", self.port)?;
+            e.emit_pair(b"token", AsString(&self.token))
+        })
+    }
+}
+
+impl FromBencode for AnnouncePeer {
+    const EXPECTED_RECURSION_DEPTH: usize = 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut id = None;
+        let mut implied_port = false;
+        let mut info_hash = None;
+        let mut port = None;
+        let mut token = None;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"id", value) => {
+                    id = NodeId::decode_bencode_object(value)
+                        .context("id")
+                        .map(Some)?;
+                },
+                (b"implied_port", value) => {
+                    implied_port = i64::decode_bencode_object(value).context("implied_port")? != 0;
+                },
+                (b"info_hash", value) => {
+                    info_hash = NodeId::decode_bencode_object(value)
+                        .context("info_hash")
+                        .map(|hash| Some(hash.0))?;
+                },
+                (b"port", value) => {
+                    port = u16::decode_bencode_object(value)
+                        .context("port")
+                        .map(Some)?;
+                },
+                (b"token", value) => {
+                    token = AsString::decode_bencode_object(value)
+                        .context("token")
+                        .map(|bytes| Some(bytes.0))?;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        // The port is irrelevant with an implied port, but still required by BEP 5
+        Ok(AnnouncePeer {
+            id: id.ok_or_else(|| decoding::Error::missing_field("id"))?,
+            implied_port,
+            info_hash: info_hash.ok_or_else(|| decoding::Error::missing_field("info_hash"))?,
+            port: port.ok_or_else(|| decoding::Error::missing_field("port"))?,
+            token: token.ok_or_else(|| decoding::Error::missing_field("token"))?,
+        })
+    }
+}
+
+/// The arguments of the queries defined by BEP 5.
+///
+/// The arguments can't be decoded on their own, since their type depends on the method
+/// of the query. Queries are decoded as part of a [`Message`] instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueryArguments {
+    /// A `ping` query
+    Ping(Ping),
+    /// A `find_node` query
+    FindNode(FindNode),
+    /// A `get_peers` query
+    GetPeers(GetPeers),
+    /// An `announce_peer` query
+    AnnouncePeer(AnnouncePeer),
+}
+
+impl QueryArguments {
+    /// The name of the queried method.
+    pub fn method(&self) -> &'static str {
+        match self {
+            QueryArguments::Ping(_) => "ping",
+            QueryArguments::FindNode(_) => "find_node",
+            QueryArguments::GetPeers(_) => "get_peers",
+            QueryArguments::AnnouncePeer(_) => "announce_peer",
+        }
+    }
+
+    /// The ID of the querying node.
+    pub fn id(&self) -> NodeId {
+        match self {
+            QueryArguments::Ping(arguments) => arguments.id,
+            QueryArguments::FindNode(arguments) => arguments.id,
+            QueryArguments::GetPeers(arguments) => arguments.id,
+            QueryArguments::AnnouncePeer(arguments) => arguments.id,
+        }
+    }
+
+    /// Create a query of the method matching the arguments.
+    pub fn into_query(self, transaction_id: impl Into<Vec<u8>>) -> Query<Self> {
+        Query::new(transaction_id, self.method(), self)
+    }
+}
+
+impl From<Ping> for QueryArguments {
+    fn from(arguments: Ping) -> Self {
+        QueryArguments::Ping(arguments)
+    }
+}
+
+impl From<FindNode> for QueryArguments {
+    fn from(arguments: FindNode) -> Self {
+        QueryArguments::FindNode(arguments)
+    }
+}
+
+impl From<GetPeers> for QueryArguments {
+    fn from(arguments: GetPeers) -> Self {
+        QueryArguments::GetPeers(arguments)
+    }
+}
+
+impl From<AnnouncePeer> for QueryArguments {
+    fn from(arguments: AnnouncePeer) -> Self {
+        QueryArguments::AnnouncePeer(arguments)
+    }
+}
+
+impl ToBencode for QueryArguments {
+    const MAX_DEPTH: usize = FindNode::MAX_DEPTH;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        match self {
+            QueryArguments::Ping(arguments) => arguments.encode(encoder),
+            QueryArguments::FindNode(arguments) => arguments.encode(encoder),
+            QueryArguments::GetPeers(arguments) => arguments.encode(encoder),
+            QueryArguments::AnnouncePeer(arguments) => arguments.encode(encoder),
+        }
+    }
+}
+
+/// The return values of a response to one of the queries defined by BEP 5.
+///
+/// Responses don't name the method they answer, so the kind of return values is derived
+/// from the keys: responses with a `token` answer `get_peers`, responses with `nodes` or
+/// `nodes6` answer `find_node` and all others only carry the ID of the responding node.
+/// Nodes that need to be strict about this should look up the query by the transaction
+/// ID and decode a [`Response`] of the expected type instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResponseValues {
+    /// The response to a `ping` or `announce_peer` query
+    Ping(Ping),
+    /// The response to a `find_node` query
+    Nodes(NodesResponse),
+    /// The response to a `get_peers` query
+    Peers(PeersResponse),
+}
+
+impl ResponseValues {
+    /// The ID of the responding node.
+    pub fn id(&self) -> NodeId {
+        match self {
+            ResponseValues::Ping(values) => values.id,
+            ResponseValues::Nodes(values) => values.id(),
+            ResponseValues::Peers(values) => values.id(),
+        }
+    }
+}
+
+impl ToBencode for ResponseValues {
+    const MAX_DEPTH: usize = PeersResponse::MAX_DEPTH;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        match self {
+            ResponseValues::Ping(values) => values.encode(encoder),
+            ResponseValues::Nodes(values) => values.encode(encoder),
+            ResponseValues::Peers(values) => values.encode(encoder),
+        }
+    }
+}
+
+impl FromBencode for ResponseValues {
+    const EXPECTED_RECURSION_DEPTH: usize = PeersResponse::EXPECTED_RECURSION_DEPTH;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let raw = object.try_into_dictionary()?.into_raw()?;
+
+        if find_string(raw, b"token")?.is_some() {
+            PeersResponse::from_bencode(raw).map(ResponseValues::Peers)
+        } else if find_string(raw, b"nodes")?.is_some() || find_string(raw, b"nodes6")?.is_some() {
+            NodesResponse::from_bencode(raw).map(ResponseValues::Nodes)
+        } else {
+            Ping::from_bencode(raw).map(ResponseValues::Ping)
+        }
+    }
+}
+
+/// Any KRPC message, dispatched on its message type (`y`) and, for queries, the method
+/// (`q`).
+///
+/// ```
+/// use bendy::{
+///     decoding::FromBencode,
+///     encoding::ToBencode,
+///     krpc::{Message, NodeId, Ping, QueryArguments},
+/// };
+///
+/// let query =
+///     QueryArguments::from(Ping::new(NodeId(*b"abcdefghij0123456789"))).into_query(*b"aa");
+/// let encoded = query.to_bencode().unwrap();
+/// assert_eq!(
+///     encoded,
+///     &b"d1:ad2:id20:abcdefghij0123456789e1:q4:ping1:t2:aa1:y1:qe"[..]
+/// );
+///
+/// match Message::from_bencode(&encoded).unwrap() {
+///     Message::Query(query) => assert_eq!(query.arguments.method(), "ping"),
+///     _ => panic!("expected a query"),
+/// }
+/// ```
+///
+/// Queries of other methods are kept with their raw arguments, so that a node can answer
+/// them with a `204 Method Unknown` error. Their arguments may be nested at most one level
+/// deeper than those of `find_node`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    /// A query of one of the methods defined by BEP 5 (`"y": "q"`)
+    Query(Query<QueryArguments>),
+    /// A query of any other method (`"y": "q"`)
+    UnknownQuery(Query<Value<'static>>),
+    /// A response (`"y": "r"`)
+    Response(Response<ResponseValues>),
+    /// An error (`"y": "e"`)
+    Error(ErrorResponse),
+}
+
+impl Message {
+    /// The transaction ID of the message.
+    pub fn transaction_id(&self) -> &[u8] {
+        match self {
+            Message::Query(query) => &query.transaction_id,
+            Message::UnknownQuery(query) => &query.transaction_id,
+            Message::Response(response) => &response.transaction_id,
+            Message::Error(error) => &error.transaction_id,
+        }
+    }
+}
+
+impl From<Query<QueryArguments>> for Message {
+    fn from(query: Query<QueryArguments>) -> Self {
+        Message::Query(query)
+    }
+}
+
+impl From<Response<ResponseValues>> for Message {
+    fn from(response: Response<ResponseValues>) -> Self {
+        Message::Response(response)
+    }
+}
+
+impl From<ErrorResponse> for Message {
+    fn from(error: ErrorResponse) -> Self {
+        Message::Error(error)
+    }
+}
+
+impl ToBencode for Message {
+    const MAX_DEPTH: usize = Query::<Value>::MAX_DEPTH;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        match self {
+            Message::Query(query) => query.encode(encoder),
+            Message::UnknownQuery(query) => query.encode(encoder),
+            Message::Response(response) => response.encode(encoder),
+            Message::Error(error) => error.encode(encoder),
+        }
+    }
+}
+
+impl FromBencode for Message {
+    const EXPECTED_RECURSION_DEPTH: usize = Query::<FindNode>::EXPECTED_RECURSION_DEPTH + 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let raw = object.try_into_dictionary()?.into_raw()?;
+
+        match find_string(raw, b"y")? {
+            Some(b"q") => (),
+            Some(b"r") => return Response::from_bencode(raw).map(Message::Response),
+            Some(b"e") => return ErrorResponse::from_bencode(raw).map(Message::Error),
+            Some(other) => {
+                return Err(decoding::Error::unexpected_token(
+                    "message type \"q\", \"r\" or \"e\"",
+                    String::from_utf8_lossy(other),
+                ))
+                .context("y")
+            },
+            None => return Err(decoding::Error::missing_field("y")),
+        }
+
+        let query = match find_string(raw, b"q")? {
+            Some(b"ping") => Query::<Ping>::from_bencode(raw).map(|query| query.map(From::from)),
+            Some(b"find_node") => {
+                Query::<FindNode>::from_bencode(raw).map(|query| query.map(From::from))
+            },
+            Some(b"get_peers") => {
+                Query::<GetPeers>::from_bencode(raw).map(|query| query.map(From::from))
+            },
+            Some(b"announce_peer") => {
+                Query::<AnnouncePeer>::from_bencode(raw).map(|query| query.map(From::from))
+            },
+            _ => return Query::from_bencode(raw).map(Message::UnknownQuery),
+        };
+        query.map(Message::Query)
+    }
+}
+
+/// The value of the key `name` of the dict in `raw`, which has to be a string if present
+fn find_string<'a>(raw: &'a [u8], name: &[u8]) -> Result<Option<&'a [u8]>, decoding::Error> {
+    let mut decoder = Decoder::new(raw);
+    let mut dict = match decoder.next_object()? {
+        Some(object) => object.try_into_dictionary()?,
+        None => return Ok(None),
+    };
+
+    while let Some((key, value)) = dict.next_pair()? {
+        if key == name {
+            return value
+                .try_into_bytes()
+                .context(String::from_utf8_lossy(name))
+                .map(Some);
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::krpc::{KrpcError, NodeInfo, Want};
+
+    fn round_trip(message: Message) {
+        let encoded = message.to_bencode().unwrap();
+        assert_eq!(Message::from_bencode(&encoded).unwrap(), message);
+    }
+
+    #[test]
+    fn queries_should_be_dispatched_on_their_method() {
+        let id = NodeId([b'a'; 20]);
+        let arguments: Vec<QueryArguments> = vec![
+            Ping::new(id).into(),
+            FindNode {
+                id,
+                target: NodeId([b'b'; 20]),
+                want: Some(Want::Both),
+            }
+            .into(),
+            GetPeers {
+                id,
+                info_hash: [b'c'; 20],
+                want: None,
+            }
+            .into(),
+            AnnouncePeer {
+                id,
+                implied_port: true,
+                info_hash: [b'c'; 20],
+                port: 6881,
+                token: b"aoeusnth".to_vec(),
+            }
+            .into(),
+        ];
+
+        for arguments in arguments {
+            assert_eq!(arguments.id(), id);
+            round_trip(arguments.into_query(*b"aa").with_version(*b"LT").into());
+        }
+
+        let message = Message::from_bencode(b"d1:ad1:xi1ee1:q3:foo1:t2:aa1:y1:qe").unwrap();
+        match message {
+            Message::UnknownQuery(query) => assert_eq!(query.method, "foo"),
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        // Each method requires its own arguments
+        let error =
+            Message::from_bencode(b"d1:ad2:id20:aaaaaaaaaaaaaaaaaaaae1:q9:find_node1:t2:aa1:y1:qe")
+                .unwrap_err();
+        assert_eq!(error.path(), Some("a"));
+    }
+
+    #[test]
+    fn responses_should_be_dispatched_on_their_keys() {
+        let id = NodeId([b'a'; 20]);
+        let nodes = NodesResponse::builder(id, Want::V4)
+            .node(NodeInfo::new(
+                NodeId([b'b'; 20]),
+                "10.0.0.1:6881".parse().unwrap(),
+            ))
+            .build();
+        let values = vec![
+            ResponseValues::Ping(Ping::new(id)),
+            ResponseValues::Nodes(nodes.clone()),
+            ResponseValues::Peers(PeersResponse::new(nodes, b"tok".to_vec())),
+        ];
+
+        for values in values {
+            assert_eq!(values.id(), id);
+            round_trip(Response::new(*b"aa", values).into());
+        }
+
+        round_trip(ErrorResponse::new(*b"aa", KrpcError::method_unknown("foo")).into());
+    }
+
+    #[test]
+    fn invalid_message_types_should_fail() {
+        let error = Message::from_bencode(b"d1:t2:aae").unwrap_err();
+        assert_eq!(error.code(), "decode.missing_field");
+
+        let error = Message::from_bencode(b"d1:t2:aa1:y1:xe").unwrap_err();
+        assert_eq!(error.path(), Some("y"));
+
+        let error = Message::from_bencode(b"d1:t2:aa1:yi1ee").unwrap_err();
+        assert_eq!(error.path(), Some("y"));
+    }
+}
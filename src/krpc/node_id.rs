@@ -0,0 +1,227 @@
+use core::fmt::{self, Display, Formatter};
+#[cfg(feature = "std")]
+use std::net::IpAddr;
+
+use crate::{
+    decoding::{self, FromBencode, Object},
+    encoding::{self, SingleItemEncoder, ToBencode},
+    hex::Hex,
+};
+
+/// The 160-bit ID of a DHT node, encoded as a 20 byte string.
+///
+/// With the `std` feature, IDs can be restricted to the external IP address of a node as
+/// specified by the DHT security extension ([BEP 42]). The randomness is provided by the
+/// caller, keeping this crate free of a random number generator.
+///
+/// [BEP 42]: http://bittorrent.org/beps/bep_0042.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(pub [u8; 20]);
+
+impl NodeId {
+    /// The raw bytes of the ID.
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+
+    /// Derive an ID from the external IP address of a node and 20 random bytes.
+    ///
+    /// The first 21 bits are given by the address and the last byte of `random`, which is
+    /// kept as the last byte of the ID. All other bits are taken from `random`.
+    ///
+    /// ```
+    /// use bendy::krpc::NodeId;
+    ///
+    /// let ip = "124.31.75.21".parse().unwrap();
+    /// let id = NodeId::secure(ip, [1; 20]);
+    /// assert_eq!(&id.as_bytes()[..3], b"\x5f\xbf\xb9");
+    ///
+    /// assert!(id.is_secure_for(ip));
+    /// assert!(!id.is_secure_for("21.75.31.124".parse().unwrap()));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn secure(ip: IpAddr, random: [u8; 20]) -> Self {
+        let crc = secure_prefix(ip, random[19]);
+
+        let mut id = random;
+        id[0] = (crc >> 24) as u8;
+        id[1] = (crc >> 16) as u8;
+        id[2] = (crc >> 8) as u8 & 0xf8 | random[2] & 0x07;
+        NodeId(id)
+    }
+
+    /// Whether the ID is valid for a node with the given external IP address.
+    ///
+    /// IDs of nodes in local networks aren't restricted, so every ID is valid for a
+    /// loopback, private or link-local IPv4 address.
+    #[cfg(feature = "std")]
+    pub fn is_secure_for(&self, ip: IpAddr) -> bool {
+        if let IpAddr::V4(ip) = ip {
+            if ip.is_loopback() || ip.is_private() || ip.is_link_local() {
+                return true;
+            }
+        }
+
+        let crc = secure_prefix(ip, self.0[19]);
+        self.0[0] == (crc >> 24) as u8
+            && self.0[1] == (crc >> 16) as u8
+            && self.0[2] & 0xf8 == (crc >> 8) as u8 & 0xf8
+    }
+}
+
+impl From<[u8; 20]> for NodeId {
+    fn from(id: [u8; 20]) -> Self {
+        NodeId(id)
+    }
+}
+
+impl Display for NodeId {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Hex(&self.0).fmt(f)
+    }
+}
+
+impl ToBencode for NodeId {
+    const MAX_DEPTH: usize = 0;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_bytes(&self.0)
+    }
+}
+
+impl FromBencode for NodeId {
+    const EXPECTED_RECURSION_DEPTH: usize = 0;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let bytes = object.try_into_bytes()?;
+        if bytes.len() != 20 {
+            return Err(decoding::Error::malformed_content(NodeIdLength {
+                len: bytes.len(),
+            }));
+        }
+
+        let mut id = [0; 20];
+        id.copy_from_slice(bytes);
+        Ok(NodeId(id))
+    }
+}
+
+/// The CRC32-C checksum from which the first 21 bits of a secure ID are taken
+#[cfg(feature = "std")]
+fn secure_prefix(ip: IpAddr, r: u8) -> u32 {
+    const IPV4_MASK: [u8; 4] = [0x03, 0x0f, 0x3f, 0xff];
+    const IPV6_MASK: [u8; 8] = [0x01, 0x03, 0x07, 0x0f, 0x1f, 0x3f, 0x7f, 0xff];
+
+    let mut masked = [0; 8];
+    let len = match ip {
+        IpAddr::V4(ip) => {
+            for (masked, (byte, mask)) in masked.iter_mut().zip(ip.octets().iter().zip(&IPV4_MASK))
+            {
+                *masked = byte & mask;
+            }
+            4
+        },
+        IpAddr::V6(ip) => {
+            for (masked, (byte, mask)) in masked.iter_mut().zip(ip.octets().iter().zip(&IPV6_MASK))
+            {
+                *masked = byte & mask;
+            }
+            8
+        },
+    };
+    masked[0] |= (r & 0x07) << 5;
+
+    crc32c(&masked[..len])
+}
+
+/// The CRC32-C (Castagnoli) checksum of `data`
+#[cfg(feature = "std")]
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                crc >> 1 ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// A node ID that isn't 20 bytes long
+#[derive(Debug)]
+struct NodeIdLength {
+    len: usize,
+}
+
+impl Display for NodeIdLength {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "node ID of {} bytes instead of 20", self.len)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NodeIdLength {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn node_ids_should_be_20_bytes() {
+        let id = NodeId::from_bencode(b"20:abcdefghij0123456789").unwrap();
+        assert_eq!(id.to_bencode().unwrap(), b"20:abcdefghij0123456789");
+        assert!(NodeId::from_bencode(b"19:abcdefghij012345678").is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn crc32c_should_match_the_check_value() {
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn secure_ids_should_match_the_bep_42_vectors() {
+        let vectors: [(&str, u8, [u8; 3]); 5] = [
+            ("124.31.75.21", 1, [0x5f, 0xbf, 0xbf]),
+            ("21.75.31.124", 86, [0x5a, 0x3c, 0xe9]),
+            ("65.23.51.170", 22, [0xa5, 0xd4, 0x32]),
+            ("84.124.73.14", 65, [0x1b, 0x03, 0x21]),
+            ("43.213.53.83", 90, [0xe5, 0x6f, 0x6c]),
+        ];
+
+        for &(ip, r, prefix) in &vectors {
+            let ip = ip.parse().unwrap();
+
+            let mut expected = [0; 20];
+            expected[..3].copy_from_slice(&prefix);
+            expected[19] = r;
+            let expected = NodeId(expected);
+            assert!(expected.is_secure_for(ip), "{}", ip);
+
+            let mut random = [0xff; 20];
+            random[19] = r;
+            let id = NodeId::secure(ip, random);
+            assert_eq!(id.0[..2], prefix[..2]);
+            assert_eq!(id.0[2] & 0xf8, prefix[2] & 0xf8);
+            assert_eq!(id.0[19], r);
+            assert!(id.is_secure_for(ip));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn local_addresses_should_be_exempt() {
+        let id = NodeId([0; 20]);
+        assert!(id.is_secure_for("192.168.1.1".parse().unwrap()));
+        assert!(id.is_secure_for("127.0.0.1".parse().unwrap()));
+        assert!(!id.is_secure_for("124.31.75.21".parse().unwrap()));
+    }
+}
@@ -0,0 +1,677 @@
+use std::net::SocketAddr;
+
+use crate::{
+    compact::{self, PEER6_LEN},
+    decoding::{self, FromBencode, Object, ResultExt},
+    encoding::{self, AsString, SingleItemEncoder, ToBencode},
+    krpc::NodeId,
+};
+
+/// The address families of the nodes a querying node would like to receive (`want`,
+/// [BEP 32]).
+///
+/// There is no way to want nothing: a query without `want` asks for nodes of the address
+/// family it was sent over, see [`Want::for_peer`].
+///
+/// [BEP 32]: http://bittorrent.org/beps/bep_0032.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Want {
+    /// IPv4 nodes only (`["n4"]`)
+    V4,
+    /// IPv6 nodes only (`["n6"]`)
+    V6,
+    /// Both kinds of nodes (`["n4", "n6"]`)
+    Both,
+}
+
+impl Want {
+    /// What a query of the given node wants if it has no `want` parameter.
+    pub fn for_peer(peer: &SocketAddr) -> Self {
+        match peer {
+            SocketAddr::V4(_) => Want::V4,
+            SocketAddr::V6(_) => Want::V6,
+        }
+    }
+
+    /// Whether IPv4 nodes (`nodes`) are wanted.
+    pub fn includes_v4(self) -> bool {
+        self != Want::V6
+    }
+
+    /// Whether IPv6 nodes (`nodes6`) are wanted.
+    pub fn includes_v6(self) -> bool {
+        self != Want::V4
+    }
+}
+
+impl ToBencode for Want {
+    const MAX_DEPTH: usize = 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_list(|e| {
+            if self.includes_v4() {
+                e.emit_str("n4")?;
+            }
+            if self.includes_v6() {
+                e.emit_str("n6")?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl FromBencode for Want {
+    const EXPECTED_RECURSION_DEPTH: usize = 1;
+
+    /// Unknown entries are ignored, but at least one known address family is required.
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut v4 = false;
+        let mut v6 = false;
+
+        let mut list = object.try_into_list()?;
+        while let Some(entry) = list.next_object()? {
+            match entry.try_into_bytes()? {
+                b"n4" => v4 = true,
+                b"n6" => v6 = true,
+                _ => (), // ignore unknown address families
+            }
+        }
+
+        match (v4, v6) {
+            (true, true) => Ok(Want::Both),
+            (true, false) => Ok(Want::V4),
+            (false, true) => Ok(Want::V6),
+            (false, false) => Err(decoding::Error::missing_field("n4 or n6")),
+        }
+    }
+}
+
+/// The ID and address of a DHT node, as found in compact node info.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeInfo {
+    /// The ID of the node
+    pub id: NodeId,
+    /// The address of the node
+    pub addr: SocketAddr,
+}
+
+impl NodeInfo {
+    /// Create info about the node with the given ID and address.
+    pub fn new(id: NodeId, addr: SocketAddr) -> Self {
+        NodeInfo { id, addr }
+    }
+
+    /// Append the 26 (IPv4) or 38 (IPv6) byte compact node info.
+    fn write_compact(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.id.as_bytes());
+        compact::write_peer(&self.addr, out);
+    }
+
+    /// Decode a list of compact node infos with `ip_len` byte addresses.
+    fn decode_compact(compact: &[u8], ip_len: usize) -> Result<Vec<NodeInfo>, decoding::Error> {
+        Ok(compact::entries(compact, 20 + ip_len + 2, "nodes")?
+            .map(|node| {
+                let mut id = [0; 20];
+                id.copy_from_slice(&node[..20]);
+
+                NodeInfo::new(NodeId(id), compact::peer(&node[20..]))
+            })
+            .collect())
+    }
+}
+
+/// The arguments of a `find_node` query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FindNode {
+    /// The ID of the querying node (`id`)
+    pub id: NodeId,
+    /// The ID of the node being looked for (`target`)
+    pub target: NodeId,
+    /// The address families of the nodes to return (`want`)
+    pub want: Option<Want>,
+}
+
+impl ToBencode for FindNode {
+    const MAX_DEPTH: usize = Want::MAX_DEPTH + 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair(b"id", self.id)?;
+            e.emit_pair(b"target", self.target)?;
+            if let Some(want) = self.want {
+                e.emit_pair(b"want", want)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl FromBencode for FindNode {
+    const EXPECTED_RECURSION_DEPTH: usize = Want::EXPECTED_RECURSION_DEPTH + 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut id = None;
+        let mut target = None;
+        let mut want = None;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"id", value) => {
+                    id = NodeId::decode_bencode_object(value)
+                        .context("id")
+                        .map(Some)?;
+                },
+                (b"target", value) => {
+                    target = NodeId::decode_bencode_object(value)
+                        .context("target")
+                        .map(Some)?;
+                },
+                (b"want", value) => {
+                    want = Want::decode_bencode_object(value)
+                        .context("want")
+                        .map(Some)?;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        Ok(FindNode {
+            id: id.ok_or_else(|| decoding::Error::missing_field("id"))?,
+            target: target.ok_or_else(|| decoding::Error::missing_field("target"))?,
+            want,
+        })
+    }
+}
+
+/// The arguments of a `get_peers` query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GetPeers {
+    /// The ID of the querying node (`id`)
+    pub id: NodeId,
+    /// The info hash of the torrent to find peers for (`info_hash`)
+    pub info_hash: [u8; 20],
+    /// The address families of the nodes to return if no peers are known (`want`)
+    pub want: Option<Want>,
+}
+
+impl ToBencode for GetPeers {
+    const MAX_DEPTH: usize = Want::MAX_DEPTH + 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair(b"id", self.id)?;
+            e.emit_pair(b"info_hash", AsString(&self.info_hash))?;
+            if let Some(want) = self.want {
+                e.emit_pair(b"want", want)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl FromBencode for GetPeers {
+    const EXPECTED_RECURSION_DEPTH: usize = Want::EXPECTED_RECURSION_DEPTH + 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut id = None;
+        let mut info_hash = None;
+        let mut want = None;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"id", value) => {
+                    id = NodeId::decode_bencode_object(value)
+                        .context("id")
+                        .map(Some)?;
+                },
+                (b"info_hash", value) => {
+                    info_hash = NodeId::decode_bencode_object(value)
+                        .context("info_hash")
+                        .map(|hash| Some(hash.0))?;
+                },
+                (b"want", value) => {
+                    want = Want::decode_bencode_object(value)
+                        .context("want")
+                        .map(Some)?;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        Ok(GetPeers {
+            id: id.ok_or_else(|| decoding::Error::missing_field("id"))?,
+            info_hash: info_hash.ok_or_else(|| decoding::Error::missing_field("info_hash"))?,
+            want,
+        })
+    }
+}
+
+/// The response to a `find_node` query: the closest nodes to the target.
+///
+/// IPv4 nodes are sent in `nodes` and IPv6 nodes in `nodes6`. A response only contains
+/// the lists the querying node asked for, which [`NodesResponse::builder`] takes care of:
+///
+/// ```
+/// use bendy::{
+///     encoding::ToBencode,
+///     krpc::{NodeId, NodeInfo, NodesResponse, Want},
+/// };
+///
+/// let node = |addr: &str| NodeInfo::new(NodeId([0xaa; 20]), addr.parse().unwrap());
+///
+/// let response = NodesResponse::builder(NodeId([0xbb; 20]), Want::V6)
+///     .node(node("10.0.0.1:6881"))
+///     .node(node("[::1]:6881"))
+///     .build();
+///
+/// assert_eq!(response.nodes(), None);
+/// assert_eq!(response.nodes6().unwrap().len(), 1);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodesResponse {
+    id: NodeId,
+    nodes: Option<Vec<NodeInfo>>,
+    nodes6: Option<Vec<NodeInfo>>,
+}
+
+impl NodesResponse {
+    /// Start a response of the node with the given ID, including the lists selected by
+    /// `want`.
+    pub fn builder(id: NodeId, want: Want) -> NodesResponseBuilder {
+        NodesResponseBuilder {
+            response: NodesResponse {
+                id,
+                nodes: if want.includes_v4() {
+                    Some(Vec::new())
+                } else {
+                    None
+                },
+                nodes6: if want.includes_v6() {
+                    Some(Vec::new())
+                } else {
+                    None
+                },
+            },
+        }
+    }
+
+    /// The ID of the responding node (`id`).
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// The IPv4 nodes (`nodes`), if included.
+    pub fn nodes(&self) -> Option<&[NodeInfo]> {
+        self.nodes.as_deref()
+    }
+
+    /// The IPv6 nodes (`nodes6`), if included.
+    pub fn nodes6(&self) -> Option<&[NodeInfo]> {
+        self.nodes6.as_deref()
+    }
+
+    /// All included nodes, IPv4 nodes first.
+    pub fn all_nodes(&self) -> impl Iterator<Item = &NodeInfo> {
+        self.nodes()
+            .unwrap_or(&[])
+            .iter()
+            .chain(self.nodes6().unwrap_or(&[]))
+    }
+}
+
+impl ToBencode for NodesResponse {
+    const MAX_DEPTH: usize = 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair(b"id", self.id)?;
+            if let Some(nodes) = &self.nodes {
+                e.emit_pair(b"nodes", AsString(compact_nodes(nodes)))?;
+            }
+            if let Some(nodes6) = &self.nodes6 {
+                e.emit_pair(b"nodes6", AsString(compact_nodes(nodes6)))?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl FromBencode for NodesResponse {
+    const EXPECTED_RECURSION_DEPTH: usize = 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut id = None;
+        let mut nodes = None;
+        let mut nodes6 = None;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"id", value) => {
+                    id = NodeId::decode_bencode_object(value)
+                        .context("id")
+                        .map(Some)?;
+                },
+                (b"nodes", value) => {
+                    nodes = value
+                        .try_into_bytes()
+                        .and_then(|compact| NodeInfo::decode_compact(compact, 4))
+                        .context("nodes")
+                        .map(Some)?;
+                },
+                (b"nodes6", value) => {
+                    nodes6 = value
+                        .try_into_bytes()
+                        .and_then(|compact| NodeInfo::decode_compact(compact, 16))
+                        .context("nodes6")
+                        .map(Some)?;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        Ok(NodesResponse {
+            id: id.ok_or_else(|| decoding::Error::missing_field("id"))?,
+            nodes,
+            nodes6,
+        })
+    }
+}
+
+/// Builder for a [`NodesResponse`], see [`NodesResponse::builder`].
+#[derive(Clone, Debug)]
+pub struct NodesResponseBuilder {
+    response: NodesResponse,
+}
+
+impl NodesResponseBuilder {
+    /// Add a node. Nodes of an address family the querying node didn't ask for are
+    /// dropped.
+    pub fn node(mut self, node: NodeInfo) -> Self {
+        let list = match node.addr {
+            SocketAddr::V4(_) => &mut self.response.nodes,
+            SocketAddr::V6(_) => &mut self.response.nodes6,
+        };
+        if let Some(list) = list {
+            list.push(node);
+        }
+        self
+    }
+
+    /// Add several nodes, see [`NodesResponseBuilder::node`].
+    pub fn nodes(self, nodes: impl IntoIterator<Item = NodeInfo>) -> Self {
+        nodes.into_iter().fold(self, Self::node)
+    }
+
+    /// Finish the response.
+    pub fn build(self) -> NodesResponse {
+        self.response
+    }
+}
+
+/// The response to a `get_peers` query.
+///
+/// A node that knows peers of the torrent returns them in `values`, otherwise it returns
+/// the closest nodes to the info hash like a [`NodesResponse`]. Either way, the response
+/// carries the token the querying node needs to announce itself:
+///
+/// ```
+/// use bendy::{
+///     decoding::FromBencode,
+///     encoding::ToBencode,
+///     krpc::{NodeId, NodesResponse, PeersResponse, Want},
+/// };
+///
+/// let nodes = NodesResponse::builder(NodeId([0xbb; 20]), Want::V4).build();
+/// let response = PeersResponse::new(nodes, b"aoeusnth".to_vec())
+///     .with_values(vec!["10.0.0.1:6881".parse().unwrap()]);
+///
+/// let encoded = response.to_bencode().unwrap();
+/// assert_eq!(PeersResponse::from_bencode(&encoded).unwrap(), response);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeersResponse {
+    /// The ID of the responding node and the closest nodes it knows
+    pub nodes: NodesResponse,
+    /// The token for a later `announce_peer` query (`token`)
+    pub token: Vec<u8>,
+    /// The known peers of the torrent, as compact peer info (`values`)
+    pub values: Vec<SocketAddr>,
+}
+
+impl PeersResponse {
+    /// Create a response without peers.
+    pub fn new(nodes: NodesResponse, token: impl Into<Vec<u8>>) -> Self {
+        PeersResponse {
+            nodes,
+            token: token.into(),
+            values: Vec::new(),
+        }
+    }
+
+    /// Set the known peers of the torrent.
+    pub fn with_values(mut self, values: impl IntoIterator<Item = SocketAddr>) -> Self {
+        self.values = values.into_iter().collect();
+        self
+    }
+
+    /// The ID of the responding node (`id`).
+    pub fn id(&self) -> NodeId {
+        self.nodes.id
+    }
+}
+
+impl ToBencode for PeersResponse {
+    const MAX_DEPTH: usize = 2;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair(b"id", self.nodes.id)?;
+            if let Some(nodes) = &self.nodes.nodes {
+                e.emit_pair(b"nodes", AsString(compact_nodes(nodes)))?;
+            }
+            if let Some(nodes6) = &self.nodes.nodes6 {
+                e.emit_pair(b"nodes6", AsString(compact_nodes(nodes6)))?;
+            }
+            e.emit_pair(b"token", AsString(&self.token))?;
+            if !self.values.is_empty() {
+                e.emit_pair_with(b"values", |e| {
+                    e.emit_list(|e| {
+                        let mut compact = Vec::with_capacity(PEER6_LEN);
+                        for peer in &self.values {
+                            compact.clear();
+                            compact::write_peer(peer, &mut compact);
+                            e.emit_bytes(&compact)?;
+                        }
+                        Ok(())
+                    })
+                })?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl FromBencode for PeersResponse {
+    const EXPECTED_RECURSION_DEPTH: usize = 2;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut id = None;
+        let mut nodes = None;
+        let mut nodes6 = None;
+        let mut token = None;
+        let mut values = Vec::new();
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"id", value) => {
+                    id = NodeId::decode_bencode_object(value)
+                        .context("id")
+                        .map(Some)?;
+                },
+                (b"nodes", value) => {
+                    nodes = value
+                        .try_into_bytes()
+                        .and_then(|compact| NodeInfo::decode_compact(compact, 4))
+                        .context("nodes")
+                        .map(Some)?;
+                },
+                (b"nodes6", value) => {
+                    nodes6 = value
+                        .try_into_bytes()
+                        .and_then(|compact| NodeInfo::decode_compact(compact, 16))
+                        .context("nodes6")
+                        .map(Some)?;
+                },
+                (b"token", value) => {
+                    token = AsString::decode_bencode_object(value)
+                        .context("token")
+                        .map(|bytes| Some(bytes.0))?;
+                },
+                (b"values", value) => {
+                    let mut list = value.try_into_list().context("values")?;
+                    while let Some(peer) = list.next_object().context("values")? {
+                        let peer = peer
+                            .try_into_bytes()
+                            .and_then(compact::decode_peer)
+                            .context(format_args!("values[{}]", values.len()))?;
+                        values.push(peer);
+                    }
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        Ok(PeersResponse {
+            nodes: NodesResponse {
+                id: id.ok_or_else(|| decoding::Error::missing_field("id"))?,
+                nodes,
+                nodes6,
+            },
+            token: token.ok_or_else(|| decoding::Error::missing_field("token"))?,
+            values,
+        })
+    }
+}
+
+/// Concatenate the compact node infos of `nodes`
+fn compact_nodes(nodes: &[NodeInfo]) -> Vec<u8> {
+    let mut compact = Vec::new();
+    for node in nodes {
+        node.write_compact(&mut compact);
+    }
+    compact
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn want_should_round_trip() {
+        for &want in &[Want::V4, Want::V6, Want::Both] {
+            let encoded = want.to_bencode().unwrap();
+            assert_eq!(Want::from_bencode(&encoded).unwrap(), want);
+        }
+        assert_eq!(Want::Both.to_bencode().unwrap(), b"l2:n42:n6e");
+        assert_eq!(Want::from_bencode(b"l2:n62:n8e").unwrap(), Want::V6);
+        assert!(Want::from_bencode(b"le").is_err());
+    }
+
+    #[test]
+    fn queries_should_encode_want() {
+        let query = FindNode {
+            id: NodeId([b'a'; 20]),
+            target: NodeId([b'b'; 20]),
+            want: Some(Want::V4),
+        };
+        let encoded = query.to_bencode().unwrap();
+        assert_eq!(
+            encoded,
+            &b"d2:id20:aaaaaaaaaaaaaaaaaaaa6:target20:bbbbbbbbbbbbbbbbbbbb4:wantl2:n4ee"[..]
+        );
+        assert_eq!(FindNode::from_bencode(&encoded).unwrap(), query);
+
+        let query = GetPeers {
+            id: NodeId([b'a'; 20]),
+            info_hash: [b'c'; 20],
+            want: None,
+        };
+        let encoded = query.to_bencode().unwrap();
+        assert_eq!(
+            encoded,
+            &b"d2:id20:aaaaaaaaaaaaaaaaaaaa9:info_hash20:cccccccccccccccccccce"[..]
+        );
+        assert_eq!(GetPeers::from_bencode(&encoded).unwrap(), query);
+    }
+
+    #[test]
+    fn responses_should_only_contain_wanted_nodes() {
+        let v4 = NodeInfo::new(NodeId([1; 20]), "10.0.0.1:1".parse().unwrap());
+        let v6 = NodeInfo::new(NodeId([2; 20]), "[::1]:2".parse().unwrap());
+
+        let response = NodesResponse::builder(NodeId([0; 20]), Want::for_peer(&v4.addr))
+            .nodes(vec![v4, v6])
+            .build();
+        let encoded = response.to_bencode().unwrap();
+        assert!(!encoded.windows(6).any(|key| key == b"nodes6"));
+        assert_eq!(NodesResponse::from_bencode(&encoded).unwrap(), response);
+
+        let response = NodesResponse::builder(NodeId([0; 20]), Want::Both)
+            .nodes(vec![v6, v4])
+            .build();
+        let encoded = response.to_bencode().unwrap();
+        let decoded = NodesResponse::from_bencode(&encoded).unwrap();
+        assert_eq!(decoded.all_nodes().collect::<Vec<_>>(), [&v4, &v6]);
+        assert_eq!(decoded.nodes().map(<[_]>::len), Some(1));
+    }
+
+    #[test]
+    fn peers_responses_should_round_trip() {
+        let nodes = NodesResponse::builder(NodeId([0; 20]), Want::Both)
+            .node(NodeInfo::new(
+                NodeId([1; 20]),
+                "10.0.0.1:1".parse().unwrap(),
+            ))
+            .build();
+        let response = PeersResponse::new(nodes, b"tok".to_vec()).with_values(vec![
+            "10.0.0.2:2".parse().unwrap(),
+            "[::1]:3".parse().unwrap(),
+        ]);
+        let encoded = response.to_bencode().unwrap();
+        assert!(encoded.ends_with(b"6:valuesl6:\x0a\x00\x00\x02\x00\x0218:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01\x00\x03ee"));
+        assert_eq!(PeersResponse::from_bencode(&encoded).unwrap(), response);
+
+        let error = PeersResponse::from_bencode(
+            b"d2:id20:aaaaaaaaaaaaaaaaaaaa5:token1:x6:valuesl6:aaaaaa5:aaaaaee",
+        )
+        .unwrap_err();
+        assert_eq!(error.path(), Some("values[1]"));
+    }
+
+    #[test]
+    fn compact_nodes_should_have_a_valid_length() {
+        assert!(NodesResponse::from_bencode(
+            b"d2:id20:aaaaaaaaaaaaaaaaaaaa5:nodes25:aaaaaaaaaaaaaaaaaaaaaaaaae"
+        )
+        .is_err());
+    }
+}
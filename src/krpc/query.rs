@@ -0,0 +1,243 @@
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+use crate::{
+    decoding::{self, FromBencode, Object, ResultExt},
+    encoding::{self, AsString, SingleItemEncoder, ToBencode},
+};
+
+/// Whether the sender of a query takes part in the DHT ([BEP 43]).
+///
+/// Read-only nodes send queries, but don't answer any. Other nodes must not add them to
+/// their routing tables.
+///
+/// [BEP 43]: http://bittorrent.org/beps/bep_0043.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NodeMode {
+    /// A regular node, sending queries without an `ro` flag
+    ReadWrite,
+    /// A node that doesn't respond to queries (`"ro": 1`)
+    ReadOnly,
+}
+
+impl NodeMode {
+    /// Whether this is [`NodeMode::ReadOnly`].
+    pub fn is_read_only(self) -> bool {
+        self == NodeMode::ReadOnly
+    }
+}
+
+// `#[default]` on enum variants requires a newer compiler than the MSRV
+#[allow(clippy::derivable_impls)]
+impl Default for NodeMode {
+    fn default() -> Self {
+        NodeMode::ReadWrite
+    }
+}
+
+/// A KRPC query message (`"y": "q"`) with arguments of type `A`.
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// use bendy::{
+///     decoding::FromBencode,
+///     encoding::{AsString, ToBencode},
+///     krpc::{NodeMode, Query},
+/// };
+///
+/// let mut arguments = BTreeMap::new();
+/// arguments.insert("id".to_string(), AsString(b"abcdefghij0123456789".to_vec()));
+///
+/// let query = Query::new(b"aa".to_vec(), "ping", arguments).with_mode(NodeMode::ReadOnly);
+/// let encoded = query.to_bencode().unwrap();
+/// assert_eq!(
+///     encoded,
+///     &b"d1:ad2:id20:abcdefghij0123456789e1:q4:ping2:roi1e1:t2:aa1:y1:qe"[..]
+/// );
+///
+/// let decoded = Query::<BTreeMap<String, AsString<Vec<u8>>>>::from_bencode(&encoded).unwrap();
+/// assert!(decoded.mode.is_read_only());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Query<A> {
+    /// The transaction ID (`t`)
+    pub transaction_id: Vec<u8>,
+    /// The name of the queried method (`q`)
+    pub method: String,
+    /// The arguments of the query (`a`)
+    pub arguments: A,
+    /// Whether the sender is a read-only node (`ro`)
+    pub mode: NodeMode,
+    /// The client version of the sender (`v`)
+    pub version: Option<Vec<u8>>,
+}
+
+impl<A> Query<A> {
+    /// Create a query of a regular node without a client version.
+    pub fn new(
+        transaction_id: impl Into<Vec<u8>>,
+        method: impl Into<String>,
+        arguments: A,
+    ) -> Self {
+        Query {
+            transaction_id: transaction_id.into(),
+            method: method.into(),
+            arguments,
+            mode: NodeMode::default(),
+            version: None,
+        }
+    }
+
+    /// Set whether the sender is a read-only node.
+    pub fn with_mode(mut self, mode: NodeMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the client version of the sender.
+    pub fn with_version(mut self, version: impl Into<Vec<u8>>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Convert the arguments, keeping everything else.
+    pub fn map<B>(self, f: impl FnOnce(A) -> B) -> Query<B> {
+        Query {
+            transaction_id: self.transaction_id,
+            method: self.method,
+            arguments: f(self.arguments),
+            mode: self.mode,
+            version: self.version,
+        }
+    }
+}
+
+impl<A: ToBencode> ToBencode for Query<A> {
+    const MAX_DEPTH: usize = A::MAX_DEPTH + 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair(b"a", &self.arguments)?;
+            e.emit_pair(b"q", &self.method)?;
+            if self.mode.is_read_only() {
+                e.emit_pair(b"ro", 1)?;
+            }
+            e.emit_pair(b"t", AsString(&self.transaction_id))?;
+            if let Some(version) = &self.version {
+                e.emit_pair(b"v", AsString(version))?;
+            }
+            e.emit_pair(b"y", "q")
+        })
+    }
+}
+
+impl<A: FromBencode> FromBencode for Query<A> {
+    const EXPECTED_RECURSION_DEPTH: usize = A::EXPECTED_RECURSION_DEPTH + 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut arguments = None;
+        let mut method = None;
+        let mut mode = NodeMode::default();
+        let mut transaction_id = None;
+        let mut version = None;
+        let mut message_type = None;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"a", value) => {
+                    arguments = A::decode_bencode_object(value).context("a").map(Some)?;
+                },
+                (b"q", value) => {
+                    method = String::decode_bencode_object(value)
+                        .context("q")
+                        .map(Some)?;
+                },
+                (b"ro", value) => {
+                    // Anything but an explicit zero marks a read-only node
+                    let ro = i64::decode_bencode_object(value).context("ro")?;
+                    if ro != 0 {
+                        mode = NodeMode::ReadOnly;
+                    }
+                },
+                (b"t", value) => {
+                    transaction_id = AsString::decode_bencode_object(value)
+                        .context("t")
+                        .map(|bytes| Some(bytes.0))?;
+                },
+                (b"v", value) => {
+                    version = AsString::decode_bencode_object(value)
+                        .context("v")
+                        .map(|bytes| Some(bytes.0))?;
+                },
+                (b"y", value) => {
+                    message_type = value.try_into_bytes().context("y").map(Some)?;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        match message_type {
+            Some(b"q") => (),
+            Some(other) => {
+                return Err(decoding::Error::unexpected_token(
+                    "message type \"q\"",
+                    String::from_utf8_lossy(other),
+                ))
+            },
+            None => return Err(decoding::Error::missing_field("y")),
+        }
+
+        Ok(Query {
+            transaction_id: transaction_id.ok_or_else(|| decoding::Error::missing_field("t"))?,
+            method: method.ok_or_else(|| decoding::Error::missing_field("q"))?,
+            arguments: arguments.ok_or_else(|| decoding::Error::missing_field("a"))?,
+            mode,
+            version,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::value::Value;
+
+    #[test]
+    fn read_only_flag_should_be_decoded() {
+        let decode = |bencode: &[u8]| Query::<Value>::from_bencode(bencode).map(|query| query.mode);
+
+        assert_eq!(
+            decode(b"d1:ade1:q4:ping1:t2:aa1:y1:qe").unwrap(),
+            NodeMode::ReadWrite
+        );
+        assert_eq!(
+            decode(b"d1:ade1:q4:ping2:roi0e1:t2:aa1:y1:qe").unwrap(),
+            NodeMode::ReadWrite
+        );
+        assert_eq!(
+            decode(b"d1:ade1:q4:ping2:roi1e1:t2:aa1:y1:qe").unwrap(),
+            NodeMode::ReadOnly
+        );
+        assert!(decode(b"d1:ade1:q4:ping2:ro1:11:t2:aa1:y1:qe").is_err());
+    }
+
+    #[test]
+    fn regular_queries_should_omit_the_flag() {
+        let query = Query::new(b"aa".to_vec(), "ping", Value::List(Vec::new()))
+            .with_version(*b"UT\x01\x02");
+        let encoded = query.to_bencode().unwrap();
+        assert_eq!(
+            encoded,
+            &b"d1:ale1:q4:ping1:t2:aa1:v4:UT\x01\x021:y1:qe"[..]
+        );
+        assert_eq!(Query::from_bencode(&encoded).unwrap(), query);
+
+        assert!(Query::<Value>::from_bencode(b"d1:ade1:q4:ping1:t2:aa1:y1:re").is_err());
+    }
+}
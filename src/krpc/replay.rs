@@ -0,0 +1,258 @@
+//! Replay of captured KRPC traffic for debugging.
+//!
+//! A capture is a sequence of records, each consisting of the length of a UDP payload as
+//! a 32-bit big-endian integer followed by the payload itself. Such dumps are easily
+//! extracted from pcap files and can be written with [`write_record`].
+//!
+//! [`replay`] decodes every payload as a KRPC message and returns a [`Summary`] of it.
+//! Malformed payloads are reported as errors without stopping the replay, so a single
+//! bad packet doesn't hide the rest of the conversation.
+//!
+//! ```
+//! use bendy::krpc::replay::{replay, write_record};
+//!
+//! let mut capture = Vec::new();
+//! write_record(&mut capture, b"d1:ad2:id2:abe1:q4:ping1:t2:aa1:y1:qe");
+//! write_record(&mut capture, b"d1:rd2:id2:cde1:t2:aa1:y1:re");
+//! write_record(&mut capture, b"not bencode");
+//!
+//! let summaries: Vec<_> = replay(&capture)
+//!     .map(|summary| match summary {
+//!         Ok(summary) => summary.to_string(),
+//!         Err(_) => "malformed".to_string(),
+//!     })
+//!     .collect();
+//!
+//! assert_eq!(
+//!     summaries,
+//!     [
+//!         "t=6161 query ping id=6162",
+//!         "t=6161 response id=6364",
+//!         "malformed"
+//!     ]
+//! );
+//! ```
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+use core::{
+    convert::TryInto,
+    fmt::{self, Display, Formatter},
+};
+
+use crate::{
+    decoding::{Cursor, Error, FromBencode, ResultExt},
+    hex::Hex,
+    krpc::KrpcError,
+    state_tracker::StructureError,
+};
+
+/// Append a record holding `payload` to a capture.
+///
+/// # Panics
+///
+/// If the payload is longer than `u32::MAX` bytes, which no UDP payload can be.
+pub fn write_record(capture: &mut Vec<u8>, payload: &[u8]) {
+    let len: u32 = payload
+        .len()
+        .try_into()
+        .expect("payload too large for a capture record");
+    capture.extend_from_slice(&len.to_be_bytes());
+    capture.extend_from_slice(payload);
+}
+
+/// Iterate over the payloads of a capture.
+///
+/// Ends with an error if the capture is truncated.
+pub fn records(capture: &[u8]) -> Records<'_> {
+    Records { capture }
+}
+
+/// Decode the payloads of a capture, see the [module documentation](self).
+pub fn replay(capture: &[u8]) -> impl Iterator<Item = Result<Summary<'_>, Error>> {
+    records(capture).map(|record| record.and_then(Summary::new))
+}
+
+/// Iterator over the payloads of a capture, see [`records`].
+#[derive(Clone, Debug)]
+pub struct Records<'a> {
+    capture: &'a [u8],
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<&'a [u8], Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.capture.is_empty() {
+            return None;
+        }
+
+        let record = match self.capture {
+            [a, b, c, d, rest @ ..] => {
+                let len = u32::from_be_bytes([*a, *b, *c, *d]) as usize;
+                rest.get(..len).map(|payload| (payload, &rest[len..]))
+            },
+            _ => None,
+        };
+
+        match record {
+            Some((payload, rest)) => {
+                self.capture = rest;
+                Some(Ok(payload))
+            },
+            None => {
+                self.capture = &[];
+                Some(Err(
+                    Error::from(StructureError::UnexpectedEof).context("capture")
+                ))
+            },
+        }
+    }
+}
+
+/// The type of a KRPC message along with its essential content.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MessageKind<'a> {
+    /// A query (`"y": "q"`) of the given method
+    Query { method: &'a [u8] },
+    /// A response (`"y": "r"`)
+    Response,
+    /// An error (`"y": "e"`)
+    Error(KrpcError),
+    /// A message of an unknown type
+    Unknown(&'a [u8]),
+}
+
+/// A structured summary of a single KRPC message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Summary<'a> {
+    /// The transaction ID (`t`)
+    pub transaction_id: &'a [u8],
+    /// The message type and its content
+    pub kind: MessageKind<'a>,
+    /// The ID of the sending node, taken from the arguments or response dict
+    pub node_id: Option<&'a [u8]>,
+    /// The client version (`v`), if present
+    pub version: Option<&'a [u8]>,
+}
+
+impl<'a> Summary<'a> {
+    /// Summarize a single bencoded KRPC message.
+    pub fn new(payload: &'a [u8]) -> Result<Self, Error> {
+        let message = Cursor::dict_document(payload)?;
+
+        let transaction_id = bytes(message, b"t")?.ok_or_else(|| Error::missing_field("t"))?;
+        let message_type = bytes(message, b"y")?.ok_or_else(|| Error::missing_field("y"))?;
+        let version = bytes(message, b"v")?;
+
+        let (kind, body) = match message_type {
+            b"q" => {
+                let method = bytes(message, b"q")?.ok_or_else(|| Error::missing_field("q"))?;
+                (MessageKind::Query { method }, message.get(b"a"))
+            },
+            b"r" => (MessageKind::Response, message.get(b"r")),
+            b"e" => {
+                let error = message.get(b"e").ok_or_else(|| Error::missing_field("e"))?;
+                let error = KrpcError::from_bencode(error.raw()).context("e")?;
+                (MessageKind::Error(error), None)
+            },
+            other => (MessageKind::Unknown(other), None),
+        };
+
+        let node_id = match body {
+            Some(body) => bytes(body, b"id")?,
+            None => None,
+        };
+
+        Ok(Summary {
+            transaction_id,
+            kind,
+            node_id,
+            version,
+        })
+    }
+}
+
+impl Display for Summary<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "t={}", Hex(self.transaction_id))?;
+        match &self.kind {
+            MessageKind::Query { method } => {
+                write!(f, " query {}", String::from_utf8_lossy(method))?
+            },
+            MessageKind::Response => f.write_str(" response")?,
+            MessageKind::Error(error) => write!(f, " error {}", error)?,
+            MessageKind::Unknown(kind) => write!(f, " unknown type {}", Hex(kind))?,
+        }
+        if let Some(node_id) = self.node_id {
+            write!(f, " id={}", Hex(node_id))?;
+        }
+        if let Some(version) = self.version {
+            write!(f, " v={}", Hex(version))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Look up a byte string in a dict, failing if the value has another type
+fn bytes<'a>(dict: Cursor<'a>, key: &[u8]) -> Result<Option<&'a [u8]>, Error> {
+    match dict.get(key) {
+        Some(value) => match value.as_bytes() {
+            Some(bytes) => Ok(Some(bytes)),
+            None => Err(Error::unexpected_token("String", value.token().name())
+                .context(String::from_utf8_lossy(key))),
+        },
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn records_should_split_the_capture() {
+        let mut capture = Vec::new();
+        write_record(&mut capture, b"abc");
+        write_record(&mut capture, b"");
+        write_record(&mut capture, b"de");
+
+        let payloads: Vec<_> = records(&capture).map(Result::unwrap).collect();
+        assert_eq!(payloads, [&b"abc"[..], b"", b"de"]);
+    }
+
+    #[test]
+    fn truncated_captures_should_fail() {
+        let mut capture = Vec::new();
+        write_record(&mut capture, b"abc");
+        capture.pop();
+
+        let mut records = records(&capture);
+        assert!(records.next().unwrap().is_err());
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn errors_should_be_summarized() {
+        let summary = Summary::new(b"d1:eli203e9:bad tokene1:t2:aa1:v4:UT\x01\x021:y1:ee").unwrap();
+        assert_eq!(
+            summary.kind,
+            MessageKind::Error(KrpcError::protocol("bad token"))
+        );
+        assert_eq!(
+            summary.to_string(),
+            "t=6161 error 203 protocol error: bad token v=55540102"
+        );
+    }
+
+    #[test]
+    fn malformed_messages_should_fail() {
+        assert!(Summary::new(b"d1:y1:qe").is_err());
+        assert!(Summary::new(b"d1:ti1e1:y1:re").is_err());
+        assert!(Summary::new(b"d1:t2:aa1:y1:qe").is_err());
+    }
+}
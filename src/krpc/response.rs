@@ -0,0 +1,149 @@
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+use crate::{
+    decoding::{self, FromBencode, Object, ResultExt},
+    encoding::{self, AsString, SingleItemEncoder, ToBencode},
+};
+
+/// A KRPC response message (`"y": "r"`) with return values of type `R`.
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// use bendy::{
+///     decoding::FromBencode,
+///     encoding::{AsString, ToBencode},
+///     krpc::Response,
+/// };
+///
+/// let mut values = BTreeMap::new();
+/// values.insert("id".to_string(), AsString(b"mnopqrstuvwxyz123456".to_vec()));
+///
+/// let response = Response::new(b"aa".to_vec(), values);
+/// let encoded = response.to_bencode().unwrap();
+/// assert_eq!(
+///     encoded,
+///     &b"d1:rd2:id20:mnopqrstuvwxyz123456e1:t2:aa1:y1:re"[..]
+/// );
+///
+/// let decoded = Response::<BTreeMap<String, AsString<Vec<u8>>>>::from_bencode(&encoded).unwrap();
+/// assert_eq!(decoded, response);
+/// ```
+///
+/// With the `std` feature, responses to the queries defined by BEP 5 can be decoded as
+/// [`Message`](super::Message)s.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response<R> {
+    /// The transaction ID of the query this response answers (`t`)
+    pub transaction_id: Vec<u8>,
+    /// The return values (`r`)
+    pub values: R,
+    /// The client version of the sender (`v`)
+    pub version: Option<Vec<u8>>,
+}
+
+impl<R> Response<R> {
+    /// Create a response to the query with the given transaction ID.
+    pub fn new(transaction_id: impl Into<Vec<u8>>, values: R) -> Self {
+        Response {
+            transaction_id: transaction_id.into(),
+            values,
+            version: None,
+        }
+    }
+
+    /// Set the client version of the sender.
+    pub fn with_version(mut self, version: impl Into<Vec<u8>>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+}
+
+impl<R: ToBencode> ToBencode for Response<R> {
+    const MAX_DEPTH: usize = R::MAX_DEPTH + 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair(b"r", &self.values)?;
+            e.emit_pair(b"t", AsString(&self.transaction_id))?;
+            if let Some(version) = &self.version {
+                e.emit_pair(b"v", AsString(version))?;
+            }
+            e.emit_pair(b"y", "r")
+        })
+    }
+}
+
+impl<R: FromBencode> FromBencode for Response<R> {
+    const EXPECTED_RECURSION_DEPTH: usize = R::EXPECTED_RECURSION_DEPTH + 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut values = None;
+        let mut transaction_id = None;
+        let mut version = None;
+        let mut message_type = None;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"r", value) => {
+                    values = R::decode_bencode_object(value).context("r").map(Some)?;
+                },
+                (b"t", value) => {
+                    transaction_id = AsString::decode_bencode_object(value)
+                        .context("t")
+                        .map(|bytes| Some(bytes.0))?;
+                },
+                (b"v", value) => {
+                    version = AsString::decode_bencode_object(value)
+                        .context("v")
+                        .map(|bytes| Some(bytes.0))?;
+                },
+                (b"y", value) => {
+                    message_type = value.try_into_bytes().context("y").map(Some)?;
+                },
+                _ => (), // ignore unknown keys, e.g. the external address (`ip`)
+            }
+        }
+
+        match message_type {
+            Some(b"r") => (),
+            Some(other) => {
+                return Err(decoding::Error::unexpected_token(
+                    "message type \"r\"",
+                    String::from_utf8_lossy(other),
+                ))
+            },
+            None => return Err(decoding::Error::missing_field("y")),
+        }
+
+        Ok(Response {
+            transaction_id: transaction_id.ok_or_else(|| decoding::Error::missing_field("t"))?,
+            values: values.ok_or_else(|| decoding::Error::missing_field("r"))?,
+            version,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::value::Value;
+
+    #[test]
+    fn responses_should_round_trip() {
+        let response = Response::new(b"aa".to_vec(), Value::List(Vec::new())).with_version(*b"LT");
+        let encoded = response.to_bencode().unwrap();
+        assert_eq!(encoded, &b"d1:rle1:t2:aa1:v2:LT1:y1:re"[..]);
+        assert_eq!(Response::from_bencode(&encoded).unwrap(), response);
+
+        assert!(Response::<Value>::from_bencode(b"d1:rle1:t2:aa1:y1:qe").is_err());
+        let error = Response::<Value>::from_bencode(b"d1:t2:aa1:y1:re").unwrap_err();
+        assert_eq!(error.code(), "decode.missing_field");
+    }
+}
@@ -0,0 +1,149 @@
+use std::net::IpAddr;
+
+/// Computes the message authentication code of a token.
+///
+/// This should be a keyed MAC like HMAC-SHA1; bendy doesn't ship one to stay free of
+/// cryptographic dependencies. Implemented for closures taking the same arguments as
+/// [`TokenMac::mac`].
+pub trait TokenMac {
+    /// The MAC of `message` under `secret`.
+    fn mac(&self, secret: &[u8], message: &[u8]) -> Vec<u8>;
+}
+
+impl<F> TokenMac for F
+where
+    F: Fn(&[u8], &[u8]) -> Vec<u8>,
+{
+    fn mac(&self, secret: &[u8], message: &[u8]) -> Vec<u8> {
+        self(secret, message)
+    }
+}
+
+/// Issues and validates the opaque `token` values of `get_peers` responses.
+///
+/// A node answering `get_peers` hands out a token bound to the IP address of the querying
+/// node, which has to present it in a later `announce_peer`. Tokens are the MAC of the
+/// address under a secret that should be rotated every five minutes with
+/// [`TokenIssuer::rotate`]. Tokens issued under the previous secret stay valid, so every
+/// token is accepted for five to ten minutes, as recommended by [BEP 5].
+///
+/// ```
+/// use bendy::krpc::TokenIssuer;
+///
+/// # fn hmac_sha1(secret: &[u8], message: &[u8]) -> Vec<u8> {
+/// #     secret.iter().chain(message).copied().collect()
+/// # }
+/// let mut issuer = TokenIssuer::new(hmac_sha1, b"first secret".to_vec());
+///
+/// let ip = "203.0.113.7".parse().unwrap();
+/// let token = issuer.issue(ip);
+/// assert!(issuer.validate(ip, &token));
+/// assert!(!issuer.validate("203.0.113.8".parse().unwrap(), &token));
+///
+/// issuer.rotate(b"second secret".to_vec());
+/// assert!(issuer.validate(ip, &token));
+///
+/// issuer.rotate(b"third secret".to_vec());
+/// assert!(!issuer.validate(ip, &token));
+/// ```
+///
+/// [BEP 5]: http://bittorrent.org/beps/bep_0005.html
+#[derive(Clone, Debug)]
+pub struct TokenIssuer<M> {
+    mac: M,
+    current: Vec<u8>,
+    previous: Option<Vec<u8>>,
+    token_len: Option<usize>,
+}
+
+impl<M: TokenMac> TokenIssuer<M> {
+    /// Create an issuer using the given MAC and initial secret.
+    pub fn new(mac: M, secret: Vec<u8>) -> Self {
+        TokenIssuer {
+            mac,
+            current: secret,
+            previous: None,
+            token_len: None,
+        }
+    }
+
+    /// Truncate tokens to at most `len` bytes to keep responses small.
+    pub fn with_token_len(mut self, len: usize) -> Self {
+        self.token_len = Some(len);
+        self
+    }
+
+    /// Replace the secret. Tokens issued under the replaced secret stay valid until the
+    /// next rotation, those issued before are invalidated.
+    pub fn rotate(&mut self, secret: Vec<u8>) {
+        self.previous = Some(core::mem::replace(&mut self.current, secret));
+    }
+
+    /// The token for a node with the given IP address.
+    pub fn issue(&self, ip: IpAddr) -> Vec<u8> {
+        self.token(&self.current, ip)
+    }
+
+    /// Whether `token` was issued to a node with the given IP address under the current
+    /// or the previous secret.
+    pub fn validate(&self, ip: IpAddr, token: &[u8]) -> bool {
+        let current = constant_time_eq(&self.token(&self.current, ip), token);
+        let previous = match &self.previous {
+            Some(secret) => constant_time_eq(&self.token(secret, ip), token),
+            None => false,
+        };
+        current | previous
+    }
+
+    fn token(&self, secret: &[u8], ip: IpAddr) -> Vec<u8> {
+        let mut token = match ip {
+            IpAddr::V4(ip) => self.mac.mac(secret, &ip.octets()),
+            IpAddr::V6(ip) => self.mac.mac(secret, &ip.octets()),
+        };
+        if let Some(len) = self.token_len {
+            token.truncate(len);
+        }
+        token
+    }
+}
+
+/// Compare without returning early, so the time taken doesn't reveal how many leading
+/// bytes of a forged token are correct
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mac(secret: &[u8], message: &[u8]) -> Vec<u8> {
+        message
+            .iter()
+            .zip(secret.iter().cycle())
+            .map(|(m, s)| m ^ s)
+            .collect()
+    }
+
+    #[test]
+    fn tokens_should_be_bound_to_the_address() {
+        let issuer = TokenIssuer::new(mac, vec![1, 2, 3]);
+        let v4 = "10.0.0.1".parse().unwrap();
+        let v6 = "::1".parse().unwrap();
+
+        assert_eq!(issuer.issue(v4), [11, 2, 3, 0]);
+        assert_ne!(issuer.issue(v6), issuer.issue(v4));
+        assert!(issuer.validate(v6, &issuer.issue(v6)));
+        assert!(!issuer.validate(v6, &issuer.issue(v4)));
+        assert!(!issuer.validate(v4, &[11, 2, 3]));
+    }
+
+    #[test]
+    fn tokens_should_be_truncated() {
+        let issuer = TokenIssuer::new(mac, vec![1, 2, 3]).with_token_len(2);
+        let ip = "10.0.0.1".parse().unwrap();
+
+        assert_eq!(issuer.issue(ip), [11, 2]);
+        assert!(issuer.validate(ip, &[11, 2]));
+    }
+}
@@ -1,7 +1,9 @@
 //! Encodes and decodes bencoded structures.
 //!
-//! The decoder is explicitly designed to be zero-copy as much as possible, and to not
-//! accept any sort of invalid encoding in any mode (including non-canonical encodings)
+//! The decoder is explicitly designed to be zero-copy as much as possible. In its default
+//! strict mode it doesn't accept any sort of invalid encoding (including non-canonical
+//! encodings); the lenient mode tolerates a fixed set of defects found in the wild, such
+//! as whitespace between tokens, see [`decoding::Mode`].
 //!
 //! The encoder is likewise designed to ensure that it only produces valid structures.
 
@@ -13,14 +15,51 @@ extern crate alloc;
 #[macro_use]
 mod assert_matches;
 
+pub mod arena;
+pub mod client;
+#[cfg(feature = "std")]
+mod compact;
 pub mod decoding;
 pub mod encoding;
+mod hex;
+pub mod integer;
+pub mod krpc;
+pub mod signing;
 pub mod state_tracker;
+pub mod websocket;
+
+#[cfg(feature = "bytes")]
+pub mod bytes;
+
+#[cfg(feature = "codegen")]
+pub mod codegen;
+
+#[cfg(feature = "gen")]
+pub mod gen;
+
+#[cfg(feature = "torrent")]
+pub mod torrent;
+
+#[cfg(feature = "std")]
+pub mod json;
+
+#[cfg(feature = "std")]
+pub mod tracker;
+
+#[cfg(feature = "heapless")]
+pub mod heapless;
 
 #[cfg(feature = "serde")]
 pub mod serde;
 
+#[cfg(feature = "testing")]
+pub mod testing;
+
+pub mod ut_metadata;
 pub mod value;
+pub mod versioned;
+
+pub use crate::decoding::is_valid;
 
 #[rustversion::since(1.40)]
 const _: () = {
@@ -12,8 +12,8 @@
 //! assert_eq!(to_bytes(&10).unwrap(), b"i10e");
 //! assert_eq!(from_bytes::<u64>(b"i10e").unwrap(), 10);
 //!
-//! #[serde(crate = "serde_")]
 //! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! #[serde(crate = "serde_")]
 //! struct Foo {
 //!     bar: bool,
 //! }
@@ -30,20 +30,19 @@
 //! - `true`: The integer value `1`.
 //! - `false`: The integer value `0`.
 //! - `char`: A string containing the UTF-8 encoding of the value.
-//! - `f32`: Represented as a length-four bencode byte string containing the big-
-//!   endian order bytes of the IEEE-754 representation of the value.
-//! - `f64`: Represented as a length-eight bencode byte string containing the big-
-//!   endian order bytes of the IEEE-754 representation of the value.
-//! - `()`: Represented as the empty bencode list, `le`.
+//! - `f32` and `f64`: Rejected by default, see below.
+//! - `()`: Rejected by default, see below.
 //! - `Some(t)`: Represented as a list containing the bencoding of `t`.
-//! - `None`: Represented as the empty list.
+//! - `None`: Represented as the empty list when nested in another value, and like
+//!   `()` otherwise. Nested options stay distinguishable, e.g. `Some(None)` is `llee`,
+//!   and within lists and dicts `None` is always the empty list.
 //! - maps, including BTreeMap and HashMap: bencoded dictionaries.
 //! - record structs: Represented as bencoded dictionaries with the fields of the
 //!   struct represented as UTF-8 keys mapped to the bencoded serializations of the
 //!   values.
 //! - tuple structs: Represented as bencoded lists containing the serialized values
 //!   of the fields.
-//! - unit structs: Represented as the empty bencode list, `le`.
+//! - unit structs: Represented like `()`.
 //! - enum unit variants: Represented as a string containing the name of the variant,
 //! - enum newtype variants: Represented as a dict mapping the name of the variant
 //!   to the value the variant contains.
@@ -56,10 +55,26 @@
 //! Bencode dictionary keys may only be byte strings. For this reason, map types with
 //! keys that do not serialize as byte strings are unsupported.
 //!
-//! Note that values of type `f32` and `f64` do not conform to bencode's canonical
-//! representation rules. For example, both `f32` and `f64` support negative zero
-//! values which have different bit patterns, but which represent the same logical
-//! value as positive zero.
+//! Bencode has no unit type either, so serializing `()`, a unit struct or a `None` that
+//! isn't nested in another value fails with [`Error::UnitForbidden`] unless the
+//! [`Serializer`] is configured with a different [`UnitPolicy`]: `EmptyList` (`le`),
+//! `EmptyString` (`0:`) or `Zero` (`i0e`). The [`Deserializer`] accepts all three.
+//!
+//! Bencode has no floating point type, so serializing an `f32` or `f64` fails with
+//! [`Error::FloatForbidden`] unless the [`Serializer`] is configured with a different
+//! [`FloatPolicy`]:
+//!
+//! - `FloatPolicy::BigEndianBytes`: A length-four (`f32`) or length-eight (`f64`) byte
+//!   string containing the big-endian order bytes of the IEEE-754 representation of
+//!   the value. This is the representation the [`Deserializer`] expects.
+//! - `FloatPolicy::AsString`: A string containing the decimal representation of the
+//!   value.
+//! - `FloatPolicy::AsScaledInt(n)`: The value multiplied by `10^n`, rounded to an
+//!   integer.
+//!
+//! Note that none of these conform to bencode's canonical representation rules. For
+//! example, both `f32` and `f64` support negative zero values which have different bit
+//! patterns, but which represent the same logical value as positive zero.
 //!
 //! If you require bencoded values to have canonical representations, then it is best
 //! to avoid floating point values.
@@ -80,16 +95,15 @@
 //!
 //! repr(true, "i1e");
 //! repr(false, "i0e");
-//! repr((), "le");
+//! assert!(to_bytes(&()).is_err());
 //! repr('a', "1:a");
 //! repr('Å', b"2:\xC3\x85");
 //! repr(0, "i0e");
 //! repr(-15, "i-15e");
-//! repr(1.0f32, b"4:\x3F\x80\x00\x00");
-//! repr(1.0f64, b"8:\x3F\xF0\x00\x00\x00\x00\x00\x00");
+//! assert!(to_bytes(&1.0f64).is_err());
 //!
 //! let none: Option<i32> = None;
-//! repr(none, "le");
+//! repr(vec![none], "llee");
 //! repr(Some(0), "li0ee");
 //!
 //! let mut map = HashMap::new();
@@ -97,23 +111,23 @@
 //! map.insert("bar", 2);
 //! repr(map, "d3:bari2e3:fooi1ee");
 //!
-//! #[serde(crate = "serde_")]
 //! #[derive(Serialize)]
+//! #[serde(crate = "serde_")]
 //! struct Unit;
-//! repr(Unit, "le");
+//! assert!(to_bytes(&Unit).is_err());
 //!
-//! #[serde(crate = "serde_")]
 //! #[derive(Serialize)]
+//! #[serde(crate = "serde_")]
 //! struct Newtype(String);
 //! repr(Newtype("foo".into()), "3:foo");
 //!
-//! #[serde(crate = "serde_")]
 //! #[derive(Serialize)]
+//! #[serde(crate = "serde_")]
 //! struct Tuple(bool, i32);
 //! repr(Tuple(false, 100), "li0ei100ee");
 //!
-//! #[serde(crate = "serde_")]
 //! #[derive(Serialize)]
+//! #[serde(crate = "serde_")]
 //! struct Record {
 //!     a: String,
 //!     b: bool,
@@ -127,8 +141,8 @@
 //!     "d1:a5:hello1:bi0ee",
 //! );
 //!
-//! #[serde(crate = "serde_")]
 //! #[derive(Serialize)]
+//! #[serde(crate = "serde_")]
 //! enum Enum {
 //!     Unit,
 //!     Newtype(i32),
@@ -141,9 +155,9 @@
 //! repr(Enum::Tuple(true, 10), "d5:Tupleli1ei10eee");
 //! repr(Enum::Struct { a: 'x', b: true }, "d6:Structd1:a1:x1:bi1eee");
 //!
+//! #[derive(Serialize)]
 //! #[serde(untagged)]
 //! #[serde(crate = "serde_")]
-//! #[derive(Serialize)]
 //! enum Untagged {
 //!     Foo { x: i32 },
 //!     Bar { y: char },
@@ -155,15 +169,17 @@
 
 mod common;
 
+pub mod compat;
 pub mod de;
 pub mod error;
 pub mod ser;
 
 pub use de::{from_bytes, Deserializer};
 pub use error::{Error, Result};
-pub use ser::{to_bytes, Serializer};
+pub use ser::{to_bytes, FloatPolicy, Serializer, UnitPolicy};
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow)]
 mod tests {
     use super::common::*;
 
@@ -171,7 +187,7 @@ mod tests {
 
     use super::{
         de::{from_bytes, Deserializer},
-        ser::to_bytes,
+        ser::{to_bytes, FloatPolicy, Serializer, UnitPolicy},
     };
 
     use serde::{de::DeserializeOwned, ser::Serialize};
@@ -274,6 +290,15 @@ mod tests {
         case(-1isize, "i-1e");
     }
 
+    fn float_case<V>(value: V, policy: FloatPolicy) -> Result<Vec<u8>>
+    where
+        V: Serialize,
+    {
+        let mut serializer = Serializer::new().with_float_policy(policy);
+        value.serialize(&mut serializer)?;
+        serializer.into_bytes()
+    }
+
     #[test]
     fn f32() {
         let value = 100.100f32;
@@ -281,7 +306,11 @@ mod tests {
         let mut bencode: Vec<u8> = Vec::new();
         bencode.extend(b"4:");
         bencode.extend(&bytes);
-        case(value, bencode);
+        assert_eq!(
+            float_case(value, FloatPolicy::BigEndianBytes).unwrap(),
+            bencode
+        );
+        assert_eq!(from_bytes::<f32>(&bencode).unwrap(), value);
     }
 
     #[test]
@@ -291,17 +320,122 @@ mod tests {
         let mut bencode: Vec<u8> = Vec::new();
         bencode.extend(b"8:");
         bencode.extend(&bytes);
-        case(value, bencode);
+        assert_eq!(
+            float_case(value, FloatPolicy::BigEndianBytes).unwrap(),
+            bencode
+        );
+        assert_eq!(from_bytes::<f64>(&bencode).unwrap(), value);
+    }
+
+    #[test]
+    fn floats_should_be_rejected_by_default() {
+        assert_matches!(to_bytes(&1.5f32), Err(Error::FloatForbidden));
+        assert_matches!(to_bytes(&vec![1.5f64]), Err(Error::FloatForbidden));
+
+        #[derive(Serialize)]
+        #[serde(crate = "serde_")]
+        struct Info {
+            length: f64,
+        }
+        assert_matches!(to_bytes(&Info { length: 1.0 }), Err(Error::FloatForbidden));
+    }
+
+    #[test]
+    fn floats_should_be_encoded_as_strings() {
+        assert_eq!(float_case(0.1f32, FloatPolicy::AsString).unwrap(), b"3:0.1");
+        assert_eq!(
+            float_case(-2.5f64, FloatPolicy::AsString).unwrap(),
+            b"4:-2.5"
+        );
+
+        let mut map = HashMap::new();
+        map.insert("ratio", 1.25);
+        assert_eq!(
+            float_case(map, FloatPolicy::AsString).unwrap(),
+            b"d5:ratio4:1.25e"
+        );
+    }
+
+    #[test]
+    fn floats_should_be_encoded_as_scaled_ints() {
+        assert_eq!(
+            float_case(1.5f32, FloatPolicy::AsScaledInt(2)).unwrap(),
+            b"i150e"
+        );
+        assert_eq!(
+            float_case(-0.125f64, FloatPolicy::AsScaledInt(2)).unwrap(),
+            b"i-13e"
+        );
+        assert_eq!(
+            float_case(7.0, FloatPolicy::AsScaledInt(0)).unwrap(),
+            b"i7e"
+        );
+        assert_matches!(
+            float_case(f64::NAN, FloatPolicy::AsScaledInt(2)),
+            Err(Error::UnrepresentableFloat(_))
+        );
+        assert_matches!(
+            float_case(1e300, FloatPolicy::AsScaledInt(2)),
+            Err(Error::UnrepresentableFloat(_))
+        );
+    }
+
+    fn unit_case<V>(value: V, policy: UnitPolicy) -> Result<Vec<u8>>
+    where
+        V: Serialize,
+    {
+        let mut serializer = Serializer::new().with_unit_policy(policy);
+        value.serialize(&mut serializer)?;
+        serializer.into_bytes()
     }
 
     #[test]
     fn unit() {
-        case((), "le");
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        #[serde(crate = "serde_")]
+        struct Unit;
+
+        assert_matches!(to_bytes(&()), Err(Error::UnitForbidden));
+        assert_matches!(to_bytes(&vec![Unit]), Err(Error::UnitForbidden));
+
+        for &(policy, bencode) in &[
+            (UnitPolicy::EmptyList, "le"),
+            (UnitPolicy::EmptyString, "0:"),
+            (UnitPolicy::Zero, "i0e"),
+        ] {
+            assert_eq!(unit_case((), policy).unwrap(), bencode.as_bytes());
+            assert_eq!(unit_case(Unit, policy).unwrap(), bencode.as_bytes());
+            from_bytes::<()>(bencode.as_bytes()).unwrap();
+            assert_eq!(from_bytes::<Unit>(bencode.as_bytes()).unwrap(), Unit);
+        }
+        assert!(from_bytes::<()>(b"i1e").is_err());
     }
 
     #[test]
     fn none() {
-        case::<Option<u8>, &str>(None, "le");
+        assert_matches!(to_bytes(&None::<u8>), Err(Error::UnitForbidden));
+        assert_eq!(unit_case(None::<u8>, UnitPolicy::Zero).unwrap(), b"i0e");
+        assert_eq!(from_bytes::<Option<u8>>(b"i0e").unwrap(), None);
+        assert_eq!(from_bytes::<Option<u8>>(b"0:").unwrap(), None);
+
+        case(vec![None, Some(1u8)], "lleli1eee");
+        case(Some(None::<u8>), "llee");
+        case::<Option<u8>, &str>(Some(0), "li0ee");
+    }
+
+    #[test]
+    fn nested_options() {
+        case(Some(Some(1u8)), "lli1eee");
+        case(Some(None::<Option<u8>>), "llee");
+        case(vec![Some(None), None, Some(Some(0u8))], "llleelelli0eeee");
+
+        let mut map = HashMap::new();
+        map.insert("a".to_string(), None::<Option<u8>>);
+        case(map, "d1:alee");
+
+        assert!(from_bytes::<Option<Option<u8>>>(b"li0ee").is_err());
+        assert!(from_bytes::<Vec<Option<u8>>>(b"li0ee").is_err());
+        assert!(from_bytes::<Vec<Option<u8>>>(b"l0:e").is_err());
     }
 
     #[test]
@@ -380,7 +514,9 @@ mod tests {
         #[derive(Debug, Serialize, Deserialize, PartialEq)]
         #[serde(crate = "serde_")]
         struct Foo;
-        case(Foo, "le");
+        assert_matches!(to_bytes(&Foo), Err(Error::UnitForbidden));
+        assert_eq!(unit_case(Foo, UnitPolicy::EmptyList).unwrap(), b"le");
+        assert_eq!(from_bytes::<Foo>(b"le").unwrap(), Foo);
     }
 
     #[test]
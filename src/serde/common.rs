@@ -25,6 +25,9 @@ pub(crate) use serde::{
 pub(crate) use crate::{
     decoding::{self, Decoder, Tokens},
     encoding::{self, Encoder, UnsortedDictEncoder},
-    serde::{ser::Serializer, Error, Result},
+    serde::{
+        ser::{Policies, Serializer},
+        Error, Result,
+    },
     state_tracker::{StructureError, Token},
 };
@@ -0,0 +1,72 @@
+//! Functions with the same signatures as those of the `serde_bencode` crate.
+//!
+//! Projects migrating from `serde_bencode` can replace `use serde_bencode::...` with
+//! `use bendy::serde::compat::...` and keep their call sites unchanged:
+//!
+//! ```
+//! use bendy::serde::compat::{de, ser};
+//!
+//! let bytes = ser::to_bytes(&vec![1, 2]).unwrap();
+//! assert_eq!(ser::to_string(&vec![1, 2]).unwrap(), "li1ei2ee");
+//! assert_eq!(de::from_bytes::<Vec<u8>>(&bytes).unwrap(), [1, 2]);
+//! assert_eq!(de::from_str::<Vec<u8>>("li1ei2ee").unwrap(), [1, 2]);
+//! ```
+//!
+//! Unlike `serde_bencode`, bendy rejects non-canonical input such as unsorted dict keys
+//! or integers with leading zeros, and represents `Option`s, floats and units as
+//! described in the [`serde`](crate::serde) module. There is no equivalent to the
+//! `serde_bencode::value::Value` type; use [`Value`](crate::value::Value) instead.
+
+pub use self::{
+    de::{from_bytes, from_str},
+    ser::{to_bytes, to_string},
+};
+pub use crate::serde::{Error, Result};
+
+/// Serialization functions, like `serde_bencode::ser`.
+pub mod ser {
+    use crate::serde::common::*;
+
+    /// Serialize a value into bencode.
+    pub fn to_bytes<T: Serialize>(b: &T) -> Result<Vec<u8>> {
+        crate::serde::to_bytes(b)
+    }
+
+    /// Serialize a value into bencode, failing if the result isn't valid UTF-8.
+    pub fn to_string<T: Serialize>(b: &T) -> Result<String> {
+        String::from_utf8(to_bytes(b)?)
+            .map_err(|_| Error::CustomEncode("bencode is not valid UTF-8".to_string()))
+    }
+}
+
+/// Deserialization functions, like `serde_bencode::de`.
+pub mod de {
+    use crate::serde::common::*;
+
+    /// Deserialize a value from bencode.
+    pub fn from_bytes<'de, T>(b: &'de [u8]) -> Result<T>
+    where
+        T: Deserialize<'de>,
+    {
+        crate::serde::from_bytes(b)
+    }
+
+    /// Deserialize a value from bencode held in a string.
+    pub fn from_str<'de, T>(s: &'de str) -> Result<T>
+    where
+        T: Deserialize<'de>,
+    {
+        from_bytes(s.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_string_should_reject_binary_output() {
+        assert!(to_string(&serde_bytes::Bytes::new(&[0xff])).is_err());
+        assert_eq!(to_string(&"spam").unwrap(), "4:spam");
+    }
+}
@@ -14,6 +14,8 @@ where
 pub struct Deserializer<'de> {
     forbid_trailing_bytes: bool,
     tokens: Peekable<Tokens<'de>>,
+    /// Whether a token has been read, so that an `Option` is nested in another value
+    nested: bool,
 }
 
 impl<'de> Deserializer<'de> {
@@ -22,6 +24,7 @@ impl<'de> Deserializer<'de> {
         Deserializer {
             forbid_trailing_bytes: false,
             tokens: Decoder::new(input).tokens().peekable(),
+            nested: false,
         }
     }
 
@@ -48,6 +51,7 @@ impl<'de> Deserializer<'de> {
 
 impl<'de> Deserializer<'de> {
     fn next_token(&mut self) -> Result<Token<'de>> {
+        self.nested = true;
         match self.tokens.next() {
             Some(result) => Ok(result?),
             None => Err(Error::Decode(StructureError::UnexpectedEof.into())),
@@ -95,10 +99,13 @@ impl<'de> Deserializer<'de> {
         }
     }
 
-    fn expect_empty_list(&mut self) -> Result<()> {
-        self.expect_list_begin()?;
-        self.expect_end()?;
-        Ok(())
+    /// Accept any of the representations of [`UnitPolicy`](crate::serde::UnitPolicy)
+    fn expect_unit(&mut self) -> Result<()> {
+        match self.next_token()? {
+            Token::List => self.expect_end(),
+            Token::String(b"") | Token::Num("0") => Ok(()),
+            other => Err(decoding::Error::unexpected_token("unit", other.name()).into()),
+        }
     }
 
     fn peek_end(&mut self) -> bool {
@@ -114,7 +121,7 @@ impl<'de> Deserializer<'de> {
     }
 }
 
-impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de> serde::de::Deserializer<'de> for &mut Deserializer<'de> {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
@@ -284,6 +291,15 @@ impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
+        // Only a `None` that isn't nested may be represented like a unit, anything else
+        // would make e.g. `li0ee` ambiguous for an `Option<Option<u8>>`
+        if !self.nested {
+            if let Some(Token::String(b"")) | Some(Token::Num("0")) = self.peek() {
+                self.next_token()?;
+                return visitor.visit_none();
+            }
+        }
+
         self.expect_list_begin()?;
         let value = if self.peek_end() {
             visitor.visit_none()
@@ -298,7 +314,7 @@ impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        self.expect_empty_list()?;
+        self.expect_unit()?;
         visitor.visit_unit()
     }
 
@@ -1,4 +1,4 @@
-///! Serde error and result types
+//! Serde error and result types
 use crate::serde::common::*;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -21,6 +21,15 @@ pub enum Error {
     /// Error that occurs if an f64 is deserialized from an string of length other
     /// than 8
     InvalidF64(usize),
+    /// Error that occurs if an f32 or f64 is serialized while the serializer's
+    /// [`FloatPolicy`](crate::serde::FloatPolicy) rejects floats
+    FloatForbidden,
+    /// Error that occurs if a non-finite float is serialized as a scaled integer, or
+    /// the scaled value doesn't fit into an i64
+    UnrepresentableFloat(f64),
+    /// Error that occurs if a unit is serialized while the serializer's
+    /// [`UnitPolicy`](crate::serde::UnitPolicy) rejects units
+    UnitForbidden,
     /// Error that occurs if a char is deserialized from a string containing more
     /// than one character
     InvalidChar(usize),
@@ -37,6 +46,30 @@ pub enum Error {
     Decode(decoding::Error),
 }
 
+impl Error {
+    /// A stable identifier of the kind of error, like `serde.invalid_bool`, see
+    /// [`decoding::Error::code`]. Errors of the underlying encoder or decoder keep their
+    /// own code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::ArbitraryMapKeysUnsupported => "serde.arbitrary_map_keys_unsupported",
+            Error::MapSerializationCallOrder => "serde.map_serialization_call_order",
+            Error::InvalidBool(_) => "serde.invalid_bool",
+            Error::InvalidF32(_) => "serde.invalid_f32",
+            Error::InvalidF64(_) => "serde.invalid_f64",
+            Error::FloatForbidden => "serde.float_forbidden",
+            Error::UnrepresentableFloat(_) => "serde.unrepresentable_float",
+            Error::UnitForbidden => "serde.unit_forbidden",
+            Error::InvalidChar(_) => "serde.invalid_char",
+            Error::TrailingBytes => "serde.trailing_bytes",
+            Error::CustomEncode(_) => "serde.custom_encode",
+            Error::CustomDecode(_) => "serde.custom_decode",
+            Error::Encode(error) => error.code(),
+            Error::Decode(error) => error.code(),
+        }
+    }
+}
+
 impl From<encoding::Error> for Error {
     fn from(encoding_error: encoding::Error) -> Self {
         Error::Encode(encoding_error)
@@ -90,6 +123,23 @@ impl Display for Error {
             Error::InvalidF64(length) => {
                 write!(f, "Invalid length byte string value for f64: {}", length)
             },
+            Error::FloatForbidden => write!(
+                f,
+                "Floats are rejected by the float policy of the serializer, as bencode \
+                 has no float type",
+            ),
+            Error::UnrepresentableFloat(value) => {
+                write!(
+                    f,
+                    "Float cannot be represented as a scaled integer: {}",
+                    value
+                )
+            },
+            Error::UnitForbidden => write!(
+                f,
+                "Units are rejected by the unit policy of the serializer, as bencode has \
+                 no unit type",
+            ),
             Error::InvalidChar(length) => {
                 write!(f, "Invalid length string value for char: {}", length)
             },
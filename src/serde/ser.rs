@@ -18,10 +18,79 @@ where
     serializer.into_bytes()
 }
 
+/// How a [`Serializer`] represents `f32` and `f64` values.
+///
+/// Bencode has no floating point type, and any conversion into one of its types may be
+/// lossy or non-canonical. Values that end up in hashed data, like the `info` dict of a
+/// torrent, should not depend on such a conversion by accident, so floats are rejected
+/// unless another policy is chosen with [`Serializer::with_float_policy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloatPolicy {
+    /// Fail with [`Error::FloatForbidden`].
+    Reject,
+    /// A byte string containing the big-endian IEEE-754 representation of the value,
+    /// which [`Deserializer`](crate::serde::Deserializer) reads back.
+    BigEndianBytes,
+    /// A string containing the shortest decimal representation of the value that
+    /// round-trips, e.g. `3:1.5`.
+    AsString,
+    /// An integer containing the value multiplied by `10^n` and rounded to the nearest
+    /// integer, e.g. `i150e` for `1.5` with `n = 2`.
+    AsScaledInt(u32),
+}
+
+// `#[default]` on enum variants requires a newer compiler than the MSRV
+#[allow(clippy::derivable_impls)]
+impl Default for FloatPolicy {
+    fn default() -> Self {
+        FloatPolicy::Reject
+    }
+}
+
+/// How a [`Serializer`] represents `()`, unit structs and a `None` that isn't nested in
+/// another value.
+///
+/// Bencode has no unit type. Encoding units as empty lists makes them indistinguishable
+/// from empty sequences and `None`, so units are rejected unless another policy is
+/// chosen with [`Serializer::with_unit_policy`]. A nested `None` is always the empty list,
+/// as `Some` values are lists holding the value. The
+/// [`Deserializer`](crate::serde::Deserializer) accepts units, and `None`, in any of these
+/// representations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnitPolicy {
+    /// Fail with [`Error::UnitForbidden`].
+    Reject,
+    /// The empty list, `le`.
+    EmptyList,
+    /// The empty string, `0:`.
+    EmptyString,
+    /// The integer zero, `i0e`.
+    Zero,
+}
+
+// `#[default]` on enum variants requires a newer compiler than the MSRV
+#[allow(clippy::derivable_impls)]
+impl Default for UnitPolicy {
+    fn default() -> Self {
+        UnitPolicy::Reject
+    }
+}
+
+/// The policies of a serializer, which are passed on to sub-serializers
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Policies {
+    float: FloatPolicy,
+    unit: UnitPolicy,
+}
+
 /// A serde Bencode serializer
 #[derive(Default)]
 pub struct Serializer {
     encoder: Encoder,
+    policies: Policies,
+    /// Whether a list or dict has been started, so that a `None` is the content of a
+    /// container and not the serialized value itself
+    nested: bool,
 }
 
 impl Serializer {
@@ -34,27 +103,92 @@ impl Serializer {
     pub fn with_max_depth(max_depth: usize) -> Serializer {
         Serializer {
             encoder: Encoder::new().with_max_depth(max_depth),
+            policies: Policies::default(),
+            nested: false,
+        }
+    }
+
+    /// Create a `Serializer` for a value nested in a dict
+    pub(crate) fn nested(max_depth: usize, policies: Policies) -> Serializer {
+        Serializer {
+            encoder: Encoder::new().with_max_depth(max_depth),
+            policies,
+            nested: true,
         }
     }
 
+    /// Represent `f32` and `f64` values according to `float_policy` instead of rejecting
+    /// them
+    pub fn with_float_policy(mut self, float_policy: FloatPolicy) -> Self {
+        self.policies.float = float_policy;
+        self
+    }
+
+    /// Represent units according to `unit_policy` instead of rejecting them
+    pub fn with_unit_policy(mut self, unit_policy: UnitPolicy) -> Self {
+        self.policies.unit = unit_policy;
+        self
+    }
+
     /// Consume this `Serializer`, returning the encoded bencode
     pub fn into_bytes(self) -> Result<Vec<u8>> {
         Ok(self.encoder.get_output()?)
     }
 
+    fn emit_float(&mut self, v: f64, big_endian_bytes: &[u8]) -> Result<()> {
+        match self.policies.float {
+            FloatPolicy::Reject => return Err(Error::FloatForbidden),
+            FloatPolicy::BigEndianBytes => self.encoder.emit_bytes(big_endian_bytes)?,
+            FloatPolicy::AsString => self.encoder.emit_display(&v)?,
+            FloatPolicy::AsScaledInt(n) => {
+                let scaled = (v * 10f64.powi(n as i32)).round();
+                // `i64::MAX as f64` rounds up to 2^63, which is already out of range
+                if !(scaled >= i64::MIN as f64 && scaled < i64::MAX as f64) {
+                    return Err(Error::UnrepresentableFloat(v));
+                }
+                self.encoder.emit(scaled as i64)?;
+            },
+        }
+        Ok(())
+    }
+
     fn emit_empty_list(&mut self) -> Result<()> {
         self.encoder.emit_list(|_| Ok(()))?;
         Ok(())
     }
 
-    fn begin_struct(&mut self) -> Result<StructSerializer> {
+    fn emit_unit(&mut self) -> Result<()> {
+        match self.policies.unit {
+            UnitPolicy::Reject => return Err(Error::UnitForbidden),
+            UnitPolicy::EmptyList => self.emit_empty_list()?,
+            UnitPolicy::EmptyString => self.encoder.emit_bytes(&[])?,
+            UnitPolicy::Zero => self.encoder.emit(0)?,
+        }
+        Ok(())
+    }
+
+    fn begin_list(&mut self) -> Result<()> {
+        self.nested = true;
+        self.encoder.emit_token(Token::List)?;
+        Ok(())
+    }
+
+    fn begin_struct(&mut self) -> Result<StructSerializer<'_>> {
         let encoder = self.encoder.begin_unsorted_dict()?;
-        Ok(StructSerializer::new(&mut self.encoder, encoder))
+        Ok(StructSerializer::new(
+            &mut self.encoder,
+            encoder,
+            self.policies,
+        ))
     }
 
-    fn begin_map(&mut self) -> Result<MapSerializer> {
+    fn begin_map(&mut self) -> Result<MapSerializer<'_>> {
         let encoder = self.encoder.begin_unsorted_dict()?;
-        Ok(MapSerializer::new(&mut self.encoder, encoder))
+        Ok(MapSerializer::new(
+            &mut self.encoder,
+            encoder,
+            self.policies,
+        ))
     }
 }
 
@@ -125,13 +259,16 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
     }
 
     fn serialize_f32(self, v: f32) -> Result<()> {
-        let bytes = v.to_bits().to_be_bytes();
-        self.serialize_bytes(&bytes)
+        // Displaying the `f32` itself keeps `AsString` from printing widening artifacts
+        if self.policies.float == FloatPolicy::AsString {
+            self.encoder.emit_display(&v)?;
+            return Ok(());
+        }
+        self.emit_float(f64::from(v), &v.to_bits().to_be_bytes())
     }
 
     fn serialize_f64(self, v: f64) -> Result<()> {
-        let bytes = v.to_bits().to_be_bytes();
-        self.serialize_bytes(&bytes)
+        self.emit_float(v, &v.to_bits().to_be_bytes())
     }
 
     fn serialize_char(self, v: char) -> Result<()> {
@@ -149,25 +286,29 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
     }
 
     fn serialize_none(self) -> Result<()> {
-        self.emit_empty_list()
+        if self.nested {
+            self.emit_empty_list()
+        } else {
+            self.emit_unit()
+        }
     }
 
     fn serialize_some<T>(self, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        self.encoder.emit_token(Token::List)?;
+        self.begin_list()?;
         value.serialize(&mut *self)?;
         self.encoder.emit_token(Token::End)?;
         Ok(())
     }
 
     fn serialize_unit(self) -> Result<()> {
-        self.emit_empty_list()
+        self.emit_unit()
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
-        self.emit_empty_list()
+        self.emit_unit()
     }
 
     fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
@@ -178,12 +319,12 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        self.encoder.emit_token(Token::List)?;
+        self.begin_list()?;
         Ok(self)
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        self.encoder.emit_token(Token::List)?;
+        self.begin_list()?;
         Ok(self)
     }
 
@@ -192,7 +333,7 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        self.encoder.emit_token(Token::List)?;
+        self.begin_list()?;
         Ok(self)
     }
 
@@ -219,6 +360,7 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
+        self.nested = true;
         self.encoder.emit_token(Token::Dict)?;
         self.serialize_str(variant)?;
         value.serialize(&mut *self)?;
@@ -239,7 +381,7 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
     ) -> Result<Self::SerializeTupleVariant> {
         self.encoder.emit_token(Token::Dict)?;
         self.serialize_str(variant)?;
-        self.encoder.emit_token(Token::List)?;
+        self.begin_list()?;
         Ok(self)
     }
 
@@ -256,7 +398,7 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
     }
 }
 
-impl<'a> SerializeSeq for &'a mut Serializer {
+impl SerializeSeq for &mut Serializer {
     type Error = Error;
     type Ok = ();
 
@@ -273,7 +415,7 @@ impl<'a> SerializeSeq for &'a mut Serializer {
     }
 }
 
-impl<'a> SerializeTuple for &'a mut Serializer {
+impl SerializeTuple for &mut Serializer {
     type Error = Error;
     type Ok = ();
 
@@ -290,7 +432,7 @@ impl<'a> SerializeTuple for &'a mut Serializer {
     }
 }
 
-impl<'a> SerializeTupleStruct for &'a mut Serializer {
+impl SerializeTupleStruct for &mut Serializer {
     type Error = Error;
     type Ok = ();
 
@@ -307,7 +449,7 @@ impl<'a> SerializeTupleStruct for &'a mut Serializer {
     }
 }
 
-impl<'a> SerializeMap for &'a mut Serializer {
+impl SerializeMap for &mut Serializer {
     type Error = Error;
     type Ok = ();
 
@@ -330,7 +472,7 @@ impl<'a> SerializeMap for &'a mut Serializer {
     }
 }
 
-impl<'a> SerializeTupleVariant for &'a mut Serializer {
+impl SerializeTupleVariant for &mut Serializer {
     type Error = Error;
     type Ok = ();
 
@@ -348,7 +490,7 @@ impl<'a> SerializeTupleVariant for &'a mut Serializer {
     }
 }
 
-impl<'a> SerializeStructVariant for &'a mut Serializer {
+impl SerializeStructVariant for &mut Serializer {
     type Error = Error;
     type Ok = ();
 
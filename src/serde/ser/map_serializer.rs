@@ -4,6 +4,7 @@ use crate::serde::common::*;
 pub struct MapSerializer<'outer> {
     pub(crate) outer: &'outer mut Encoder,
     encoder: UnsortedDictEncoder,
+    policies: Policies,
     key: Option<Vec<u8>>,
 }
 
@@ -11,10 +12,12 @@ impl<'outer> MapSerializer<'outer> {
     pub(crate) fn new(
         outer: &'outer mut Encoder,
         encoder: UnsortedDictEncoder,
+        policies: Policies,
     ) -> MapSerializer<'outer> {
         MapSerializer {
             encoder,
             outer,
+            policies,
             key: None,
         }
     }
@@ -23,7 +26,7 @@ impl<'outer> MapSerializer<'outer> {
     where
         T: ?Sized + Serialize,
     {
-        let mut serializer = Serializer::with_max_depth(self.encoder.remaining_depth());
+        let mut serializer = Serializer::nested(self.encoder.remaining_depth(), self.policies);
         value.serialize(&mut serializer)?;
         serializer.into_bytes()
     }
@@ -4,21 +4,27 @@ use crate::serde::common::*;
 pub struct StructSerializer<'outer> {
     pub(crate) outer: &'outer mut Encoder,
     encoder: UnsortedDictEncoder,
+    policies: Policies,
 }
 
 impl<'outer> StructSerializer<'outer> {
     pub(crate) fn new(
         outer: &'outer mut Encoder,
         encoder: UnsortedDictEncoder,
+        policies: Policies,
     ) -> StructSerializer<'outer> {
-        StructSerializer { outer, encoder }
+        StructSerializer {
+            outer,
+            encoder,
+            policies,
+        }
     }
 
     fn save_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        let mut serializer = Serializer::with_max_depth(self.encoder.remaining_depth());
+        let mut serializer = Serializer::nested(self.encoder.remaining_depth(), self.policies);
         value.serialize(&mut serializer)?;
         let value_bytes = serializer.into_bytes()?;
 
@@ -0,0 +1,340 @@
+//! Detached signatures over parts of a bencoded document.
+//!
+//! Some formats, like the signed torrents of [BEP 35], authenticate a single subtree of a
+//! document (e.g. the `info` dict) and store the signature next to it. Since the decoder
+//! only accepts canonical bencode, the raw bytes of a subtree are its canonical
+//! representation and can be signed as they are, without re-encoding anything.
+//!
+//! An [`Envelope`] selects the signed subtree by its path of dictionary keys and the
+//! top-level field holding the signature. The signature itself is stored as a dict with
+//! the `pubkey` and `sig` keys (see [`DetachedSignature`]). The actual cryptography is
+//! left to implementations of [`Signer`] and [`Verifier`].
+//!
+//! ```
+//! use bendy::signing::{Envelope, Signer, Verifier};
+//!
+//! // A (very insecure) signature scheme for demonstration purposes
+//! struct Xor(u8);
+//!
+//! impl Signer for Xor {
+//!     fn public_key(&self) -> Vec<u8> {
+//!         vec![self.0]
+//!     }
+//!
+//!     fn sign(&self, message: &[u8]) -> Vec<u8> {
+//!         message.iter().map(|byte| byte ^ self.0).collect()
+//!     }
+//! }
+//!
+//! impl Verifier for Xor {
+//!     fn verify(&self, public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+//!         public_key == [self.0] && self.sign(message) == signature
+//!     }
+//! }
+//!
+//! let envelope = Envelope::new(&[b"info"]);
+//! let signed = envelope.sign(b"d4:infod4:name3:fooee", &Xor(1)).unwrap();
+//! assert!(signed.starts_with(b"d4:infod4:name3:fooe9:signatured6:pubkey1:\x013:sig13:"));
+//!
+//! assert!(envelope.verify(&signed, &Xor(1)).unwrap());
+//! assert!(!envelope.verify(&signed, &Xor(2)).unwrap());
+//! ```
+//!
+//! [BEP 35]: http://bittorrent.org/beps/bep_0035.html
+
+use alloc::format;
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+use crate::{
+    decoding::{self, Cursor, FromBencode, Object, ResultExt},
+    encoding::{self, AsString, SingleItemEncoder, ToBencode},
+};
+
+/// The field an [`Envelope`] stores the signature in unless configured otherwise.
+pub const DEFAULT_SIGNATURE_FIELD: &[u8] = b"signature";
+
+/// Creates signatures for an [`Envelope`].
+pub trait Signer {
+    /// The public key that verifies the signatures of this signer.
+    fn public_key(&self) -> Vec<u8>;
+
+    /// Sign the given message.
+    fn sign(&self, message: &[u8]) -> Vec<u8>;
+}
+
+/// Checks signatures for an [`Envelope`].
+pub trait Verifier {
+    /// Check that `signature` is a valid signature of `message` under `public_key`.
+    fn verify(&self, public_key: &[u8], message: &[u8], signature: &[u8]) -> bool;
+}
+
+/// A signature stored separately from the data it signs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DetachedSignature {
+    /// The public key of the signer (`pubkey`)
+    pub public_key: Vec<u8>,
+    /// The signature itself (`sig`)
+    pub signature: Vec<u8>,
+}
+
+impl ToBencode for DetachedSignature {
+    const MAX_DEPTH: usize = 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair(b"pubkey", AsString(&self.public_key))?;
+            e.emit_pair(b"sig", AsString(&self.signature))
+        })
+    }
+}
+
+impl FromBencode for DetachedSignature {
+    const EXPECTED_RECURSION_DEPTH: usize = 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut public_key = None;
+        let mut signature = None;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"pubkey", value) => {
+                    public_key = AsString::decode_bencode_object(value)
+                        .context("pubkey")
+                        .map(|bytes| Some(bytes.0))?;
+                },
+                (b"sig", value) => {
+                    signature = AsString::decode_bencode_object(value)
+                        .context("sig")
+                        .map(|bytes| Some(bytes.0))?;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        Ok(DetachedSignature {
+            public_key: public_key.ok_or_else(|| decoding::Error::missing_field("pubkey"))?,
+            signature: signature.ok_or_else(|| decoding::Error::missing_field("sig"))?,
+        })
+    }
+}
+
+/// Describes which part of a document is signed and where the signature is stored.
+#[derive(Clone, Copy, Debug)]
+pub struct Envelope<'a> {
+    path: &'a [&'a [u8]],
+    field: &'a [u8],
+}
+
+impl<'a> Envelope<'a> {
+    /// Create an envelope signing the subtree at `path`, a sequence of dictionary keys
+    /// starting at the top-level dict of the document. The signature is stored in the
+    /// [`DEFAULT_SIGNATURE_FIELD`] of the top-level dict.
+    ///
+    /// # Panics
+    ///
+    /// If `path` is empty, as the document itself can't contain its own signature.
+    pub fn new(path: &'a [&'a [u8]]) -> Self {
+        assert!(
+            !path.is_empty(),
+            "the signed subtree must not be the whole document"
+        );
+
+        Envelope {
+            path,
+            field: DEFAULT_SIGNATURE_FIELD,
+        }
+    }
+
+    /// Store the signature in the given top-level field instead.
+    ///
+    /// # Panics
+    ///
+    /// If the field would be part of the signed subtree.
+    pub fn with_field(mut self, field: &'a [u8]) -> Self {
+        assert_ne!(
+            self.path[0], field,
+            "the signature must not be part of the signed subtree"
+        );
+
+        self.field = field;
+        self
+    }
+
+    /// The canonical bytes of the signed subtree.
+    pub fn signed_bytes<'doc>(&self, document: &'doc [u8]) -> Result<&'doc [u8], decoding::Error> {
+        let mut cursor = Cursor::dict_document(document)?;
+        for key in self.path {
+            cursor = cursor
+                .get(key)
+                .ok_or_else(|| decoding::Error::missing_field(String::from_utf8_lossy(key)))?;
+        }
+
+        Ok(cursor.raw())
+    }
+
+    /// The signature stored in the document, if there is one.
+    pub fn signature(&self, document: &[u8]) -> Result<Option<DetachedSignature>, decoding::Error> {
+        Cursor::dict_document(document)?
+            .get(self.field)
+            .map(|value| {
+                DetachedSignature::from_bencode(value.raw())
+                    .context(String::from_utf8_lossy(self.field))
+            })
+            .transpose()
+    }
+
+    /// Sign the document, returning a copy with the signature attached. Any previous
+    /// signature is replaced.
+    pub fn sign(&self, document: &[u8], signer: &impl Signer) -> Result<Vec<u8>, decoding::Error> {
+        let signature = DetachedSignature {
+            signature: signer.sign(self.signed_bytes(document)?),
+            public_key: signer.public_key(),
+        };
+
+        self.attach(document, &signature)
+    }
+
+    /// Attach a signature created elsewhere to the document, replacing any previous one.
+    pub fn attach(
+        &self,
+        document: &[u8],
+        signature: &DetachedSignature,
+    ) -> Result<Vec<u8>, decoding::Error> {
+        let root = Cursor::dict_document(document)?;
+
+        // The signature dict is written by hand, as its structure is fixed
+        let mut value = Vec::new();
+        value.push(b'd');
+        push_string(&mut value, b"pubkey");
+        push_string(&mut value, &signature.public_key);
+        push_string(&mut value, b"sig");
+        push_string(&mut value, &signature.signature);
+        value.push(b'e');
+
+        let mut output = Vec::with_capacity(document.len() + self.field.len() + value.len() + 8);
+        let mut attached = false;
+        output.push(b'd');
+
+        let mut children = root.children();
+        while let (Some(key), Some(pair_value)) = (children.next(), children.next()) {
+            let name = key.as_bytes().unwrap_or_default();
+            if !attached && name >= self.field {
+                push_string(&mut output, self.field);
+                output.extend_from_slice(&value);
+                attached = true;
+            }
+            if name != self.field {
+                output.extend_from_slice(key.raw());
+                output.extend_from_slice(pair_value.raw());
+            }
+        }
+
+        if !attached {
+            push_string(&mut output, self.field);
+            output.extend_from_slice(&value);
+        }
+        output.push(b'e');
+
+        Ok(output)
+    }
+
+    /// Check the signature attached to the document.
+    ///
+    /// Returns an error if the document is malformed or doesn't contain a signature, and
+    /// `Ok(false)` if the signature doesn't match.
+    pub fn verify(
+        &self,
+        document: &[u8],
+        verifier: &impl Verifier,
+    ) -> Result<bool, decoding::Error> {
+        let signature = self
+            .signature(document)?
+            .ok_or_else(|| decoding::Error::missing_field(String::from_utf8_lossy(self.field)))?;
+        let message = self.signed_bytes(document)?;
+
+        Ok(verifier.verify(&signature.public_key, message, &signature.signature))
+    }
+}
+
+fn push_string(output: &mut Vec<u8>, content: &[u8]) {
+    output.extend_from_slice(format!("{}:", content.len()).as_bytes());
+    output.extend_from_slice(content);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Sum;
+
+    impl Signer for Sum {
+        fn public_key(&self) -> Vec<u8> {
+            b"sum".to_vec()
+        }
+
+        fn sign(&self, message: &[u8]) -> Vec<u8> {
+            let sum = message
+                .iter()
+                .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+            Vec::from([sum])
+        }
+    }
+
+    impl Verifier for Sum {
+        fn verify(&self, public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+            public_key == b"sum" && self.sign(message) == signature
+        }
+    }
+
+    static DOCUMENT: &[u8] = b"d1:ai1e4:infod4:name3:fooe1:zi2ee";
+
+    #[test]
+    fn signature_should_be_inserted_in_order() {
+        let signed = Envelope::new(&[b"info"]).sign(DOCUMENT, &Sum).unwrap();
+        assert!(crate::is_valid(&signed).is_ok());
+        assert!(signed.starts_with(b"d1:ai1e4:infod4:name3:fooe9:signatured"));
+        assert!(signed.ends_with(b"e1:zi2ee"));
+    }
+
+    #[test]
+    fn existing_signature_should_be_replaced() {
+        let envelope = Envelope::new(&[b"info", b"name"]).with_field(b"sig");
+        let stale = DetachedSignature {
+            public_key: b"sum".to_vec(),
+            signature: b"stale".to_vec(),
+        };
+
+        let attached = envelope.attach(DOCUMENT, &stale).unwrap();
+        assert!(!envelope.verify(&attached, &Sum).unwrap());
+        assert_eq!(envelope.signature(&attached).unwrap(), Some(stale));
+
+        let signed = envelope.sign(&attached, &Sum).unwrap();
+        assert_eq!(signed, envelope.sign(DOCUMENT, &Sum).unwrap());
+        assert!(envelope.verify(&signed, &Sum).unwrap());
+    }
+
+    #[test]
+    fn tampering_should_be_detected() {
+        let envelope = Envelope::new(&[b"info"]);
+        let mut signed = envelope.sign(DOCUMENT, &Sum).unwrap();
+        assert!(envelope.verify(&signed, &Sum).unwrap());
+
+        // Change the name from "foo" to "poo"
+        signed[22] = b'p';
+        assert!(!envelope.verify(&signed, &Sum).unwrap());
+    }
+
+    #[test]
+    fn missing_parts_should_fail() {
+        let envelope = Envelope::new(&[b"info"]);
+        assert!(envelope.verify(DOCUMENT, &Sum).is_err());
+        assert!(envelope.sign(b"d1:ai1ee", &Sum).is_err());
+        assert!(envelope.sign(b"li1ee", &Sum).is_err());
+        assert!(envelope.sign(b"d4:infodeei1e", &Sum).is_err());
+    }
+}
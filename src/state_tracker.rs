@@ -4,4 +4,8 @@ mod structure_error;
 mod token;
 
 pub use self::token::Token;
-pub(crate) use self::{stack::Stack, state::StateTracker, structure_error::StructureError};
+pub(crate) use self::{
+    stack::Stack,
+    state::{OwnedKey, StateTracker},
+    structure_error::StructureError,
+};
@@ -1,8 +1,16 @@
-#[cfg(not(feature = "std"))]
-use alloc::vec::Vec;
+use smallvec::SmallVec;
 
 use crate::state_tracker::{Stack, StructureError, Token};
 
+/// The number of levels, including a latched error, kept without allocating. Deeper
+/// structures move the levels to the heap.
+const INLINE_LEVELS: usize = 8;
+
+/// The last key of a dict as tracked by the encoders, which can't borrow it from their
+/// input like the decoder. Keys of up to 32 bytes are kept inline, so encoding the dicts
+/// of most documents doesn't allocate.
+pub type OwnedKey = SmallVec<[u8; 32]>;
+
 /// The state of current level of the decoder
 #[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
 enum State<S: AsRef<[u8]>, E> {
@@ -17,17 +25,19 @@ enum State<S: AsRef<[u8]>, E> {
 }
 
 /// Used to validate that a structure is valid
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct StateTracker<S: AsRef<[u8]>, E = StructureError> {
-    state: Vec<State<S, E>>,
+    state: SmallVec<[State<S, E>; INLINE_LEVELS]>,
     max_depth: usize,
+    reject_empty_keys: bool,
 }
 
 impl<S: AsRef<[u8]>, E> Default for StateTracker<S, E> {
     fn default() -> Self {
         StateTracker {
-            state: Vec::with_capacity(2048),
+            state: SmallVec::new(),
             max_depth: 2048,
+            reject_empty_keys: false,
         }
     }
 }
@@ -45,6 +55,15 @@ where
         self.max_depth = new_max_depth
     }
 
+    pub fn set_reject_empty_keys(&mut self, reject: bool) {
+        self.reject_empty_keys = reject
+    }
+
+    /// Forget all open containers and any latched error, keeping the limits
+    pub fn reset(&mut self) {
+        self.state.clear()
+    }
+
     pub fn remaining_depth(&self) -> usize {
         self.max_depth - self.state.len()
     }
@@ -77,6 +96,10 @@ where
             (Some(Seq), End) | (Some(MapKey(_)), End) => {
                 self.state.pop();
             },
+            (Some(MapKey(None)), String(label)) if label.is_empty() && self.reject_empty_keys => {
+                self.state.pop();
+                return self.latch_err(Err(E::from(StructureError::EmptyKey)));
+            },
             (Some(MapKey(None)), String(label)) => {
                 self.state[last_index] = MapValue(S::from(label)); //TODO: looks similar!
             },
@@ -95,7 +118,7 @@ where
             },
             (Some(MapValue(label)), List) | (Some(MapValue(label)), Dict) => {
                 let dummy: &[u8] = &[1];
-                self.state[last_index] = MapKey(Some(std::mem::replace(label, dummy.into())));
+                self.state[last_index] = MapKey(Some(core::mem::replace(label, dummy.into())));
                 if self.state.len() >= self.max_depth {
                     return self.latch_err(Err(E::from(StructureError::NestingTooDeep)));
                 }
@@ -110,10 +133,10 @@ where
             },
             (Some(MapValue(label)), _) => {
                 let dummy: &[u8] = &[1];
-                self.state[last_index] = MapKey(Some(std::mem::replace(label, dummy.into())));
+                self.state[last_index] = MapKey(Some(core::mem::replace(label, dummy.into())));
             },
             (oldstate, List) | (oldstate, Dict) => {
-                if oldstate.is_none() && 0 < self.state.len() {
+                if oldstate.is_none() && !self.state.is_empty() {
                     self.state.pop();
                 }
                 if self.state.len() >= self.max_depth {
@@ -138,8 +161,15 @@ where
         result
     }
 
+    /// Update the latched error, e.g. with details only known to the caller
+    pub fn update_err(&mut self, update: impl FnOnce(E) -> E) {
+        if let Some(State::Failed(error)) = self.state.last_mut() {
+            *error = update(error.clone());
+        }
+    }
+
     pub fn check_error(&self) -> Result<(), E> {
-        if let Some(&State::Failed(ref error)) = self.state.peek() {
+        if let Some(State::Failed(error)) = self.state.peek() {
             Err(error.clone())
         } else {
             Ok(())
@@ -12,6 +12,7 @@ use snafu::Snafu;
 
 /// An encoding or decoding error
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Snafu)]
+#[non_exhaustive]
 pub enum StructureError {
     /// Wrong type of token detected.
     #[snafu(display("Saw the wrong type of token: {}", state))]
@@ -21,6 +22,10 @@ pub enum StructureError {
     #[snafu(display("Keys were not sorted"))]
     UnsortedKeys,
 
+    /// A dict contained an empty key, which was rejected.
+    #[snafu(display("Empty dict key"))]
+    EmptyKey,
+
     /// EOF reached to early.
     #[snafu(display("Reached EOF in the middle of a message"))]
     UnexpectedEof,
@@ -35,6 +40,20 @@ pub enum StructureError {
 }
 
 impl StructureError {
+    /// A stable identifier of the kind of error, see [`decoding::Error::code`].
+    ///
+    /// [`decoding::Error::code`]: crate::decoding::Error::code
+    pub fn code(&self) -> &'static str {
+        match self {
+            StructureError::InvalidState { .. } => "structure.invalid_state",
+            StructureError::UnsortedKeys => "structure.unsorted_keys",
+            StructureError::EmptyKey => "structure.empty_key",
+            StructureError::UnexpectedEof => "structure.unexpected_eof",
+            StructureError::SyntaxError { .. } => "structure.syntax_error",
+            StructureError::NestingTooDeep => "structure.nesting_too_deep",
+        }
+    }
+
     pub fn unexpected(expected: impl Display, got: char, offset: usize) -> Self {
         StructureError::SyntaxError {
             unexpected: format!("Expected {}, got {:?} at offset {}", expected, got, offset),
@@ -0,0 +1,419 @@
+//! Helpers for testing encoders.
+//!
+//! This module is only available with the `testing` feature.
+//!
+//! The [`assert_encodes_to!`](crate::assert_encodes_to) macro compares the encoding of a
+//! value with the expected bencode. Instead of printing two long byte strings on failure,
+//! it reports the first token where both diverge, together with its path and offsets:
+//!
+//! ```should_panic
+//! use bendy::assert_encodes_to;
+//!
+//! assert_encodes_to!(vec![1, 2, 3], b"li1ei2ei4ee");
+//! // panics with:
+//! // first difference at token 3 (path `[2]`)
+//! //   actual:   Num(3) at offset 7
+//! //   expected: Num(4) at offset 7
+//! ```
+//!
+//! # Snapshot tests
+//!
+//! [`Pretty`] renders bencode as indented, line-based text with one value per line.
+//! Its output is deterministic, so it can be stored as a golden file or used with
+//! snapshot testing libraries such as `insta` (e.g. `assert_snapshot!(to_pretty_string(&output)?)`),
+//! which then show meaningful line diffs when an encoding changes:
+//!
+//! ```
+//! use bendy::testing::to_pretty_string;
+//!
+//! let pretty =
+//!     to_pretty_string(b"d4:infod6:lengthi42e6:pieces2:\x00\xffe4:tagsl3:fooee").unwrap();
+//! assert_eq!(
+//!     pretty,
+//!     r#"{
+//!   "info": {
+//!     "length": 42,
+//!     "pieces": <2 bytes: 00ff>,
+//!   },
+//!   "tags": [
+//!     "foo",
+//!   ],
+//! }
+//! "#
+//! );
+//! ```
+
+use alloc::{format, string::ToString};
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+use core::fmt::{self, Display, Formatter, Write};
+
+use crate::{decoding::Decoder, hex::Hex, state_tracker::Token};
+
+pub mod alloc_audit;
+#[cfg(feature = "differential")]
+pub mod differential;
+pub mod mock;
+
+/// Assert that a value implementing [`ToBencode`](crate::encoding::ToBencode) encodes to
+/// the expected bytes, reporting the first differing token on failure.
+///
+/// ```
+/// use bendy::assert_encodes_to;
+///
+/// assert_encodes_to!("spam", b"4:spam");
+/// assert_encodes_to!(vec![1, 2], b"li1ei2ee", "lists should be encoded in order");
+/// ```
+#[macro_export]
+macro_rules! assert_encodes_to {
+    ($value:expr, $expected:expr $(,)?) => {
+        $crate::assert_encodes_to!($value, $expected, "`{}` is encoded differently", stringify!($value))
+    };
+    ($value:expr, $expected:expr, $($message:tt)+) => {{
+        let actual = $crate::encoding::ToBencode::to_bencode(&$value)
+            .expect("the value should be encodable");
+        let expected: &[u8] = $expected.as_ref();
+        if let Some(difference) = $crate::testing::first_difference(&actual, expected) {
+            panic!("assertion failed: {}\n{}", format_args!($($message)+), difference);
+        }
+    }};
+}
+
+/// The first difference between two bencoded byte strings, see [`first_difference`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Difference {
+    /// The index of the first differing token
+    pub token_index: usize,
+    /// The location of the token within the structure, e.g. `info.files[2].length`
+    pub path: String,
+    /// The differing token of the actual input and its byte offset
+    pub actual: (String, usize),
+    /// The differing token of the expected input and its byte offset
+    pub expected: (String, usize),
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "first difference at token {} (path `{}`)",
+            self.token_index, self.path
+        )?;
+        writeln!(
+            f,
+            "  actual:   {} at offset {}",
+            self.actual.0, self.actual.1
+        )?;
+        write!(
+            f,
+            "  expected: {} at offset {}",
+            self.expected.0, self.expected.1
+        )
+    }
+}
+
+/// Compare two bencoded byte strings token by token. Returns `None` if they are equal.
+///
+/// Invalid input is tokenized up to the first error, which is reported as a token of its
+/// own.
+pub fn first_difference(actual: &[u8], expected: &[u8]) -> Option<Difference> {
+    if actual == expected {
+        return None;
+    }
+
+    let mut actual_tokens = Decoder::new(actual).tokens();
+    let mut expected_tokens = Decoder::new(expected).tokens();
+    let mut path = Path::default();
+    let mut offsets = (0, 0);
+    let mut token_index = 0;
+
+    loop {
+        let left = describe(actual_tokens.next());
+        let right = describe(expected_tokens.next());
+
+        match (&left, &right) {
+            (Ok(left), Ok(right)) if left == right && left.is_some() => {
+                let token = left.unwrap();
+                path.observe(&token);
+                offsets.0 += token_len(&token);
+                offsets.1 += token_len(&token);
+                token_index += 1;
+            },
+            _ => {
+                return Some(Difference {
+                    token_index,
+                    path: path.to_string(),
+                    actual: (display(left), offsets.0),
+                    expected: (display(right), offsets.1),
+                });
+            },
+        }
+    }
+}
+
+fn describe<'a>(
+    token: Option<Result<Token<'a>, crate::decoding::Error>>,
+) -> Result<Option<Token<'a>>, String> {
+    token.transpose().map_err(|error| error.to_string())
+}
+
+fn display(token: Result<Option<Token>, String>) -> String {
+    match token {
+        Ok(Some(Token::String(bytes))) => format!("String({:?})", Escaped(bytes)),
+        Ok(Some(Token::Num(number))) => format!("Num({})", number),
+        Ok(Some(token)) => token.name().to_string(),
+        Ok(None) => "end of input".to_string(),
+        Err(error) => format!("error ({})", error),
+    }
+}
+
+fn token_len(token: &Token) -> usize {
+    match token {
+        Token::String(bytes) => bytes.len().to_string().len() + 1 + bytes.len(),
+        Token::Num(number) => number.len() + 2,
+        Token::List | Token::Dict | Token::End => 1,
+    }
+}
+
+/// Tracks the location within the structure while walking the token stream
+#[derive(Default)]
+struct Path {
+    segments: Vec<Segment>,
+}
+
+enum Segment {
+    /// A list and the number of elements started so far
+    List(usize),
+    /// A dict, its last key and whether the next token is a key
+    Dict(String, bool),
+}
+
+impl Path {
+    fn observe(&mut self, token: &Token) {
+        // The enclosing container sees a new element unless the token closes it
+        if *token != Token::End {
+            match self.segments.last_mut() {
+                Some(Segment::List(index)) => *index += 1,
+                Some(Segment::Dict(key, expect_key @ true)) => {
+                    if let Token::String(bytes) = token {
+                        *key = String::from_utf8_lossy(bytes).into_owned();
+                    }
+                    *expect_key = false;
+                    return;
+                },
+                Some(Segment::Dict(_, expect_key)) => *expect_key = true,
+                None => (),
+            }
+        }
+
+        match token {
+            Token::List => self.segments.push(Segment::List(0)),
+            Token::Dict => self.segments.push(Segment::Dict(String::new(), true)),
+            Token::End => {
+                self.segments.pop();
+            },
+            _ => (),
+        }
+    }
+}
+
+impl Display for Path {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let innermost = self.segments.len().saturating_sub(1);
+        for (position, segment) in self.segments.iter().enumerate() {
+            match segment {
+                // The innermost container points at the next element, all others at the
+                // one currently being read
+                Segment::List(count) if position == innermost => write!(f, "[{}]", count)?,
+                Segment::List(count) => write!(f, "[{}]", count - 1)?,
+                Segment::Dict(_, true) if position == innermost => (),
+                Segment::Dict(key, _) if position == 0 => f.write_str(key)?,
+                Segment::Dict(key, _) => write!(f, ".{}", key)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Formats a byte string with non-printable bytes escaped
+struct Escaped<'a>(&'a [u8]);
+
+impl fmt::Debug for Escaped<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_char('"')?;
+        for byte in self.0 {
+            for c in core::ascii::escape_default(*byte) {
+                f.write_char(c as char)?;
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// Render bencode as indented text, see the [module documentation](self).
+///
+/// Byte strings are shown as quoted text if they are printable UTF-8 and as hex
+/// otherwise.
+pub fn to_pretty_string(bencode: &[u8]) -> Result<String, crate::decoding::Error> {
+    let mut output = String::new();
+    let mut tokens = Decoder::new(bencode).tokens().peekable();
+    // For each open container: whether it is a dict and the next token is a key
+    let mut containers: Vec<(bool, bool)> = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        let token = token?;
+        let depth = containers.len();
+
+        if token == Token::End {
+            let (is_dict, _) = containers.pop().unwrap_or_default();
+            push_indent(&mut output, depth - 1);
+            output.push_str(if is_dict { "}" } else { "]" });
+            end_value(&mut output, &containers);
+            continue;
+        }
+
+        match containers.last_mut() {
+            Some((true, expect_key @ true)) => {
+                push_indent(&mut output, depth);
+                if let Token::String(key) = token {
+                    push_bytes(&mut output, key);
+                }
+                output.push_str(": ");
+                *expect_key = false;
+                continue;
+            },
+            Some((true, expect_key)) => *expect_key = true,
+            _ => push_indent(&mut output, depth),
+        }
+
+        match token {
+            Token::List | Token::Dict => {
+                let is_dict = token == Token::Dict;
+                if let Some(Ok(Token::End)) = tokens.peek() {
+                    tokens.next();
+                    output.push_str(if is_dict { "{}" } else { "[]" });
+                    end_value(&mut output, &containers);
+                } else {
+                    output.push_str(if is_dict { "{\n" } else { "[\n" });
+                    containers.push((is_dict, is_dict));
+                }
+            },
+            Token::String(bytes) => {
+                push_bytes(&mut output, bytes);
+                end_value(&mut output, &containers);
+            },
+            Token::Num(number) => {
+                output.push_str(number);
+                end_value(&mut output, &containers);
+            },
+            Token::End => unreachable!("handled above"),
+        }
+    }
+
+    Ok(output)
+}
+
+/// Displays bencode the same way as [`to_pretty_string`]. Invalid input is rendered up
+/// to the error, followed by a description of the error.
+#[derive(Clone, Copy, Debug)]
+pub struct Pretty<'a>(pub &'a [u8]);
+
+impl Display for Pretty<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match to_pretty_string(self.0) {
+            Ok(pretty) => f.write_str(&pretty),
+            Err(error) => write!(f, "<invalid bencode: {}>", error),
+        }
+    }
+}
+
+fn push_indent(output: &mut String, depth: usize) {
+    for _ in 0..depth {
+        output.push_str("  ");
+    }
+}
+
+fn push_bytes(output: &mut String, bytes: &[u8]) {
+    match core::str::from_utf8(bytes) {
+        Ok(text) if !text.chars().any(char::is_control) => {
+            write!(output, "{:?}", text).ok();
+        },
+        _ => {
+            write!(output, "<{} bytes: {}>", bytes.len(), Hex(bytes)).ok();
+        },
+    }
+}
+
+fn end_value(output: &mut String, containers: &[(bool, bool)]) {
+    if !containers.is_empty() {
+        output.push(',');
+    }
+    output.push('\n');
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn equal_input_should_have_no_difference() {
+        assert_eq!(first_difference(b"li1ee", b"li1ee"), None);
+    }
+
+    #[test]
+    fn difference_should_report_path_and_offsets() {
+        let difference = first_difference(
+            b"d4:infod5:filesld6:lengthi1eeeee",
+            b"d4:infod5:filesld6:lengthi2eeeee",
+        )
+        .unwrap();
+
+        assert_eq!(difference.token_index, 7);
+        assert_eq!(difference.path, "info.files[0].length");
+        assert_eq!(difference.actual, ("Num(1)".to_string(), 25));
+        assert_eq!(difference.expected, ("Num(2)".to_string(), 25));
+    }
+
+    #[test]
+    fn truncated_and_invalid_input_should_be_reported() {
+        let difference = first_difference(b"li1ee", b"li1ei2ee").unwrap();
+        assert_eq!(difference.path, "[1]");
+        assert_eq!(difference.actual.0, "End");
+        assert_eq!(difference.expected.0, "Num(2)");
+
+        let difference = first_difference(b"3:a\x00c", b"x").unwrap();
+        assert_eq!(difference.actual.0, "String(\"a\\x00c\")");
+        assert!(difference.expected.0.starts_with("error"));
+    }
+
+    #[test]
+    fn macro_should_accept_matching_values() {
+        assert_encodes_to!(42, b"i42e");
+        assert_encodes_to!(vec!["a", "b"], b"l1:a1:be", "with a {}", "message");
+    }
+
+    #[test]
+    #[should_panic(expected = "path `[1]`")]
+    fn macro_should_panic_with_difference() {
+        assert_encodes_to!(vec![1, 2], b"li1ei3ee");
+    }
+
+    #[test]
+    fn pretty_output_should_handle_nesting_and_empty_containers() {
+        assert_eq!(
+            to_pretty_string(b"ldeleli1eee").unwrap(),
+            "[\n  {},\n  [],\n  [\n    1,\n  ],\n]\n"
+        );
+        assert_eq!(to_pretty_string(b"3:abc").unwrap(), "\"abc\"\n");
+    }
+
+    #[test]
+    fn pretty_should_report_invalid_input() {
+        assert!(to_pretty_string(b"li1e").is_err());
+        assert!(Pretty(b"x").to_string().starts_with("<invalid bencode"));
+    }
+}
@@ -0,0 +1,138 @@
+//! Counting allocations of encoding and decoding workloads.
+//!
+//! The [`CountingAllocator`] wraps any global allocator and counts the allocations made
+//! through it. Installing it in a benchmark or test binary shows the allocation profile of
+//! a workload, e.g. how many small allocations the unsorted dict path of the encoder
+//! makes, which is the part of bendy most sensitive to the choice of global allocator:
+//!
+//! ```
+//! use std::alloc::System;
+//!
+//! use bendy::{encoding::ToBencode, testing::alloc_audit::CountingAllocator};
+//!
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator<System> = CountingAllocator::new(System);
+//!
+//! let (_, stats) = ALLOCATOR.measure(|| vec![1, 2, 3].to_bencode().unwrap());
+//! assert!(stats.allocations > 0);
+//! ```
+//!
+//! To compare allocators, wrap e.g. `mimalloc::MiMalloc` or `jemallocator::Jemalloc`
+//! instead of `System`. The `alloc_profile` example prints the profile of a few typical
+//! workloads.
+//!
+//! The counters are shared by all threads, so measurements should be taken while no other
+//! thread allocates.
+
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    ops::Sub,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Allocations of at most this many bytes are counted as small.
+pub const SMALL_ALLOCATION: usize = 64;
+
+/// A global allocator counting the allocations made through it.
+#[derive(Debug)]
+pub struct CountingAllocator<A> {
+    inner: A,
+    allocations: AtomicUsize,
+    small_allocations: AtomicUsize,
+    reallocations: AtomicUsize,
+    deallocations: AtomicUsize,
+    bytes_allocated: AtomicUsize,
+}
+
+/// The counters of a [`CountingAllocator`] at some point in time, or the difference
+/// between two such points.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocationStats {
+    /// The number of allocations, including reallocations
+    pub allocations: usize,
+    /// The number of allocations of at most [`SMALL_ALLOCATION`] bytes
+    pub small_allocations: usize,
+    /// The number of reallocations
+    pub reallocations: usize,
+    /// The number of deallocations
+    pub deallocations: usize,
+    /// The total number of bytes requested
+    pub bytes_allocated: usize,
+}
+
+impl<A> CountingAllocator<A> {
+    /// Wrap an allocator.
+    pub const fn new(inner: A) -> Self {
+        CountingAllocator {
+            inner,
+            allocations: AtomicUsize::new(0),
+            small_allocations: AtomicUsize::new(0),
+            reallocations: AtomicUsize::new(0),
+            deallocations: AtomicUsize::new(0),
+            bytes_allocated: AtomicUsize::new(0),
+        }
+    }
+
+    /// The current values of all counters.
+    pub fn snapshot(&self) -> AllocationStats {
+        AllocationStats {
+            allocations: self.allocations.load(Ordering::SeqCst),
+            small_allocations: self.small_allocations.load(Ordering::SeqCst),
+            reallocations: self.reallocations.load(Ordering::SeqCst),
+            deallocations: self.deallocations.load(Ordering::SeqCst),
+            bytes_allocated: self.bytes_allocated.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Run a workload and return its result along with the allocations it made.
+    pub fn measure<R>(&self, workload: impl FnOnce() -> R) -> (R, AllocationStats) {
+        let before = self.snapshot();
+        let result = workload();
+        (result, self.snapshot() - before)
+    }
+
+    fn count_allocation(&self, size: usize) {
+        self.allocations.fetch_add(1, Ordering::SeqCst);
+        self.bytes_allocated.fetch_add(size, Ordering::SeqCst);
+        if size <= SMALL_ALLOCATION {
+            self.small_allocations.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.count_allocation(layout.size());
+        self.inner.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.count_allocation(layout.size());
+        self.inner.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.deallocations.fetch_add(1, Ordering::SeqCst);
+        self.inner.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.reallocations.fetch_add(1, Ordering::SeqCst);
+        self.count_allocation(new_size);
+        self.inner.realloc(ptr, layout, new_size)
+    }
+}
+
+impl Sub for AllocationStats {
+    type Output = AllocationStats;
+
+    fn sub(self, earlier: AllocationStats) -> AllocationStats {
+        AllocationStats {
+            allocations: self.allocations - earlier.allocations,
+            small_allocations: self.small_allocations - earlier.small_allocations,
+            reallocations: self.reallocations - earlier.reallocations,
+            deallocations: self.deallocations - earlier.deallocations,
+            bytes_allocated: self.bytes_allocated - earlier.bytes_allocated,
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Differential testing of the decoder against other bencode implementations.
+//!
+//! [`compare`] decodes the same inputs with bendy and with a reference implementation
+//! and reports every input on which both disagree. The reference is any function that
+//! converts its result into a [`Value`], so it can wrap any other crate:
+//!
+//! ```
+//! use bendy::{
+//!     testing::differential::{compare, Divergence},
+//!     value::Value,
+//! };
+//!
+//! // A reference that only understands the integer zero
+//! let reference = |input: &[u8]| match input {
+//!     b"i0e" => Some(Value::Integer(0)),
+//!     _ => None,
+//! };
+//!
+//! let inputs: &[&[u8]] = &[b"i0e", b"i1e", b"i01e"];
+//! let divergences = compare(inputs.iter().copied(), &reference);
+//! assert_eq!(divergences.len(), 1);
+//! assert_eq!(divergences[0].input, b"i1e");
+//! assert_eq!(
+//!     divergences[0].divergence,
+//!     Divergence::OnlyBendyAccepts(Value::Integer(1))
+//! );
+//! ```
+//!
+//! Some divergences are expected when comparing against lenient implementations, since
+//! bendy rejects every non-canonical encoding; [`Divergence::is_canonicalization`]
+//! tells those apart from real disagreements.
+//!
+//! This module is only available with the `differential` feature.
+
+use crate::{
+    decoding::{is_valid, FromBencode},
+    encoding::ToBencode,
+    value::Value,
+};
+
+/// A bencode decoder to compare bendy against.
+///
+/// Implemented for closures returning the decoded value, or `None` if the input is
+/// rejected.
+pub trait Reference {
+    /// Decode a complete bencoded document.
+    fn decode(&self, input: &[u8]) -> Option<Value<'static>>;
+}
+
+impl<F> Reference for F
+where
+    F: Fn(&[u8]) -> Option<Value<'static>>,
+{
+    fn decode(&self, input: &[u8]) -> Option<Value<'static>> {
+        self(input)
+    }
+}
+
+/// How the results of bendy and the reference differ for an input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Divergence {
+    /// Only bendy accepted the input
+    OnlyBendyAccepts(Value<'static>),
+    /// Only the reference accepted the input
+    OnlyReferenceAccepts(Value<'static>),
+    /// Both accepted the input, but decoded different values
+    Different {
+        /// The value decoded by bendy
+        bendy: Value<'static>,
+        /// The value decoded by the reference
+        reference: Value<'static>,
+    },
+}
+
+impl Divergence {
+    /// Whether the divergence is caused by the reference accepting input that isn't
+    /// canonical bencode, but re-encodes to a valid document. This is the expected result
+    /// for lenient implementations accepting e.g. unsorted keys.
+    pub fn is_canonicalization(&self, input: &[u8]) -> bool {
+        match self {
+            Divergence::OnlyReferenceAccepts(value) => {
+                matches!(value.to_bencode(), Ok(canonical) if canonical != input)
+            },
+            _ => false,
+        }
+    }
+}
+
+/// An input on which bendy and the reference disagree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report<'a> {
+    /// The input
+    pub input: &'a [u8],
+    /// How the results differ
+    pub divergence: Divergence,
+}
+
+/// Decode a complete document with bendy, rejecting trailing data.
+pub fn decode(input: &[u8]) -> Option<Value<'static>> {
+    match is_valid(input) {
+        Ok(len) if len == input.len() => Value::from_bencode(input).ok().map(Value::into_owned),
+        _ => None,
+    }
+}
+
+/// Decode every input with bendy and the reference, returning the inputs on which they
+/// disagree.
+pub fn compare<'a>(
+    inputs: impl IntoIterator<Item = &'a [u8]>,
+    reference: &impl Reference,
+) -> Vec<Report<'a>> {
+    inputs
+        .into_iter()
+        .filter_map(|input| {
+            let divergence = match (decode(input), reference.decode(input)) {
+                (Some(bendy), Some(reference)) if bendy == reference => return None,
+                (None, None) => return None,
+                (Some(bendy), Some(reference)) => Divergence::Different { bendy, reference },
+                (Some(bendy), None) => Divergence::OnlyBendyAccepts(bendy),
+                (None, Some(reference)) => Divergence::OnlyReferenceAccepts(reference),
+            };
+
+            Some(Report { input, divergence })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{borrow::Cow, collections::BTreeMap, mem};
+
+    use super::*;
+
+    /// A lenient decoder accepting unsorted keys and leading zeros
+    fn lenient(input: &[u8]) -> Option<Value<'static>> {
+        fn value(input: &[u8]) -> Option<(Value<'static>, &[u8])> {
+            match input.first()? {
+                b'i' => {
+                    let end = input.iter().position(|&c| c == b'e')?;
+                    let number = std::str::from_utf8(&input[1..end]).ok()?.parse().ok()?;
+                    Some((Value::Integer(number), &input[end + 1..]))
+                },
+                b'l' => {
+                    let (mut rest, mut list) = (&input[1..], Vec::new());
+                    while rest.first()? != &b'e' {
+                        let (item, remaining) = value(rest)?;
+                        list.push(item);
+                        rest = remaining;
+                    }
+                    Some((Value::List(list), &rest[1..]))
+                },
+                b'd' => {
+                    let (mut rest, mut dict) = (&input[1..], BTreeMap::new());
+                    while rest.first()? != &b'e' {
+                        let (mut key, remaining) = value(rest)?;
+                        let (item, remaining) = value(remaining)?;
+                        match &mut key {
+                            Value::Bytes(key) => dict.insert(mem::take(key), item),
+                            _ => return None,
+                        };
+                        rest = remaining;
+                    }
+                    Some((Value::Dict(dict), &rest[1..]))
+                },
+                _ => {
+                    let colon = input.iter().position(|&c| c == b':')?;
+                    let len: usize = std::str::from_utf8(&input[..colon]).ok()?.parse().ok()?;
+                    let bytes = input.get(colon + 1..colon + 1 + len)?;
+                    Some((
+                        Value::Bytes(Cow::Owned(bytes.to_vec())),
+                        &input[colon + 1 + len..],
+                    ))
+                },
+            }
+        }
+
+        match value(input)? {
+            (value, []) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn lenient_references_should_only_diverge_on_non_canonical_input() {
+        let inputs: &[&[u8]] = &[
+            b"i42e",
+            b"i-0e",
+            b"i042e",
+            b"d1:bi1e1:ai2ee",
+            b"d1:ai1e1:bi2ee",
+            b"l4:spami1ee",
+            b"li1ee trailing",
+            b"03:abc",
+        ];
+
+        let reports = compare(inputs.iter().copied(), &lenient);
+        assert_eq!(reports.len(), 4);
+        for report in reports {
+            assert!(
+                report.divergence.is_canonicalization(report.input),
+                "unexpected divergence {:?} for {:?}",
+                report.divergence,
+                String::from_utf8_lossy(report.input)
+            );
+        }
+    }
+
+    #[test]
+    fn different_values_should_be_reported() {
+        let reference = |_: &[u8]| Some(Value::Integer(1));
+        let reports = compare(vec![&b"i2e"[..]], &reference);
+        assert_eq!(
+            reports[0].divergence,
+            Divergence::Different {
+                bendy: Value::Integer(2),
+                reference: Value::Integer(1),
+            }
+        );
+        assert!(!reports[0].divergence.is_canonicalization(b"i2e"));
+    }
+}
@@ -0,0 +1,254 @@
+//! Mock input for unit tests of [`FromBencode`] implementations.
+//!
+//! The [`mock_tokens!`](crate::mock_tokens) macro builds input with a JSON-like syntax,
+//! similar to [`emit!`](crate::emit), but writes every token exactly as given. Keys stay
+//! in the order they are written and [`Token`]s can be inserted verbatim, so edge cases
+//! like unsorted keys, non-canonical integers or unexpected types are easy to trigger
+//! without writing out bencode by hand:
+//!
+//! ```
+//! use bendy::{mock_tokens, state_tracker::Token};
+//!
+//! let input = mock_tokens!({ "interval": 1800, "peers": [b"\x7f\x00\x00\x01\x1a\xe1"] });
+//! assert_eq!(input.as_bytes(), b"d8:intervali1800e5:peersl6:\x7f\x00\x00\x01\x1a\xe1ee");
+//!
+//! // Tokens are passed through unchanged, even if the decoder will reject them
+//! let input = mock_tokens!({ "b": 1, "a": (Token::Num("-0")) });
+//! let error = input.decode::<std::collections::BTreeMap<String, i64>>().unwrap_err();
+//! assert_eq!(error.code(), "structure.unsorted_keys");
+//! ```
+//!
+//! Byte strings are written as `b"..."` literals and strings as `"..."` literals. Negative
+//! numbers and other expressions have to be wrapped in parentheses, e.g. `(-1)`.
+//!
+//! [`FromBencode`]: crate::decoding::FromBencode
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write;
+
+use crate::{
+    decoding::{Decoder, Error, FromBencode},
+    state_tracker::{StructureError, Token},
+};
+
+/// Build [`MockInput`] from a JSON-like literal, see the [module documentation](self).
+#[macro_export]
+macro_rules! mock_tokens {
+    (@value $input:ident, { $($key:literal : $value:tt),* $(,)? }) => {{
+        $input.push($crate::state_tracker::Token::Dict);
+        $(
+            $crate::testing::mock::MockAtom::push_to(&$key, &mut $input);
+            $crate::mock_tokens!(@value $input, $value);
+        )*
+        $input.push($crate::state_tracker::Token::End);
+    }};
+    (@value $input:ident, [ $($value:tt),* $(,)? ]) => {{
+        $input.push($crate::state_tracker::Token::List);
+        $( $crate::mock_tokens!(@value $input, $value); )*
+        $input.push($crate::state_tracker::Token::End);
+    }};
+    (@value $input:ident, $value:expr) => {
+        $crate::testing::mock::MockAtom::push_to(&$value, &mut $input)
+    };
+    ($($value:tt)+) => {{
+        let mut input = $crate::testing::mock::MockInput::new();
+        $crate::mock_tokens!(@value input, $($value)+);
+        input
+    }};
+}
+
+/// A sequence of tokens to feed to a decoder, which isn't checked for validity.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MockInput {
+    bytes: Vec<u8>,
+}
+
+impl MockInput {
+    /// Create an empty input
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an input from a sequence of tokens
+    ///
+    /// ```
+    /// use bendy::{state_tracker::Token, testing::mock::MockInput};
+    ///
+    /// // A list that is never closed
+    /// let input = MockInput::from_tokens(vec![Token::List, Token::Num("1")]);
+    /// assert!(input.decode::<Vec<u8>>().is_err());
+    /// ```
+    pub fn from_tokens<'a>(tokens: impl IntoIterator<Item = Token<'a>>) -> Self {
+        let mut input = Self::new();
+        for token in tokens {
+            input.push(token);
+        }
+        input
+    }
+
+    /// Append a token
+    pub fn push(&mut self, token: Token) -> &mut Self {
+        match token {
+            Token::List => self.bytes.push(b'l'),
+            Token::Dict => self.bytes.push(b'd'),
+            Token::End => self.bytes.push(b'e'),
+            Token::Num(num) => {
+                self.bytes.push(b'i');
+                self.bytes.extend_from_slice(num.as_bytes());
+                self.bytes.push(b'e');
+            },
+            Token::String(bytes) => {
+                let mut len = String::new();
+                write!(len, "{}:", bytes.len()).expect("writing to a string can't fail");
+                self.bytes.extend_from_slice(len.as_bytes());
+                self.bytes.extend_from_slice(bytes);
+            },
+        }
+        self
+    }
+
+    /// The input as bencode
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// A decoder reading the input, to test code working with
+    /// [`Object`](crate::decoding::Object)s directly
+    pub fn decoder(&self) -> Decoder<'_> {
+        Decoder::new(&self.bytes)
+    }
+
+    /// Decode the input like [`FromBencode::from_bencode`]
+    pub fn decode<T: FromBencode>(&self) -> Result<T, Error> {
+        let mut decoder = self.decoder().with_max_depth(T::EXPECTED_RECURSION_DEPTH);
+        let object = decoder.next_object()?;
+
+        object.map_or(
+            Err(Error::from(StructureError::UnexpectedEof)),
+            T::decode_bencode_object,
+        )
+    }
+}
+
+/// A value written as a single token by [`mock_tokens!`](crate::mock_tokens): integers,
+/// strings, byte strings and [`Token`]s themselves.
+pub trait MockAtom {
+    /// Append the token of this value to `input`
+    fn push_to(&self, input: &mut MockInput);
+}
+
+impl<T: MockAtom + ?Sized> MockAtom for &T {
+    fn push_to(&self, input: &mut MockInput) {
+        (**self).push_to(input)
+    }
+}
+
+impl MockAtom for Token<'_> {
+    fn push_to(&self, input: &mut MockInput) {
+        input.push(*self);
+    }
+}
+
+impl MockAtom for str {
+    fn push_to(&self, input: &mut MockInput) {
+        input.push(Token::String(self.as_bytes()));
+    }
+}
+
+impl MockAtom for String {
+    fn push_to(&self, input: &mut MockInput) {
+        self.as_str().push_to(input)
+    }
+}
+
+impl MockAtom for [u8] {
+    fn push_to(&self, input: &mut MockInput) {
+        input.push(Token::String(self));
+    }
+}
+
+impl MockAtom for Vec<u8> {
+    fn push_to(&self, input: &mut MockInput) {
+        self.as_slice().push_to(input)
+    }
+}
+
+macro_rules! impl_mock_atom_for_arrays {
+    ($($len:literal)*) => {
+        $(
+            impl MockAtom for [u8; $len] {
+                fn push_to(&self, input: &mut MockInput) {
+                    self[..].push_to(input)
+                }
+            }
+        )*
+    };
+}
+
+impl_mock_atom_for_arrays!(
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+);
+
+macro_rules! impl_mock_atom_for_integers {
+    ($($type:ty)*) => {
+        $(
+            impl MockAtom for $type {
+                fn push_to(&self, input: &mut MockInput) {
+                    let mut num = String::new();
+                    write!(num, "{}", self).expect("writing to a string can't fail");
+                    input.push(Token::Num(&num));
+                }
+            }
+        )*
+    };
+}
+
+impl_mock_atom_for_integers!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize);
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::{string::ToString, vec};
+
+    use super::*;
+    use crate::decoding::Object;
+
+    #[test]
+    fn mocks_should_keep_tokens_as_written() {
+        let input = mock_tokens!({ "z": [], "a": {}, "n": [(-1), (Token::Num("01"))] });
+        assert_eq!(input.as_bytes(), b"d1:zle1:ade1:nli-1ei01eee");
+        assert_eq!(mock_tokens!(b"").as_bytes(), b"0:");
+    }
+
+    #[test]
+    fn mocks_should_reach_decoders() {
+        let input = mock_tokens!(["a", 2]);
+        let error = input.decode::<Vec<String>>().unwrap_err();
+        assert_eq!(error.path(), Some("[1]"));
+
+        let mut decoder = input.decoder();
+        match decoder.next_object().unwrap() {
+            Some(Object::List(mut list)) => {
+                assert!(matches!(list.next_object(), Ok(Some(Object::Bytes(b"a")))));
+            },
+            _ => panic!("expected a list"),
+        }
+
+        let error = MockInput::new().decode::<u8>().unwrap_err();
+        assert_eq!(error.code(), "structure.unexpected_eof");
+        assert_eq!(
+            mock_tokens!((u64::MAX))
+                .decode::<u64>()
+                .unwrap()
+                .to_string(),
+            u64::MAX.to_string()
+        );
+        assert_eq!(
+            MockInput::from_tokens(vec![Token::List, Token::End])
+                .decode::<Vec<u8>>()
+                .unwrap(),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,22 @@
+//! Types for BitTorrent metainfo (`.torrent`) files.
+//!
+//! This module is only available with the `torrent` feature. With the `gzip` feature,
+//! `decode_http_body` decodes torrents from compressed HTTP responses.
+
+mod cache;
+#[cfg(feature = "gzip")]
+mod http_body;
+mod info_hash;
+mod metainfo;
+mod signature;
+mod v2;
+
+#[cfg(feature = "gzip")]
+pub use self::http_body::decode_http_body;
+pub use self::{
+    cache::{CacheStats, MetainfoCache},
+    info_hash::{InfoHash, InfoHashBytes, ParseInfoHashError},
+    metainfo::{File, FileLayout, Info, MetaInfo},
+    signature::{Signature, SignatureVerifier, Signatures},
+    v2::{FileTree, FileTreeNode, FileV2, InfoHashV2, InfoV2, MetaInfoV2, Sha256Bytes},
+};
@@ -0,0 +1,224 @@
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeMap, sync::Arc};
+#[cfg(feature = "std")]
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::torrent::InfoHash;
+
+/// A least recently used cache of parsed torrent metadata keyed by info hash, as used by
+/// DHT crawlers and proxies to avoid fetching and parsing the same metadata twice.
+///
+/// The cache can be bounded by the number of entries, by the total size of the entries or
+/// both. The size of an entry is chosen by the caller when inserting it, e.g. the length
+/// of the bencoded metadata or an estimate like [`Value::deep_size`].
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use bendy::torrent::{InfoHash, MetainfoCache};
+///
+/// let mut cache = MetainfoCache::new().with_max_entries(2);
+/// cache.insert(InfoHash([1; 20]), Arc::new("first"), 100);
+/// cache.insert(InfoHash([2; 20]), Arc::new("second"), 100);
+///
+/// // Using the first entry makes the second one the least recently used
+/// assert!(cache.get(&InfoHash([1; 20])).is_some());
+/// cache.insert(InfoHash([3; 20]), Arc::new("third"), 100);
+/// assert!(cache.get(&InfoHash([2; 20])).is_none());
+///
+/// let stats = cache.stats();
+/// assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 1, 1));
+/// ```
+///
+/// [`Value::deep_size`]: crate::value::Value::deep_size
+#[derive(Clone, Debug)]
+pub struct MetainfoCache<T> {
+    entries: BTreeMap<InfoHash, Entry<T>>,
+    /// Info hashes by the time of their last use
+    recency: BTreeMap<u64, InfoHash>,
+    clock: u64,
+    size: usize,
+    max_entries: usize,
+    max_size: usize,
+    stats: CacheStats,
+}
+
+#[derive(Clone, Debug)]
+struct Entry<T> {
+    value: Arc<T>,
+    size: usize,
+    last_used: u64,
+}
+
+/// Counters describing the effectiveness of a [`MetainfoCache`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Lookups that found an entry
+    pub hits: u64,
+    /// Lookups that didn't find an entry
+    pub misses: u64,
+    /// Entries removed to stay within the bounds of the cache
+    pub evictions: u64,
+}
+
+impl<T> MetainfoCache<T> {
+    /// Create an unbounded cache.
+    pub fn new() -> Self {
+        MetainfoCache {
+            entries: BTreeMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+            size: 0,
+            max_entries: usize::MAX,
+            max_size: usize::MAX,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Set the maximum number of entries.
+    pub fn with_max_entries(mut self, new_max_entries: usize) -> Self {
+        self.max_entries = new_max_entries;
+        self.evict();
+        self
+    }
+
+    /// Set the maximum total size of all entries. An entry larger than this is never
+    /// stored.
+    pub fn with_max_size(mut self, new_max_size: usize) -> Self {
+        self.max_size = new_max_size;
+        self.evict();
+        self
+    }
+
+    /// Look up the metadata of a torrent, marking it as recently used.
+    pub fn get(&mut self, info_hash: &InfoHash) -> Option<Arc<T>> {
+        self.clock += 1;
+        match self.entries.get_mut(info_hash) {
+            Some(entry) => {
+                self.recency.remove(&entry.last_used);
+                self.recency.insert(self.clock, *info_hash);
+                entry.last_used = self.clock;
+                self.stats.hits += 1;
+                Some(entry.value.clone())
+            },
+            None => {
+                self.stats.misses += 1;
+                None
+            },
+        }
+    }
+
+    /// Whether the cache holds the metadata of a torrent, without counting as a use.
+    pub fn contains(&self, info_hash: &InfoHash) -> bool {
+        self.entries.contains_key(info_hash)
+    }
+
+    /// Add or replace the metadata of a torrent, evicting the least recently used entries
+    /// if the cache is full. Returns the previous metadata of the torrent, if any.
+    pub fn insert(&mut self, info_hash: InfoHash, value: Arc<T>, size: usize) -> Option<Arc<T>> {
+        let previous = self.remove(&info_hash);
+
+        if size <= self.max_size && self.max_entries > 0 {
+            self.clock += 1;
+            self.recency.insert(self.clock, info_hash);
+            self.entries.insert(
+                info_hash,
+                Entry {
+                    value,
+                    size,
+                    last_used: self.clock,
+                },
+            );
+            self.size += size;
+            self.evict();
+        }
+
+        previous
+    }
+
+    /// Remove the metadata of a torrent.
+    pub fn remove(&mut self, info_hash: &InfoHash) -> Option<Arc<T>> {
+        let entry = self.entries.remove(info_hash)?;
+        self.recency.remove(&entry.last_used);
+        self.size -= entry.size;
+        Some(entry.value)
+    }
+
+    /// The number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The total size of all entries.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The hit, miss and eviction counters of the cache.
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Remove least recently used entries until the cache is within its bounds
+    fn evict(&mut self) {
+        while self.entries.len() > self.max_entries || self.size > self.max_size {
+            let oldest = match self.recency.keys().next() {
+                Some(&oldest) => oldest,
+                None => break,
+            };
+            if let Some(info_hash) = self.recency.remove(&oldest) {
+                if let Some(entry) = self.entries.remove(&info_hash) {
+                    self.size -= entry.size;
+                }
+            }
+            self.stats.evictions += 1;
+        }
+    }
+}
+
+impl<T> Default for MetainfoCache<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cache_should_respect_the_size_bound() {
+        let mut cache = MetainfoCache::new().with_max_size(250);
+        cache.insert(InfoHash([1; 20]), Arc::new(1), 100);
+        cache.insert(InfoHash([2; 20]), Arc::new(2), 100);
+        cache.insert(InfoHash([3; 20]), Arc::new(3), 100);
+
+        assert!(!cache.contains(&InfoHash([1; 20])));
+        assert_eq!((cache.len(), cache.size()), (2, 200));
+
+        // Too large to ever be stored
+        cache.insert(InfoHash([4; 20]), Arc::new(4), 300);
+        assert!(!cache.contains(&InfoHash([4; 20])));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn replacing_an_entry_should_update_the_size() {
+        let mut cache = MetainfoCache::new();
+        cache.insert(InfoHash([1; 20]), Arc::new(1), 100);
+        assert_eq!(
+            cache.insert(InfoHash([1; 20]), Arc::new(2), 50),
+            Some(Arc::new(1))
+        );
+        assert_eq!((cache.len(), cache.size()), (1, 50));
+
+        assert_eq!(cache.remove(&InfoHash([1; 20])), Some(Arc::new(2)));
+        assert!(cache.is_empty());
+        assert_eq!(cache.size(), 0);
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    io::Read,
+};
+
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+
+use crate::{
+    decoding::{Error, FromBencode},
+    torrent::MetaInfo,
+};
+
+/// Decode a `.torrent` file from the body of an HTTP response, undoing the compression
+/// given in its `Content-Encoding` header.
+///
+/// The `gzip` (or `x-gzip`), `deflate` and `identity` codings are supported, also as a
+/// comma separated list of codings applied in order. As some servers send raw deflate
+/// streams for `deflate`, these are accepted next to the zlib format of the standard.
+///
+/// Both the body and the decompressed file are limited to `max_len` bytes. Decompression
+/// stops as soon as the limit is exceeded, so a small body that inflates to gigabytes
+/// (a decompression bomb) fails with [`ErrorKind::LimitExceeded`] without ever being held
+/// in memory:
+///
+/// ```
+/// use std::io::Write;
+///
+/// use bendy::torrent::decode_http_body;
+/// use flate2::{write::GzEncoder, Compression};
+///
+/// let torrent = b"d4:infod6:lengthi42e4:name7:example12:piece lengthi16384e\
+///                 6:pieces20:01234567890123456789ee";
+/// let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+/// gzip.write_all(torrent).unwrap();
+/// let body = gzip.finish().unwrap();
+///
+/// let metainfo = decode_http_body(&body, Some("gzip"), 1 << 20).unwrap();
+/// assert_eq!(metainfo.info.name, "example");
+///
+/// let error = decode_http_body(&body, Some("gzip"), 64).unwrap_err();
+/// assert_eq!(error.code(), "decode.limit_exceeded");
+/// ```
+///
+/// This needs the `gzip` feature.
+///
+/// [`ErrorKind::LimitExceeded`]: crate::decoding::ErrorKind::LimitExceeded
+pub fn decode_http_body(
+    body: &[u8],
+    content_encoding: Option<&str>,
+    max_len: usize,
+) -> Result<MetaInfo, Error> {
+    if body.len() > max_len {
+        return Err(Error::limit_exceeded(format!(
+            "body of {} bytes, more than {}",
+            body.len(),
+            max_len
+        )));
+    }
+
+    let mut decoded = body.to_vec();
+    let codings = content_encoding.unwrap_or("").split(',').rev();
+    for coding in codings.map(str::trim).filter(|coding| !coding.is_empty()) {
+        decoded = match coding.to_ascii_lowercase().as_str() {
+            "identity" => decoded,
+            "gzip" | "x-gzip" => inflate(GzDecoder::new(&decoded[..]), max_len)?,
+            "deflate" if is_zlib(&decoded) => inflate(ZlibDecoder::new(&decoded[..]), max_len)?,
+            "deflate" => inflate(DeflateDecoder::new(&decoded[..]), max_len)?,
+            _ => {
+                return Err(Error::malformed_content(UnsupportedCoding(
+                    coding.to_owned(),
+                )));
+            },
+        };
+    }
+
+    MetaInfo::from_bencode(&decoded)
+}
+
+/// Read the decompressed stream of `decoder`, failing once it exceeds `max_len` bytes
+fn inflate(decoder: impl Read, max_len: usize) -> Result<Vec<u8>, Error> {
+    let mut inflated = Vec::new();
+    decoder
+        .take(max_len as u64 + 1)
+        .read_to_end(&mut inflated)
+        .map_err(Error::malformed_content)?;
+
+    if inflated.len() > max_len {
+        return Err(Error::limit_exceeded(format!(
+            "decompressed body of more than {} bytes",
+            max_len
+        )));
+    }
+    Ok(inflated)
+}
+
+/// Whether `data` starts with a valid zlib header ([RFC 1950])
+///
+/// [RFC 1950]: https://www.rfc-editor.org/rfc/rfc1950
+fn is_zlib(data: &[u8]) -> bool {
+    match data {
+        [cmf, flg, ..] => cmf & 0x0f == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0,
+        _ => false,
+    }
+}
+
+/// A `Content-Encoding` that can't be decoded
+#[derive(Debug)]
+struct UnsupportedCoding(String);
+
+impl Display for UnsupportedCoding {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "unsupported content coding {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnsupportedCoding {}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use flate2::{
+        write::{DeflateEncoder, GzEncoder, ZlibEncoder},
+        Compression,
+    };
+
+    use super::*;
+
+    const TORRENT: &[u8] = b"d4:infod6:lengthi42e4:name7:example12:piece lengthi16384e\
+                             6:pieces20:01234567890123456789ee";
+
+    #[test]
+    fn all_codings_should_be_decoded() {
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(TORRENT).unwrap();
+        let zlib = zlib.finish().unwrap();
+        let mut deflate = DeflateEncoder::new(Vec::new(), Compression::default());
+        deflate.write_all(TORRENT).unwrap();
+        let deflate = deflate.finish().unwrap();
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(&zlib).unwrap();
+        let gzip_of_zlib = gzip.finish().unwrap();
+
+        let expected = MetaInfo::from_bencode(TORRENT).unwrap();
+        let decode = |body: &[u8], coding| decode_http_body(body, coding, 1024).unwrap();
+        assert_eq!(decode(TORRENT, None), expected);
+        assert_eq!(decode(TORRENT, Some("identity")), expected);
+        assert_eq!(decode(&zlib, Some("deflate")), expected);
+        assert_eq!(decode(&deflate, Some("Deflate")), expected);
+        assert_eq!(decode(&gzip_of_zlib, Some("deflate, gzip")), expected);
+
+        let error = decode_http_body(TORRENT, Some("br"), 1024).unwrap_err();
+        assert_eq!(error.code(), "decode.malformed_content");
+        assert!(decode_http_body(TORRENT, Some("gzip"), 1024).is_err());
+    }
+
+    #[test]
+    fn bombs_should_be_stopped_at_the_limit() {
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::best());
+        gzip.write_all(&vec![b'0'; 10 << 20]).unwrap();
+        let bomb = gzip.finish().unwrap();
+        assert!(bomb.len() < 64 << 10);
+
+        let error = decode_http_body(&bomb, Some("gzip"), 1 << 20).unwrap_err();
+        assert_eq!(error.code(), "decode.limit_exceeded");
+        let error = decode_http_body(TORRENT, None, 16).unwrap_err();
+        assert_eq!(error.code(), "decode.limit_exceeded");
+    }
+}
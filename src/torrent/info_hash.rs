@@ -0,0 +1,256 @@
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+use core::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use crate::{
+    decoding::{self, Decoder, FromBencode, Object},
+    encoding::{self, SingleItemEncoder, ToBencode},
+    hex::{self, Hex},
+    state_tracker::StructureError,
+};
+
+/// The raw bytes of an [`InfoHash`].
+pub type InfoHashBytes = [u8; 20];
+
+/// The alphabet of base32 ([RFC 4648]) as used in magnet links
+///
+/// [RFC 4648]: https://www.rfc-editor.org/rfc/rfc4648
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// The SHA-1 hash of the bencoded info dict identifying a v1 torrent, encoded as a 20 byte
+/// string.
+///
+/// Info hashes are printed as 40 lowercase hex digits, the usual form in magnet links
+/// today, or in the 32 characters of base32 with [`to_base32`](Self::to_base32), as in
+/// older magnet links. Parsing detects the encoding from the length and accepts both
+/// upper and lower case:
+///
+/// ```
+/// use bendy::torrent::InfoHash;
+///
+/// let hex: InfoHash = "c12fe1c06bba254a9dc9f519b335aa7c1367a88a".parse().unwrap();
+/// let base32: InfoHash = "YEX6DQDLXISUVHOJ6UM3GNNKPQJWPKEK".parse().unwrap();
+///
+/// assert_eq!(hex, base32);
+/// assert_eq!(hex.to_string(), "c12fe1c06bba254a9dc9f519b335aa7c1367a88a");
+/// assert_eq!(hex.to_base32(), "YEX6DQDLXISUVHOJ6UM3GNNKPQJWPKEK");
+/// ```
+///
+/// As with [`InfoHashV2`](super::InfoHashV2), the hash function has to be passed in to
+/// compute an info hash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InfoHash(pub InfoHashBytes);
+
+impl InfoHash {
+    /// Hash the bencoded info dict of a torrent
+    pub fn from_info(info: &[u8], sha1: impl FnOnce(&[u8]) -> InfoHashBytes) -> Self {
+        InfoHash(sha1(info))
+    }
+
+    /// Hash the info dict of a bencoded torrent, exactly as it appears in the torrent
+    pub fn from_torrent(
+        torrent: &[u8],
+        sha1: impl FnOnce(&[u8]) -> InfoHashBytes,
+    ) -> Result<Self, decoding::Error> {
+        raw_info(torrent).map(|info| InfoHash::from_info(info, sha1))
+    }
+
+    /// The raw bytes of the hash.
+    pub fn as_bytes(&self) -> &InfoHashBytes {
+        &self.0
+    }
+
+    /// The hash in 32 characters of upper case base32
+    pub fn to_base32(&self) -> String {
+        let mut encoded = String::with_capacity(32);
+        // 5 bytes are exactly 8 characters of 5 bits each
+        for chunk in self.0.chunks(5) {
+            let bits = chunk
+                .iter()
+                .fold(0u64, |bits, byte| bits << 8 | u64::from(*byte));
+            for index in (0..8).rev() {
+                encoded.push(char::from(BASE32[(bits >> (5 * index)) as usize & 0x1f]));
+            }
+        }
+        encoded
+    }
+}
+
+impl From<InfoHashBytes> for InfoHash {
+    fn from(info_hash: InfoHashBytes) -> Self {
+        InfoHash(info_hash)
+    }
+}
+
+impl Display for InfoHash {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Hex(&self.0).fmt(f)
+    }
+}
+
+impl FromStr for InfoHash {
+    type Err = ParseInfoHashError;
+
+    /// Parse 40 hex digits or 32 characters of base32, in upper or lower case.
+    fn from_str(encoded: &str) -> Result<Self, Self::Err> {
+        let mut info_hash = [0; 20];
+        match encoded.len() {
+            40 => hex::decode_into(encoded.as_bytes(), &mut info_hash).ok_or(ParseInfoHashError)?,
+            32 => {
+                let chunks = info_hash.chunks_mut(5).zip(encoded.as_bytes().chunks(8));
+                for (bytes, characters) in chunks {
+                    let mut bits = 0u64;
+                    for character in characters {
+                        let value = BASE32
+                            .iter()
+                            .position(|c| *c == character.to_ascii_uppercase())
+                            .ok_or(ParseInfoHashError)?;
+                        bits = bits << 5 | value as u64;
+                    }
+                    for (index, byte) in bytes.iter_mut().enumerate() {
+                        *byte = (bits >> (8 * (4 - index))) as u8;
+                    }
+                }
+            },
+            _ => return Err(ParseInfoHashError),
+        }
+
+        Ok(InfoHash(info_hash))
+    }
+}
+
+impl ToBencode for InfoHash {
+    const MAX_DEPTH: usize = 0;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_bytes(&self.0)
+    }
+}
+
+impl FromBencode for InfoHash {
+    const EXPECTED_RECURSION_DEPTH: usize = 0;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let bytes = object.try_into_bytes()?;
+        if bytes.len() != 20 {
+            return Err(decoding::Error::malformed_content(InfoHashLength {
+                len: bytes.len(),
+            }));
+        }
+
+        let mut info_hash = [0; 20];
+        info_hash.copy_from_slice(bytes);
+        Ok(InfoHash(info_hash))
+    }
+}
+
+/// The info dict of a bencoded torrent, exactly as it appears in the torrent
+pub(crate) fn raw_info(torrent: &[u8]) -> Result<&[u8], decoding::Error> {
+    let mut decoder = Decoder::new(torrent);
+    let mut dict = decoder
+        .next_object()?
+        .ok_or(StructureError::UnexpectedEof)?
+        .try_into_dictionary()?;
+
+    while let Some((key, raw)) = dict.next_pair_raw()? {
+        if key == b"info" {
+            return Ok(raw);
+        }
+    }
+
+    Err(decoding::Error::missing_field("info"))
+}
+
+/// The error returned when parsing an invalid [`InfoHash`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseInfoHashError;
+
+impl Display for ParseInfoHashError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("not an info hash of 40 hex digits or 32 base32 characters")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseInfoHashError {}
+
+/// An info hash that isn't 20 bytes long
+#[derive(Debug)]
+struct InfoHashLength {
+    len: usize,
+}
+
+impl Display for InfoHashLength {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "info hash of {} bytes instead of 20", self.len)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InfoHashLength {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(not(feature = "std"))]
+    use alloc::string::ToString;
+
+    #[test]
+    fn info_hashes_should_round_trip_as_text() {
+        let info_hash = InfoHash(*b"\x00\x01\x02\x7f\x80\xfe\xffabcdefghijklm");
+        let hex = info_hash.to_string();
+        assert_eq!(hex, "0001027f80feff6162636465666768696a6b6c6d");
+        assert_eq!(hex.parse(), Ok(info_hash));
+        assert_eq!(hex.to_uppercase().parse(), Ok(info_hash));
+
+        let base32 = info_hash.to_base32();
+        assert_eq!(base32, "AAAQE74A737WCYTDMRSWMZ3INFVGW3DN");
+        assert_eq!(base32.parse(), Ok(info_hash));
+        assert_eq!(base32.to_lowercase().parse(), Ok(info_hash));
+    }
+
+    #[test]
+    fn invalid_text_should_be_rejected() {
+        let invalid = [
+            "",
+            "0001027f80feff6162636465666768696a6b6c6",
+            "+001027f80feff6162636465666768696a6b6c6d",
+            "0001027f80feff6162636465666768696a6b6c6g",
+            "AAAQE74A737WCYTDMRSWMZ3INFVGW3A1",
+            "AAAQE74A737WCYTDMRSWMZ3INFVGW3A=",
+            "é001027f80feff6162636465666768696a6b6c6",
+        ];
+        for text in invalid.iter() {
+            assert_eq!(
+                text.parse::<InfoHash>(),
+                Err(ParseInfoHashError),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn info_hashes_should_be_20_byte_strings() {
+        let info_hash = InfoHash([b'x'; 20]);
+        let encoded = info_hash.to_bencode().unwrap();
+        assert_eq!(encoded, b"20:xxxxxxxxxxxxxxxxxxxx");
+        assert_eq!(InfoHash::from_bencode(&encoded).unwrap(), info_hash);
+        assert!(InfoHash::from_bencode(b"3:abc").is_err());
+
+        let torrent = b"d8:announce1:a4:infod4:name1:xee";
+        let info_hash = InfoHash::from_torrent(torrent, |info| {
+            assert_eq!(info, b"d4:name1:xe");
+            [1; 20]
+        });
+        assert_eq!(info_hash.unwrap(), InfoHash([1; 20]));
+        assert!(InfoHash::from_torrent(b"de", |_| [1; 20]).is_err());
+    }
+}
@@ -0,0 +1,554 @@
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+use core::{
+    fmt::{self, Display, Formatter},
+    slice::ChunksExact,
+};
+
+use crate::{
+    decoding::{self, FromBencode, Object, ResultExt},
+    encoding::{self, AsString, SingleItemEncoder, ToBencode},
+    torrent::{SignatureVerifier, Signatures},
+};
+
+/// The contents of a `.torrent` file ([BEP 3]).
+///
+/// Besides the required `info` dict, the commonly used optional keys are supported:
+/// `announce`, the tiered `announce-list` of [BEP 12], `comment`, `created by`,
+/// `creation date`, `encoding` and the `signatures` of [BEP 35]. Unknown keys are ignored.
+///
+/// ```
+/// use bendy::{
+///     decoding::FromBencode,
+///     torrent::{FileLayout, MetaInfo},
+/// };
+///
+/// let torrent = MetaInfo::from_bencode(
+///     b"d8:announce14:http://tracker13:creation datei1700000000e\
+///       4:infod6:lengthi42e4:name7:example12:piece lengthi16384e6:pieces20:01234567890123456789ee",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(torrent.announce.as_deref(), Some("http://tracker"));
+/// assert_eq!(torrent.creation_date, Some(1_700_000_000));
+/// assert_eq!(torrent.info.name, "example");
+/// assert_eq!(torrent.info.total_length(), 42);
+/// assert!(matches!(
+///     torrent.info.layout,
+///     FileLayout::Single { length: 42, .. }
+/// ));
+/// ```
+///
+/// Encoding a `MetaInfo` only preserves the known keys, so the info dict of a decoded
+/// torrent doesn't necessarily encode to the same bytes. The info hash has to be computed
+/// from the original bytes of the info dict instead, which
+/// [`DictDecoder::next_pair_raw`](crate::decoding::DictDecoder::next_pair_raw) returns.
+///
+/// [BEP 3]: http://bittorrent.org/beps/bep_0003.html
+/// [BEP 12]: http://bittorrent.org/beps/bep_0012.html
+/// [BEP 35]: http://bittorrent.org/beps/bep_0035.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MetaInfo {
+    /// The URL of the tracker (`announce`)
+    pub announce: Option<String>,
+    /// Tiers of tracker URLs, tried in order (`announce-list`)
+    pub announce_list: Option<Vec<Vec<String>>>,
+    /// A free-form comment (`comment`)
+    pub comment: Option<String>,
+    /// The name and version of the program that created the torrent (`created by`)
+    pub created_by: Option<String>,
+    /// The creation time in seconds since the UNIX epoch (`creation date`)
+    pub creation_date: Option<i64>,
+    /// The character encoding of the strings in the info dict (`encoding`)
+    pub encoding: Option<String>,
+    /// The description of the content (`info`)
+    pub info: Info,
+    /// The signatures of the torrent, empty if it isn't signed (`signatures`)
+    pub signatures: Signatures,
+}
+
+impl MetaInfo {
+    /// Check the signature of `signer` against the info dict.
+    ///
+    /// The signed message is built from the encoded [`info`](Self::info), which only
+    /// matches the original torrent if its info dict has no unknown keys. Use
+    /// [`Signatures::verify_torrent`] to check the original bytes instead. Returns
+    /// `Ok(false)` if there is no signature by `signer`.
+    pub fn verify_signature(
+        &self,
+        signer: &str,
+        verifier: &impl SignatureVerifier,
+    ) -> Result<bool, encoding::Error> {
+        match self.signatures.get(signer) {
+            Some(signature) => signature.verify(signer, &self.info.to_bencode()?, verifier),
+            None => Ok(false),
+        }
+    }
+}
+
+/// The info dict of a torrent, describing its content.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Info {
+    /// The suggested name of the file or directory (`name`)
+    pub name: String,
+    /// The number of bytes per piece (`piece length`)
+    pub piece_length: u64,
+    /// The concatenated SHA-1 hashes of all pieces (`pieces`)
+    pub pieces: Vec<u8>,
+    /// Whether peers may only be obtained from the trackers, see [BEP 27] (`private`)
+    ///
+    /// [BEP 27]: http://bittorrent.org/beps/bep_0027.html
+    pub private: bool,
+    /// Whether the torrent holds a single file or a directory of files
+    pub layout: FileLayout,
+}
+
+/// The files of a torrent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileLayout {
+    /// A single file named like the torrent (`length` in the info dict)
+    Single {
+        /// The length of the file in bytes (`length`)
+        length: u64,
+        /// The MD5 sum of the file as hex digits (`md5sum`)
+        md5sum: Option<String>,
+    },
+    /// A directory named like the torrent (`files` in the info dict)
+    Multiple {
+        /// The files in the directory, in the order their content is stored in the pieces
+        files: Vec<File>,
+    },
+}
+
+/// An entry of the `files` list of a multi-file torrent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct File {
+    /// The length of the file in bytes (`length`)
+    pub length: u64,
+    /// The MD5 sum of the file as hex digits (`md5sum`)
+    pub md5sum: Option<String>,
+    /// The components of the path of the file below the torrent directory (`path`)
+    pub path: Vec<String>,
+}
+
+impl Info {
+    /// The total length of all files in bytes
+    pub fn total_length(&self) -> u64 {
+        match &self.layout {
+            FileLayout::Single { length, .. } => *length,
+            FileLayout::Multiple { files } => files.iter().map(|file| file.length).sum(),
+        }
+    }
+
+    /// The SHA-1 hashes of the pieces
+    pub fn piece_hashes(&self) -> ChunksExact<'_, u8> {
+        self.pieces.chunks_exact(20)
+    }
+}
+
+impl ToBencode for MetaInfo {
+    const MAX_DEPTH: usize = encoding::max_depth(&[Info::MAX_DEPTH, Signatures::MAX_DEPTH]) + 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            if let Some(announce) = &self.announce {
+                e.emit_pair(b"announce", announce)?;
+            }
+            if let Some(announce_list) = &self.announce_list {
+                e.emit_pair(b"announce-list", announce_list)?;
+            }
+            if let Some(comment) = &self.comment {
+                e.emit_pair(b"comment", comment)?;
+            }
+            if let Some(created_by) = &self.created_by {
+                e.emit_pair(b"created by", created_by)?;
+            }
+            if let Some(creation_date) = self.creation_date {
+                e.emit_pair(b"creation date", creation_date)?;
+            }
+            if let Some(encoding) = &self.encoding {
+                e.emit_pair(b"encoding", encoding)?;
+            }
+            e.emit_pair(b"info", &self.info)?;
+            if !self.signatures.is_empty() {
+                e.emit_pair(b"signatures", &self.signatures)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl FromBencode for MetaInfo {
+    const EXPECTED_RECURSION_DEPTH: usize = encoding::max_depth(&[
+        Info::EXPECTED_RECURSION_DEPTH,
+        Signatures::EXPECTED_RECURSION_DEPTH,
+    ]) + 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut announce = None;
+        let mut announce_list = None;
+        let mut comment = None;
+        let mut created_by = None;
+        let mut creation_date = None;
+        let mut encoding = None;
+        let mut info = None;
+        let mut signatures = Signatures::default();
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"announce", value) => {
+                    announce = String::decode_bencode_object(value)
+                        .context("announce")
+                        .map(Some)?;
+                },
+                (b"announce-list", value) => {
+                    announce_list = Vec::decode_bencode_object(value)
+                        .context("announce-list")
+                        .map(Some)?;
+                },
+                (b"comment", value) => {
+                    comment = String::decode_bencode_object(value)
+                        .context("comment")
+                        .map(Some)?;
+                },
+                (b"created by", value) => {
+                    created_by = String::decode_bencode_object(value)
+                        .context("created by")
+                        .map(Some)?;
+                },
+                (b"creation date", value) => {
+                    creation_date = i64::decode_bencode_object(value)
+                        .context("creation date")
+                        .map(Some)?;
+                },
+                (b"encoding", value) => {
+                    encoding = String::decode_bencode_object(value)
+                        .context("encoding")
+                        .map(Some)?;
+                },
+                (b"info", value) => {
+                    info = Info::decode_bencode_object(value)
+                        .context("info")
+                        .map(Some)?;
+                },
+                (b"signatures", value) => {
+                    signatures = Signatures::decode_bencode_object(value).context("signatures")?;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        Ok(MetaInfo {
+            announce,
+            announce_list,
+            comment,
+            created_by,
+            creation_date,
+            encoding,
+            info: info.ok_or_else(|| decoding::Error::missing_field("info"))?,
+            signatures,
+        })
+    }
+}
+
+impl ToBencode for Info {
+    const MAX_DEPTH: usize = File::MAX_DEPTH + 2;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            match &self.layout {
+                FileLayout::Single { length, md5sum } => {
+                    e.emit_pair(b"length", length)?;
+                    if let Some(md5sum) = md5sum {
+                        e.emit_pair(b"md5sum", md5sum)?;
+                    }
+                },
+                FileLayout::Multiple { files } => e.emit_pair(b"files", files)?,
+            }
+            e.emit_pair(b"name", &self.name)?;
+            e.emit_pair(b"piece length", self.piece_length)?;
+            e.emit_pair(b"pieces", AsString(&self.pieces))?;
+            if self.private {
+                e.emit_pair(b"private", 1)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl FromBencode for Info {
+    const EXPECTED_RECURSION_DEPTH: usize = File::EXPECTED_RECURSION_DEPTH + 2;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut files = None;
+        let mut length = None;
+        let mut md5sum = None;
+        let mut name = None;
+        let mut piece_length = None;
+        let mut pieces = None;
+        let mut private = false;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"files", value) => {
+                    files = Vec::decode_bencode_object(value)
+                        .context("files")
+                        .map(Some)?;
+                },
+                (b"length", value) => {
+                    length = u64::decode_bencode_object(value)
+                        .context("length")
+                        .map(Some)?;
+                },
+                (b"md5sum", value) => {
+                    md5sum = String::decode_bencode_object(value)
+                        .context("md5sum")
+                        .map(Some)?;
+                },
+                (b"name", value) => {
+                    name = String::decode_bencode_object(value)
+                        .context("name")
+                        .map(Some)?;
+                },
+                (b"piece length", value) => {
+                    piece_length = u64::decode_bencode_object(value)
+                        .context("piece length")
+                        .map(Some)?;
+                },
+                (b"pieces", value) => {
+                    let bytes = AsString::decode_bencode_object(value).context("pieces")?;
+                    if !bytes.0.chunks_exact(20).remainder().is_empty() {
+                        return Err(decoding::Error::malformed_content(PiecesLength(
+                            bytes.0.len(),
+                        )))
+                        .context("pieces");
+                    }
+                    pieces = Some(bytes.0);
+                },
+                (b"private", value) => {
+                    private = i64::decode_bencode_object(value).context("private")? != 0;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        let layout = match (length, files) {
+            (Some(length), None) => FileLayout::Single { length, md5sum },
+            (None, Some(files)) => FileLayout::Multiple { files },
+            (Some(_), Some(_)) => return Err(decoding::Error::unexpected_field("files")),
+            (None, None) => return Err(decoding::Error::missing_field("length or files")),
+        };
+
+        Ok(Info {
+            name: name.ok_or_else(|| decoding::Error::missing_field("name"))?,
+            piece_length: piece_length
+                .ok_or_else(|| decoding::Error::missing_field("piece length"))?,
+            pieces: pieces.ok_or_else(|| decoding::Error::missing_field("pieces"))?,
+            private,
+            layout,
+        })
+    }
+}
+
+impl ToBencode for File {
+    const MAX_DEPTH: usize = 2;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair(b"length", self.length)?;
+            if let Some(md5sum) = &self.md5sum {
+                e.emit_pair(b"md5sum", md5sum)?;
+            }
+            e.emit_pair(b"path", &self.path)
+        })
+    }
+}
+
+impl FromBencode for File {
+    const EXPECTED_RECURSION_DEPTH: usize = 2;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut length = None;
+        let mut md5sum = None;
+        let mut path = None;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"length", value) => {
+                    length = u64::decode_bencode_object(value)
+                        .context("length")
+                        .map(Some)?;
+                },
+                (b"md5sum", value) => {
+                    md5sum = String::decode_bencode_object(value)
+                        .context("md5sum")
+                        .map(Some)?;
+                },
+                (b"path", value) => {
+                    path = Vec::decode_bencode_object(value)
+                        .context("path")
+                        .map(Some)?;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        Ok(File {
+            length: length.ok_or_else(|| decoding::Error::missing_field("length"))?,
+            md5sum,
+            path: path.ok_or_else(|| decoding::Error::missing_field("path"))?,
+        })
+    }
+}
+
+/// Error of a `pieces` string whose length isn't a multiple of the hash length
+#[derive(Debug)]
+struct PiecesLength(usize);
+
+impl Display for PiecesLength {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "pieces of {} bytes are not a sequence of 20 byte hashes",
+            self.0
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PiecesLength {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    static MULTI_FILE: &[u8] = b"d13:announce-listll8:http://a8:http://bel8:http://cee\
+        10:created by5:bendy4:infod5:filesld6:lengthi3e4:pathl1:a1:beed6:lengthi4e6:md5sum2:004:pathl1:ceee\
+        4:name3:dir12:piece lengthi16e6:pieces40:0123456789012345678901234567890123456789\
+        7:privatei1ee3:url0:e";
+
+    fn multi_file() -> MetaInfo {
+        MetaInfo {
+            announce: None,
+            announce_list: Some(vec![
+                vec!["http://a".into(), "http://b".into()],
+                vec!["http://c".into()],
+            ]),
+            comment: None,
+            created_by: Some("bendy".into()),
+            creation_date: None,
+            encoding: None,
+            info: Info {
+                name: "dir".into(),
+                piece_length: 16,
+                pieces: b"0123456789012345678901234567890123456789".to_vec(),
+                private: true,
+                layout: FileLayout::Multiple {
+                    files: vec![
+                        File {
+                            length: 3,
+                            md5sum: None,
+                            path: vec!["a".into(), "b".into()],
+                        },
+                        File {
+                            length: 4,
+                            md5sum: Some("00".into()),
+                            path: vec!["c".into()],
+                        },
+                    ],
+                },
+            },
+            signatures: Signatures::default(),
+        }
+    }
+
+    #[test]
+    fn multi_file_torrents_should_round_trip() {
+        let torrent = MetaInfo::from_bencode(MULTI_FILE).unwrap();
+        assert_eq!(torrent, multi_file());
+        assert_eq!(torrent.info.total_length(), 7);
+        assert_eq!(torrent.info.piece_hashes().count(), 2);
+
+        let encoded = torrent.to_bencode().unwrap();
+        // Without the unknown key
+        assert_eq!(
+            encoded,
+            [&MULTI_FILE[..MULTI_FILE.len() - 8], b"e"].concat()
+        );
+        assert_eq!(MetaInfo::from_bencode(&encoded).unwrap(), torrent);
+    }
+
+    #[test]
+    fn real_torrents_should_decode() {
+        let torrent = MetaInfo::from_bencode(include_bytes!(
+            "../../examples/torrent_files/debian-9.4.0-amd64-netinst.iso.torrent"
+        ))
+        .unwrap();
+
+        assert_eq!(torrent.info.name, "debian-9.4.0-amd64-netinst.iso");
+        let pieces = torrent.info.piece_hashes().count() as u64;
+        let length = torrent.info.total_length();
+        assert!(pieces * torrent.info.piece_length >= length);
+        assert!((pieces - 1) * torrent.info.piece_length < length);
+    }
+
+    #[test]
+    fn signatures_should_be_part_of_the_torrent() {
+        let torrent = b"d4:infod6:lengthi1e4:name1:a12:piece lengthi1e6:pieces0:e\
+            10:signaturesd11:com.exampled9:signature3:sigeee";
+        let decoded = MetaInfo::from_bencode(torrent).unwrap();
+        assert_eq!(
+            decoded.signatures.get("com.example").unwrap().signature,
+            b"sig"
+        );
+        assert_eq!(decoded.to_bencode().unwrap(), &torrent[..]);
+
+        let verifier = |signer: &str, _: Option<&[u8]>, message: &[u8], signature: &[u8]| {
+            signer == "com.example"
+                && message == b"d6:lengthi1e4:name1:a12:piece lengthi1e6:pieces0:e"
+                && signature == b"sig"
+        };
+        assert!(decoded.verify_signature("com.example", &verifier).unwrap());
+        assert!(!decoded.verify_signature("org.example", &verifier).unwrap());
+
+        let error = MetaInfo::from_bencode(
+            b"d4:infod6:lengthi1e4:name1:a12:piece lengthi1e6:pieces0:e10:signaturesi1ee",
+        )
+        .unwrap_err();
+        assert_eq!(error.path(), Some("signatures"));
+    }
+
+    #[test]
+    fn invalid_info_dicts_should_fail() {
+        let error =
+            MetaInfo::from_bencode(b"d4:infod4:name1:a12:piece lengthi1e6:pieces0:ee").unwrap_err();
+        assert_eq!(error.path(), Some("info"));
+
+        let error = MetaInfo::from_bencode(
+            b"d4:infod6:lengthi1e4:name1:a12:piece lengthi1e6:pieces3:abcee",
+        )
+        .unwrap_err();
+        assert_eq!(error.path(), Some("info.pieces"));
+
+        let error = MetaInfo::from_bencode(
+            b"d4:infod5:filesld6:lengthi1e4:pathli1eeee4:name1:a12:piece lengthi1e6:pieces0:ee",
+        )
+        .unwrap_err();
+        assert_eq!(error.path(), Some("info.files[0].path[0]"));
+    }
+}
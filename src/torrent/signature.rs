@@ -0,0 +1,321 @@
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+use crate::{
+    decoding::{self, Cursor, FromBencode, Object, ResultExt},
+    encoding::{self, AsString, SingleItemEncoder, ToBencode},
+    value::Value,
+};
+
+/// Checks the signatures of a signed torrent.
+///
+/// Implemented for closures taking the same arguments as [`SignatureVerifier::verify`].
+pub trait SignatureVerifier {
+    /// Check that `signature` is a valid signature of `message` by `signer`. The
+    /// `certificate` is the one embedded in the torrent, if any. Implementations that
+    /// don't trust embedded certificates should look up the signer by name instead.
+    fn verify(
+        &self,
+        signer: &str,
+        certificate: Option<&[u8]>,
+        message: &[u8],
+        signature: &[u8],
+    ) -> bool;
+}
+
+impl<F> SignatureVerifier for F
+where
+    F: Fn(&str, Option<&[u8]>, &[u8], &[u8]) -> bool,
+{
+    fn verify(
+        &self,
+        signer: &str,
+        certificate: Option<&[u8]>,
+        message: &[u8],
+        signature: &[u8],
+    ) -> bool {
+        self(signer, certificate, message, signature)
+    }
+}
+
+/// A single entry of the `signatures` dict of a signed torrent ([BEP 35]).
+///
+/// [BEP 35]: http://bittorrent.org/beps/bep_0035.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    /// The X.509 certificate of the signer, if embedded (`certificate`)
+    pub certificate: Option<Vec<u8>>,
+    /// Additional signed data, e.g. an expiration date (`info`). Must be a dict.
+    pub info: Option<Value<'static>>,
+    /// The signature itself (`signature`)
+    pub signature: Vec<u8>,
+}
+
+impl Signature {
+    /// The message covered by the signature: the bencoded info dict of the torrent,
+    /// followed by the bencoded `info` of this signature if present.
+    pub fn signed_message(&self, info: &[u8]) -> Result<Vec<u8>, encoding::Error> {
+        let mut message = info.to_vec();
+        if let Some(extra) = &self.info {
+            message.extend_from_slice(&extra.to_bencode()?);
+        }
+
+        Ok(message)
+    }
+
+    /// Check the signature against the bencoded info dict of the torrent.
+    pub fn verify(
+        &self,
+        signer: &str,
+        info: &[u8],
+        verifier: &impl SignatureVerifier,
+    ) -> Result<bool, encoding::Error> {
+        let message = self.signed_message(info)?;
+
+        Ok(verifier.verify(
+            signer,
+            self.certificate.as_deref(),
+            &message,
+            &self.signature,
+        ))
+    }
+}
+
+impl ToBencode for Signature {
+    const MAX_DEPTH: usize = Value::MAX_DEPTH + 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            if let Some(certificate) = &self.certificate {
+                e.emit_pair(b"certificate", AsString(certificate))?;
+            }
+            if let Some(info) = &self.info {
+                e.emit_pair(b"info", info)?;
+            }
+            e.emit_pair(b"signature", AsString(&self.signature))
+        })
+    }
+}
+
+impl FromBencode for Signature {
+    const EXPECTED_RECURSION_DEPTH: usize = <Value as FromBencode>::EXPECTED_RECURSION_DEPTH + 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut certificate = None;
+        let mut info = None;
+        let mut signature = None;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"certificate", value) => {
+                    certificate = AsString::decode_bencode_object(value)
+                        .context("certificate")
+                        .map(|bytes| Some(bytes.0))?;
+                },
+                (b"info", value @ Object::Dict(_)) => {
+                    info = Value::decode_bencode_object(value)
+                        .context("info")
+                        .map(Some)?;
+                },
+                (b"info", value) => {
+                    return Err(decoding::Error::unexpected_token(
+                        "Dict",
+                        value.into_token().name(),
+                    ))
+                    .context("info");
+                },
+                (b"signature", value) => {
+                    signature = AsString::decode_bencode_object(value)
+                        .context("signature")
+                        .map(|bytes| Some(bytes.0))?;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        Ok(Signature {
+            certificate,
+            info,
+            signature: signature.ok_or_else(|| decoding::Error::missing_field("signature"))?,
+        })
+    }
+}
+
+/// The `signatures` dict of a signed torrent ([BEP 35]), mapping the name of each
+/// signer to its signature.
+///
+/// ```
+/// use bendy::torrent::Signatures;
+///
+/// let torrent = b"d4:infod4:name3:fooe10:signaturesd11:com.exampled9:signature3:\x01\x02\x03eee";
+///
+/// let signatures = Signatures::from_torrent(torrent).unwrap();
+/// assert_eq!(signatures.get("com.example").unwrap().signature, [1, 2, 3]);
+///
+/// let verifier = |signer: &str, _: Option<&[u8]>, message: &[u8], signature: &[u8]| {
+///     signer == "com.example" && message == b"d4:name3:fooe" && signature == [1, 2, 3]
+/// };
+/// assert!(Signatures::verify_torrent(torrent, "com.example", &verifier).unwrap());
+/// ```
+///
+/// [BEP 35]: http://bittorrent.org/beps/bep_0035.html
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Signatures(pub BTreeMap<String, Signature>);
+
+impl Signatures {
+    /// Read the signatures of a bencoded torrent file. Returns an empty set if the torrent
+    /// isn't signed.
+    pub fn from_torrent(torrent: &[u8]) -> Result<Self, decoding::Error> {
+        match Cursor::dict_document(torrent)?.get(b"signatures") {
+            Some(signatures) => Self::from_bencode(signatures.raw()).context("signatures"),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Check the signature of `signer` in a bencoded torrent file.
+    ///
+    /// As the torrent has already been validated, the signed message is taken straight
+    /// from the input. Returns an error if the torrent is malformed or has no signature by
+    /// `signer`, and `Ok(false)` if the signature doesn't match.
+    pub fn verify_torrent(
+        torrent: &[u8],
+        signer: &str,
+        verifier: &impl SignatureVerifier,
+    ) -> Result<bool, decoding::Error> {
+        let root = Cursor::dict_document(torrent)?;
+        let info = root
+            .get(b"info")
+            .ok_or_else(|| decoding::Error::missing_field("info"))?;
+        let entry = root
+            .get(b"signatures")
+            .and_then(|signatures| signatures.get(signer.as_bytes()))
+            .ok_or_else(|| decoding::Error::missing_field(signer).context("signatures"))?;
+        let signature = Signature::from_bencode(entry.raw())
+            .context(signer)
+            .context("signatures")?;
+
+        let mut message = info.raw().to_vec();
+        if let Some(extra) = entry.get(b"info") {
+            message.extend_from_slice(extra.raw());
+        }
+
+        Ok(verifier.verify(
+            signer,
+            signature.certificate.as_deref(),
+            &message,
+            &signature.signature,
+        ))
+    }
+
+    /// The signature of the given signer.
+    pub fn get(&self, signer: &str) -> Option<&Signature> {
+        self.0.get(signer)
+    }
+
+    /// Add or replace the signature of the given signer.
+    pub fn insert(&mut self, signer: impl Into<String>, signature: Signature) -> Option<Signature> {
+        self.0.insert(signer.into(), signature)
+    }
+
+    /// Whether there are no signatures.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl ToBencode for Signatures {
+    const MAX_DEPTH: usize = Signature::MAX_DEPTH + 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        self.0.encode(encoder)
+    }
+}
+
+impl FromBencode for Signatures {
+    const EXPECTED_RECURSION_DEPTH: usize = Signature::EXPECTED_RECURSION_DEPTH + 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        BTreeMap::decode_bencode_object(object).map(Signatures)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use alloc::borrow::Cow;
+
+    static TORRENT: &[u8] = b"d4:infod4:name3:fooe10:signaturesd\
+        1:ad11:certificate4:cert4:infod7:expiresi10ee9:signature3:sige\
+        1:bd9:signature3:sigeee";
+
+    fn verifier(
+        signer: &str,
+        certificate: Option<&[u8]>,
+        message: &[u8],
+        signature: &[u8],
+    ) -> bool {
+        let expected: &[u8] = match signer {
+            "a" => b"d4:name3:fooed7:expiresi10ee",
+            _ => b"d4:name3:fooe",
+        };
+        certificate.unwrap_or(b"cert") == b"cert" && message == expected && signature == b"sig"
+    }
+
+    #[test]
+    fn signatures_should_round_trip() {
+        let signatures = Signatures::from_torrent(TORRENT).unwrap();
+        let a = signatures.get("a").unwrap();
+        assert_eq!(a.certificate.as_deref(), Some(&b"cert"[..]));
+
+        let mut expires = BTreeMap::new();
+        expires.insert(Cow::Borrowed(&b"expires"[..]), Value::Integer(10));
+        assert_eq!(a.info, Some(Value::Dict(expires)));
+
+        let encoded = signatures.to_bencode().unwrap();
+        assert_eq!(Signatures::from_bencode(&encoded).unwrap(), signatures);
+        assert!(TORRENT
+            .windows(encoded.len())
+            .any(|window| window == &encoded[..]));
+    }
+
+    #[test]
+    fn signatures_should_verify() {
+        let signatures = Signatures::from_torrent(TORRENT).unwrap();
+        for (signer, signature) in &signatures.0 {
+            assert!(signature
+                .verify(signer, b"d4:name3:fooe", &verifier)
+                .unwrap());
+            assert!(Signatures::verify_torrent(TORRENT, signer, &verifier).unwrap());
+        }
+
+        assert!(!signatures
+            .get("a")
+            .unwrap()
+            .verify("a", b"de", &verifier)
+            .unwrap());
+        assert!(Signatures::verify_torrent(TORRENT, "c", &verifier).is_err());
+    }
+
+    #[test]
+    fn unsigned_torrents_should_have_no_signatures() {
+        assert!(Signatures::from_torrent(b"d4:infod4:name3:fooee")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn signature_info_should_be_a_dict() {
+        assert!(Signature::from_bencode(b"d4:infoi1e9:signature3:sige").is_err());
+        assert!(Signature::from_bencode(b"d4:infode9:signature3:sige").is_ok());
+    }
+}
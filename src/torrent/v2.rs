@@ -0,0 +1,758 @@
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::{
+    convert::TryFrom,
+    fmt::{self, Display, Formatter},
+};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+use crate::{
+    decoding::{self, FromBencode, Object, ResultExt},
+    encoding::{self, AsString, SingleItemEncoder, ToBencode},
+    hex::Hex,
+    torrent::{info_hash::raw_info, InfoHashBytes},
+};
+
+/// A SHA-256 hash, like a v2 info hash or the pieces root of a file.
+pub type Sha256Bytes = [u8; 32];
+
+/// The smallest piece length of a v2 torrent
+const MIN_PIECE_LENGTH: u64 = 16 * 1024;
+
+/// The contents of a v2 `.torrent` file ([BEP 52]).
+///
+/// Like [`MetaInfo`](super::MetaInfo), only the commonly used keys are supported and
+/// unknown keys are ignored, including the v1 keys of hybrid torrents. Decoding validates
+/// the file tree and checks that the piece layers match the files:
+///
+/// ```
+/// use bendy::{decoding::FromBencode, torrent::MetaInfoV2};
+///
+/// let torrent = MetaInfoV2::from_bencode(
+///     b"d4:infod9:file treed5:a.txtd0:d6:lengthi5e11:pieces root32:\
+///       0123456789abcdef0123456789abcdefeee12:meta versioni2e4:name1:x12:piece lengthi16384ee\
+///       12:piece layersdee",
+/// )
+/// .unwrap();
+///
+/// let files = torrent.info.file_tree.files();
+/// assert_eq!(files[0].0, ["a.txt"]);
+/// assert_eq!(files[0].1.length, 5);
+/// ```
+///
+/// [BEP 52]: http://bittorrent.org/beps/bep_0052.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MetaInfoV2 {
+    /// The URL of the tracker (`announce`)
+    pub announce: Option<String>,
+    /// Tiers of tracker URLs, tried in order (`announce-list`)
+    pub announce_list: Option<Vec<Vec<String>>>,
+    /// A free-form comment (`comment`)
+    pub comment: Option<String>,
+    /// The name and version of the program that created the torrent (`created by`)
+    pub created_by: Option<String>,
+    /// The creation time in seconds since the UNIX epoch (`creation date`)
+    pub creation_date: Option<i64>,
+    /// The description of the content (`info`)
+    pub info: InfoV2,
+    /// The concatenated hashes of the pieces of every file larger than a piece, by the
+    /// pieces root of the file (`piece layers`)
+    pub piece_layers: BTreeMap<Sha256Bytes, Vec<u8>>,
+}
+
+/// The info dict of a v2 torrent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InfoV2 {
+    /// The files of the torrent (`file tree`)
+    pub file_tree: FileTree,
+    /// The suggested name of the torrent directory, or of the file of a single file
+    /// torrent (`name`)
+    pub name: String,
+    /// The number of bytes per piece, a power of two of at least 16 KiB (`piece length`)
+    pub piece_length: u64,
+    /// Whether peers may only be obtained from the trackers (`private`)
+    pub private: bool,
+}
+
+/// A directory of a v2 file tree, by the names of its entries.
+///
+/// The tree is validated when decoded: every directory has at least one entry, names are
+/// neither empty, `.` nor `..` and contain no `/`, and every non-empty file has a pieces
+/// root. Directories can be nested up to 64 levels deep.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileTree(pub BTreeMap<String, FileTreeNode>);
+
+/// An entry of a [`FileTree`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileTreeNode {
+    /// A file, encoded as a dict with the file properties under an empty key
+    File(FileV2),
+    /// A subdirectory
+    Directory(FileTree),
+}
+
+/// A file of a v2 torrent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileV2 {
+    /// The length of the file in bytes (`length`)
+    pub length: u64,
+    /// The root of the merkle tree of the file's blocks, absent for empty files
+    /// (`pieces root`)
+    pub pieces_root: Option<Sha256Bytes>,
+}
+
+/// The SHA-256 hash of the bencoded info dict identifying a v2 torrent.
+///
+/// As with [`signing`](crate::signing), bendy doesn't implement any cryptography itself,
+/// so the hash function has to be passed in:
+///
+/// ```
+/// use bendy::torrent::InfoHashV2;
+///
+/// # fn sha256(data: &[u8]) -> [u8; 32] { [data.len() as u8; 32] }
+/// let torrent = b"d4:infod4:name1:xe12:piece layersdee";
+/// let info_hash = InfoHashV2::from_torrent(torrent, sha256).unwrap();
+/// assert_eq!(info_hash, InfoHashV2::from_info(b"d4:name1:xe", sha256));
+///
+/// // Trackers and the DHT use the first 20 bytes of the hash
+/// assert_eq!(info_hash.truncated(), [11; 20]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InfoHashV2(pub Sha256Bytes);
+
+impl InfoHashV2 {
+    /// Hash the bencoded info dict of a torrent
+    pub fn from_info(info: &[u8], sha256: impl FnOnce(&[u8]) -> Sha256Bytes) -> Self {
+        InfoHashV2(sha256(info))
+    }
+
+    /// Hash the info dict of a bencoded torrent, exactly as it appears in the torrent
+    pub fn from_torrent(
+        torrent: &[u8],
+        sha256: impl FnOnce(&[u8]) -> Sha256Bytes,
+    ) -> Result<Self, decoding::Error> {
+        raw_info(torrent).map(|info| InfoHashV2::from_info(info, sha256))
+    }
+
+    /// The hash truncated to the length of a v1 info hash, as used by trackers and the DHT
+    pub fn truncated(&self) -> InfoHashBytes {
+        let mut truncated = [0; 20];
+        truncated.copy_from_slice(&self.0[..20]);
+        truncated
+    }
+}
+
+impl Display for InfoHashV2 {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Hex(&self.0).fmt(f)
+    }
+}
+
+impl FileTree {
+    /// The maximum nesting depth of directories
+    const MAX_DIRECTORIES: usize = 64;
+
+    /// All files with the components of their path, in the order of the tree
+    pub fn files(&self) -> Vec<(Vec<&str>, &FileV2)> {
+        let mut files = Vec::new();
+        self.collect_files(&mut Vec::new(), &mut files);
+        files
+    }
+
+    fn collect_files<'a>(
+        &'a self,
+        path: &mut Vec<&'a str>,
+        files: &mut Vec<(Vec<&'a str>, &'a FileV2)>,
+    ) {
+        for (name, node) in &self.0 {
+            path.push(name);
+            match node {
+                FileTreeNode::File(file) => files.push((path.clone(), file)),
+                FileTreeNode::Directory(directory) => directory.collect_files(path, files),
+            }
+            path.pop();
+        }
+    }
+
+    /// The total length of all files in bytes, or `None` if it doesn't fit into a `u64`,
+    /// which [`validate`](Self::validate) rejects
+    pub fn total_length(&self) -> Option<u64> {
+        self.files()
+            .iter()
+            .try_fold(0u64, |total, (_, file)| total.checked_add(file.length))
+    }
+
+    /// Check that the tree is well-formed, see the [type documentation](Self), and that
+    /// its total length fits into a `u64`.
+    pub fn validate(&self) -> Result<(), decoding::Error> {
+        self.validate_at(1)?;
+        match self.total_length() {
+            Some(_) => Ok(()),
+            None => Err(malformed(Malformed::TotalLength)),
+        }
+    }
+
+    fn validate_at(&self, depth: usize) -> Result<(), decoding::Error> {
+        if depth > Self::MAX_DIRECTORIES {
+            return Err(malformed(Malformed::TooDeep));
+        }
+        if self.0.is_empty() {
+            return Err(malformed(Malformed::EmptyDirectory));
+        }
+
+        for (name, node) in &self.0 {
+            if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+                return Err(malformed(Malformed::InvalidName)).context(name);
+            }
+            match node {
+                FileTreeNode::File(file) if file.length > 0 && file.pieces_root.is_none() => {
+                    return Err(decoding::Error::missing_field("pieces root")).context(name);
+                },
+                FileTreeNode::File(_) => (),
+                FileTreeNode::Directory(directory) => {
+                    directory.validate_at(depth + 1).context(name)?
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl MetaInfoV2 {
+    /// Check that the file tree is well-formed and that there is a piece layer of the
+    /// right length for every file larger than a piece.
+    pub fn validate(&self) -> Result<(), decoding::Error> {
+        self.info.validate().context("info")?;
+
+        let piece_length = self.info.piece_length;
+        for (path, file) in self.info.file_tree.files() {
+            let root = match file.pieces_root {
+                Some(root) if file.length > piece_length => root,
+                _ => continue,
+            };
+            let pieces = file.length / piece_length + (file.length % piece_length != 0) as u64;
+            match self.piece_layers.get(&root) {
+                Some(layer) if layer.len() as u64 == pieces * 32 => (),
+                Some(layer) => {
+                    return Err(malformed(Malformed::PieceLayerLength {
+                        expected: pieces * 32,
+                        actual: layer.len(),
+                    }))
+                    .context("piece layers")
+                },
+                None => {
+                    return Err(decoding::Error::missing_field(path.join("/")))
+                        .context("piece layers")
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl InfoV2 {
+    /// Check the piece length and the file tree
+    pub fn validate(&self) -> Result<(), decoding::Error> {
+        if self.piece_length < MIN_PIECE_LENGTH || !self.piece_length.is_power_of_two() {
+            return Err(malformed(Malformed::PieceLength(self.piece_length)))
+                .context("piece length");
+        }
+        self.file_tree.validate().context("file tree")
+    }
+}
+
+impl ToBencode for MetaInfoV2 {
+    const MAX_DEPTH: usize = InfoV2::MAX_DEPTH + 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            if let Some(announce) = &self.announce {
+                e.emit_pair(b"announce", announce)?;
+            }
+            if let Some(announce_list) = &self.announce_list {
+                e.emit_pair(b"announce-list", announce_list)?;
+            }
+            if let Some(comment) = &self.comment {
+                e.emit_pair(b"comment", comment)?;
+            }
+            if let Some(created_by) = &self.created_by {
+                e.emit_pair(b"created by", created_by)?;
+            }
+            if let Some(creation_date) = self.creation_date {
+                e.emit_pair(b"creation date", creation_date)?;
+            }
+            e.emit_pair(b"info", &self.info)?;
+            e.emit_pair_with(b"piece layers", |e| {
+                e.emit_dict(|mut e| {
+                    for (root, layer) in &self.piece_layers {
+                        e.emit_pair(root, AsString(layer))?;
+                    }
+                    Ok(())
+                })
+            })
+        })
+    }
+}
+
+impl FromBencode for MetaInfoV2 {
+    const EXPECTED_RECURSION_DEPTH: usize = InfoV2::EXPECTED_RECURSION_DEPTH + 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut announce = None;
+        let mut announce_list = None;
+        let mut comment = None;
+        let mut created_by = None;
+        let mut creation_date = None;
+        let mut info = None;
+        let mut piece_layers = BTreeMap::new();
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"announce", value) => {
+                    announce = String::decode_bencode_object(value)
+                        .context("announce")
+                        .map(Some)?;
+                },
+                (b"announce-list", value) => {
+                    announce_list = Vec::decode_bencode_object(value)
+                        .context("announce-list")
+                        .map(Some)?;
+                },
+                (b"comment", value) => {
+                    comment = String::decode_bencode_object(value)
+                        .context("comment")
+                        .map(Some)?;
+                },
+                (b"created by", value) => {
+                    created_by = String::decode_bencode_object(value)
+                        .context("created by")
+                        .map(Some)?;
+                },
+                (b"creation date", value) => {
+                    creation_date = i64::decode_bencode_object(value)
+                        .context("creation date")
+                        .map(Some)?;
+                },
+                (b"info", value) => {
+                    info = InfoV2::decode_bencode_object(value)
+                        .context("info")
+                        .map(Some)?;
+                },
+                (b"piece layers", value) => {
+                    piece_layers = decode_piece_layers(value).context("piece layers")?;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        let torrent = MetaInfoV2 {
+            announce,
+            announce_list,
+            comment,
+            created_by,
+            creation_date,
+            info: info.ok_or_else(|| decoding::Error::missing_field("info"))?,
+            piece_layers,
+        };
+        torrent.validate()?;
+        Ok(torrent)
+    }
+}
+
+fn decode_piece_layers(object: Object) -> Result<BTreeMap<Sha256Bytes, Vec<u8>>, decoding::Error> {
+    let mut layers = BTreeMap::new();
+    let mut dict = object.try_into_dictionary()?;
+    while let Some((root, value)) = dict.next_pair()? {
+        let root = sha256_bytes(root).map_err(decoding::Error::malformed_content)?;
+        let layer = value.try_into_bytes()?;
+        if !layer.chunks_exact(32).remainder().is_empty() {
+            return Err(malformed(Malformed::PieceLayerLength {
+                expected: (layer.len() as u64 / 32 + 1) * 32,
+                actual: layer.len(),
+            }));
+        }
+        layers.insert(root, layer.to_vec());
+    }
+    Ok(layers)
+}
+
+impl ToBencode for InfoV2 {
+    const MAX_DEPTH: usize = FileTree::MAX_DEPTH + 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair(b"file tree", &self.file_tree)?;
+            e.emit_pair(b"meta version", 2)?;
+            e.emit_pair(b"name", &self.name)?;
+            e.emit_pair(b"piece length", self.piece_length)?;
+            if self.private {
+                e.emit_pair(b"private", 1)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl FromBencode for InfoV2 {
+    const EXPECTED_RECURSION_DEPTH: usize = FileTree::EXPECTED_RECURSION_DEPTH + 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut file_tree = None;
+        let mut meta_version = None;
+        let mut name = None;
+        let mut piece_length = None;
+        let mut private = false;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"file tree", value) => {
+                    file_tree = FileTree::decode_bencode_object(value)
+                        .context("file tree")
+                        .map(Some)?;
+                },
+                (b"meta version", value) => {
+                    meta_version = u64::decode_bencode_object(value)
+                        .context("meta version")
+                        .map(Some)?;
+                },
+                (b"name", value) => {
+                    name = String::decode_bencode_object(value)
+                        .context("name")
+                        .map(Some)?;
+                },
+                (b"piece length", value) => {
+                    piece_length = u64::decode_bencode_object(value)
+                        .context("piece length")
+                        .map(Some)?;
+                },
+                (b"private", value) => {
+                    private = i64::decode_bencode_object(value).context("private")? != 0;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        match meta_version {
+            Some(2) => (),
+            Some(version) => {
+                return Err(malformed(Malformed::MetaVersion(version))).context("meta version")
+            },
+            None => return Err(decoding::Error::missing_field("meta version")),
+        }
+
+        Ok(InfoV2 {
+            file_tree: file_tree.ok_or_else(|| decoding::Error::missing_field("file tree"))?,
+            name: name.ok_or_else(|| decoding::Error::missing_field("name"))?,
+            piece_length: piece_length
+                .ok_or_else(|| decoding::Error::missing_field("piece length"))?,
+            private,
+        })
+    }
+}
+
+impl ToBencode for FileTree {
+    // A file is a dict in a dict below its directory
+    const MAX_DEPTH: usize = Self::MAX_DIRECTORIES + 2;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            for (name, node) in &self.0 {
+                e.emit_pair(name.as_bytes(), node)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl FromBencode for FileTree {
+    const EXPECTED_RECURSION_DEPTH: usize = Self::MAX_DIRECTORIES + 2;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        match FileTreeNode::decode_bencode_object(object)? {
+            FileTreeNode::Directory(tree) => Ok(tree),
+            FileTreeNode::File(_) => Err(malformed(Malformed::RootFile)),
+        }
+    }
+}
+
+impl ToBencode for FileTreeNode {
+    const MAX_DEPTH: usize = FileTree::MAX_DEPTH;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        match self {
+            FileTreeNode::File(file) => encoder.emit_dict(|mut e| e.emit_pair(b"", file)),
+            FileTreeNode::Directory(directory) => directory.encode(encoder),
+        }
+    }
+}
+
+impl FromBencode for FileTreeNode {
+    const EXPECTED_RECURSION_DEPTH: usize = FileTree::EXPECTED_RECURSION_DEPTH;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut entries = BTreeMap::new();
+        let mut file = None;
+        let mut dict = object.try_into_dictionary()?;
+        while let Some((name, value)) = dict.next_pair()? {
+            if name.is_empty() {
+                // The empty key sorts first, so no other entries have been read yet
+                file = FileV2::decode_bencode_object(value).map(Some)?;
+                break;
+            }
+
+            let name =
+                String::from_utf8(name.to_vec()).map_err(decoding::Error::malformed_content)?;
+            let node = FileTreeNode::decode_bencode_object(value).context(&name)?;
+            entries.insert(name, node);
+        }
+
+        if let Some(file) = file {
+            if dict.next_pair()?.is_some() {
+                return Err(malformed(Malformed::FileWithEntries));
+            }
+            return Ok(FileTreeNode::File(file));
+        }
+        Ok(FileTreeNode::Directory(FileTree(entries)))
+    }
+}
+
+impl ToBencode for FileV2 {
+    const MAX_DEPTH: usize = 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair(b"length", self.length)?;
+            if let Some(root) = &self.pieces_root {
+                e.emit_pair(b"pieces root", AsString(root))?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl FromBencode for FileV2 {
+    const EXPECTED_RECURSION_DEPTH: usize = 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut length = None;
+        let mut pieces_root = None;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"length", value) => {
+                    length = u64::decode_bencode_object(value)
+                        .context("length")
+                        .map(Some)?;
+                },
+                (b"pieces root", value) => {
+                    let root = value.try_into_bytes().context("pieces root")?;
+                    pieces_root = sha256_bytes(root)
+                        .map_err(decoding::Error::malformed_content)
+                        .context("pieces root")
+                        .map(Some)?;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        Ok(FileV2 {
+            length: length.ok_or_else(|| decoding::Error::missing_field("length"))?,
+            pieces_root,
+        })
+    }
+}
+
+fn sha256_bytes(bytes: &[u8]) -> Result<Sha256Bytes, Malformed> {
+    Sha256Bytes::try_from(bytes).map_err(|_| Malformed::HashLength(bytes.len()))
+}
+
+fn malformed(error: Malformed) -> decoding::Error {
+    decoding::Error::malformed_content(error)
+}
+
+/// Errors of v2 torrents that are well-formed bencode
+#[derive(Debug)]
+enum Malformed {
+    EmptyDirectory,
+    FileWithEntries,
+    HashLength(usize),
+    InvalidName,
+    MetaVersion(u64),
+    PieceLayerLength { expected: u64, actual: usize },
+    PieceLength(u64),
+    RootFile,
+    TooDeep,
+    TotalLength,
+}
+
+impl Display for Malformed {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Malformed::EmptyDirectory => write!(f, "empty directory in file tree"),
+            Malformed::FileWithEntries => write!(f, "file in file tree with further entries"),
+            Malformed::HashLength(len) => write!(f, "hash of {} bytes instead of 32", len),
+            Malformed::InvalidName => write!(f, "invalid name in file tree"),
+            Malformed::MetaVersion(version) => write!(f, "unsupported meta version {}", version),
+            Malformed::PieceLayerLength { expected, actual } => {
+                write!(f, "piece layer of {} bytes instead of {}", actual, expected)
+            },
+            Malformed::PieceLength(len) => write!(
+                f,
+                "piece length {} is not a power of two of at least 16 KiB",
+                len
+            ),
+            Malformed::RootFile => write!(f, "file tree is a file instead of a directory"),
+            Malformed::TooDeep => write!(f, "file tree nested too deeply"),
+            Malformed::TotalLength => write!(f, "total length of the files overflows"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Malformed {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(not(feature = "std"))]
+    use alloc::{string::ToString, vec};
+
+    static ROOT_A: &[u8] = b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+
+    fn torrent() -> MetaInfoV2 {
+        let mut docs = BTreeMap::new();
+        docs.insert(
+            "a.txt".into(),
+            FileTreeNode::File(FileV2 {
+                length: 40_000,
+                pieces_root: Some(sha256_bytes(ROOT_A).unwrap()),
+            }),
+        );
+        docs.insert(
+            "empty".into(),
+            FileTreeNode::File(FileV2 {
+                length: 0,
+                pieces_root: None,
+            }),
+        );
+        let mut tree = BTreeMap::new();
+        tree.insert("docs".into(), FileTreeNode::Directory(FileTree(docs)));
+        tree.insert(
+            "z".into(),
+            FileTreeNode::File(FileV2 {
+                length: 3,
+                pieces_root: Some([b'z'; 32]),
+            }),
+        );
+
+        let mut piece_layers = BTreeMap::new();
+        piece_layers.insert([b'a'; 32], vec![7; 3 * 32]);
+
+        MetaInfoV2 {
+            announce: Some("http://tracker".into()),
+            announce_list: None,
+            comment: None,
+            created_by: None,
+            creation_date: None,
+            info: InfoV2 {
+                file_tree: FileTree(tree),
+                name: "example".into(),
+                piece_length: 16384,
+                private: false,
+            },
+            piece_layers,
+        }
+    }
+
+    #[test]
+    fn torrents_should_round_trip() {
+        let torrent = torrent();
+        let encoded = torrent.to_bencode().unwrap();
+        assert_eq!(MetaInfoV2::from_bencode(&encoded).unwrap(), torrent);
+
+        let files = torrent.info.file_tree.files();
+        let paths: Vec<_> = files.iter().map(|(path, _)| path.join("/")).collect();
+        assert_eq!(paths, ["docs/a.txt", "docs/empty", "z"]);
+        assert_eq!(torrent.info.file_tree.total_length(), Some(40_003));
+
+        let info_hash = InfoHashV2::from_torrent(&encoded, |info| {
+            assert_eq!(info, &torrent.info.to_bencode().unwrap()[..]);
+            [0xab; 32]
+        })
+        .unwrap();
+        assert_eq!(info_hash.truncated(), [0xab; 20]);
+        assert!(info_hash.to_string().starts_with("abab"));
+    }
+
+    #[test]
+    fn invalid_torrents_should_fail() {
+        let mut torrent = torrent();
+        torrent.piece_layers.clear();
+        let error = MetaInfoV2::from_bencode(&torrent.to_bencode().unwrap()).unwrap_err();
+        assert_eq!(error.path(), Some("piece layers"));
+
+        let mut torrent = self::torrent();
+        torrent.info.piece_length = 20_000;
+        let error = MetaInfoV2::from_bencode(&torrent.to_bencode().unwrap()).unwrap_err();
+        assert_eq!(error.path(), Some("info.piece length"));
+
+        let invalid_trees: &[(&[u8], Option<&str>)] = &[
+            (b"de", None),
+            (b"d1:ad0:d6:lengthi0ee1:xdeee", Some("a")),
+            (b"d2:..d0:d6:lengthi0eeee", Some("..")),
+            (b"d0:d6:lengthi0eee", None),
+            (b"d1:ad0:d6:lengthi1eeee", Some("a")),
+            (b"d1:ad1:bdeee", Some("a.b")),
+            (
+                b"d1:ad0:d6:lengthi1e11:pieces root1:xeee",
+                Some("a.pieces root"),
+            ),
+        ];
+        for (tree, path) in invalid_trees {
+            let error = FileTree::from_bencode(tree)
+                .and_then(|tree| tree.validate().map(|_| tree))
+                .unwrap_err();
+            assert_eq!(error.path(), *path, "{}", String::from_utf8_lossy(tree));
+        }
+
+        let file = |name: &[u8]| {
+            [
+                b"1:",
+                name,
+                b"d0:d6:lengthi9223372036854775808e11:pieces root32:",
+                &[b'r'; 32],
+                b"ee",
+            ]
+            .concat()
+        };
+        let tree = [&b"d"[..], &file(b"a"), &file(b"b"), b"e"].concat();
+        let tree = FileTree::from_bencode(&tree).unwrap();
+        assert_eq!(tree.total_length(), None);
+        assert_eq!(
+            tree.validate().unwrap_err().code(),
+            "decode.malformed_content"
+        );
+    }
+}
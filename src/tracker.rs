@@ -0,0 +1,36 @@
+//! Types for HTTP tracker requests and responses ([BEP 3]).
+//!
+//! Trackers answer announce requests with a bencoded dict holding the re-announce interval
+//! and a list of peers, which is either a list of dicts or a compact byte string ([BEP 23]).
+//! IPv6 peers may additionally be given in the compact `peers6` string ([BEP 7]);
+//! [`AnnounceResponse`] merges both into a single peer list. Scrape requests are answered
+//! with the statistics of each torrent, see [`ScrapeResponse`].
+//!
+//! With the `http` feature, `http_response` turns a response into an `http::Response`
+//! for tracker servers built on axum, hyper or similar frameworks.
+//!
+//! This module is only available with the `std` feature, as peers are represented as
+//! `std::net::SocketAddr`.
+//!
+//! [BEP 3]: http://bittorrent.org/beps/bep_0003.html
+//! [BEP 7]: http://bittorrent.org/beps/bep_0007.html
+//! [BEP 23]: http://bittorrent.org/beps/bep_0023.html
+
+#[cfg(feature = "http")]
+mod http;
+mod peers;
+mod request;
+mod response;
+mod schedule;
+mod scrape;
+
+pub use self::{
+    peers::{decode_compact_peers6_into, decode_compact_peers_into, decode_peers_into, PeerCount},
+    request::AnnounceRequest,
+    response::{AnnounceResponse, TrackerResult},
+    schedule::{AnnounceSchedule, Event},
+    scrape::{ScrapeResponse, ScrapeStats},
+};
+
+#[cfg(feature = "http")]
+pub use self::http::http_response;
@@ -0,0 +1,66 @@
+use http_::{header, Response, StatusCode};
+
+use crate::encoding::{self, ToBencode};
+
+/// Build the HTTP response of a tracker from an announce or scrape response, which is
+/// usually wrapped in a [`TrackerResult`](super::TrackerResult).
+///
+/// The body is the bencoded response, sent as `text/plain` with its `Content-Length`.
+/// Failures are reported in the body as well, so the status is always `200 OK`. Any body
+/// type that can be built from a `Vec<u8>` can be used, like `axum::body::Body` or
+/// `http_body_util::Full<Bytes>`, so the response can be returned from an axum handler or
+/// a hyper service directly:
+///
+/// ```
+/// use bendy::tracker::{http_response, AnnounceResponse, TrackerResult};
+///
+/// let result = TrackerResult::<AnnounceResponse>::Failure {
+///     reason: "torrent not found".to_owned(),
+/// };
+/// let response = http_response::<_, Vec<u8>>(&result).unwrap();
+///
+/// assert_eq!(response.headers()["content-type"], "text/plain");
+/// assert_eq!(response.body(), b"d14:failure reason17:torrent not founde");
+/// ```
+///
+/// This needs the `http` feature.
+pub fn http_response<T, B>(response: &T) -> Result<Response<B>, encoding::Error>
+where
+    T: ToBencode,
+    B: From<Vec<u8>>,
+{
+    let body = response.to_bencode()?;
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .header(header::CONTENT_LENGTH, body.len())
+        .body(B::from(body))
+        // All parts of the response are valid by construction
+        .unwrap();
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::tracker::{AnnounceResponse, TrackerResult};
+
+    #[test]
+    fn responses_should_carry_the_encoded_body() {
+        let result = TrackerResult::Success {
+            response: AnnounceResponse {
+                interval: 1800,
+                ..AnnounceResponse::default()
+            },
+            warning: Some("slow".to_owned()),
+        };
+
+        let response = http_response::<_, Vec<u8>>(&result).unwrap();
+        let body = b"d8:intervali1800e5:peers0:15:warning message4:slowe";
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "51");
+        assert_eq!(response.body(), body);
+    }
+}
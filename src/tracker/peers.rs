@@ -0,0 +1,223 @@
+use std::net::{IpAddr, SocketAddr};
+
+use crate::{
+    compact::{self, PEER6_LEN, PEER_LEN},
+    decoding::{Error, Object, ResultExt},
+};
+
+/// The number of peers written by [`decode_peers_into`] and how many were available.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PeerCount {
+    /// The number of peers written to the output
+    pub written: usize,
+    /// The number of peers in the response
+    pub total: usize,
+}
+
+impl PeerCount {
+    /// Whether the output was too small to hold all peers.
+    pub fn is_truncated(self) -> bool {
+        self.written < self.total
+    }
+}
+
+/// Decode the `peers` of a tracker response into a caller-provided buffer, without
+/// allocating.
+///
+/// Both the dict list and the compact ([BEP 23]) representation are accepted. Peers that
+/// don't fit into `out` are counted, but otherwise skipped. Peers given by DNS name
+/// instead of an IP address are skipped entirely, as they can't be represented without a
+/// lookup.
+///
+/// ```
+/// use std::net::SocketAddr;
+///
+/// use bendy::{decoding::Decoder, tracker::decode_peers_into};
+///
+/// let mut decoder = Decoder::new(b"12:\x7f\x00\x00\x01\x1a\xe1\x0a\x00\x00\x01\x1a\xe2");
+/// let peers = decoder.next_object().unwrap().unwrap();
+///
+/// let mut out = ["0.0.0.0:0".parse::<SocketAddr>().unwrap(); 1];
+/// let count = decode_peers_into(peers, &mut out).unwrap();
+///
+/// assert_eq!(out[0], "127.0.0.1:6881".parse().unwrap());
+/// assert!(count.is_truncated());
+/// assert_eq!(count.total, 2);
+/// ```
+///
+/// [BEP 23]: http://bittorrent.org/beps/bep_0023.html
+pub fn decode_peers_into(peers: Object, out: &mut [SocketAddr]) -> Result<PeerCount, Error> {
+    match peers {
+        Object::Bytes(compact) => decode_compact_peers_into(compact, out),
+        other => {
+            let mut count = PeerCount::default();
+            for_each_peer(other, |peer| {
+                if let Some(slot) = out.get_mut(count.written) {
+                    *slot = peer;
+                    count.written += 1;
+                }
+                count.total += 1;
+            })?;
+            Ok(count)
+        },
+    }
+}
+
+/// Decode the `peers` of a tracker response in either representation, see
+/// [`decode_peers_into`].
+pub(crate) fn decode_peers(peers: Object) -> Result<Vec<SocketAddr>, Error> {
+    let mut out = Vec::new();
+    for_each_peer(peers, |peer| out.push(peer))?;
+    Ok(out)
+}
+
+/// Call `f` with every peer of a dict list or compact peer string
+fn for_each_peer(peers: Object, mut f: impl FnMut(SocketAddr)) -> Result<(), Error> {
+    match peers {
+        Object::Bytes(compact) => {
+            let entries = compact::entries(compact, PEER_LEN, "peers")?;
+            entries.for_each(|peer| f(compact::peer(peer)));
+        },
+        Object::List(mut list) => {
+            let mut index = 0;
+            while let Some(peer) = list.next_object()? {
+                if let Some(peer) = decode_peer_dict(peer).context(format_args!("[{}]", index))? {
+                    f(peer);
+                }
+                index += 1;
+            }
+        },
+        other => {
+            return Err(Error::unexpected_token(
+                "List or String",
+                other.into_token().name(),
+            ))
+        },
+    }
+
+    Ok(())
+}
+
+/// Decode the compact IPv6 `peers6` of a tracker response ([BEP 7]).
+///
+/// [BEP 7]: http://bittorrent.org/beps/bep_0007.html
+pub(crate) fn decode_compact_peers6(compact: &[u8]) -> Result<Vec<SocketAddr>, Error> {
+    Ok(compact::entries(compact, PEER6_LEN, "peers")?
+        .map(compact::peer)
+        .collect())
+}
+
+/// Decode compact IPv4 peers ([BEP 23]), six bytes per peer, into a caller-provided buffer.
+///
+/// [BEP 23]: http://bittorrent.org/beps/bep_0023.html
+pub fn decode_compact_peers_into(
+    compact: &[u8],
+    out: &mut [SocketAddr],
+) -> Result<PeerCount, Error> {
+    fill(
+        compact::entries(compact, PEER_LEN, "peers")?.map(compact::peer),
+        out,
+    )
+}
+
+/// Decode compact IPv6 peers ([BEP 7]), eighteen bytes per peer, into a caller-provided
+/// buffer.
+///
+/// [BEP 7]: http://bittorrent.org/beps/bep_0007.html
+pub fn decode_compact_peers6_into(
+    compact: &[u8],
+    out: &mut [SocketAddr],
+) -> Result<PeerCount, Error> {
+    fill(
+        compact::entries(compact, PEER6_LEN, "peers")?.map(compact::peer),
+        out,
+    )
+}
+
+/// Write as many peers to `out` as fit
+fn fill(
+    peers: impl ExactSizeIterator<Item = SocketAddr>,
+    out: &mut [SocketAddr],
+) -> Result<PeerCount, Error> {
+    let mut count = PeerCount {
+        written: 0,
+        total: peers.len(),
+    };
+    for (slot, peer) in out.iter_mut().zip(peers) {
+        *slot = peer;
+        count.written += 1;
+    }
+
+    Ok(count)
+}
+
+/// Decode a peer dict, returning `None` if it names the peer by DNS name
+fn decode_peer_dict(peer: Object) -> Result<Option<SocketAddr>, Error> {
+    let mut ip = None;
+    let mut port = None;
+
+    let mut dict = peer.try_into_dictionary()?;
+    while let Some(pair) = dict.next_pair()? {
+        match pair {
+            (b"ip", value) => {
+                let value = value.try_into_bytes().context("ip")?;
+                ip = Some(value);
+            },
+            (b"port", value) => {
+                let value = value.try_into_integer().context("port")?;
+                port = Some(value.parse::<u16>().map_err(Error::from).context("port")?);
+            },
+            _ => (), // ignore the peer id and unknown keys
+        }
+    }
+
+    let ip = ip.ok_or_else(|| Error::missing_field("ip"))?;
+    let port = port.ok_or_else(|| Error::missing_field("port"))?;
+
+    Ok(std::str::from_utf8(ip)
+        .ok()
+        .and_then(|ip| ip.parse::<IpAddr>().ok())
+        .map(|ip| SocketAddr::new(ip, port)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::decoding::Decoder;
+
+    fn decode(bencode: &[u8], out: &mut [SocketAddr]) -> Result<PeerCount, Error> {
+        let mut decoder = Decoder::new(bencode);
+        let peers = decoder.next_object()?.unwrap();
+        decode_peers_into(peers, out)
+    }
+
+    #[test]
+    fn peer_dicts_should_be_decoded() {
+        let mut out = ["0.0.0.0:0".parse().unwrap(); 4];
+        let count = decode(
+            b"ld2:ip9:127.0.0.17:peer id20:aaaaaaaaaaaaaaaaaaaa4:porti6881eed2:ip11:example.com4:porti1eed2:ip3:::14:porti2eee",
+            &mut out,
+        )
+        .unwrap();
+
+        assert_eq!(
+            count,
+            PeerCount {
+                written: 2,
+                total: 2
+            }
+        );
+        assert_eq!(out[0], "127.0.0.1:6881".parse().unwrap());
+        assert_eq!(out[1], "[::1]:2".parse().unwrap());
+    }
+
+    #[test]
+    fn malformed_peers_should_fail() {
+        let mut out = [];
+        assert!(decode(b"5:abcde", &mut out).is_err());
+        assert!(decode(b"ld2:ip9:127.0.0.14:porti65536eee", &mut out).is_err());
+        assert!(decode(b"ld4:porti1eee", &mut out).is_err());
+        assert!(decode(b"i1e", &mut out).is_err());
+    }
+}
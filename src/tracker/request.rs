@@ -0,0 +1,159 @@
+use std::{
+    fmt::{self, Display, Formatter, Write},
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+use crate::tracker::Event;
+
+/// The parameters of an announce request.
+///
+/// HTTP trackers receive announces as the query string of a `GET` request, which is
+/// produced by [`AnnounceRequest::to_query`]. Multi-homed clients may announce an
+/// additional address of the other IP version in `ipv4` or `ipv6` ([BEP 7]), so the
+/// tracker can hand out both.
+///
+/// ```
+/// use bendy::tracker::{AnnounceRequest, Event};
+///
+/// let request = AnnounceRequest {
+///     event: Event::Started,
+///     ipv6: Some("2001:db8::1".parse().unwrap()),
+///     ..AnnounceRequest::new([0xab; 20], *b"-XX0001-abcdefghijkl", 6881)
+/// };
+///
+/// let query = request.to_query();
+/// assert!(query.starts_with("info_hash=%AB%AB"));
+/// assert!(query.ends_with("&compact=1&event=started&ipv6=2001%3Adb8%3A%3A1"));
+/// ```
+///
+/// [BEP 7]: http://bittorrent.org/beps/bep_0007.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnnounceRequest {
+    /// The SHA-1 hash of the info dict (`info_hash`)
+    pub info_hash: [u8; 20],
+    /// The ID of the announcing peer (`peer_id`)
+    pub peer_id: [u8; 20],
+    /// The port the peer is listening on (`port`)
+    pub port: u16,
+    /// Bytes uploaded since the `started` event (`uploaded`)
+    pub uploaded: u64,
+    /// Bytes downloaded since the `started` event (`downloaded`)
+    pub downloaded: u64,
+    /// Bytes still missing to complete the download (`left`)
+    pub left: u64,
+    /// Why the announce is made (`event`)
+    pub event: Event,
+    /// Whether the peer accepts compact peer lists (`compact`, [BEP 23])
+    ///
+    /// [BEP 23]: http://bittorrent.org/beps/bep_0023.html
+    pub compact: bool,
+    /// The number of peers the client would like to receive (`numwant`)
+    pub numwant: Option<u32>,
+    /// The `tracker id` of a previous response (`trackerid`)
+    pub tracker_id: Option<Vec<u8>>,
+    /// An additional IPv4 address of the peer (`ipv4`)
+    pub ipv4: Option<Ipv4Addr>,
+    /// An additional IPv6 address of the peer (`ipv6`)
+    pub ipv6: Option<Ipv6Addr>,
+}
+
+impl AnnounceRequest {
+    /// A regular announce of a peer that hasn't transferred anything yet and asks for a
+    /// compact peer list.
+    pub fn new(info_hash: [u8; 20], peer_id: [u8; 20], port: u16) -> Self {
+        AnnounceRequest {
+            info_hash,
+            peer_id,
+            port,
+            uploaded: 0,
+            downloaded: 0,
+            left: 0,
+            event: Event::Regular,
+            compact: true,
+            numwant: None,
+            tracker_id: None,
+            ipv4: None,
+            ipv6: None,
+        }
+    }
+
+    /// The percent-encoded query string of the request, without the leading `?`.
+    pub fn to_query(&self) -> String {
+        let mut query = String::new();
+        let mut param = |name: &str, value: &dyn Display| {
+            if !query.is_empty() {
+                query.push('&');
+            }
+            // Writing to a `String` can't fail
+            let _ = write!(query, "{}={}", name, value);
+        };
+
+        param("info_hash", &PercentEncoded(&self.info_hash));
+        param("peer_id", &PercentEncoded(&self.peer_id));
+        param("port", &self.port);
+        param("uploaded", &self.uploaded);
+        param("downloaded", &self.downloaded);
+        param("left", &self.left);
+        param("compact", &(self.compact as u8));
+        if let Some(event) = self.event.as_str() {
+            param("event", &event);
+        }
+        if let Some(numwant) = self.numwant {
+            param("numwant", &numwant);
+        }
+        if let Some(tracker_id) = &self.tracker_id {
+            param("trackerid", &PercentEncoded(tracker_id));
+        }
+        if let Some(ipv4) = self.ipv4 {
+            param("ipv4", &ipv4);
+        }
+        if let Some(ipv6) = self.ipv6 {
+            param("ipv6", &PercentEncoded(ipv6.to_string().as_bytes()));
+        }
+
+        query
+    }
+}
+
+/// Formats bytes for a URL query, escaping everything but unreserved characters
+struct PercentEncoded<'a>(&'a [u8]);
+
+impl Display for PercentEncoded<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for &byte in self.0 {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    f.write_char(byte as char)?
+                },
+                _ => write!(f, "%{:02X}", byte)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn queries_should_be_percent_encoded() {
+        let request = AnnounceRequest {
+            uploaded: 1,
+            downloaded: 2,
+            left: 3,
+            compact: false,
+            numwant: Some(50),
+            tracker_id: Some(b"a b".to_vec()),
+            ipv4: Some(Ipv4Addr::new(10, 0, 0, 1)),
+            ..AnnounceRequest::new(*b"abcdefghij\x00\x01\x02 ~-._/%", [b'x'; 20], 1)
+        };
+
+        assert_eq!(
+            request.to_query(),
+            "info_hash=abcdefghij%00%01%02%20~-._%2F%25&peer_id=xxxxxxxxxxxxxxxxxxxx&port=1\
+             &uploaded=1&downloaded=2&left=3&compact=0&numwant=50&trackerid=a%20b\
+             &ipv4=10.0.0.1"
+        );
+    }
+}
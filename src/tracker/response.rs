@@ -0,0 +1,393 @@
+use std::{
+    borrow::Cow,
+    net::{IpAddr, SocketAddr},
+};
+
+use crate::{
+    compact,
+    decoding::{Cursor, Error, FromBencode, Object, ResultExt},
+    encoding::{self, AsString, SingleItemEncoder, ToBencode},
+    state_tracker::StructureError,
+    tracker::peers::{decode_compact_peers6, decode_peers},
+    value::Value,
+};
+
+/// The outcome of a tracker request: either the expected response, possibly with a
+/// warning, or a failure (`failure reason`).
+///
+/// ```
+/// use bendy::{
+///     decoding::FromBencode,
+///     tracker::{AnnounceResponse, TrackerResult},
+/// };
+///
+/// let failure = b"d14:failure reason17:torrent not founde";
+/// match TrackerResult::<AnnounceResponse>::from_bencode(failure).unwrap() {
+///     TrackerResult::Failure { reason } => assert_eq!(reason, "torrent not found"),
+///     TrackerResult::Success { .. } => unreachable!(),
+/// }
+///
+/// let success = b"d8:intervali1800e5:peers0:15:warning message4:slowe";
+/// let result = TrackerResult::<AnnounceResponse>::from_bencode(success).unwrap();
+/// assert_eq!(result.warning(), Some("slow"));
+/// assert_eq!(result.into_result().unwrap().interval, 1800);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrackerResult<T> {
+    /// The tracker processed the request
+    Success {
+        /// The response
+        response: T,
+        /// A warning about the request (`warning message`)
+        warning: Option<String>,
+    },
+    /// The tracker rejected the request
+    Failure {
+        /// Why the request was rejected (`failure reason`)
+        reason: String,
+    },
+}
+
+impl<T> TrackerResult<T> {
+    /// The warning of a successful response, if any.
+    pub fn warning(&self) -> Option<&str> {
+        match self {
+            TrackerResult::Success { warning, .. } => warning.as_deref(),
+            TrackerResult::Failure { .. } => None,
+        }
+    }
+
+    /// Convert into a `Result`, dropping any warning.
+    pub fn into_result(self) -> Result<T, String> {
+        match self {
+            TrackerResult::Success { response, .. } => Ok(response),
+            TrackerResult::Failure { reason } => Err(reason),
+        }
+    }
+}
+
+impl<T: FromBencode> FromBencode for TrackerResult<T> {
+    const EXPECTED_RECURSION_DEPTH: usize = T::EXPECTED_RECURSION_DEPTH;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        let raw = object.try_into_dictionary()?.into_raw()?;
+        let root = Cursor::new(raw)?;
+        let text = |key: &'static str| -> Result<Option<String>, Error> {
+            match root.get(key.as_bytes()) {
+                Some(value) => match value.as_bytes() {
+                    Some(bytes) => Ok(Some(String::from_utf8_lossy(bytes).into_owned())),
+                    None => {
+                        Err(Error::unexpected_token("String", value.token().name())).context(key)
+                    },
+                },
+                None => Ok(None),
+            }
+        };
+
+        if let Some(reason) = text("failure reason")? {
+            return Ok(TrackerResult::Failure { reason });
+        }
+
+        Ok(TrackerResult::Success {
+            warning: text("warning message")?,
+            response: T::from_bencode(raw)?,
+        })
+    }
+}
+
+impl<T: ToBencode> ToBencode for TrackerResult<T> {
+    const MAX_DEPTH: usize = encoding::max_depth(&[T::MAX_DEPTH, 1]);
+
+    /// A warning is added to the dict of the response, so a response with a warning has
+    /// to encode to a dict.
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        match self {
+            TrackerResult::Failure { reason } => {
+                encoder.emit_dict(|mut e| e.emit_pair(b"failure reason", reason))
+            },
+            TrackerResult::Success {
+                response,
+                warning: None,
+            } => response.encode(encoder),
+            TrackerResult::Success {
+                response,
+                warning: Some(warning),
+            } => {
+                let mut response = Value::decode(&response.to_bencode()?)
+                    .map_err(encoding::Error::malformed_content)?;
+                let dict = match &mut response {
+                    Value::Dict(dict) => dict,
+                    _ => {
+                        return Err(encoding::Error::from(StructureError::invalid_state(
+                            "A response with a warning must be a dict",
+                        )));
+                    },
+                };
+                dict.insert(
+                    Cow::Borrowed(&b"warning message"[..]),
+                    Value::Bytes(Cow::Borrowed(warning.as_bytes())),
+                );
+                encoder.emit(&response)
+            },
+        }
+    }
+}
+
+/// The response to an announce request.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AnnounceResponse {
+    /// Seconds the client should wait between regular announces (`interval`)
+    pub interval: u64,
+    /// Seconds the client must wait between announces (`min interval`)
+    pub min_interval: Option<u64>,
+    /// An ID to send with subsequent announces (`tracker id`)
+    pub tracker_id: Option<Vec<u8>>,
+    /// The address the tracker saw the request coming from (`external ip`, [BEP 24])
+    ///
+    /// Clients behind a NAT can use it to learn their public address.
+    ///
+    /// [BEP 24]: http://bittorrent.org/beps/bep_0024.html
+    pub external_ip: Option<IpAddr>,
+    /// The number of seeders (`complete`)
+    pub complete: Option<u64>,
+    /// The number of leechers (`incomplete`)
+    pub incomplete: Option<u64>,
+    /// The peers, decoded from either the dict or the compact representation of `peers`,
+    /// followed by the compact IPv6 peers of `peers6` ([BEP 7])
+    ///
+    /// [BEP 7]: http://bittorrent.org/beps/bep_0007.html
+    pub peers: Vec<SocketAddr>,
+}
+
+impl FromBencode for AnnounceResponse {
+    const EXPECTED_RECURSION_DEPTH: usize = 3;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        let mut interval = None;
+        let mut response = AnnounceResponse::default();
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"complete", value) => {
+                    response.complete = u64::decode_bencode_object(value)
+                        .context("complete")
+                        .map(Some)?;
+                },
+                (b"external ip", value) => {
+                    response.external_ip = value
+                        .try_into_bytes()
+                        .and_then(decode_ip)
+                        .context("external ip")
+                        .map(Some)?;
+                },
+                (b"incomplete", value) => {
+                    response.incomplete = u64::decode_bencode_object(value)
+                        .context("incomplete")
+                        .map(Some)?;
+                },
+                (b"interval", value) => {
+                    interval = u64::decode_bencode_object(value)
+                        .context("interval")
+                        .map(Some)?;
+                },
+                (b"min interval", value) => {
+                    response.min_interval = u64::decode_bencode_object(value)
+                        .context("min interval")
+                        .map(Some)?;
+                },
+                (b"peers", value) => {
+                    let mut peers = decode_peers(value).context("peers")?;
+                    peers.append(&mut response.peers);
+                    response.peers = peers;
+                },
+                (b"peers6", value) => {
+                    let peers = value
+                        .try_into_bytes()
+                        .and_then(decode_compact_peers6)
+                        .context("peers6")?;
+                    response.peers.extend(peers);
+                },
+                (b"tracker id", value) => {
+                    response.tracker_id = AsString::decode_bencode_object(value)
+                        .context("tracker id")
+                        .map(|bytes| Some(bytes.0))?;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        response.interval = interval.ok_or_else(|| Error::missing_field("interval"))?;
+        Ok(response)
+    }
+}
+
+impl ToBencode for AnnounceResponse {
+    const MAX_DEPTH: usize = 3;
+
+    /// Peers are encoded in the compact representation, IPv4 peers in `peers` and IPv6
+    /// peers in `peers6`. The latter is omitted if there are no IPv6 peers.
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            if let Some(complete) = self.complete {
+                e.emit_pair(b"complete", complete)?;
+            }
+            if let Some(external_ip) = self.external_ip {
+                match external_ip {
+                    IpAddr::V4(ip) => e.emit_pair(b"external ip", AsString(&ip.octets()))?,
+                    IpAddr::V6(ip) => e.emit_pair(b"external ip", AsString(&ip.octets()))?,
+                }
+            }
+            if let Some(incomplete) = self.incomplete {
+                e.emit_pair(b"incomplete", incomplete)?;
+            }
+            e.emit_pair(b"interval", self.interval)?;
+            if let Some(min_interval) = self.min_interval {
+                e.emit_pair(b"min interval", min_interval)?;
+            }
+            let (compact, compact6) = compact_peers(&self.peers);
+            e.emit_pair(b"peers", AsString(&compact))?;
+            if !compact6.is_empty() {
+                e.emit_pair(b"peers6", AsString(&compact6))?;
+            }
+            if let Some(tracker_id) = &self.tracker_id {
+                e.emit_pair(b"tracker id", AsString(tracker_id))?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Decode a compact IPv4 or IPv6 address
+fn decode_ip(bytes: &[u8]) -> Result<IpAddr, Error> {
+    compact::decode_ip(bytes)
+        .ok_or_else(|| Error::malformed_content(CompactIpLength { len: bytes.len() }))
+}
+
+/// Split peers into the compact IPv4 and IPv6 representations
+fn compact_peers(peers: &[SocketAddr]) -> (Vec<u8>, Vec<u8>) {
+    let mut compact = Vec::new();
+    let mut compact6 = Vec::new();
+    for peer in peers {
+        match peer {
+            SocketAddr::V4(_) => compact::write_peer(peer, &mut compact),
+            SocketAddr::V6(_) => compact::write_peer(peer, &mut compact6),
+        }
+    }
+
+    (compact, compact6)
+}
+
+/// A compact IP address that is neither 4 nor 16 bytes long
+#[derive(Debug)]
+struct CompactIpLength {
+    len: usize,
+}
+
+impl std::fmt::Display for CompactIpLength {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "compact IP address of {} bytes is neither 4 nor 16 bytes long",
+            self.len
+        )
+    }
+}
+
+impl std::error::Error for CompactIpLength {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn announce_responses_should_round_trip() {
+        let response = AnnounceResponse {
+            interval: 1800,
+            min_interval: Some(60),
+            tracker_id: Some(b"abc".to_vec()),
+            external_ip: Some("192.0.2.1".parse().unwrap()),
+            complete: Some(3),
+            incomplete: Some(4),
+            peers: vec!["10.0.0.1:6881".parse().unwrap()],
+        };
+        let encoded = response.to_bencode().unwrap();
+        assert_eq!(
+            encoded,
+            &b"d8:completei3e11:external ip4:\xc0\x00\x02\x0110:incompletei4e8:intervali1800e12:min intervali60e\
+               5:peers6:\x0a\x00\x00\x01\x1a\xe110:tracker id3:abce"[..]
+        );
+        assert_eq!(AnnounceResponse::from_bencode(&encoded).unwrap(), response);
+
+        let ipv6 = AnnounceResponse {
+            peers: vec!["10.0.0.1:2".parse().unwrap(), "[::1]:1".parse().unwrap()],
+            ..response
+        };
+        let encoded = ipv6.to_bencode().unwrap();
+        assert!(encoded.ends_with(
+            b"5:peers6:\x0a\x00\x00\x01\x00\x02\
+              6:peers618:\x00\x00\x00\x00\x00\x00\x00\x00\
+              \x00\x00\x00\x00\x00\x00\x00\x01\x00\x01\
+              10:tracker id3:abce"
+        ));
+        assert_eq!(AnnounceResponse::from_bencode(&encoded).unwrap(), ipv6);
+    }
+
+    #[test]
+    fn ipv6_peers_should_follow_the_other_peers() {
+        let response = AnnounceResponse::from_bencode(
+            b"d8:intervali1e5:peersld2:ip3:::24:porti2eee\
+              6:peers618:\x00\x00\x00\x00\x00\x00\x00\x00\
+              \x00\x00\x00\x00\x00\x00\x00\x01\x00\x01e",
+        )
+        .unwrap();
+        let expected: Vec<SocketAddr> =
+            vec!["[::2]:2".parse().unwrap(), "[::1]:1".parse().unwrap()];
+        assert_eq!(response.peers, expected);
+
+        assert!(AnnounceResponse::from_bencode(b"d8:intervali1e6:peers61:xe").is_err());
+        assert!(AnnounceResponse::from_bencode(b"d8:intervali1e6:peers6lee").is_err());
+    }
+
+    #[test]
+    fn external_ips_should_be_decoded() {
+        let decode =
+            |bencode: &[u8]| AnnounceResponse::from_bencode(bencode).map(|r| r.external_ip);
+
+        assert_eq!(
+            decode(b"d11:external ip4:\x0a\x00\x00\x018:intervali1ee").unwrap(),
+            Some("10.0.0.1".parse().unwrap())
+        );
+        assert_eq!(
+            decode(
+                b"d11:external ip16:\x00\x00\x00\x00\x00\x00\x00\x00\
+                     \x00\x00\x00\x00\x00\x00\x00\x018:intervali1ee"
+            )
+            .unwrap(),
+            Some("::1".parse().unwrap())
+        );
+        assert_eq!(decode(b"d8:intervali1ee").unwrap(), None);
+        assert!(decode(b"d11:external ip5:\x0a\x00\x00\x01\x008:intervali1ee").is_err());
+    }
+
+    #[test]
+    fn failures_should_take_precedence() {
+        let result = TrackerResult::<AnnounceResponse>::from_bencode(
+            b"d14:failure reason3:bad8:intervali1ee",
+        )
+        .unwrap();
+        assert_eq!(result.into_result(), Err("bad".to_string()));
+
+        // Without a failure reason, the response has to be valid
+        assert!(TrackerResult::<AnnounceResponse>::from_bencode(b"d5:peers0:e").is_err());
+        assert!(
+            TrackerResult::<AnnounceResponse>::from_bencode(b"d14:failure reasoni1ee").is_err()
+        );
+    }
+}
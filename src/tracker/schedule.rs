@@ -0,0 +1,230 @@
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+use crate::tracker::AnnounceResponse;
+
+/// The `event` parameter of an announce request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Event {
+    /// A regular announce, sent without an `event` parameter
+    Regular,
+    /// The first announce of a download (`started`)
+    Started,
+    /// The download has completed (`completed`)
+    Completed,
+    /// The client stops downloading and uploading (`stopped`)
+    Stopped,
+    /// A partial seed stops downloading, but keeps uploading (`paused`, [BEP 21])
+    ///
+    /// [BEP 21]: http://bittorrent.org/beps/bep_0021.html
+    Paused,
+}
+
+impl Event {
+    /// The value of the `event` parameter, or `None` for regular announces.
+    pub fn as_str(self) -> Option<&'static str> {
+        match self {
+            Event::Regular => None,
+            Event::Started => Some("started"),
+            Event::Completed => Some("completed"),
+            Event::Stopped => Some("stopped"),
+            Event::Paused => Some("paused"),
+        }
+    }
+
+    /// Parse the value of an `event` parameter. An empty value is a regular announce.
+    pub fn from_bytes(event: &[u8]) -> Option<Self> {
+        match event {
+            b"" => Some(Event::Regular),
+            b"started" => Some(Event::Started),
+            b"completed" => Some(Event::Completed),
+            b"stopped" => Some(Event::Stopped),
+            b"paused" => Some(Event::Paused),
+            _ => None,
+        }
+    }
+
+    /// The event of the next regular announce after an announce with this event, or
+    /// `None` if no further announce should be made.
+    pub fn next(self) -> Option<Event> {
+        match self {
+            Event::Stopped => None,
+            // A paused partial seed keeps announcing as paused
+            Event::Paused => Some(Event::Paused),
+            _ => Some(Event::Regular),
+        }
+    }
+}
+
+// `#[default]` on enum variants requires a newer compiler than the MSRV
+#[allow(clippy::derivable_impls)]
+impl Default for Event {
+    fn default() -> Self {
+        Event::Regular
+    }
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(self.as_str().unwrap_or(""))
+    }
+}
+
+/// When to announce again, derived from the `interval` and `min interval` of an
+/// announce response.
+///
+/// Regular announces are spread by a random jitter of up to [`AnnounceSchedule::JITTER`]
+/// around the interval, so that clients started at the same time don't announce in
+/// lockstep. The randomness is provided by the caller, keeping this crate free of a
+/// random number generator:
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use bendy::tracker::{AnnounceResponse, AnnounceSchedule, Event};
+///
+/// let response = AnnounceResponse {
+///     interval: 1800,
+///     min_interval: Some(1700),
+///     ..AnnounceResponse::default()
+/// };
+/// let schedule = AnnounceSchedule::from_response(&response);
+///
+/// let delay = schedule.next_announce(Event::Started, 0).unwrap();
+/// assert_eq!(delay, Duration::from_secs(1700)); // limited by `min interval`
+/// assert_eq!(schedule.next_announce(Event::Stopped, 0), None);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AnnounceSchedule {
+    /// The interval between regular announces
+    pub interval: Duration,
+    /// The minimal interval between any two announces
+    pub min_interval: Duration,
+}
+
+impl AnnounceSchedule {
+    /// The maximal jitter of regular announces in percent of the interval.
+    pub const JITTER: u32 = 10;
+    /// The longest interval accepted from a tracker. Longer intervals are shortened to
+    /// it, so that a broken or hostile tracker can't stop the announces.
+    pub const MAX_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+    /// The delay before retrying the first failed announce, doubled after every further
+    /// failure.
+    pub const RETRY_DELAY: Duration = Duration::from_secs(15);
+
+    /// The schedule requested by a tracker, with intervals of at most
+    /// [`MAX_INTERVAL`](Self::MAX_INTERVAL).
+    pub fn from_response(response: &AnnounceResponse) -> Self {
+        let max = Self::MAX_INTERVAL.as_secs();
+        AnnounceSchedule {
+            interval: Duration::from_secs(response.interval.min(max)),
+            min_interval: Duration::from_secs(response.min_interval.unwrap_or(0).min(max)),
+        }
+    }
+
+    /// The delay until the regular announce following an announce with the given event,
+    /// or `None` if no further announce should be made, see [`Event::next`].
+    ///
+    /// `random` selects the jitter and should be uniformly distributed over all `u32`
+    /// values.
+    pub fn next_announce(&self, event: Event, random: u32) -> Option<Duration> {
+        event.next()?;
+
+        // Computed in u128, which can't overflow for any `Duration`
+        let interval = self.interval.as_millis();
+        let max_jitter = interval * u128::from(Self::JITTER) / 100;
+        let jitter = (2 * max_jitter) * u128::from(random) / u128::from(u32::MAX);
+        let millis = interval - max_jitter + jitter;
+        let delay = Duration::from_millis(u64::try_from(millis).unwrap_or(u64::MAX));
+
+        Some(delay.max(self.min_interval))
+    }
+
+    /// The delay before retrying an announce that failed `failures` times in a row, using
+    /// exponential backoff from [`RETRY_DELAY`](Self::RETRY_DELAY) up to the interval.
+    pub fn retry_after(&self, failures: u32) -> Duration {
+        let backoff = Self::RETRY_DELAY
+            .checked_mul(1 << failures.saturating_sub(1).min(16))
+            .unwrap_or(self.interval);
+
+        backoff.min(self.interval).max(self.min_interval)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoding::FromBencode;
+
+    fn schedule() -> AnnounceSchedule {
+        AnnounceSchedule {
+            interval: Duration::from_secs(1000),
+            min_interval: Duration::from_secs(10),
+        }
+    }
+
+    #[test]
+    fn jitter_should_stay_within_bounds() {
+        let schedule = schedule();
+        assert_eq!(
+            schedule.next_announce(Event::Regular, 0),
+            Some(Duration::from_secs(900))
+        );
+        assert_eq!(
+            schedule.next_announce(Event::Completed, u32::MAX),
+            Some(Duration::from_secs(1100))
+        );
+        assert_eq!(
+            schedule.next_announce(Event::Paused, u32::MAX / 2),
+            Some(Duration::from_millis(999_999))
+        );
+    }
+
+    #[test]
+    fn huge_intervals_should_not_overflow() {
+        let response = AnnounceResponse::from_bencode(b"d8:intervali30000000e5:peers0:e").unwrap();
+        let schedule = AnnounceSchedule::from_response(&response);
+        assert_eq!(schedule.interval, AnnounceSchedule::MAX_INTERVAL);
+        assert_eq!(
+            schedule.next_announce(Event::Regular, u32::MAX),
+            Some(AnnounceSchedule::MAX_INTERVAL * 11 / 10)
+        );
+
+        let schedule = AnnounceSchedule {
+            interval: Duration::from_secs(u64::MAX),
+            min_interval: Duration::from_secs(0),
+        };
+        assert_eq!(
+            schedule.next_announce(Event::Regular, u32::MAX),
+            Some(Duration::from_millis(u64::MAX))
+        );
+    }
+
+    #[test]
+    fn retries_should_back_off() {
+        let schedule = schedule();
+        assert_eq!(schedule.retry_after(1), Duration::from_secs(15));
+        assert_eq!(schedule.retry_after(3), Duration::from_secs(60));
+        assert_eq!(schedule.retry_after(100), Duration::from_secs(1000));
+    }
+
+    #[test]
+    fn events_should_round_trip() {
+        for event in &[
+            Event::Regular,
+            Event::Started,
+            Event::Completed,
+            Event::Stopped,
+            Event::Paused,
+        ] {
+            assert_eq!(
+                Event::from_bytes(event.to_string().as_bytes()),
+                Some(*event)
+            );
+        }
+        assert_eq!(Event::from_bytes(b"resumed"), None);
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    decoding::{Error, FromBencode, Object, ResultExt},
+    encoding::{self, SingleItemEncoder, ToBencode},
+    hex::Hex,
+};
+
+/// The response to a scrape request, holding the statistics of every requested torrent.
+///
+/// Failures are reported like for announces, so scrape responses are usually decoded as
+/// a [`TrackerResult<ScrapeResponse>`](super::TrackerResult):
+///
+/// ```
+/// use bendy::{
+///     decoding::FromBencode,
+///     tracker::{ScrapeResponse, TrackerResult},
+/// };
+///
+/// let response =
+///     b"d5:filesd20:aaaaaaaaaaaaaaaaaaaad8:completei5e10:downloadedi50e10:incompletei10eeee";
+/// let scrape = TrackerResult::<ScrapeResponse>::from_bencode(response)
+///     .unwrap()
+///     .into_result()
+///     .unwrap();
+///
+/// let stats = &scrape.files[&[b'a'; 20]];
+/// assert_eq!(
+///     (stats.complete, stats.downloaded, stats.incomplete),
+///     (5, 50, 10)
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScrapeResponse {
+    /// The statistics of the torrents by info hash (`files`)
+    pub files: BTreeMap<[u8; 20], ScrapeStats>,
+    /// Seconds the client must wait between scrapes (`min_request_interval` in `flags`)
+    pub min_request_interval: Option<u64>,
+}
+
+/// The statistics of a single torrent in a [`ScrapeResponse`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScrapeStats {
+    /// The number of seeders (`complete`)
+    pub complete: u64,
+    /// The number of completed downloads (`downloaded`)
+    pub downloaded: u64,
+    /// The number of leechers (`incomplete`)
+    pub incomplete: u64,
+    /// The name of the torrent, as sent by some trackers (`name`)
+    pub name: Option<String>,
+}
+
+impl FromBencode for ScrapeResponse {
+    const EXPECTED_RECURSION_DEPTH: usize = ScrapeStats::EXPECTED_RECURSION_DEPTH + 2;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        let mut response = ScrapeResponse::default();
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"files", value) => {
+                    let mut files = value.try_into_dictionary().context("files")?;
+                    while let Some((info_hash, stats)) = files.next_pair().context("files")? {
+                        let info_hash = decode_info_hash(info_hash).context("files")?;
+                        let stats = ScrapeStats::decode_bencode_object(stats)
+                            .context(format_args!("files.{}", Hex(&info_hash)))?;
+                        response.files.insert(info_hash, stats);
+                    }
+                },
+                (b"flags", value) => {
+                    let mut flags = value.try_into_dictionary().context("flags")?;
+                    while let Some(pair) = flags.next_pair().context("flags")? {
+                        if let (b"min_request_interval", value) = pair {
+                            response.min_request_interval = u64::decode_bencode_object(value)
+                                .context("flags.min_request_interval")
+                                .map(Some)?;
+                        }
+                    }
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        Ok(response)
+    }
+}
+
+impl ToBencode for ScrapeResponse {
+    const MAX_DEPTH: usize = ScrapeStats::MAX_DEPTH + 2;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair_with(b"files", |e| {
+                e.emit_dict(|mut e| {
+                    for (info_hash, stats) in &self.files {
+                        e.emit_pair(info_hash, stats)?;
+                    }
+                    Ok(())
+                })
+            })?;
+            if let Some(min_request_interval) = self.min_request_interval {
+                e.emit_pair_with(b"flags", |e| {
+                    e.emit_dict(|mut e| e.emit_pair(b"min_request_interval", min_request_interval))
+                })?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl FromBencode for ScrapeStats {
+    const EXPECTED_RECURSION_DEPTH: usize = 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        let mut complete = None;
+        let mut downloaded = None;
+        let mut incomplete = None;
+        let mut name = None;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"complete", value) => {
+                    complete = u64::decode_bencode_object(value)
+                        .context("complete")
+                        .map(Some)?;
+                },
+                (b"downloaded", value) => {
+                    downloaded = u64::decode_bencode_object(value)
+                        .context("downloaded")
+                        .map(Some)?;
+                },
+                (b"incomplete", value) => {
+                    incomplete = u64::decode_bencode_object(value)
+                        .context("incomplete")
+                        .map(Some)?;
+                },
+                (b"name", value) => {
+                    name = String::decode_bencode_object(value)
+                        .context("name")
+                        .map(Some)?;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        Ok(ScrapeStats {
+            complete: complete.ok_or_else(|| Error::missing_field("complete"))?,
+            downloaded: downloaded.ok_or_else(|| Error::missing_field("downloaded"))?,
+            incomplete: incomplete.ok_or_else(|| Error::missing_field("incomplete"))?,
+            name,
+        })
+    }
+}
+
+impl ToBencode for ScrapeStats {
+    const MAX_DEPTH: usize = 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair(b"complete", self.complete)?;
+            e.emit_pair(b"downloaded", self.downloaded)?;
+            e.emit_pair(b"incomplete", self.incomplete)?;
+            if let Some(name) = &self.name {
+                e.emit_pair(b"name", name)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+fn decode_info_hash(bytes: &[u8]) -> Result<[u8; 20], Error> {
+    let mut info_hash = [0; 20];
+    if bytes.len() != info_hash.len() {
+        return Err(Error::malformed_content(InfoHashLength {
+            len: bytes.len(),
+        }));
+    }
+    info_hash.copy_from_slice(bytes);
+    Ok(info_hash)
+}
+
+/// An info hash in a scrape response that isn't 20 bytes long
+#[derive(Debug)]
+struct InfoHashLength {
+    len: usize,
+}
+
+impl std::fmt::Display for InfoHashLength {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "info hash of {} bytes instead of 20", self.len)
+    }
+}
+
+impl std::error::Error for InfoHashLength {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scrape_responses_should_round_trip() {
+        let mut response = ScrapeResponse {
+            min_request_interval: Some(900),
+            ..ScrapeResponse::default()
+        };
+        response.files.insert(
+            [1; 20],
+            ScrapeStats {
+                complete: 1,
+                downloaded: 2,
+                incomplete: 3,
+                name: Some("a".to_string()),
+            },
+        );
+        response.files.insert([0; 20], ScrapeStats::default());
+
+        let encoded = response.to_bencode().unwrap();
+        assert!(encoded.starts_with(b"d5:filesd20:\x00\x00"));
+        assert!(encoded.ends_with(b"5:flagsd20:min_request_intervali900eee"));
+        assert_eq!(ScrapeResponse::from_bencode(&encoded).unwrap(), response);
+    }
+
+    #[test]
+    fn invalid_scrape_responses_should_fail() {
+        let error = ScrapeResponse::from_bencode(b"d5:filesd3:abcdeee").unwrap_err();
+        assert_eq!(error.path(), Some("files"));
+
+        let error = ScrapeResponse::from_bencode(
+            b"d5:filesd20:\x01\x01\x01\x01\x01\x01\x01\x01\x01\x01\x01\x01\x01\x01\x01\x01\x01\x01\x01\x01\
+              d8:completei1eeee",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.path(),
+            Some("files.0101010101010101010101010101010101010101")
+        );
+    }
+}
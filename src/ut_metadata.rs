@@ -0,0 +1,304 @@
+//! Messages of the metadata exchange extension (`ut_metadata`, [BEP 9]).
+//!
+//! Peers that joined a torrent through a magnet link download its `info` dict from other
+//! peers in pieces of [`PIECE_LEN`] bytes. Every message is a bencoded dict with the
+//! message type (`msg_type`) and the index of the piece; `data` messages carry the piece
+//! itself as raw bytes appended directly after the dict:
+//!
+//! ```
+//! use bendy::ut_metadata::Message;
+//!
+//! let message = Message::data(0, 5, b"d1:ae").unwrap();
+//! let encoded = message.to_bytes().unwrap();
+//! assert_eq!(encoded, b"d8:msg_typei1e5:piecei0e10:total_sizei5eed1:ae");
+//!
+//! match Message::decode(&encoded).unwrap() {
+//!     Message::Data { payload, .. } => assert_eq!(payload, b"d1:ae"),
+//!     _ => panic!("expected a data message"),
+//! }
+//! ```
+//!
+//! Decoding checks that the payload of a `data` message has exactly the length of its
+//! piece, and that no other message carries a payload.
+//!
+//! [BEP 9]: http://bittorrent.org/beps/bep_0009.html
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+
+use crate::{
+    decoding::{self, FromBencode, Object, ResultExt},
+    encoding::{self, SingleItemEncoder, ToBencode},
+};
+
+/// The length of every piece of the metadata but the last.
+pub const PIECE_LEN: usize = 16 * 1024;
+
+/// The number of pieces of metadata of `total_size` bytes.
+pub fn piece_count(total_size: u64) -> u64 {
+    let piece_len = PIECE_LEN as u64;
+    let full = total_size / piece_len;
+    if full * piece_len == total_size {
+        full
+    } else {
+        full + 1
+    }
+}
+
+/// A `ut_metadata` message, borrowing the payload of `data` messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Message<'a> {
+    /// A request for a piece (`"msg_type": 0`)
+    Request {
+        /// The index of the requested piece (`piece`)
+        piece: u32,
+    },
+    /// A piece of the metadata (`"msg_type": 1`)
+    Data {
+        /// The index of the piece (`piece`)
+        piece: u32,
+        /// The length of the whole metadata (`total_size`)
+        total_size: u64,
+        /// The content of the piece, following the dict
+        payload: &'a [u8],
+    },
+    /// The refusal to send a piece (`"msg_type": 2`)
+    Reject {
+        /// The index of the rejected piece (`piece`)
+        piece: u32,
+    },
+}
+
+impl<'a> Message<'a> {
+    /// Create a `data` message, checking that `payload` has the length of the piece.
+    pub fn data(piece: u32, total_size: u64, payload: &'a [u8]) -> Result<Self, decoding::Error> {
+        check_payload(piece, total_size, payload.len())?;
+        Ok(Message::Data {
+            piece,
+            total_size,
+            payload,
+        })
+    }
+
+    /// The index of the requested, sent or rejected piece.
+    pub fn piece(&self) -> u32 {
+        match *self {
+            Message::Request { piece }
+            | Message::Data { piece, .. }
+            | Message::Reject { piece } => piece,
+        }
+    }
+
+    /// The message type (`msg_type`).
+    pub fn message_type(&self) -> u8 {
+        match self {
+            Message::Request { .. } => 0,
+            Message::Data { .. } => 1,
+            Message::Reject { .. } => 2,
+        }
+    }
+
+    /// Decode a message, which is the payload of an extension message (BEP 10) without
+    /// its extended message ID.
+    pub fn decode(bytes: &'a [u8]) -> Result<Self, decoding::Error> {
+        // The dict has to be split from the payload that follows it
+        let len = decoding::is_valid(bytes)?;
+        let (header, payload) = bytes.split_at(len);
+        let header = Header::from_bencode(header)?;
+
+        match header.message_type {
+            1 => {
+                let total_size = header
+                    .total_size
+                    .ok_or_else(|| decoding::Error::missing_field("total_size"))?;
+                Message::data(header.piece, total_size, payload)
+            },
+            message_type => {
+                if !payload.is_empty() {
+                    return Err(decoding::Error::unexpected_field("payload"));
+                }
+                match message_type {
+                    0 => Ok(Message::Request {
+                        piece: header.piece,
+                    }),
+                    2 => Ok(Message::Reject {
+                        piece: header.piece,
+                    }),
+                    other => Err(decoding::Error::unexpected_token(
+                        "msg_type 0, 1 or 2",
+                        other,
+                    ))
+                    .context("msg_type"),
+                }
+            },
+        }
+    }
+
+    /// Encode the message, followed by the payload of `data` messages.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, encoding::Error> {
+        let mut bytes = self.to_bencode()?;
+        if let Message::Data { payload, .. } = self {
+            bytes.extend_from_slice(payload);
+        }
+        Ok(bytes)
+    }
+}
+
+/// Encodes the dict only; use [`Message::to_bytes`] to append the payload of `data`
+/// messages.
+impl ToBencode for Message<'_> {
+    const MAX_DEPTH: usize = 1;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair(b"msg_type", self.message_type())?;
+            e.emit_pair(b"piece", self.piece())?;
+            if let Message::Data { total_size, .. } = self {
+                e.emit_pair(b"total_size", total_size)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// The dict of a message
+struct Header {
+    message_type: i64,
+    piece: u32,
+    total_size: Option<u64>,
+}
+
+impl FromBencode for Header {
+    const EXPECTED_RECURSION_DEPTH: usize = 1;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let mut message_type = None;
+        let mut piece = None;
+        let mut total_size = None;
+
+        let mut dict = object.try_into_dictionary()?;
+        while let Some(pair) = dict.next_pair()? {
+            match pair {
+                (b"msg_type", value) => {
+                    message_type = i64::decode_bencode_object(value)
+                        .context("msg_type")
+                        .map(Some)?;
+                },
+                (b"piece", value) => {
+                    piece = u32::decode_bencode_object(value)
+                        .context("piece")
+                        .map(Some)?;
+                },
+                (b"total_size", value) => {
+                    total_size = u64::decode_bencode_object(value)
+                        .context("total_size")
+                        .map(Some)?;
+                },
+                _ => (), // ignore unknown keys
+            }
+        }
+
+        Ok(Header {
+            message_type: message_type.ok_or_else(|| decoding::Error::missing_field("msg_type"))?,
+            piece: piece.ok_or_else(|| decoding::Error::missing_field("piece"))?,
+            total_size,
+        })
+    }
+}
+
+/// Check that a payload of `len` bytes is piece `piece` of metadata of `total_size` bytes
+fn check_payload(piece: u32, total_size: u64, len: usize) -> Result<(), decoding::Error> {
+    let count = piece_count(total_size);
+    if u64::from(piece) >= count {
+        return Err(decoding::Error::malformed_content(PayloadLength::Piece {
+            piece,
+            count,
+        }))
+        .context("piece");
+    }
+
+    let start = u64::from(piece) * PIECE_LEN as u64;
+    let expected = (total_size - start).min(PIECE_LEN as u64);
+    if len as u64 != expected {
+        return Err(decoding::Error::malformed_content(PayloadLength::Payload {
+            len,
+            expected,
+        }))
+        .context("payload");
+    }
+    Ok(())
+}
+
+/// A piece that doesn't fit the size of the metadata
+#[derive(Debug)]
+enum PayloadLength {
+    Piece { piece: u32, count: u64 },
+    Payload { len: usize, expected: u64 },
+}
+
+impl Display for PayloadLength {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            PayloadLength::Piece { piece, count } => {
+                write!(f, "piece {} of metadata with {} pieces", piece, count)
+            },
+            PayloadLength::Payload { len, expected } => {
+                write!(f, "payload of {} bytes instead of {}", len, expected)
+            },
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PayloadLength {}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn messages_should_round_trip() {
+        let payload = vec![b'x'; PIECE_LEN];
+        let messages = [
+            Message::Request { piece: 3 },
+            Message::Reject { piece: 0 },
+            Message::data(1, PIECE_LEN as u64 * 2, &payload).unwrap(),
+            Message::data(2, PIECE_LEN as u64 * 2 + 1, &payload[..1]).unwrap(),
+        ];
+
+        for message in &messages {
+            let encoded = message.to_bytes().unwrap();
+            assert_eq!(Message::decode(&encoded).unwrap(), *message);
+        }
+        assert_eq!(
+            messages[0].to_bytes().unwrap(),
+            b"d8:msg_typei0e5:piecei3ee"
+        );
+    }
+
+    #[test]
+    fn payloads_should_match_their_piece() {
+        assert_eq!(piece_count(0), 0);
+        assert_eq!(piece_count(PIECE_LEN as u64), 1);
+        assert_eq!(piece_count(PIECE_LEN as u64 + 1), 2);
+
+        let error = Message::data(1, PIECE_LEN as u64, b"").unwrap_err();
+        assert_eq!(error.path(), Some("piece"));
+        let error = Message::decode(b"d8:msg_typei1e5:piecei0e10:total_sizei5eed1:a").unwrap_err();
+        assert_eq!(error.path(), Some("payload"));
+        let error = Message::decode(b"d8:msg_typei1e5:piecei0eexxxxx").unwrap_err();
+        assert_eq!(error.code(), "decode.missing_field");
+
+        let error = Message::decode(b"d8:msg_typei0e5:piecei0eex").unwrap_err();
+        assert_eq!(error.code(), "decode.unexpected_field");
+        let error = Message::decode(b"d8:msg_typei3e5:piecei0ee").unwrap_err();
+        assert_eq!(error.path(), Some("msg_type"));
+    }
+}
@@ -6,9 +6,11 @@
 
 use alloc::{
     borrow::{Cow, ToOwned},
-    collections::BTreeMap,
-    vec::Vec,
+    collections::{btree_map, BTreeMap},
+    string::String,
+    vec::{IntoIter, Vec},
 };
+use core::{mem, slice};
 
 #[cfg(feature = "serde")]
 use std::{
@@ -27,8 +29,9 @@ use serde::{
 };
 
 use crate::{
-    decoding::{FromBencode, Object},
-    encoding::{SingleItemEncoder, ToBencode},
+    decoding::{self, parse_integer, Decoder, FromBencode, Object, ResultExt},
+    encoding::{self, Encoder, SingleItemEncoder, ToBencode},
+    state_tracker::{StructureError, Token},
 };
 
 /// An owned or borrowed bencoded value.
@@ -44,62 +47,389 @@ pub enum Value<'a> {
     List(Vec<Value<'a>>),
 }
 
+impl Value<'static> {
+    /// Decode a single bencoded value into an owned `Value`, which can outlive the input.
+    ///
+    /// ```
+    /// use bendy::value::Value;
+    ///
+    /// let value = {
+    ///     let input = b"li1e3:fooe".to_vec();
+    ///     Value::decode(&input).unwrap()
+    /// };
+    /// assert!(matches!(value, Value::List(ref list) if list.len() == 2));
+    /// ```
+    pub fn decode(bytes: &[u8]) -> Result<Self, decoding::Error> {
+        Self::from_bencode(bytes)
+    }
+}
+
 impl<'a> Value<'a> {
+    /// Emit this value as the next item of `encoder`.
+    ///
+    /// Unlike [`ToBencode::encode`], this works on a top-level [`Encoder`], so a value can
+    /// be emitted next to other items without going through `emit`.
+    pub fn encode_into(&self, encoder: &mut Encoder) -> Result<(), encoding::Error> {
+        encoder.emit(self)
+    }
+
     /// Convert this Value into an owned Value with static lifetime
     pub fn into_owned(self) -> Value<'static> {
+        // The lists and dicts being converted, innermost last, so that deeply nested
+        // values don't overflow the stack
+        let mut stack: Vec<OwnedFrame<'a>> = Vec::new();
+        let mut next = Some(self);
+
+        loop {
+            let mut done = None;
+            if let Some(mut value) = next.take() {
+                match &mut value {
+                    Value::Bytes(bytes) => {
+                        done = Some(Value::Bytes(Cow::Owned(mem::take(bytes).into_owned())));
+                    },
+                    Value::Dict(dict) => {
+                        stack.push(OwnedFrame::Dict(
+                            BTreeMap::new(),
+                            mem::take(dict).into_iter(),
+                            None,
+                        ));
+                    },
+                    Value::Integer(integer) => done = Some(Value::Integer(*integer)),
+                    Value::List(list) => {
+                        let list = mem::take(list);
+                        stack.push(OwnedFrame::List(
+                            Vec::with_capacity(list.len()),
+                            list.into_iter(),
+                        ));
+                    },
+                }
+            }
+
+            // Add the converted value to its container and continue with the next
+            // element, completing every container that has none left
+            loop {
+                let frame = match stack.last_mut() {
+                    Some(frame) => frame,
+                    None => return done.expect("a value is converted before the stack empties"),
+                };
+
+                if let Some(value) = done.take() {
+                    frame.push(value);
+                }
+                match frame.next_element() {
+                    Some(element) => {
+                        next = Some(element);
+                        break;
+                    },
+                    None => done = stack.pop().map(OwnedFrame::finish),
+                }
+            }
+        }
+    }
+
+    /// Estimate the memory used by this value in bytes, including the value itself and
+    /// everything it owns.
+    ///
+    /// Borrowed byte strings are not counted as they live in the input buffer. The
+    /// overhead of dict nodes depends on the allocator and the `BTreeMap` implementation,
+    /// so only the size of each entry is counted for dicts.
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    ///
+    /// use bendy::value::Value;
+    ///
+    /// let borrowed = Value::Bytes(Cow::Borrowed(&[0; 1000]));
+    /// let owned = borrowed.clone().into_owned();
+    /// assert!(owned.deep_size() >= borrowed.deep_size() + 1000);
+    /// ```
+    pub fn deep_size(&self) -> usize {
+        mem::size_of::<Self>() + self.heap_size()
+    }
+
+    /// The memory owned by this value, excluding the value itself
+    fn heap_size(&self) -> usize {
+        let mut size = 0;
+        // Walked with an explicit stack, so that deeply nested values don't overflow the
+        // call stack
+        let mut stack = Vec::new();
+        stack.push(self);
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::Bytes(bytes) => size += owned_size(bytes),
+                Value::Dict(dict) => {
+                    for (key, value) in dict {
+                        size += mem::size_of::<(Cow<[u8]>, Value)>() + owned_size(key);
+                        stack.push(value);
+                    }
+                },
+                Value::Integer(_) => {},
+                Value::List(list) => {
+                    size += list.capacity() * mem::size_of::<Self>();
+                    stack.extend(list);
+                },
+            }
+        }
+        size
+    }
+
+    /// Move the lists and dicts held by this value to `stack`, dropping everything else
+    fn take_nested(&mut self, stack: &mut Vec<Value<'a>>) {
+        let is_nested = |value: &Value| match value {
+            Value::Dict(dict) => !dict.is_empty(),
+            Value::List(list) => !list.is_empty(),
+            _ => false,
+        };
+
         match self {
-            Value::Bytes(bytes) => Value::Bytes(Cow::Owned(bytes.into_owned())),
-            Value::Dict(dict) => Value::Dict(
-                dict.into_iter()
-                    .map(|(key, value)| (Cow::Owned(key.into_owned()), value.into_owned()))
-                    .collect(),
+            Value::Dict(dict) => stack.extend(
+                mem::take(dict)
+                    .into_iter()
+                    .map(|(_, value)| value)
+                    .filter(is_nested),
             ),
-            Value::Integer(integer) => Value::Integer(integer),
-            Value::List(list) => Value::List(list.into_iter().map(Value::into_owned).collect()),
+            Value::List(list) => stack.extend(list.drain(..).filter(is_nested)),
+            _ => {},
         }
     }
 }
 
+/// Drops nested values one at a time instead of recursing, so that deeply nested values
+/// don't overflow the stack. Values without nested lists or dicts don't allocate.
+impl Drop for Value<'_> {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        self.take_nested(&mut stack);
+        while let Some(mut value) = stack.pop() {
+            value.take_nested(&mut stack);
+        }
+    }
+}
+
+/// A list or dict being converted by [`Value::into_owned`]
+enum OwnedFrame<'a> {
+    /// The converted elements and the remaining ones
+    List(Vec<Value<'static>>, IntoIter<Value<'a>>),
+    /// The converted pairs, the remaining ones and the key of the value being converted
+    Dict(
+        BTreeMap<Cow<'static, [u8]>, Value<'static>>,
+        btree_map::IntoIter<Cow<'a, [u8]>, Value<'a>>,
+        Option<Cow<'static, [u8]>>,
+    ),
+}
+
+impl<'a> OwnedFrame<'a> {
+    /// The next element to convert
+    fn next_element(&mut self) -> Option<Value<'a>> {
+        match self {
+            OwnedFrame::List(_, rest) => rest.next(),
+            OwnedFrame::Dict(_, rest, key) => {
+                let (next_key, value) = rest.next()?;
+                *key = Some(Cow::Owned(next_key.into_owned()));
+                Some(value)
+            },
+        }
+    }
+
+    /// Add the converted value of the last element returned by `next_element`
+    fn push(&mut self, value: Value<'static>) {
+        match self {
+            OwnedFrame::List(done, _) => done.push(value),
+            OwnedFrame::Dict(done, _, key) => {
+                if let Some(key) = key.take() {
+                    done.insert(key, value);
+                }
+            },
+        }
+    }
+
+    fn finish(self) -> Value<'static> {
+        match self {
+            OwnedFrame::List(done, _) => Value::List(done),
+            OwnedFrame::Dict(done, _, _) => Value::Dict(done),
+        }
+    }
+}
+
+/// Decode only the subtree at `path`, a sequence of dictionary keys, into an owned `Value`.
+///
+/// Everything next to the path is skipped token by token, without building values for
+/// it, so storing e.g. just the `info` dict of a torrent doesn't pay for the rest of the
+/// document. Decoding stops at the end of the subtree; the input following it is not
+/// checked.
+///
+/// ```
+/// use bendy::value::{extract, Value};
+///
+/// let torrent = b"d8:announce3:url4:infod6:lengthi42e4:name3:fooee";
+///
+/// let info = extract(torrent, &["info"]).unwrap();
+/// assert_eq!(info, Value::decode(b"d6:lengthi42e4:name3:fooe").unwrap());
+/// assert_eq!(
+///     extract(torrent, &["info", "length"]).unwrap(),
+///     Value::Integer(42)
+/// );
+///
+/// let error = extract(torrent, &["info", "pieces"]).unwrap_err();
+/// assert_eq!(error.code(), "decode.missing_field");
+/// ```
+pub fn extract<K: AsRef<[u8]>>(
+    input: &[u8],
+    path: &[K],
+) -> Result<Value<'static>, decoding::Error> {
+    let mut decoder = Decoder::new(input);
+    let object = decoder
+        .next_object()?
+        .ok_or_else(|| decoding::Error::from(StructureError::UnexpectedEof))?;
+
+    extract_object(object, path)
+}
+
+fn extract_object<K: AsRef<[u8]>>(
+    object: Object,
+    path: &[K],
+) -> Result<Value<'static>, decoding::Error> {
+    let (key, rest) = match path.split_first() {
+        Some((key, rest)) => (key.as_ref(), rest),
+        None => return Value::decode_bencode_object(object),
+    };
+
+    let mut dict = object.try_into_dictionary()?;
+    while let Some((current, value)) = dict.next_pair()? {
+        if current == key {
+            return extract_object(value, rest).context(String::from_utf8_lossy(key));
+        }
+    }
+
+    Err(decoding::Error::missing_field(String::from_utf8_lossy(key)))
+}
+
+// Needs the `Cow` itself to tell owned from borrowed data
+#[allow(clippy::ptr_arg)]
+fn owned_size(bytes: &Cow<[u8]>) -> usize {
+    match bytes {
+        Cow::Borrowed(_) => 0,
+        Cow::Owned(bytes) => bytes.capacity(),
+    }
+}
+
 impl<'a> ToBencode for Value<'a> {
     // This leaves some room for external containers.
     // TODO(#38): Change this to 0 for v0.4
-    const MAX_DEPTH: usize = usize::max_value() / 4;
+    const MAX_DEPTH: usize = usize::MAX / 4;
+
+    /// Lists and dicts are written with a heap allocated stack of their iterators instead
+    /// of by recursion, so values of any depth the encoder accepts can be encoded.
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        encoder.emit_tokens(|encoder| {
+            let mut open = Vec::new();
+            let mut next = Some(self);
+            loop {
+                match next {
+                    Some(Value::Bytes(bytes)) => encoder.emit_bytes(bytes)?,
+                    Some(Value::Integer(integer)) => encoder.emit_int(*integer)?,
+                    Some(Value::List(list)) => {
+                        encoder.emit_token(Token::List)?;
+                        open.push(Open::List(list.iter()));
+                    },
+                    Some(Value::Dict(dict)) => {
+                        encoder.emit_token(Token::Dict)?;
+                        open.push(Open::Dict(dict.iter()));
+                    },
+                    None => {},
+                }
 
-    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), crate::encoding::Error> {
-        match self {
-            Value::Bytes(bytes) => encoder.emit_bytes(bytes),
-            Value::Dict(dict) => dict.encode(encoder),
-            Value::Integer(integer) => integer.encode(encoder),
-            Value::List(list) => list.encode(encoder),
-        }
+                next = match open.last_mut() {
+                    Some(Open::List(items)) => items.next(),
+                    Some(Open::Dict(pairs)) => match pairs.next() {
+                        Some((key, value)) => {
+                            encoder.emit_bytes(key)?;
+                            Some(value)
+                        },
+                        None => None,
+                    },
+                    None => return Ok(()),
+                };
+
+                if next.is_none() {
+                    open.pop();
+                    encoder.emit_token(Token::End)?;
+                }
+            }
+        })
     }
 }
 
+/// A list or dict of a `Value` being encoded, with the items that are still to be written
+enum Open<'v, 'a> {
+    List(slice::Iter<'v, Value<'a>>),
+    Dict(btree_map::Iter<'v, Cow<'a, [u8]>, Value<'a>>),
+}
+
 impl<'a> FromBencode for Value<'a> {
     const EXPECTED_RECURSION_DEPTH: usize = <Self as ToBencode>::MAX_DEPTH;
 
-    fn decode_bencode_object(object: Object) -> Result<Self, crate::decoding::Error> {
+    /// Lists and dicts are built from their tokens with a heap allocated stack instead of
+    /// by recursion, so values of any depth the decoder accepts can be decoded.
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error> {
+        let mut builder = Builder::default();
         match object {
-            Object::Bytes(bytes) => Ok(Value::Bytes(Cow::Owned(bytes.to_owned()))),
+            Object::Bytes(bytes) => return Ok(Value::Bytes(Cow::Owned(bytes.to_owned()))),
+            Object::Integer(text) => return Ok(Value::Integer(parse_integer(text)?)),
             Object::Dict(mut decoder) => {
-                let mut dict = BTreeMap::new();
-                while let Some((key, value)) = decoder.next_pair()? {
-                    dict.insert(
-                        Cow::Owned(key.to_owned()),
-                        Value::decode_bencode_object(value)?,
-                    );
-                }
-                Ok(Value::Dict(dict))
+                builder.push(Token::Dict)?;
+                decoder.consume_with(|token| builder.push(token))?;
             },
-            Object::Integer(text) => Ok(Value::Integer(text.parse()?)),
             Object::List(mut decoder) => {
-                let mut list = Vec::new();
-                while let Some(object) = decoder.next_object()? {
-                    list.push(Value::decode_bencode_object(object)?);
-                }
-                Ok(Value::List(list))
+                builder.push(Token::List)?;
+                decoder.consume_with(|token| builder.push(token))?;
+            },
+        }
+
+        // The decoder only ends successfully with the container closed
+        Ok(builder.value.unwrap())
+    }
+}
+
+/// Builds a `Value` from a valid sequence of tokens
+#[derive(Default)]
+struct Builder<'a> {
+    /// The open lists and dicts, with the pending key of each dict
+    open: Vec<(Value<'a>, Option<Cow<'a, [u8]>>)>,
+    value: Option<Value<'a>>,
+}
+
+impl<'a> Builder<'a> {
+    fn push(&mut self, token: Token) -> Result<(), decoding::Error> {
+        let value = match token {
+            Token::List => {
+                self.open.push((Value::List(Vec::new()), None));
+                return Ok(());
+            },
+            Token::Dict => {
+                self.open.push((Value::Dict(BTreeMap::new()), None));
+                return Ok(());
             },
+            Token::String(bytes) => match self.open.last_mut() {
+                Some((Value::Dict(_), key @ None)) => {
+                    *key = Some(Cow::Owned(bytes.to_owned()));
+                    return Ok(());
+                },
+                _ => Value::Bytes(Cow::Owned(bytes.to_owned())),
+            },
+            Token::Num(text) => Value::Integer(parse_integer(text)?),
+            // The token stream is valid, so there's an open container to end
+            Token::End => self.open.pop().unwrap().0,
+        };
+
+        match self.open.last_mut() {
+            Some((Value::List(list), _)) => list.push(value),
+            Some((Value::Dict(dict), key)) => {
+                dict.insert(key.take().unwrap(), value);
+            },
+            _ => self.value = Some(value),
         }
+        Ok(())
     }
 }
 
@@ -217,6 +547,7 @@ mod serde_impls {
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow)]
 mod tests {
     use super::*;
 
@@ -297,6 +628,10 @@ mod tests {
         dict.insert(Cow::Borrowed("foo".as_bytes()), Value::Integer(1));
         dict.insert(Cow::Borrowed("bar".as_bytes()), Value::Integer(2));
         case(Value::Dict(dict), "d3:bari2e3:fooi1ee");
+
+        let mut dict = BTreeMap::new();
+        dict.insert(Cow::Borrowed(&b""[..]), Value::Integer(1));
+        case(Value::Dict(dict), "d0:i1ee");
     }
 
     #[test]
@@ -316,4 +651,107 @@ mod tests {
             b"li0e3:\x01\x02\x03e",
         );
     }
+
+    #[test]
+    fn deep_size_should_count_owned_data() {
+        let integer = Value::Integer(1);
+        assert_eq!(integer.deep_size(), mem::size_of::<Value>());
+
+        let list = Value::List(vec![Value::Bytes(Cow::Owned(vec![0; 100])), integer]);
+        assert!(list.deep_size() >= 3 * mem::size_of::<Value>() + 100);
+
+        let mut dict = BTreeMap::new();
+        dict.insert(Cow::Borrowed(&b"key"[..]), list.clone());
+        let dict = Value::Dict(dict);
+        assert!(dict.deep_size() > list.deep_size());
+    }
+
+    #[test]
+    fn decoded_values_should_be_owned() {
+        let input = b"d3:keyl3:fooi-1eee".to_vec();
+        let value = Value::decode(&input).unwrap();
+        drop(input);
+        assert!(value.heap_size() > 0);
+
+        let mut encoder = Encoder::new();
+        value.encode_into(&mut encoder).unwrap();
+        encoder.emit_int(1).unwrap();
+        assert_eq!(encoder.get_output().unwrap(), b"d3:keyl3:fooi-1eeei1e");
+    }
+
+    #[test]
+    fn subtrees_should_be_extracted_by_path() {
+        let input = b"d1:ad1:bli1eee1:c3:fooe";
+        assert_eq!(
+            extract(input, &[b"a", b"b"]).unwrap(),
+            Value::List(vec![Value::Integer(1)])
+        );
+        assert_eq!(
+            extract(input, &[] as &[&[u8]]).unwrap(),
+            Value::decode(input).unwrap()
+        );
+
+        // Decoding stops at the subtree, so later garbage goes unnoticed
+        assert!(extract(b"d1:ai1e1:bxxx", &["a"]).is_ok());
+
+        let error = extract(input, &["a", "c"]).unwrap_err();
+        assert_eq!(error.code(), "decode.missing_field");
+        assert_eq!(error.path(), Some("a"));
+        let error = extract(input, &["c", "d"]).unwrap_err();
+        assert_eq!(error.path(), Some("c"));
+        assert!(extract(b"", &["a"]).is_err());
+    }
+
+    #[test]
+    fn deeply_nested_values_should_decode() {
+        let depth = 100_000;
+        let mut input = vec![b'l'; depth];
+        input.extend_from_slice(b"d1:ai1ee");
+        input.resize(input.len() + depth, b'e');
+
+        let mut decoder = decoding::Decoder::new(&input).with_max_depth(depth + 1);
+        let object = decoder.next_object().unwrap().unwrap();
+        let value = Value::decode_bencode_object(object).unwrap();
+        assert!(value.deep_size() > depth * mem::size_of::<Value>());
+
+        // Converting, measuring and dropping the value don't recurse either
+        let value = value.into_owned();
+        assert!(value.deep_size() > depth * mem::size_of::<Value>());
+
+        let mut inner = &value;
+        for _ in 0..depth {
+            inner = match inner {
+                Value::List(list) if list.len() == 1 => &list[0],
+                other => panic!("unexpected value {:?}", other),
+            };
+        }
+        assert_eq!(inner, &Value::decode(b"d1:ai1ee").unwrap());
+        drop(value);
+
+        let error = Value::decode(b"ld1:ai01eee").unwrap_err();
+        assert_eq!(error.code(), "structure.syntax_error");
+    }
+
+    #[test]
+    fn deeply_nested_values_should_encode() {
+        let depth = 100_000;
+        let mut value = Value::decode(b"d1:ai1ee").unwrap();
+        for _ in 0..depth {
+            value = Value::List(vec![value]);
+        }
+
+        let mut expected = vec![b'l'; depth];
+        expected.extend_from_slice(b"d1:ai1ee");
+        expected.resize(expected.len() + depth, b'e');
+        assert_eq!(value.to_bencode().unwrap(), expected);
+
+        let mut encoder = Encoder::new().with_max_depth(depth);
+        let error = encoder.emit(&value).unwrap_err();
+        assert!(matches!(
+            error,
+            encoding::Error::StructureError {
+                source: StructureError::NestingTooDeep
+            }
+        ));
+    }
 }
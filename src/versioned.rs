@@ -0,0 +1,306 @@
+//! Versioned documents for data persisted across schema changes.
+//!
+//! A versioned document is a dict with an integer `version` key next to the fields of the
+//! schema of that version. Decoding a [`Versioned<T>`] reads the version first and then
+//! decodes the dict with the type registered for that version, which is converted into
+//! `T`. Encoding writes the dict of `T` with its current version added.
+//!
+//! The versions of a type are registered with the [`versioned_schema!`] macro. Old
+//! versions are converted with [`From`] implementations, so every version only has to
+//! know how to upgrade to the current one:
+//!
+//! ```
+//! use bendy::{
+//!     decoding::{Error, FromBencode, Object, ResultExt},
+//!     encoding::{self, SingleItemEncoder, ToBencode},
+//!     versioned::Versioned,
+//!     versioned_schema,
+//! };
+//!
+//! /// Version 1 only had a single server
+//! struct SettingsV1 {
+//!     server: String,
+//! }
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct Settings {
+//!     servers: Vec<String>,
+//! }
+//!
+//! impl From<SettingsV1> for Settings {
+//!     fn from(old: SettingsV1) -> Self {
+//!         Settings { servers: vec![old.server] }
+//!     }
+//! }
+//!
+//! versioned_schema!(Settings {
+//!     1 => SettingsV1,
+//!     2 => Settings,
+//! });
+//!
+//! # impl FromBencode for SettingsV1 {
+//! #     fn decode_bencode_object(object: Object) -> Result<Self, Error> {
+//! #         let mut dict = object.try_into_dictionary()?;
+//! #         let mut server = None;
+//! #         while let Some((key, value)) = dict.next_pair()? {
+//! #             if key == b"server" {
+//! #                 server = Some(String::decode_bencode_object(value).context("server")?);
+//! #             }
+//! #         }
+//! #         Ok(SettingsV1 { server: server.ok_or_else(|| Error::missing_field("server"))? })
+//! #     }
+//! # }
+//! #
+//! # impl FromBencode for Settings {
+//! #     fn decode_bencode_object(object: Object) -> Result<Self, Error> {
+//! #         let mut dict = object.try_into_dictionary()?;
+//! #         let mut servers = None;
+//! #         while let Some((key, value)) = dict.next_pair()? {
+//! #             if key == b"servers" {
+//! #                 servers = Some(Vec::decode_bencode_object(value).context("servers")?);
+//! #             }
+//! #         }
+//! #         Ok(Settings { servers: servers.ok_or_else(|| Error::missing_field("servers"))? })
+//! #     }
+//! # }
+//! #
+//! # impl ToBencode for Settings {
+//! #     const MAX_DEPTH: usize = 2;
+//! #
+//! #     fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+//! #         encoder.emit_dict(|mut e| e.emit_pair(b"servers", &self.servers))
+//! #     }
+//! # }
+//! #
+//! let old = Versioned::<Settings>::from_bencode(b"d6:server3:foo7:versioni1ee").unwrap();
+//! assert_eq!(old.0.servers, ["foo"]);
+//!
+//! let current = old.to_bencode().unwrap();
+//! assert_eq!(current, b"d7:serversl3:fooe7:versioni2ee");
+//! assert_eq!(Versioned::<Settings>::from_bencode(&current).unwrap(), old);
+//! ```
+//!
+//! The types of the individual versions are decoded from the whole dict, including the
+//! `version` key, so they should ignore unknown keys.
+//!
+//! [`versioned_schema!`]: crate::versioned_schema
+
+pub mod migration;
+
+use core::fmt::{self, Display, Formatter};
+
+use crate::{
+    decoding::{self, Cursor, Decoder, FromBencode, Object, ResultExt},
+    encoding::{self, SingleItemEncoder, ToBencode},
+    state_tracker::StructureError,
+    value::Value,
+};
+
+/// A type with several versions of its encoding, usually implemented with
+/// [`versioned_schema!`](crate::versioned_schema).
+pub trait Schema: Sized {
+    /// The current version, which is written when encoding
+    const VERSION: u64;
+
+    /// Decode `object`, a dict in the format of the given version.
+    fn decode_version(version: u64, object: Object) -> Result<Self, decoding::Error>;
+}
+
+/// Register the versions of a [`Schema`].
+///
+/// Every version maps to a type implementing [`FromBencode`](crate::decoding::FromBencode)
+/// which the schema type can be converted from, usually the schema type itself for the
+/// last version. The last version listed is the current one.
+///
+/// ```
+/// # use bendy::{decoding::{Error, FromBencode, Object}, versioned::Schema, versioned_schema};
+/// #
+/// # #[derive(Debug, PartialEq)]
+/// # struct Config;
+/// # struct ConfigV1;
+/// # impl From<ConfigV1> for Config { fn from(_: ConfigV1) -> Self { Config } }
+/// # impl FromBencode for Config {
+/// #     fn decode_bencode_object(_: Object) -> Result<Self, Error> { Ok(Config) }
+/// # }
+/// # impl FromBencode for ConfigV1 {
+/// #     fn decode_bencode_object(_: Object) -> Result<Self, Error> { Ok(ConfigV1) }
+/// # }
+/// versioned_schema!(Config {
+///     1 => ConfigV1,
+///     3 => Config,
+/// });
+///
+/// assert_eq!(Config::VERSION, 3);
+/// ```
+#[macro_export]
+macro_rules! versioned_schema {
+    ($schema:ty { $($version:literal => $type:ty),+ $(,)? }) => {
+        impl $crate::versioned::Schema for $schema {
+            const VERSION: u64 = [$($version),+][[$($version),+].len() - 1];
+
+            fn decode_version(
+                version: u64,
+                object: $crate::decoding::Object,
+            ) -> ::core::result::Result<Self, $crate::decoding::Error> {
+                match version {
+                    $(
+                        $version => {
+                            <$type as $crate::decoding::FromBencode>::decode_bencode_object(object)
+                                .map(<Self as ::core::convert::From<$type>>::from)
+                        },
+                    )+
+                    _ => ::core::result::Result::Err($crate::versioned::unknown_version(version)),
+                }
+            }
+        }
+    };
+}
+
+/// A document of a [`Schema`] type tagged with its version, see the
+/// [module documentation](self).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Versioned<T>(pub T);
+
+impl<T: Schema + FromBencode> FromBencode for Versioned<T> {
+    const EXPECTED_RECURSION_DEPTH: usize = T::EXPECTED_RECURSION_DEPTH;
+
+    fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error>
+    where
+        Self: Sized,
+    {
+        let raw = match object {
+            Object::Dict(dict) => dict.into_raw()?,
+            other => {
+                return Err(decoding::Error::unexpected_token(
+                    "Dict",
+                    other.into_token().name(),
+                ))
+            },
+        };
+
+        let version = Cursor::new(raw)?
+            .get(b"version")
+            .ok_or_else(|| decoding::Error::missing_field("version"))?;
+        let version = u64::from_bencode(version.raw()).context("version")?;
+
+        let mut decoder = Decoder::new(raw).with_max_depth(T::EXPECTED_RECURSION_DEPTH);
+        let object = decoder
+            .next_object()?
+            .ok_or(StructureError::UnexpectedEof)?;
+        T::decode_version(version, object).map(Versioned)
+    }
+}
+
+impl<T: Schema + ToBencode> ToBencode for Versioned<T> {
+    const MAX_DEPTH: usize = T::MAX_DEPTH;
+
+    fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+        let encoded = self.0.to_bencode()?;
+        let mut value =
+            Value::decode(&encoded).map_err(|_| encoding::Error::malformed_content(NotADict))?;
+        match &mut value {
+            Value::Dict(dict) => {
+                dict.insert((&b"version"[..]).into(), Value::Integer(T::VERSION as i64));
+            },
+            _ => return Err(encoding::Error::malformed_content(NotADict)),
+        }
+        value.encode(encoder)
+    }
+}
+
+/// The error of a document with a version that isn't registered with its schema.
+#[doc(hidden)]
+pub fn unknown_version(version: u64) -> decoding::Error {
+    decoding::Error::malformed_content(UnknownVersion(version)).context("version")
+}
+
+#[derive(Debug)]
+struct UnknownVersion(u64);
+
+impl Display for UnknownVersion {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "unknown schema version {}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownVersion {}
+
+#[derive(Debug)]
+struct NotADict;
+
+impl Display for NotADict {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "only values encoded as a dict can be versioned")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NotADict {}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::{collections::BTreeMap, string::String};
+    #[cfg(feature = "std")]
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    /// Counters by name, which only had names in version 1
+    #[derive(Debug, PartialEq)]
+    struct Counts(BTreeMap<String, u64>);
+
+    impl From<Value<'static>> for Counts {
+        fn from(value: Value<'static>) -> Self {
+            match &value {
+                Value::Dict(dict) => Counts(
+                    dict.keys()
+                        .filter(|key| key.as_ref() != b"version")
+                        .map(|key| (String::from_utf8_lossy(key).into_owned(), 0))
+                        .collect(),
+                ),
+                _ => unreachable!("versioned documents are dicts"),
+            }
+        }
+    }
+
+    impl FromBencode for Counts {
+        fn decode_bencode_object(object: Object) -> Result<Self, decoding::Error> {
+            let mut counts = BTreeMap::<String, u64>::decode_bencode_object(object)?;
+            counts.remove("version");
+            Ok(Counts(counts))
+        }
+    }
+
+    impl ToBencode for Counts {
+        const MAX_DEPTH: usize = 1;
+
+        fn encode(&self, encoder: SingleItemEncoder) -> Result<(), encoding::Error> {
+            self.0.encode(encoder)
+        }
+    }
+
+    versioned_schema!(Counts {
+        1 => Value<'static>,
+        2 => Counts,
+    });
+
+    #[test]
+    fn versions_should_be_dispatched() {
+        let old = Versioned::<Counts>::from_bencode(b"d1:a1:x7:versioni1ee").unwrap();
+        assert_eq!(old.0 .0.get("a"), Some(&0));
+
+        let current = Versioned::<Counts>::from_bencode(b"d1:ai3e7:versioni2ee").unwrap();
+        assert_eq!(current.0 .0.get("a"), Some(&3));
+        assert_eq!(current.to_bencode().unwrap(), b"d1:ai3e7:versioni2ee");
+    }
+
+    #[test]
+    fn unknown_or_missing_versions_should_fail() {
+        let error = Versioned::<Counts>::from_bencode(b"d7:versioni9ee").unwrap_err();
+        assert_eq!(error.path(), Some("version"));
+        assert!(Versioned::<Counts>::from_bencode(b"d1:ai1ee").is_err());
+        assert!(Versioned::<Counts>::from_bencode(b"li2ee").is_err());
+    }
+}
@@ -0,0 +1,422 @@
+//! Migrations of persisted documents to the current version of their schema.
+//!
+//! Where [`Versioned`](super::Versioned) decodes old versions into the current type, a
+//! [`Migrations`] runner rewrites the stored documents themselves, e.g. the resume and
+//! session files of a daemon. Every migration is a function upgrading a [`Value`] by one
+//! version. The runner applies them in order, starting at the `version` key of the
+//! document, and updates the key after every step:
+//!
+//! ```
+//! use bendy::{value::Value, versioned::migration::Migrations};
+//!
+//! let migrations = Migrations::new(2)
+//!     // Version 0 documents predate the `version` key and named the port `p`
+//!     .with_migration(0, |mut value| {
+//!         if let Value::Dict(dict) = &mut value {
+//!             if let Some(port) = dict.remove(&b"p"[..]) {
+//!                 dict.insert(b"port".to_vec().into(), port);
+//!             }
+//!         }
+//!         Ok(value)
+//!     })
+//!     .with_migration(1, Ok);
+//!
+//! let (migrated, report) = migrations.migrate_bytes(b"d1:pi6881ee").unwrap();
+//! assert_eq!(migrated, b"d4:porti6881e7:versioni2ee");
+//! assert_eq!((report.from, report.to), (0, 2));
+//! ```
+//!
+//! With the `std` feature, documents can be migrated in place with
+//! [`Migrations::migrate_file`], which can keep a backup of the original file and supports
+//! a dry run.
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    borrow::Cow,
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Display;
+#[cfg(feature = "std")]
+use std::{
+    borrow::Cow,
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use snafu::Snafu;
+
+use crate::{decoding, encoding, encoding::ToBencode, value::Value};
+
+/// A migration upgrading a document by one version
+type Migration = Box<dyn Fn(Value<'static>) -> Result<Value<'static>, Error> + Send + Sync>;
+
+/// An enumeration of potential errors that appear while migrating a document.
+#[derive(Debug, Clone, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    /// Error that occurs if the document isn't valid bencode.
+    #[snafu(display("invalid document: {}", source))]
+    Decode { source: decoding::Error },
+
+    /// Error that occurs if a migrated document can't be encoded.
+    #[snafu(display("failed to encode the migrated document: {}", source))]
+    Encode { source: encoding::Error },
+
+    /// Error that occurs if the document or the result of a migration isn't a dict.
+    #[snafu(display("versioned documents have to be dicts"))]
+    NotADict,
+
+    /// Error that occurs if the `version` key isn't a non-negative integer.
+    #[snafu(display("invalid version key"))]
+    InvalidVersion,
+
+    /// Error that occurs if the document is newer than the current version.
+    #[snafu(display("document version {} is newer than {}", version, current))]
+    TooNew { version: u64, current: u64 },
+
+    /// Error that occurs if no migration from a version of the document is registered.
+    #[snafu(display("no migration from version {}", version))]
+    MissingMigration { version: u64 },
+
+    /// Error that occurs if a migration function fails.
+    #[snafu(display("migration from version {} failed: {}", version, message))]
+    Failed { version: u64, message: String },
+
+    /// Error that occurs if reading or writing a document file fails.
+    #[cfg(feature = "std")]
+    #[snafu(display("failed to access the document file: {}", source))]
+    Io { source: Arc<io::Error> },
+}
+
+impl Error {
+    /// A stable identifier of the kind of error, like `migration.too_new`, see
+    /// [`decoding::Error::code`]. Errors of the bencode decoder and encoder keep their
+    /// own code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Decode { source } => source.code(),
+            Error::Encode { source } => source.code(),
+            Error::NotADict => "migration.not_a_dict",
+            Error::InvalidVersion => "migration.invalid_version",
+            Error::TooNew { .. } => "migration.too_new",
+            Error::MissingMigration { .. } => "migration.missing_migration",
+            Error::Failed { .. } => "migration.failed",
+            #[cfg(feature = "std")]
+            Error::Io { .. } => "migration.io",
+        }
+    }
+
+    /// The error of a migration function, which the runner tags with the version it
+    /// migrates from.
+    pub fn failed(message: impl Display) -> Self {
+        Error::Failed {
+            version: 0,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<decoding::Error> for Error {
+    fn from(error: decoding::Error) -> Self {
+        Error::Decode { source: error }
+    }
+}
+
+impl From<encoding::Error> for Error {
+    fn from(error: encoding::Error) -> Self {
+        Error::Encode { source: error }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io {
+            source: Arc::new(error),
+        }
+    }
+}
+
+/// The outcome of a migration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    /// The version of the original document
+    pub from: u64,
+    /// The version of the migrated document
+    pub to: u64,
+    /// Where the original file was backed up to, if it was
+    #[cfg(feature = "std")]
+    pub backup: Option<PathBuf>,
+}
+
+impl Report {
+    /// Whether any migration was applied
+    pub fn changed(&self) -> bool {
+        self.from != self.to
+    }
+}
+
+/// An ordered set of migrations to the current version, see the
+/// [module documentation](self).
+pub struct Migrations {
+    current: u64,
+    migrations: Vec<(u64, Migration)>,
+    dry_run: bool,
+    backup: bool,
+}
+
+impl Migrations {
+    /// Create a runner migrating documents to the `current` version. Documents without a
+    /// `version` key are treated as version 0.
+    pub fn new(current: u64) -> Self {
+        Migrations {
+            current,
+            migrations: Vec::new(),
+            dry_run: false,
+            backup: false,
+        }
+    }
+
+    /// Register the migration from version `from` to the next version
+    pub fn with_migration<F>(mut self, from: u64, migration: F) -> Self
+    where
+        F: Fn(Value<'static>) -> Result<Value<'static>, Error> + Send + Sync + 'static,
+    {
+        let index = self
+            .migrations
+            .iter()
+            .position(|(version, _)| *version >= from)
+            .unwrap_or(self.migrations.len());
+        if self.migrations.get(index).map(|(version, _)| *version) == Some(from) {
+            self.migrations[index].1 = Box::new(migration);
+        } else {
+            self.migrations.insert(index, (from, Box::new(migration)));
+        }
+        self
+    }
+
+    /// Only report what [`migrate_file`](Self::migrate_file) would do, without writing
+    /// anything
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Keep a copy of files changed by [`migrate_file`](Self::migrate_file), named like
+    /// the file with `.v{version}.bak` appended
+    pub fn with_backup(mut self, backup: bool) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    /// Migrate a document to the current version
+    pub fn migrate(&self, mut document: Value<'static>) -> Result<(Value<'static>, Report), Error> {
+        let from = version(&document)?;
+        if from > self.current {
+            return Err(Error::TooNew {
+                version: from,
+                current: self.current,
+            });
+        }
+
+        for version in from..self.current {
+            let migration = self
+                .migrations
+                .iter()
+                .find(|(from, _)| *from == version)
+                .map(|(_, migration)| migration)
+                .ok_or(Error::MissingMigration { version })?;
+
+            document = migration(document).map_err(|error| match error {
+                Error::Failed { message, .. } => Error::Failed { version, message },
+                other => other,
+            })?;
+            match &mut document {
+                Value::Dict(dict) => {
+                    dict.insert(
+                        Cow::Borrowed(b"version"),
+                        Value::Integer(version as i64 + 1),
+                    );
+                },
+                _ => return Err(Error::NotADict),
+            }
+        }
+
+        let report = Report {
+            from,
+            to: self.current,
+            #[cfg(feature = "std")]
+            backup: None,
+        };
+        Ok((document, report))
+    }
+
+    /// Migrate a bencoded document to the current version
+    pub fn migrate_bytes(&self, bencode: &[u8]) -> Result<(Vec<u8>, Report), Error> {
+        let (document, report) = self.migrate(Value::decode(bencode)?)?;
+        if !report.changed() {
+            return Ok((bencode.to_vec(), report));
+        }
+        Ok((document.to_bencode()?, report))
+    }
+
+    /// Migrate a document file in place.
+    ///
+    /// The migrated document is written to a temporary file next to the original first,
+    /// which is synced to disk and then replaces the original, so a crash never leaves a
+    /// partially written document behind. On Unix, the directory is synced as well, so
+    /// the replacement itself survives a crash. Files that are already current aren't
+    /// touched.
+    #[cfg(feature = "std")]
+    pub fn migrate_file(&self, path: impl AsRef<Path>) -> Result<Report, Error> {
+        let path = path.as_ref();
+        let original = fs::read(path)?;
+        let (migrated, mut report) = self.migrate_bytes(&original)?;
+        if self.dry_run || !report.changed() {
+            return Ok(report);
+        }
+
+        if self.backup {
+            let backup = with_suffix(path, &format!(".v{}.bak", report.from));
+            write_synced(&backup, &original)?;
+            report.backup = Some(backup);
+        }
+        let temporary = with_suffix(path, ".tmp");
+        write_synced(&temporary, &migrated)?;
+        fs::rename(&temporary, path)?;
+        sync_parent(path)?;
+
+        Ok(report)
+    }
+}
+
+/// The `version` key of a document
+fn version(document: &Value) -> Result<u64, Error> {
+    match document {
+        Value::Dict(dict) => match dict.get(&b"version"[..]) {
+            None => Ok(0),
+            Some(Value::Integer(version)) if *version >= 0 => Ok(*version as u64),
+            Some(_) => Err(Error::InvalidVersion),
+        },
+        _ => Err(Error::NotADict),
+    }
+}
+
+/// `path` with `suffix` appended to its file name
+#[cfg(feature = "std")]
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Write a file and wait until its content reached the disk
+#[cfg(feature = "std")]
+fn write_synced(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+/// Wait until the directory entry of `path` reached the disk. Only Unix allows syncing a
+/// directory by opening it as a file, elsewhere this does nothing.
+#[cfg(feature = "std")]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        File::open(parent)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn migrations() -> Migrations {
+        Migrations::new(2)
+            .with_migration(1, |mut value| match &mut value {
+                Value::Dict(dict) => {
+                    dict.insert(Cow::Borrowed(b"b"), Value::Integer(2));
+                    Ok(value)
+                },
+                _ => Err(Error::failed("not a dict")),
+            })
+            .with_migration(0, |mut value| match &mut value {
+                Value::Dict(dict) if dict.contains_key(&b"a"[..]) => {
+                    dict.insert(Cow::Borrowed(b"a"), Value::Integer(1));
+                    Ok(value)
+                },
+                _ => Err(Error::failed("missing a")),
+            })
+    }
+
+    #[test]
+    fn migrations_should_run_in_order() {
+        let (migrated, report) = migrations().migrate_bytes(b"d1:ai0ee").unwrap();
+        assert_eq!(migrated, b"d1:ai1e1:bi2e7:versioni2ee");
+        assert_eq!((report.from, report.to), (0, 2));
+
+        let (migrated, report) = migrations().migrate_bytes(b"d1:ci0e7:versioni1ee").unwrap();
+        assert_eq!(migrated, b"d1:bi2e1:ci0e7:versioni2ee");
+        assert_eq!(report.from, 1);
+
+        let current = b"d1:ai9e7:versioni2ee";
+        let (migrated, report) = migrations().migrate_bytes(current).unwrap();
+        assert_eq!(migrated, current);
+        assert!(!report.changed());
+    }
+
+    #[test]
+    fn failed_migrations_should_report_their_version() {
+        assert!(matches!(
+            migrations().migrate_bytes(b"de"),
+            Err(Error::Failed { version: 0, .. })
+        ));
+        assert!(matches!(
+            migrations().migrate_bytes(b"d7:versioni3ee"),
+            Err(Error::TooNew { version: 3, .. })
+        ));
+        assert!(matches!(
+            Migrations::new(1).migrate_bytes(b"de"),
+            Err(Error::MissingMigration { version: 0 })
+        ));
+        assert!(matches!(
+            migrations().migrate_bytes(b"le"),
+            Err(Error::NotADict)
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn files_should_be_migrated_in_place() {
+        let directory =
+            std::env::temp_dir().join(format!("bendy-migration-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("state.bencode");
+        fs::write(&path, b"d1:ai0e7:versioni1ee").unwrap();
+
+        let report = migrations().with_dry_run(true).migrate_file(&path).unwrap();
+        assert!(report.changed());
+        assert_eq!(fs::read(&path).unwrap(), b"d1:ai0e7:versioni1ee");
+
+        let report = migrations().with_backup(true).migrate_file(&path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"d1:ai0e1:bi2e7:versioni2ee");
+        let backup = report.backup.unwrap();
+        assert_eq!(backup, directory.join("state.bencode.v1.bak"));
+        assert_eq!(fs::read(&backup).unwrap(), b"d1:ai0e7:versioni1ee");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
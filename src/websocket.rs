@@ -0,0 +1,158 @@
+//! One bencode document per WebSocket message, as used by browser based DHT and tracker
+//! experiments.
+//!
+//! The helpers don't depend on a WebSocket implementation: incoming messages are mapped
+//! to a [`Frame`] by the caller, e.g. from a `tungstenite::Message`, and outgoing
+//! documents are sent as binary messages.
+//!
+//! ```
+//! use bendy::websocket::{Frame, Framing, Received};
+//!
+//! let framing = Framing::new().with_max_message_len(1024);
+//!
+//! let message = framing.encode(&vec![1, 2, 3]).unwrap();
+//! match framing.receive::<Vec<u8>>(Frame::Binary(&message)).unwrap() {
+//!     Received::Document(list) => assert_eq!(list, [1, 2, 3]),
+//!     _ => unreachable!(),
+//! }
+//!
+//! // Pings are handed back to be answered with a pong carrying the same payload
+//! let ping = framing.receive::<Vec<u8>>(Frame::Ping(b"42")).unwrap();
+//! assert_eq!(ping, Received::Ping(&b"42"[..]));
+//!
+//! // Every message has to hold exactly one document
+//! assert!(framing.receive::<Vec<u8>>(Frame::Binary(b"lei1e")).is_err());
+//! assert!(framing.receive::<Vec<u8>>(Frame::Text("le")).is_err());
+//! ```
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::{
+    decoding::{DecodeOptions, Decoder, Error, FromBencode},
+    encoding::{self, ToBencode},
+    state_tracker::StructureError,
+};
+
+/// A WebSocket message, borrowing its payload
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frame<'a> {
+    /// A binary message, which should hold a bencoded document
+    Binary(&'a [u8]),
+    /// A text message, which can't hold bencode as it isn't necessarily UTF-8
+    Text(&'a str),
+    /// A ping with its application data
+    Ping(&'a [u8]),
+    /// A pong with its application data
+    Pong(&'a [u8]),
+    /// A close message
+    Close,
+}
+
+/// A message received by [`Framing::receive`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Received<'a, T> {
+    /// The document of a binary message
+    Document(T),
+    /// A ping, which has to be answered with a pong carrying the same data
+    Ping(&'a [u8]),
+    /// A pong, e.g. to measure the round trip time of an earlier ping
+    Pong(&'a [u8]),
+    /// The peer closed the connection
+    Close,
+}
+
+/// Maps WebSocket messages to bencode documents, see the [module documentation](self).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Framing {
+    options: DecodeOptions,
+}
+
+impl Default for Framing {
+    fn default() -> Self {
+        Framing {
+            options: DecodeOptions::new().with_max_input_len(1 << 20),
+        }
+    }
+}
+
+impl Framing {
+    /// Create a framing that accepts messages of up to 1 MiB and otherwise decodes with
+    /// the defaults of a [`Decoder`]
+    pub fn new() -> Self {
+        <Self as Default>::default()
+    }
+
+    /// Decode documents with the limits of `options`. This replaces the max message
+    /// length with the max input length of `options`.
+    pub fn with_options(mut self, options: DecodeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set the maximum length of a binary message in bytes
+    pub fn with_max_message_len(mut self, max_len: usize) -> Self {
+        self.options = self.options.with_max_input_len(max_len);
+        self
+    }
+
+    /// Decode a received message. Binary messages are decoded into a `T`, failing unless
+    /// they hold exactly one document; text messages are rejected.
+    pub fn receive<'a, T: FromBencode>(&self, frame: Frame<'a>) -> Result<Received<'a, T>, Error> {
+        let message = match frame {
+            Frame::Binary(message) => message,
+            Frame::Text(_) => return Err(Error::unexpected_token("binary message", "text")),
+            Frame::Ping(data) => return Ok(Received::Ping(data)),
+            Frame::Pong(data) => return Ok(Received::Pong(data)),
+            Frame::Close => return Ok(Received::Close),
+        };
+
+        let mut decoder = Decoder::with_options(message, &self.options);
+        let document = match decoder.next_object()? {
+            Some(object) => T::decode_bencode_object(object)?,
+            None => return Err(Error::from(StructureError::UnexpectedEof)),
+        };
+        if decoder.next_object()?.is_some() {
+            return Err(Error::unexpected_token("end of message", "trailing data"));
+        }
+
+        Ok(Received::Document(document))
+    }
+
+    /// Encode a document as the payload of a binary message
+    pub fn encode<T: ToBencode>(&self, document: &T) -> Result<Vec<u8>, encoding::Error> {
+        document.to_bencode()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn messages_should_be_limited_in_length() {
+        let framing = Framing::new().with_max_message_len(8);
+        let received = framing.receive::<Vec<i64>>(Frame::Binary(b"li1ei2ee"));
+        assert_eq!(received.unwrap(), Received::Document(vec![1, 2]));
+
+        let error = framing
+            .receive::<Vec<i64>>(Frame::Binary(b"li1ei23ee"))
+            .unwrap_err();
+        assert_eq!(error.code(), "decode.limit_exceeded");
+    }
+
+    #[test]
+    fn messages_should_hold_one_document() {
+        let framing = Framing::new();
+        assert!(framing.receive::<i64>(Frame::Binary(b"")).is_err());
+        assert!(framing.receive::<i64>(Frame::Binary(b"i1ei2e")).is_err());
+        assert!(framing.receive::<i64>(Frame::Binary(b"i1ex")).is_err());
+        assert_eq!(
+            framing.receive::<i64>(Frame::Close).unwrap(),
+            Received::Close
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Allocations made by the state tracking of the decoder and the encoder.
+//!
+//! The counting allocator is shared by all tests of this binary, so it must only contain
+//! a single test.
+
+use std::alloc::System;
+
+#[cfg(feature = "std")]
+use bendy::krpc::{Message, NodeId, Ping, QueryArguments};
+use bendy::{
+    decoding::{Decoder, FromBencode},
+    encoding::Encoder,
+    testing::alloc_audit::CountingAllocator,
+};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator<System> = CountingAllocator::new(System);
+
+#[test]
+fn shallow_documents_should_not_allocate_state() {
+    let message = b"d1:ad2:id20:abcdefghij0123456789e1:q4:ping1:t2:aa1:y1:qe";
+    let (tokens, stats) = ALLOCATOR.measure(|| Decoder::new(message).tokens().count());
+    assert_eq!(tokens, 13);
+    assert_eq!(stats.allocations, 0);
+
+    let deep = [&[b'l'; 20][..], &[b'e'; 20]].concat();
+    let (tokens, stats) = ALLOCATOR.measure(|| Decoder::new(&deep).tokens().count());
+    assert_eq!(tokens, 40);
+    assert!(stats.allocations > 0);
+
+    // Only the output buffer is allocated, integers and lengths are formatted in place
+    let (output, stats) = ALLOCATOR.measure(|| {
+        let mut encoder = Encoder::with_capacity(64);
+        encoder.emit_list(|e| {
+            e.emit_list(|e| e.emit_list(|_| Ok(())))?;
+            e.emit_int(-42)?;
+            e.emit_bytes(b"spam")
+        })?;
+        encoder.get_output()
+    });
+    assert_eq!(output.unwrap(), b"llleei-42e4:spame");
+    assert_eq!(stats.allocations, 1);
+
+    // Neither are the keys of dicts
+    let mut encoder = Encoder::with_capacity(128);
+    let (result, stats) = ALLOCATOR.measure(|| {
+        encoder.emit_dict(|mut e| {
+            e.emit_pair(b"a", 1)?;
+            e.emit_pair(b"q", "ping")?;
+            e.emit_pair(b"t", "aa")?;
+            e.emit_pair(b"y", "q")
+        })
+    });
+    result.unwrap();
+    assert_eq!(stats.allocations, 0);
+    assert_eq!(
+        encoder.get_output().unwrap(),
+        b"d1:ai1e1:q4:ping1:t2:aa1:y1:qe"
+    );
+
+    #[cfg(feature = "std")]
+    {
+        let message = Message::Query(
+            QueryArguments::from(Ping::new(NodeId(*b"abcdefghij0123456789"))).into_query(*b"aa"),
+        );
+        let mut encoder = Encoder::with_capacity(128);
+        let (result, stats) = ALLOCATOR.measure(|| encoder.emit(&message));
+        result.unwrap();
+        assert_eq!(stats.allocations, 0);
+        assert_eq!(
+            encoder.get_output().unwrap(),
+            &b"d1:ad2:id20:abcdefghij0123456789e1:q4:ping1:t2:aa1:y1:qe"[..]
+        );
+    }
+
+    // Decoding a list only grows the vector, the context of errors isn't formatted for
+    // elements that decode
+    let list = [&b"l"[..], &b"i1e".repeat(1000), b"e"].concat();
+    let (decoded, stats) = ALLOCATOR.measure(|| Vec::<u64>::from_bencode(&list));
+    assert_eq!(decoded.unwrap().len(), 1000);
+    assert!(stats.allocations < 20);
+}
@@ -3,6 +3,12 @@
 //! Should only use #![no_std] compatible features but still requires the
 //! `std` feature flag to avoid that we need to define a global allocator.
 
+#![allow(
+    clippy::legacy_numeric_constants,
+    clippy::vec_init_then_push,
+    clippy::zero_prefixed_literal
+)]
+
 extern crate alloc;
 use alloc::collections::BTreeMap;
 
@@ -409,6 +415,8 @@ impl ToBencode for Something {
 // Error
 // -----------------------------------------------------------------------------
 
+// The payloads are only read through the `Debug` impl
+#[allow(dead_code)]
 #[derive(Debug)]
 enum Error {
     DecodingError(DecodingError),
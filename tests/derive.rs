@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+
+use bendy::{decoding::FromBencode, encoding::ToBencode};
+
+#[derive(ToBencode, FromBencode, PartialEq, Eq, Debug)]
+struct File {
+    path: Vec<String>,
+    length: u64,
+    #[bendy(rename = "md5sum")]
+    checksum: Option<String>,
+}
+
+#[derive(ToBencode, FromBencode, PartialEq, Eq, Debug)]
+struct Info {
+    name: String,
+    files: Vec<File>,
+    #[bendy(rename = "piece length")]
+    piece_length: u64,
+}
+
+#[derive(ToBencode, FromBencode, PartialEq, Eq, Debug)]
+struct Empty {}
+
+#[derive(ToBencode, FromBencode, PartialEq, Eq, Debug)]
+struct Wrapper<T> {
+    r#type: T,
+}
+
+#[derive(ToBencode, FromBencode, PartialEq, Eq, Debug)]
+struct Nested<T> {
+    inner: Option<T>,
+    list: Vec<Option<u8>>,
+}
+
+/// A file tree in the style of BitTorrent v2, which nests two dicts per directory
+#[derive(ToBencode, FromBencode, PartialEq, Eq, Debug)]
+struct Directory {
+    entries: BTreeMap<String, Directory>,
+    length: Option<u64>,
+}
+
+#[derive(ToBencode, FromBencode, PartialEq, Eq, Debug)]
+#[bendy(max_depth = 8)]
+struct Tree {
+    children: Vec<Self>,
+}
+
+fn info() -> Info {
+    Info {
+        name: "example".into(),
+        files: vec![
+            File {
+                path: vec!["a".into(), "b".into()],
+                length: 1,
+                checksum: None,
+            },
+            File {
+                path: vec!["c".into()],
+                length: 2,
+                checksum: Some("00".into()),
+            },
+        ],
+        piece_length: 16,
+    }
+}
+
+const INFO: &[u8] = b"d5:filesld6:lengthi1e4:pathl1:a1:beed6:lengthi2e6:md5sum2:004:pathl1:ceee\
+                      4:name7:example12:piece lengthi16ee";
+
+#[test]
+fn derived_structs_should_encode_with_sorted_keys() {
+    assert_eq!(info().to_bencode().unwrap(), INFO);
+    assert_eq!(Empty {}.to_bencode().unwrap(), b"de");
+    assert_eq!(Wrapper { r#type: 1 }.to_bencode().unwrap(), b"d4:typei1ee");
+}
+
+#[test]
+fn derived_structs_should_round_trip() {
+    assert_eq!(Info::from_bencode(INFO).unwrap(), info());
+    assert_eq!(Empty::from_bencode(b"d3:fooi1ee").unwrap(), Empty {});
+    assert_eq!(
+        Wrapper::<String>::from_bencode(b"d4:type1:xe").unwrap(),
+        Wrapper { r#type: "x".into() }
+    );
+}
+
+#[test]
+fn derived_structs_should_require_non_optional_fields() {
+    let error = File::from_bencode(b"d4:pathl1:aee").unwrap_err();
+    assert!(error.to_string().contains("length"), "{}", error);
+
+    let error =
+        Info::from_bencode(b"d5:filesld6:lengthi1e4:pathli1eeee4:name0:12:piece lengthi1ee")
+            .unwrap_err();
+    assert_eq!(error.path(), Some("files[0].path[0]"));
+}
+
+#[test]
+fn derived_depths_should_count_nested_containers() {
+    assert_eq!(<Info as ToBencode>::MAX_DEPTH, 4);
+    assert_eq!(<Info as FromBencode>::EXPECTED_RECURSION_DEPTH, 4);
+    assert_eq!(<Empty as ToBencode>::MAX_DEPTH, 1);
+}
+
+#[test]
+fn only_the_outer_option_should_mark_optional_fields() {
+    let cases: [(Nested<Option<u8>>, &[u8]); 3] = [
+        (
+            Nested {
+                inner: None,
+                list: vec![None, Some(1)],
+            },
+            b"d4:listlleli1eeee",
+        ),
+        (
+            Nested {
+                inner: Some(None),
+                list: vec![],
+            },
+            b"d5:innerle4:listlee",
+        ),
+        (
+            Nested {
+                inner: Some(Some(2)),
+                list: vec![],
+            },
+            b"d5:innerli2ee4:listlee",
+        ),
+    ];
+
+    for (value, bencode) in &cases {
+        assert_eq!(value.to_bencode().unwrap(), *bencode);
+        assert_eq!(Nested::from_bencode(bencode).unwrap(), *value);
+    }
+
+    assert!(Nested::<Option<u8>>::from_bencode(b"d5:inneri2e4:listlee").is_err());
+    assert!(Nested::<u8>::from_bencode(b"d4:listli1eee").is_err());
+}
+
+#[test]
+fn recursive_structs_should_have_a_bounded_depth() {
+    assert_eq!(<Directory as FromBencode>::EXPECTED_RECURSION_DEPTH, 64);
+    assert_eq!(<Directory as ToBencode>::MAX_DEPTH, 64);
+    assert_eq!(<Tree as FromBencode>::EXPECTED_RECURSION_DEPTH, 8);
+
+    let mut tree = Directory {
+        entries: BTreeMap::new(),
+        length: Some(1),
+    };
+    tree = Directory {
+        entries: vec![("a".to_string(), tree)].into_iter().collect(),
+        length: None,
+    };
+    let encoded = tree.to_bencode().unwrap();
+    assert_eq!(encoded, b"d7:entriesd1:ad7:entriesde6:lengthi1eeee");
+    assert_eq!(Directory::from_bencode(&encoded).unwrap(), tree);
+
+    for _ in 0..32 {
+        tree = Directory {
+            entries: vec![("a".to_string(), tree)].into_iter().collect(),
+            length: None,
+        };
+    }
+    let error = tree.to_bencode().unwrap_err();
+    assert_eq!(error.code(), "structure.nesting_too_deep");
+}
+
+#[test]
+fn adversarially_deep_file_trees_should_fail() {
+    let levels = 100_000;
+    let mut input = b"d7:entriesd1:a".repeat(levels);
+    input.extend_from_slice(b"de");
+    input.extend_from_slice(&b"ee".repeat(levels));
+
+    let error = Directory::from_bencode(&input).unwrap_err();
+    assert_eq!(error.code(), "structure.nesting_too_deep");
+
+    let error = Tree::from_bencode(&b"d8:childrenl".repeat(levels)).unwrap_err();
+    assert_eq!(error.code(), "structure.nesting_too_deep");
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::disallowed_names, clippy::needless_borrows_for_generic_args)]
+
 use bendy::{
     decoding::{Error as DecodingError, FromBencode, Object},
     encoding::{Error as EncodingError, SingleItemEncoder, ToBencode},